@@ -0,0 +1,250 @@
+// Command-line decoder printing the power events, statistics or blackouts of data files to
+// stdout, so that they can be piped to other tools
+use chrono::FixedOffset;
+use std::env;
+use std::io::{self, Write};
+use std::process;
+use voltcraft_energy_analyzer::export::{daily_stats_to_csv, to_csv, ExportError};
+use voltcraft_energy_analyzer::summary::QuickSummary;
+use voltcraft_energy_analyzer::voltcraft::data::{DuplicatePolicy, PowerEvent, VoltcraftData};
+use voltcraft_energy_analyzer::voltcraft::diagnostics;
+use voltcraft_energy_analyzer::voltcraft::error::VoltcraftError;
+use voltcraft_energy_analyzer::voltcraft::stats::{PowerBlackout, VoltcraftStatistics};
+
+const USAGE: &str = "\
+Usage: voltcraft <command> [options] <file>...
+
+Commands:
+  decode <file>... [--format csv|json]   print the power events (CSV by default)
+  stats <file>... [--daily|--overall]    print the overall statistics as JSON (default) or
+                                         the daily statistics as CSV
+  blackouts <file>...                    print the blackouts as CSV
+  debug <file>                           print the diagnostic dump of a data file, to paste
+                                         into a bug report
+
+Options:
+  --tz <offset>   time zone of the device clock, e.g. +02:00 (the local one by default)
+
+The power events of several files are merged in chronological order.";
+
+// Exit codes (from sysexits.h)
+const EX_USAGE: i32 = 64;
+const EX_DATAERR: i32 = 65;
+const EX_NOINPUT: i32 = 66;
+
+#[derive(PartialEq)]
+enum Format {
+    Csv,
+    Json,
+}
+
+struct Options {
+    command: String,
+    files: Vec<String>,
+    format: Format,
+    daily: bool,
+    tz: Option<FixedOffset>,
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1).collect()) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(EX_USAGE);
+        }
+    };
+    if options.command == "debug" {
+        match VoltcraftData::from_file(&options.files[0]) {
+            Ok(vdf) => print!("{}", diagnostics::dump(&vdf)),
+            Err(e) => exit_on(data_error(&options.files[0], e)),
+        }
+        return;
+    }
+    let events = match decode_files(&options) {
+        Ok(events) => events,
+        Err(failure) => exit_on(failure),
+    };
+    let mut stdout = io::stdout().lock();
+    let result = match options.command.as_str() {
+        "decode" if options.format == Format::Json => write_json(&events, &mut stdout),
+        "decode" => to_csv(&events, &mut stdout),
+        "stats" if options.daily => {
+            let stats = VoltcraftStatistics::new(&events);
+            let days = match options.tz {
+                Some(tz) => stats.daily_stats_in(&tz),
+                None => stats.daily_stats(),
+            };
+            daily_stats_to_csv(&days, &mut stdout)
+        }
+        "stats" => {
+            let summary = QuickSummary::new(&events, options.files.len());
+            writeln!(stdout, "{}", summary.to_json()).map_err(ExportError::from)
+        }
+        _ => {
+            let blackouts = VoltcraftStatistics::new(&events).blackout_stats();
+            write_blackouts(&blackouts.blackouts, &mut stdout)
+        }
+    };
+    if let Err(e) = result {
+        // A closed pipe (e.g. piped to head) is not worth reporting
+        if !matches!(&e, ExportError::Io(io) if io.kind() == io::ErrorKind::BrokenPipe) {
+            eprintln!("{}", e);
+            process::exit(e.exit_code());
+        }
+    }
+}
+
+// Message and exit code of the error of a data file
+fn data_error(file: &str, e: VoltcraftError) -> (String, i32) {
+    let code = match e {
+        VoltcraftError::Io(_) => EX_NOINPUT,
+        _ => EX_DATAERR,
+    };
+    (format!("{}: {}", file, e), code)
+}
+
+// Report the failure and exit with its code
+fn exit_on((message, code): (String, i32)) -> ! {
+    eprintln!("{}", message);
+    process::exit(code);
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut args = args.into_iter();
+    let command = args.next().ok_or("Missing command")?;
+    if !["decode", "stats", "blackouts", "debug"].contains(&command.as_str()) {
+        return Err(format!("Unknown command {}", command));
+    }
+    let mut options = Options {
+        command,
+        files: Vec::new(),
+        format: Format::Csv,
+        daily: false,
+        tz: None,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" if options.command == "decode" => {
+                options.format = match args.next().as_deref() {
+                    Some("csv") => Format::Csv,
+                    Some("json") => Format::Json,
+                    _ => return Err("--format takes csv or json".to_string()),
+                }
+            }
+            "--daily" if options.command == "stats" => options.daily = true,
+            "--overall" if options.command == "stats" => options.daily = false,
+            "--tz" => {
+                let offset = args.next().ok_or("--tz takes an offset, e.g. +02:00")?;
+                options.tz = Some(
+                    offset
+                        .parse()
+                        .map_err(|_| format!("Invalid time zone offset {}", offset))?,
+                );
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => options.files.push(arg),
+        }
+    }
+    if options.files.is_empty() {
+        return Err("Missing data file".to_string());
+    }
+    if options.command == "debug" && options.files.len() > 1 {
+        return Err("debug takes a single data file".to_string());
+    }
+    Ok(options)
+}
+
+// Power events of all the files in chronological order, without the ones recorded twice
+fn decode_files(options: &Options) -> Result<Vec<PowerEvent>, (String, i32)> {
+    let mut per_file = Vec::new();
+    for file in &options.files {
+        let events = VoltcraftData::from_file(file).and_then(|vdf| match options.tz {
+            Some(tz) => vdf.parse_in(&tz),
+            None => vdf.parse(),
+        });
+        per_file.push(events.map_err(|e| data_error(file, e))?);
+    }
+    match VoltcraftData::merge(per_file, DuplicatePolicy::KeepFirst) {
+        Ok((events, _)) => Ok(events),
+        Err(conflict) => Err((
+            format!("{}: {}", options.files[conflict.set], conflict),
+            EX_DATAERR,
+        )),
+    }
+}
+
+// Power events as a JSON array, with the names and values of to_csv. JSON has no NaN or
+// infinity, so values that are not finite are null.
+fn write_json<W: Write>(events: &[PowerEvent], mut w: W) -> Result<(), ExportError> {
+    let number = |x: f64| match x.is_finite() {
+        true => x.to_string(),
+        false => "null".to_string(),
+    };
+    writeln!(w, "[")?;
+    for (i, pe) in events.iter().enumerate() {
+        let separator = if i + 1 < events.len() { "," } else { "" };
+        writeln!(
+            w,
+            "  {{\"timestamp\":\"{}\",\"voltage\":{},\"current\":{},\"power_factor\":{},\
+             \"power_kw\":{},\"apparent_power_kva\":{}}}{}",
+            pe.timestamp.to_rfc3339(),
+            number(pe.voltage),
+            number(pe.current),
+            number(pe.power_factor),
+            number(pe.power),
+            number(pe.apparent_power),
+            separator
+        )?;
+    }
+    writeln!(w, "]")?;
+    Ok(())
+}
+
+// Blackouts as CSV, with RFC 3339 start timestamps
+fn write_blackouts<W: Write>(blackouts: &[PowerBlackout], w: W) -> Result<(), ExportError> {
+    let mut wtr = csv::Writer::from_writer(w);
+    wtr.write_record(["start", "duration_min"])?;
+    for b in blackouts {
+        wtr.write_record([b.timestamp.to_rfc3339(), b.duration_minutes().to_string()])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::write_json;
+    use chrono::{DateTime, Local, TimeZone};
+    use voltcraft_energy_analyzer::voltcraft::data::PowerEvent;
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    #[test]
+    fn json_without_non_finite_numbers() {
+        let pe = PowerEvent {
+            current: f64::INFINITY,
+            power_factor: f64::NAN,
+            apparent_power: f64::NEG_INFINITY,
+            ..event(Local.with_ymd_and_hms(2014, 9, 10, 12, 0, 0).unwrap(), 0.5)
+        };
+        let mut json = Vec::new();
+        write_json(&[pe], &mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains(
+            "\"voltage\":230,\"current\":null,\"power_factor\":null,\"power_kw\":0.5,\
+             \"apparent_power_kva\":null}"
+        ));
+    }
+}
@@ -0,0 +1,509 @@
+//! Complete analyses (power events, statistics and metadata) saved as a single gzip-compressed JSON file.
+//!
+//! `AnalysisBundle` is the uncompressed, single JSON document counterpart, e.g. for a web
+//! frontend.
+//!
+//! Bundles carry a `major.minor` format version. Minor versions only add fields, which take
+//! their default value when an older bundle is loaded, and fields unknown to this version are
+//! ignored. Bundles with a newer major version are rejected.
+
+use crate::voltcraft::data::PowerEvent;
+use crate::voltcraft::dataset::Dataset;
+use crate::voltcraft::resample::{resample, Aggregation};
+use crate::voltcraft::stats::{BlackoutInfo, OverallPowerInfo, PowerInterval, VoltcraftStatistics};
+use chrono::{DateTime, Duration, Local};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+pub const FORMAT_MAJOR: u32 = 1;
+pub const FORMAT_MINOR: u32 = 0;
+
+// Version of the AnalysisBundle JSON schema, raised when a field is renamed or removed
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Analysis {
+    pub overall_stats: OverallPowerInfo,
+    pub daily_stats: Vec<PowerInterval>,
+    pub blackout_stats: BlackoutInfo,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleMetadata {
+    pub format_major: u32, // bundle format version
+    pub format_minor: u32,
+    pub crate_version: String, // version of the crate that saved the bundle
+    pub saved: DateTime<Local>, // when the bundle was saved
+    pub event_count: usize,    // number of power events
+}
+
+pub struct Bundle {
+    pub metadata: BundleMetadata,
+    pub dataset: Dataset,
+    pub analysis: Analysis,
+}
+
+/// A complete analysis of power events as a single JSON document (see `to_json_writer`):
+///
+/// ```text
+/// {
+///   "metadata": {
+///     "schema_version": 1,
+///     "crate_version": "0.3.0",
+///     "file_name": "A0810702.BIN",         // null if not given
+///     "start": "2014-09-10T00:00:00+02:00", // first and last power events, null without any
+///     "end": "2014-09-11T23:59:00+02:00",
+///     "event_count": 2870,                  // power events analyzed
+///     "event_interval": 60                  // seconds between the power events included
+///   },
+///   "events": [                             // all the power events, or their means
+///     { "timestamp": "2014-09-10T00:00:00+02:00", "voltage": 220.0, "current": 0.5,
+///       "power_factor": 0.9, "power": 0.099, "apparent_power": 0.11 }, ...
+///   ],
+///   "overall_stats": { "start": ..., "end": ..., "stats": { ... }, ... }, // null without events
+///   "daily_stats": [ { "date": "2014-09-10", "stats": { ... }, ... }, ... ],
+///   "blackout_stats": { "blackout_count": 1, "total_blackout_duration": 600, "blackouts": [
+///     { "timestamp": "2014-09-11T01:00:00+02:00", "duration": 600 } ] }
+/// }
+/// ```
+///
+/// Field names are snake_case, timestamps RFC 3339 with the local offset, durations integer
+/// seconds, powers kW and energies kWh. The statistics are those of all the power events,
+/// whether or not the events included are resampled. Fields are only added within a schema
+/// version.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnalysisBundle {
+    pub metadata: AnalysisMetadata,
+    pub events: Vec<PowerEvent>,
+    pub overall_stats: Option<OverallPowerInfo>,
+    pub daily_stats: Vec<PowerInterval>,
+    pub blackout_stats: BlackoutInfo,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisMetadata {
+    pub schema_version: u32,
+    pub crate_version: String, // version of the crate that built the bundle
+    pub file_name: Option<String>, // data file of the power events, if given
+    pub start: Option<DateTime<Local>>, // first power event
+    pub end: Option<DateTime<Local>>, // last power event
+    pub event_count: usize,    // number of power events analyzed
+    #[serde(with = "crate::voltcraft::serde_helpers::duration_seconds")]
+    pub event_interval: Duration, // between the power events included: a minute, or the bucket
+}
+
+/// Options of `AnalysisBundle::build`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct BundleOptions {
+    pub max_events: Option<usize>, // power events included at most (at least one), resampled
+    pub file_name: Option<String>, // name of the data file, for the metadata
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BundleError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    UnsupportedVersion { major: u32, minor: u32 },
+}
+
+// Layout of the bundle file
+#[derive(Serialize)]
+struct Container<'a> {
+    metadata: BundleMetadata,
+    events: &'a [PowerEvent],
+    analysis: &'a Analysis,
+}
+
+#[derive(Deserialize)]
+struct OwnedContainer {
+    metadata: BundleMetadata,
+    events: Vec<PowerEvent>,
+    analysis: Analysis,
+}
+
+// Only the version is read first, so that the rest of a newer bundle is never interpreted
+#[derive(Deserialize)]
+struct VersionProbe {
+    metadata: VersionMetadata,
+}
+
+#[derive(Deserialize)]
+struct VersionMetadata {
+    format_major: u32,
+    format_minor: u32,
+}
+
+impl Analysis {
+    // Analysis of the power events, None without any
+    pub fn new(stats: &VoltcraftStatistics) -> Option<Analysis> {
+        Some(Analysis {
+            overall_stats: stats.overall_stats()?,
+            daily_stats: stats.daily_stats(),
+            blackout_stats: stats.blackout_stats(),
+        })
+    }
+}
+
+impl BundleOptions {
+    // Resample the power events by their mean over whole minutes, so that there are at most
+    // `max_events` of them (at least one)
+    pub fn with_max_events(mut self, max_events: usize) -> Self {
+        self.max_events = Some(max_events.max(1));
+        self
+    }
+
+    pub fn with_file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+}
+
+impl AnalysisBundle {
+    // Analysis of the power events, recorded every minute in chronological order
+    pub fn build(events: &[PowerEvent], options: BundleOptions) -> AnalysisBundle {
+        let stats = VoltcraftStatistics::new(events);
+        let (included, event_interval) = match options.max_events {
+            Some(max_events) if events.len() > max_events.max(1) => {
+                downsample(events, max_events.max(1))
+            }
+            _ => (events.to_vec(), Duration::minutes(1)),
+        };
+        AnalysisBundle {
+            metadata: AnalysisMetadata {
+                schema_version: SCHEMA_VERSION,
+                crate_version: env!("CARGO_PKG_VERSION").to_string(),
+                file_name: options.file_name,
+                start: events.first().map(|pe| pe.timestamp),
+                end: events.last().map(|pe| pe.timestamp),
+                event_count: events.len(),
+                event_interval,
+            },
+            events: included,
+            overall_stats: stats.overall_stats(),
+            daily_stats: stats.daily_stats(),
+            blackout_stats: stats.blackout_stats(),
+        }
+    }
+
+    // Write the bundle as JSON (see AnalysisBundle for the schema)
+    pub fn to_json_writer<W: Write>(&self, writer: W) -> Result<(), BundleError> {
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+}
+
+// Means of the power events over the shortest bucket (whole minutes) leaving at most
+// max_events (at least one) of them, with the bucket
+fn downsample(events: &[PowerEvent], max_events: usize) -> (Vec<PowerEvent>, Duration) {
+    let span = (events[events.len() - 1].timestamp - events[0].timestamp).num_minutes() + 1;
+    let mut minutes = (span as usize).div_ceil(max_events).max(2) as i64;
+    loop {
+        let bucket = Duration::minutes(minutes);
+        let resampled = resample(events, bucket, Aggregation::Mean);
+        if resampled.len() <= max_events {
+            return (resampled, bucket);
+        }
+        // Buckets are aligned on the wall clock, so the first and last ones may be partial
+        minutes += (minutes / 8).max(1);
+    }
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BundleError::Io(e) => write!(f, "Error accessing the bundle file: {}", e),
+            BundleError::Format(e) => write!(f, "Invalid bundle file: {}", e),
+            BundleError::UnsupportedVersion { major, minor } => write!(
+                f,
+                "Bundle format {}.{} is newer than the supported format {}.{}",
+                major, minor, FORMAT_MAJOR, FORMAT_MINOR
+            ),
+        }
+    }
+}
+
+impl Error for BundleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BundleError::Io(e) => Some(e),
+            BundleError::Format(e) => Some(e),
+            BundleError::UnsupportedVersion { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for BundleError {
+    fn from(e: io::Error) -> Self {
+        BundleError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for BundleError {
+    fn from(e: serde_json::Error) -> Self {
+        BundleError::Format(e)
+    }
+}
+
+pub fn save<P: AsRef<Path>>(
+    path: P,
+    dataset: &Dataset,
+    analysis: &Analysis,
+) -> Result<(), BundleError> {
+    let container = Container {
+        metadata: BundleMetadata {
+            format_major: FORMAT_MAJOR,
+            format_minor: FORMAT_MINOR,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            saved: Local::now(),
+            event_count: dataset.len(),
+        },
+        events: dataset.events(),
+        analysis,
+    };
+    let f = BufWriter::new(File::create(path)?);
+    let mut encoder = GzEncoder::new(f, Compression::default());
+    serde_json::to_writer(&mut encoder, &container)?;
+    encoder.finish()?.flush()?;
+    Ok(())
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Bundle, BundleError> {
+    let decoder = GzDecoder::new(BufReader::new(File::open(path)?));
+    let value: serde_json::Value = serde_json::from_reader(decoder)?;
+    let probe = VersionProbe::deserialize(&value)?;
+    if probe.metadata.format_major > FORMAT_MAJOR {
+        return Err(BundleError::UnsupportedVersion {
+            major: probe.metadata.format_major,
+            minor: probe.metadata.format_minor,
+        });
+    }
+    let container = OwnedContainer::deserialize(value)?;
+    Ok(Bundle {
+        metadata: container.metadata,
+        dataset: Dataset::new(container.events),
+        analysis: container.analysis,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bundle::{
+        self, Analysis, AnalysisBundle, BundleError, BundleOptions, FORMAT_MAJOR, SCHEMA_VERSION,
+    };
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::dataset::Dataset;
+    use crate::voltcraft::stats::VoltcraftStatistics;
+    use chrono::{Duration, Local, TimeZone};
+    use flate2::read::GzDecoder;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::fs::File;
+    use std::path::PathBuf;
+
+    // Two days of data with a 10 minute gap
+    fn events() -> Vec<PowerEvent> {
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        (0..2880)
+            .filter(|m| !(1500..1510).contains(m))
+            .map(|m| PowerEvent {
+                timestamp: start + Duration::minutes(m),
+                voltage: 220.0 + (m % 10) as f64,
+                current: 0.5,
+                power_factor: 0.9,
+                power: 0.099,
+                apparent_power: 0.11,
+                frequency: None,
+            })
+            .collect()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("voltcraft-{}-{}.json.gz", name, std::process::id()))
+    }
+
+    fn save(name: &str) -> PathBuf {
+        let events = events();
+        let analysis = Analysis::new(&VoltcraftStatistics::new(&events)).unwrap();
+        let path = temp_path(name);
+        bundle::save(&path, &Dataset::new(events), &analysis).unwrap();
+        path
+    }
+
+    // Apply a change to the JSON inside a saved bundle
+    fn rewrite(path: &PathBuf, change: impl Fn(&mut serde_json::Value)) {
+        let mut value: serde_json::Value =
+            serde_json::from_reader(GzDecoder::new(File::open(path).unwrap())).unwrap();
+        change(&mut value);
+        let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        serde_json::to_writer(&mut encoder, &value).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    #[test]
+    fn bundle_round_trip() {
+        let path = save("round-trip");
+        let loaded = bundle::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.metadata.format_major, FORMAT_MAJOR);
+        assert_eq!(loaded.metadata.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(loaded.metadata.event_count, 2870);
+        let original = events();
+        assert_eq!(loaded.dataset.len(), original.len());
+        for (a, b) in loaded.dataset.events().iter().zip(&original) {
+            assert_eq!(a.timestamp, b.timestamp);
+            assert_eq!(a.voltage, b.voltage);
+            assert_eq!(a.power, b.power);
+        }
+        let analysis = loaded.analysis;
+        assert_eq!(analysis.daily_stats.len(), 2);
+        assert_eq!(
+            analysis.daily_stats[1].date,
+            original[2000].timestamp.date_naive()
+        );
+        assert_eq!(analysis.blackout_stats.blackout_count, 1);
+        assert_eq!(
+            analysis.blackout_stats.blackouts[0].duration,
+            Duration::minutes(10)
+        );
+        assert_eq!(analysis.overall_stats.stats.max_voltage.voltage, 229.0);
+    }
+
+    #[test]
+    fn bundle_ignores_unknown_fields() {
+        let path = save("unknown-field");
+        rewrite(&path, |value| {
+            value["metadata"]["format_minor"] = 7.into();
+            value["metadata"]["written_by"] = "a future version".into();
+            value["annotations"] = serde_json::json!([{ "label": "holiday" }]);
+        });
+        let loaded = bundle::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.metadata.format_minor, 7);
+        assert_eq!(loaded.dataset.len(), 2870);
+    }
+
+    #[test]
+    fn bundle_rejects_newer_major() {
+        let path = save("newer-major");
+        rewrite(&path, |value| {
+            value["metadata"]["format_major"] = (FORMAT_MAJOR + 1).into();
+            value["events"] = "no longer a list".into();
+        });
+        let result = bundle::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        match result {
+            Err(e @ BundleError::UnsupportedVersion { .. }) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Bundle format 2.0 is newer than the supported format 1.0"
+                )
+            }
+            _ => panic!("newer major version accepted"),
+        }
+    }
+
+    // The JSON with every number replaced by zero, to compare the fields only
+    fn shape(value: serde_json::Value) -> serde_json::Value {
+        use serde_json::Value;
+        match value {
+            Value::Number(_) => 0.into(),
+            Value::Array(values) => Value::Array(values.into_iter().map(shape).collect()),
+            Value::Object(map) => {
+                Value::Object(map.into_iter().map(|(k, v)| (k, shape(v))).collect())
+            }
+            other => other,
+        }
+    }
+
+    #[test]
+    fn analysis_bundle_json_schema() {
+        let events = events();
+        let options = BundleOptions::default().with_file_name("A0810702.BIN");
+        let full = AnalysisBundle::build(&events, options.clone());
+        assert_eq!(full.events.len(), 2870);
+        assert_eq!(full.metadata.event_interval, Duration::minutes(1));
+
+        let bundle = AnalysisBundle::build(&events, options.with_max_events(500));
+        let mut json = Vec::new();
+        bundle.to_json_writer(&mut json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let keys =
+            |v: &serde_json::Value| v.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+        // serde_json::Value lists the keys in order
+        assert_eq!(
+            keys(&value),
+            [
+                "blackout_stats",
+                "daily_stats",
+                "events",
+                "metadata",
+                "overall_stats"
+            ]
+        );
+        let metadata = &value["metadata"];
+        assert_eq!(metadata["schema_version"], SCHEMA_VERSION);
+        assert_eq!(metadata["file_name"], "A0810702.BIN");
+        assert_eq!(metadata["event_count"], 2870);
+        assert_eq!(metadata["event_interval"], 360);
+        let start = metadata["start"].as_str().unwrap();
+        assert_eq!(
+            chrono::DateTime::parse_from_rfc3339(start).unwrap(),
+            events[0].timestamp
+        );
+        let resampled = value["events"].as_array().unwrap();
+        assert!(resampled.len() <= 500 && resampled.len() > 400);
+        assert_eq!(
+            keys(&resampled[0]),
+            [
+                "apparent_power",
+                "current",
+                "power",
+                "power_factor",
+                "timestamp",
+                "voltage"
+            ]
+        );
+        let blackout = &value["blackout_stats"]["blackouts"][0];
+        assert_eq!(blackout["duration"], 600);
+        assert_eq!(value["daily_stats"].as_array().unwrap().len(), 2);
+        // The statistics are those of every power event
+        assert_eq!(
+            value["overall_stats"]["stats"]["total_active_power"],
+            serde_json::to_value(full.overall_stats.unwrap().stats.total_active_power).unwrap()
+        );
+
+        // The schema reads back into the bundle without losing anything
+        let back: AnalysisBundle = serde_json::from_slice(&json).unwrap();
+        assert_eq!(back.metadata, bundle.metadata);
+        for (a, b) in back.events.iter().zip(&bundle.events) {
+            assert_eq!(a.timestamp, b.timestamp);
+            // serde_json parses floats to within an ulp unless its float_roundtrip feature is on
+            assert!((a.voltage - b.voltage).abs() < 1e-12);
+        }
+        assert_eq!(back.events.len(), bundle.events.len());
+        assert_eq!(shape(serde_json::to_value(&back).unwrap()), shape(value));
+
+        // A maximum of zero set on the field is one power event
+        let options = BundleOptions {
+            max_events: Some(0),
+            ..BundleOptions::default()
+        };
+        let single = AnalysisBundle::build(&events, options);
+        assert_eq!(single.events.len(), 1);
+        assert_eq!(single.metadata.event_count, 2870);
+
+        let empty = AnalysisBundle::build(&[], BundleOptions::default().with_max_events(10));
+        let empty = serde_json::to_value(&empty).unwrap();
+        assert!(empty["overall_stats"].is_null() && empty["metadata"]["start"].is_null());
+    }
+}
@@ -1,25 +1,232 @@
+use crate::format::{format_fixed, format_trimmed};
+use crate::report::{render, ReportContext, ReportOptions, TemplateError, TEXT_TEMPLATE};
+use crate::sink::{Sink, SinkError};
 use crate::voltcraft::data::PowerEvent;
-use crate::voltcraft::stats::{BlackoutInfo, DailyPowerInfo, OverallPowerInfo};
+use crate::voltcraft::stats::{
+    BlackoutInfo, NumericMode, OverallPowerInfo, PowerBlackout, PowerInterval, VoltcraftStatistics,
+};
+use chrono::{DateTime, Local};
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::{self, Write};
 
+/// Exporters and integrations compiled into this build, e.g. to hide menu entries at runtime.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    pub text: bool,     // text parameter history and statistics
+    pub csv: bool,      // CSV parameter history, daily statistics and blackouts
+    pub markdown: bool, // Markdown reports
+    pub serde: bool,    // serialization of the power events and statistics (serde feature)
+    pub bundle: bool,   // compressed analysis bundles (bundle feature)
+    pub parallel: bool, // parallel multi-file analysis (rayon feature)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExportFormat {
+    HistoryText,  // save_parameter_history_txt
+    HistoryCsv,   // save_parameter_history_csv
+    Statistics,   // save_statistics
+    DailyCsv,     // save_daily_stats_csv
+    BlackoutsCsv, // save_blackouts_csv
+    Bundle,       // bundle::save
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ExportError {
+    Io(io::Error),
+    Encoding { detail: String }, // the data could not be encoded in the format
+    Unsupported { format: ExportFormat }, // the format is not compiled in
+    EmptyDataSet,                // statistics need at least one power event
+    Sink { source: SinkError },  // the sink failed to take the power events
+}
+
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        text: true,
+        csv: true,
+        markdown: true,
+        serde: cfg!(feature = "serde"),
+        bundle: cfg!(feature = "bundle"),
+        parallel: cfg!(feature = "rayon"),
+    }
+}
+
+impl ExportFormat {
+    pub fn is_supported(&self) -> bool {
+        match self {
+            ExportFormat::Bundle => capabilities().bundle,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ExportFormat::HistoryText => "text parameter history",
+            ExportFormat::HistoryCsv => "CSV parameter history",
+            ExportFormat::Statistics => "statistics",
+            ExportFormat::DailyCsv => "CSV daily statistics",
+            ExportFormat::BlackoutsCsv => "CSV blackouts",
+            ExportFormat::Bundle => "analysis bundle",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl ExportError {
+    // Exit code of a command-line tool failing with the error (from sysexits.h), so that
+    // scripts can tell a full disk from a build without the needed feature
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ExportError::Io(_) => 74,              // EX_IOERR
+            ExportError::Encoding { .. } => 65,    // EX_DATAERR
+            ExportError::Unsupported { .. } => 69, // EX_UNAVAILABLE
+            ExportError::EmptyDataSet => 66,       // EX_NOINPUT
+            ExportError::Sink { .. } => 75,        // EX_TEMPFAIL, the sink may accept a retry
+        }
+    }
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportError::Io(e) => write!(f, "error writing the export: {}", e),
+            ExportError::Encoding { detail } => write!(f, "error encoding the export: {}", detail),
+            ExportError::Unsupported { format } => {
+                write!(f, "{} export is not compiled into this build", format)
+            }
+            ExportError::EmptyDataSet => write!(f, "no power data to export"),
+            ExportError::Sink { source } => write!(f, "error exporting to the sink: {}", source),
+        }
+    }
+}
+
+impl Error for ExportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExportError::Io(e) => Some(e),
+            ExportError::Sink { source } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(e: io::Error) -> Self {
+        ExportError::Io(e)
+    }
+}
+
+impl From<csv::Error> for ExportError {
+    fn from(e: csv::Error) -> Self {
+        if e.is_io_error() {
+            match e.into_kind() {
+                csv::ErrorKind::Io(e) => ExportError::Io(e),
+                _ => unreachable!("checked to be an I/O error"),
+            }
+        } else {
+            ExportError::Encoding {
+                detail: e.to_string(),
+            }
+        }
+    }
+}
+
+impl From<TemplateError> for ExportError {
+    fn from(e: TemplateError) -> Self {
+        ExportError::Encoding {
+            detail: e.to_string(),
+        }
+    }
+}
+
+impl From<SinkError> for ExportError {
+    fn from(source: SinkError) -> Self {
+        ExportError::Sink { source }
+    }
+}
+
+#[cfg(feature = "bundle")]
+impl From<crate::bundle::BundleError> for ExportError {
+    fn from(e: crate::bundle::BundleError) -> Self {
+        match e {
+            crate::bundle::BundleError::Io(e) => ExportError::Io(e),
+            e => ExportError::Encoding {
+                detail: e.to_string(),
+            },
+        }
+    }
+}
+
+/// Export the power events in any format, returning `ExportError::Unsupported` for formats
+/// whose cargo feature is disabled. Events are expected in chronological order.
+pub fn try_export(
+    format: ExportFormat,
+    filename: &str,
+    power_events: &[PowerEvent],
+) -> Result<(), ExportError> {
+    try_export_with_mode(format, filename, power_events, NumericMode::Fast)
+}
+
+// Same as try_export, computing the statistics in the given numeric mode
+pub fn try_export_with_mode(
+    format: ExportFormat,
+    filename: &str,
+    power_events: &[PowerEvent],
+    numeric_mode: NumericMode,
+) -> Result<(), ExportError> {
+    if !format.is_supported() {
+        return Err(ExportError::Unsupported { format });
+    }
+    match format {
+        ExportFormat::HistoryText => return save_parameter_history_txt(filename, power_events),
+        ExportFormat::HistoryCsv => return save_parameter_history_csv(filename, power_events),
+        _ if power_events.is_empty() => return Err(ExportError::EmptyDataSet),
+        _ => {}
+    }
+    let stats = VoltcraftStatistics::new(power_events).with_numeric_mode(numeric_mode);
+    match format {
+        ExportFormat::Statistics => save_statistics(
+            filename,
+            &stats.overall_stats().ok_or(ExportError::EmptyDataSet)?,
+            &stats.daily_stats(),
+            &stats.blackout_stats(),
+        )?,
+        ExportFormat::DailyCsv => save_daily_stats_csv(filename, &stats.daily_stats())?,
+        ExportFormat::BlackoutsCsv => save_blackouts_csv(filename, &stats.blackout_stats())?,
+        #[cfg(feature = "bundle")]
+        ExportFormat::Bundle => {
+            use crate::bundle::{self, Analysis};
+            let analysis = Analysis::new(&stats).ok_or(ExportError::EmptyDataSet)?;
+            let dataset = crate::voltcraft::dataset::Dataset::new(power_events.to_vec());
+            bundle::save(filename, &dataset, &analysis)?
+        }
+        _ => unreachable!("unsupported formats are rejected above"),
+    }
+    Ok(())
+}
+
 pub fn save_parameter_history_txt(
     filename: &str,
     power_events: &[PowerEvent],
-) -> Result<(), io::Error> {
+) -> Result<(), ExportError> {
     let mut f = File::create(filename)?;
     writeln!(f, "== PARAMETER HISTORY ==")?;
     writeln!(f)?;
     for pe in power_events {
         writeln!(
             f,
-            "{} U={:.1}V I={:.3}A cosPHI={:.2} P={:.3}kW S={:.3}kVA",
+            "{} U={}V I={}A cosPHI={} P={}kW S={}kVA",
             pe.timestamp.format("[%Y-%m-%d %H:%M]"),
-            pe.voltage,
-            pe.current,
-            pe.power_factor,
-            pe.power,
-            pe.apparent_power
+            format_fixed(pe.voltage, 1),
+            format_fixed(pe.current, 3),
+            format_fixed(pe.power_factor, 2),
+            format_fixed(pe.power, 3),
+            format_fixed(pe.apparent_power, 3)
         )?;
     }
     Ok(())
@@ -28,9 +235,9 @@ pub fn save_parameter_history_txt(
 pub fn save_parameter_history_csv(
     filename: &str,
     power_events: &[PowerEvent],
-) -> Result<(), io::Error> {
+) -> Result<(), ExportError> {
     let mut wtr = csv::Writer::from_path(filename)?;
-    wtr.write_record(&[
+    wtr.write_record([
         "Timestamp",
         "Voltage (V)",
         "Current (A)",
@@ -39,13 +246,14 @@ pub fn save_parameter_history_csv(
         "Apparent Power (kVA)",
     ])?;
     for pe in power_events {
-        wtr.write_record(&[
+        wtr.write_record([
             pe.timestamp.format("%Y-%m-%d %H:%M").to_string(),
-            pe.voltage.to_string(),
-            pe.current.to_string(),
-            pe.power_factor.to_string(),
-            pe.power.to_string(),
-            pe.apparent_power.to_string(),
+            // Enough decimals to keep the full resolution of the decoded values
+            format_trimmed(pe.voltage, 1),
+            format_trimmed(pe.current, 3),
+            format_trimmed(pe.power_factor, 2),
+            format_trimmed(pe.power, 9),
+            format_trimmed(pe.apparent_power, 7),
         ])?;
     }
     wtr.flush()?;
@@ -55,189 +263,526 @@ pub fn save_parameter_history_csv(
 pub fn save_statistics(
     filename: &str,
     overall_stats: &OverallPowerInfo,
-    daily_stats: &[DailyPowerInfo],
+    daily_stats: &[PowerInterval],
+    blackout_stats: &BlackoutInfo,
+) -> Result<(), ExportError> {
+    save_statistics_with_options(
+        filename,
+        overall_stats,
+        daily_stats,
+        blackout_stats,
+        &ReportOptions::default(),
+    )
+}
+
+// Same as save_statistics, with a custom locale, labels and precision
+pub fn save_statistics_with_options(
+    filename: &str,
+    overall_stats: &OverallPowerInfo,
+    daily_stats: &[PowerInterval],
     blackout_stats: &BlackoutInfo,
-) -> Result<(), io::Error> {
+    options: &ReportOptions,
+) -> Result<(), ExportError> {
+    let ctx = ReportContext::with_options(overall_stats, daily_stats, blackout_stats, options);
+    save_report(filename, TEXT_TEMPLATE, &ctx)
+}
+
+// Render a report template (e.g. report::MARKDOWN_TEMPLATE) to a file
+pub fn save_report(filename: &str, template: &str, ctx: &ReportContext) -> Result<(), ExportError> {
+    let report = render(template, ctx)?;
     let mut f = File::create(filename)?;
-    // Statistics for the entire period
-    writeln!(f, "==== OVERALL STATISTICS ==================")?;
-    writeln!(
-        f,
-        "Interval: {}-{} ({})",
-        overall_stats.start.format("[%Y-%m-%d %H:%M]"),
-        overall_stats.end.format("[%Y-%m-%d %H:%M]"),
-        format_duration(overall_stats.end - overall_stats.start)
-    )?;
-    match overall_stats.avg_daily_power_consumption {
-        None => {}
-        Some(d) => {
-            writeln!(
-                f,
-                "Average consumption: {:.2}kWh/day | Projected: {:.2}kWh/month or {:.2}kWh/year.",
-                d,
-                d * 30.0,
-                d * 365.0
-            )?;
-        }
-    }
-    writeln!(f)?;
-    writeln!(f, "- ACTIVE POWER")?;
-    writeln!(
-        f,
-        "Total energy consumption: {:.2}kWh.",
-        overall_stats.stats.total_active_power
-    )?;
-    writeln!(
-        f,
-        "Peak power was {:.2}kW and occured on {}.",
-        overall_stats.stats.max_active_power.power,
-        overall_stats
-            .stats
-            .max_active_power
-            .timestamp
-            .format("[%Y-%m-%d %H:%M]")
-    )?;
-    writeln!(
-        f,
-        "Minute by minute average power: {:.2}kW.",
-        overall_stats.stats.avg_active_power
-    )?;
-    writeln!(f)?;
-    writeln!(f, "- APPARENT POWER")?;
-    writeln!(
-        f,
-        "Total energy consumption: {:.2}kVAh.",
-        overall_stats.stats.total_apparent_power
-    )?;
-    writeln!(
-        f,
-        "Peak power was {:.2}kVA and occured on {}.",
-        overall_stats.stats.max_apparent_power.power,
-        overall_stats
-            .stats
-            .max_apparent_power
-            .timestamp
-            .format("[%Y-%m-%d %H:%M]")
-    )?;
-    writeln!(
-        f,
-        "Minute by minute average power: {:.2}kVA.",
-        overall_stats.stats.avg_apparent_power
-    )?;
-    writeln!(f)?;
-    writeln!(f, "- VOLTAGE")?;
-    writeln!(
-        f,
-        "Minimum voltage was {:.1}V and occured on {}.",
-        overall_stats.stats.min_voltage.voltage,
-        overall_stats
-            .stats
-            .min_voltage
-            .timestamp
-            .format("[%Y-%m-%d %H:%M]")
-    )?;
-    writeln!(
-        f,
-        "Maximum voltage was {:.1}V and occured on {}.",
-        overall_stats.stats.max_voltage.voltage,
-        overall_stats
-            .stats
-            .max_voltage
-            .timestamp
-            .format("[%Y-%m-%d %H:%M]")
-    )?;
-    writeln!(
-        f,
-        "Minute by minute average voltage: {:.1}V.",
-        overall_stats.stats.avg_voltage
-    )?;
-    writeln!(f)?;
-    writeln!(f)?;
+    f.write_all(report.as_bytes())?;
+    Ok(())
+}
 
-    writeln!(f, "==== DAILY STATISTICS ====================")?;
-    // Daily statistics
+pub fn save_daily_stats_csv(
+    filename: &str,
+    daily_stats: &[PowerInterval],
+) -> Result<(), ExportError> {
+    let mut wtr = csv::Writer::from_path(filename)?;
+    wtr.write_record([
+        "Date",
+        "Recorded (min)",
+        "Active Energy (kWh)",
+        "Average Power (kW)",
+        "Peak Power (kW)",
+        "Peak Time",
+        "Apparent Energy (kVAh)",
+        "Average Voltage (V)",
+        "Minimum Voltage (V)",
+        "Maximum Voltage (V)",
+    ])?;
     for interval in daily_stats {
-        writeln!(
-            f,
-            "{} - {} recorded activity ({:.1}%)",
-            interval.date.format("[%Y-%m-%d]"),
-            format_duration(interval.stats.total_duration),
-            interval.stats.total_duration.num_seconds() as f64 * 100.0 / 86400.0
-        )?;
-        writeln!(
-            f,
-            "      Total active power: {:.2}kWh  | Average: {:.2}kW  | Maximum: {:.2}kW on {}",
-            interval.stats.total_active_power,
-            interval.stats.avg_active_power,
-            interval.stats.max_active_power.power,
-            interval
-                .stats
-                .max_active_power
-                .timestamp
-                .format("[%Y-%m-%d %H:%M]")
-        )?;
-        writeln!(
-            f,
-            "    Total apparent power: {:.2}kVAh | Average: {:.2}kVA | Maximum: {:.2}kVA on {}",
-            interval.stats.total_active_power,
-            interval.stats.avg_active_power,
-            interval.stats.max_active_power.power,
-            interval
-                .stats
+        let stats = &interval.stats;
+        wtr.write_record([
+            interval.date.format("%Y-%m-%d").to_string(),
+            stats.total_duration.num_minutes().to_string(),
+            format_fixed(stats.total_active_power, 3),
+            format_fixed(stats.avg_active_power, 3),
+            format_fixed(stats.max_active_power.power, 3),
+            stats
                 .max_active_power
                 .timestamp
-                .format("[%Y-%m-%d %H:%M]")
-        )?;
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+            format_fixed(stats.total_apparent_power, 3),
+            format_fixed(stats.avg_voltage, 1),
+            format_fixed(stats.min_voltage.voltage, 1),
+            format_fixed(stats.max_voltage.voltage, 1),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+pub fn save_blackouts_csv(
+    filename: &str,
+    blackout_stats: &BlackoutInfo,
+) -> Result<(), ExportError> {
+    let mut wtr = csv::Writer::from_path(filename)?;
+    wtr.write_record(["Start", "Duration (min)"])?;
+    for be in &blackout_stats.blackouts {
+        wtr.write_record([
+            be.timestamp.format("%Y-%m-%d %H:%M").to_string(),
+            be.duration_minutes().to_string(),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// Write the power events as CSV with RFC 3339 timestamps, streaming to the writer. Values keep
+// their full precision (shortest representation reading back the same value, with a dot).
+pub fn to_csv<W: Write>(power_events: &[PowerEvent], w: W) -> Result<(), ExportError> {
+    let mut wtr = csv::Writer::from_writer(w);
+    wtr.write_record([
+        "timestamp",
+        "voltage",
+        "current",
+        "power_factor",
+        "power_kw",
+        "apparent_power_kva",
+    ])?;
+    for pe in power_events {
+        wtr.write_record([
+            pe.timestamp.to_rfc3339(),
+            pe.voltage.to_string(),
+            pe.current.to_string(),
+            pe.power_factor.to_string(),
+            pe.power.to_string(),
+            pe.apparent_power.to_string(),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// Write the daily statistics as CSV, in the same format as to_csv
+pub fn daily_stats_to_csv<W: Write>(
+    daily_stats: &[PowerInterval],
+    w: W,
+) -> Result<(), ExportError> {
+    let mut wtr = csv::Writer::from_writer(w);
+    wtr.write_record([
+        "date",
+        "duration_min",
+        "energy_kwh",
+        "avg_power_kw",
+        "max_power_kw",
+        "max_power_timestamp",
+        "apparent_energy_kvah",
+        "avg_apparent_power_kva",
+        "avg_voltage",
+        "min_voltage",
+        "max_voltage",
+    ])?;
+    for interval in daily_stats {
+        let stats = &interval.stats;
+        wtr.write_record([
+            interval.date.format("%Y-%m-%d").to_string(),
+            stats.total_duration.num_minutes().to_string(),
+            stats.total_active_power.to_string(),
+            stats.avg_active_power.to_string(),
+            stats.max_active_power.power.to_string(),
+            stats.max_active_power.timestamp.to_rfc3339(),
+            stats.total_apparent_power.to_string(),
+            stats.avg_apparent_power.to_string(),
+            stats.avg_voltage.to_string(),
+            stats.min_voltage.voltage.to_string(),
+            stats.max_voltage.voltage.to_string(),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// Write the power events in the InfluxDB line protocol (e.g. for `influx write`), one line per
+// event with the given tags and nanosecond timestamps. Values keep their full precision. The
+// protocol has no NaN or infinity, so fields that are not finite are left out, and so are
+// events without any finite field. Tags with an empty key or value are left out too.
+pub fn to_line_protocol<W: Write>(
+    power_events: &[PowerEvent],
+    measurement: &str,
+    tags: &[(&str, &str)],
+    mut w: W,
+) -> Result<(), ExportError> {
+    let series = line_series(measurement, tags);
+    for pe in power_events {
+        let fields = [
+            ("voltage", pe.voltage),
+            ("current", pe.current),
+            ("power_factor", pe.power_factor),
+            ("power", pe.power),
+            ("apparent_power", pe.apparent_power),
+        ]
+        .iter()
+        .filter(|(_, value)| value.is_finite())
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>();
+        if fields.is_empty() {
+            continue;
+        }
         writeln!(
-            f,
-            "    Voltage: Average: {:.1}V | Minimum: {:.1}V on {} | Maximum: {:.1}V on {}",
-            interval.stats.avg_voltage,
-            interval.stats.min_voltage.voltage,
-            interval
-                .stats
-                .min_voltage
-                .timestamp
-                .format("[%Y-%m-%d %H:%M]"),
-            interval.stats.max_voltage.voltage,
-            interval
-                .stats
-                .max_voltage
-                .timestamp
-                .format("[%Y-%m-%d %H:%M]")
+            w,
+            "{} {} {}",
+            series,
+            fields.join(","),
+            nanos(pe.timestamp)?
         )?;
-        writeln!(f)?;
     }
+    w.flush()?;
+    Ok(())
+}
 
-    writeln!(f)?;
-    // Blackout history
-    writeln!(f, "==== BLACKOUT HISTORY ====================")?;
-    writeln!(
-        f,
-        "{} blackout(s) for a total of {}.",
-        blackout_stats.blackout_count,
-        format_duration(blackout_stats.total_blackout_duration)
-    )?;
-    writeln!(f)?;
-    for be in &blackout_stats.blackouts {
+// Write the blackouts in the InfluxDB line protocol, as to_line_protocol, timestamped at their
+// start, e.g. to annotate the outages on a dashboard
+pub fn blackouts_to_line_protocol<W: Write>(
+    blackouts: &[PowerBlackout],
+    measurement: &str,
+    tags: &[(&str, &str)],
+    mut w: W,
+) -> Result<(), ExportError> {
+    let series = line_series(measurement, tags);
+    for b in blackouts {
         writeln!(
-            f,
-            "{} Duration: {}",
-            be.timestamp.format("[%Y-%m-%d %H:%M]"),
-            format_duration(be.duration),
+            w,
+            "{} duration_seconds={}i {}",
+            series,
+            b.duration.num_seconds(),
+            nanos(b.timestamp)?
         )?;
     }
+    w.flush()?;
     Ok(())
 }
 
-fn format_duration(duration: chrono::Duration) -> String {
-    let minutes = (duration.num_seconds() / 60) % 60;
-    let hours = (duration.num_seconds() / 3600) % 24;
-    let days = duration.num_seconds() / 86400;
-    if days > 0 {
-        format!("{:0>2}d:{:0>2}h:{:0>2}m", days, hours, minutes)
-    } else if hours > 0 {
-        format!("{:0>2}h:{:0>2}m", hours, minutes)
-    } else {
-        format!("{:0>2}m", minutes)
+// Measurement and tag set of a line protocol line, escaped, without the empty tags which the
+// protocol does not allow
+fn line_series(measurement: &str, tags: &[(&str, &str)]) -> String {
+    let escape = |s: &str, special: &[char]| {
+        s.chars().fold(String::new(), |mut out, c| {
+            if special.contains(&c) {
+                out.push('\\');
+            }
+            out.push(c);
+            out
+        })
+    };
+    let mut series = escape(measurement, &[',', ' ']);
+    for (key, value) in tags.iter().filter(|(k, v)| !k.is_empty() && !v.is_empty()) {
+        series.push(',');
+        series.push_str(&escape(key, &[',', '=', ' ']));
+        series.push('=');
+        series.push_str(&escape(value, &[',', '=', ' ']));
+    }
+    series
+}
+
+// Line protocol timestamp, which only covers the years 1677 to 2262
+fn nanos(timestamp: DateTime<Local>) -> Result<i64, ExportError> {
+    timestamp
+        .timestamp_nanos_opt()
+        .ok_or_else(|| ExportError::Encoding {
+            detail: format!(
+                "{} is out of the line protocol range",
+                timestamp.to_rfc3339()
+            ),
+        })
+}
+
+// Publish the power events to the sink in batches of up to batch_size events, stopping at the
+// first failure. Returns the number of batches published.
+pub fn export_to_sink(
+    sink: &mut dyn Sink,
+    power_events: &[PowerEvent],
+    batch_size: usize,
+) -> Result<usize, ExportError> {
+    let mut batches = 0;
+    for batch in power_events.chunks(batch_size.max(1)) {
+        sink.publish_events(batch)?;
+        batches += 1;
+    }
+    Ok(batches)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::export::{
+        blackouts_to_line_protocol, capabilities, daily_stats_to_csv, export_to_sink, save_report,
+        to_csv, to_line_protocol, try_export, ExportError, ExportFormat,
+    };
+    use crate::report::ReportContext;
+    use crate::sink::{Alert, Sink, SinkError};
+    use crate::voltcraft::data::{PowerEvent, VoltcraftData};
+    use crate::voltcraft::stats::{PowerBlackout, VoltcraftStatistics};
+    use chrono::{DateTime, Duration, Local, TimeZone};
+    use std::io;
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    fn events() -> Vec<PowerEvent> {
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        (0..90)
+            .map(|m| event(start + Duration::minutes(m), 0.46))
+            .collect()
+    }
+
+    #[test]
+    fn export_capabilities() {
+        let caps = capabilities();
+        assert!(caps.text && caps.csv && caps.markdown);
+        let path = std::env::temp_dir().join("voltcraft_try_export.csv");
+        let path = path.to_str().unwrap();
+        try_export(ExportFormat::DailyCsv, path, &events()).unwrap();
+        assert!(std::fs::read_to_string(path).unwrap().starts_with("Date,"));
+        assert!(matches!(
+            try_export(ExportFormat::Statistics, path, &[]),
+            Err(ExportError::EmptyDataSet)
+        ));
+    }
+
+    // Sink taking a limited number of batches
+    struct QuotaSink(usize);
+
+    impl Sink for QuotaSink {
+        fn publish_events(&mut self, _: &[PowerEvent]) -> Result<(), SinkError> {
+            if self.0 == 0 {
+                return Err(SinkError::Rejected {
+                    detail: "quota exceeded".to_string(),
+                });
+            }
+            self.0 -= 1;
+            Ok(())
+        }
+
+        fn publish_alert(&mut self, _: &Alert) -> Result<(), SinkError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn export_error_variants() {
+        // A folder that does not exist
+        let missing = std::env::temp_dir().join("voltcraft-missing-folder/history.csv");
+        assert!(matches!(
+            try_export(
+                ExportFormat::HistoryCsv,
+                missing.to_str().unwrap(),
+                &events()
+            ),
+            Err(ExportError::Io(_))
+        ));
+
+        // A report template with a typo
+        let events = events();
+        let stats = VoltcraftStatistics::new(&events);
+        let ctx = ReportContext::new(
+            &stats.overall_stats().unwrap(),
+            &stats.daily_stats(),
+            &stats.blackout_stats(),
+        );
+        let path = std::env::temp_dir().join("voltcraft_export_error.txt");
+        let result = save_report(path.to_str().unwrap(), "{{total_kwhh}}", &ctx);
+        match result {
+            Err(ExportError::Encoding { detail }) => assert!(detail.contains("total_kwhh")),
+            _ => panic!("encoding error expected"),
+        }
+
+        // A sink that stops accepting power events
+        assert_eq!(export_to_sink(&mut QuotaSink(5), &events, 30).unwrap(), 3);
+        let result = export_to_sink(&mut QuotaSink(2), &events, 30);
+        assert!(matches!(
+            result,
+            Err(ExportError::Sink {
+                source: SinkError::Rejected { .. }
+            })
+        ));
+        let error = result.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "error exporting to the sink: the sink rejected the data: quota exceeded"
+        );
+        assert_eq!(error.exit_code(), 75);
+    }
+
+    #[test]
+    fn export_exit_codes() {
+        let disk_full = io::Error::new(io::ErrorKind::StorageFull, "disk full");
+        let encoding = ExportError::Encoding {
+            detail: "unknown placeholder".to_string(),
+        };
+        let unsupported = ExportError::Unsupported {
+            format: ExportFormat::Bundle,
+        };
+        assert_eq!(ExportError::Io(disk_full).exit_code(), 74);
+        assert_eq!(encoding.exit_code(), 65);
+        assert_eq!(unsupported.exit_code(), 69);
+        assert_eq!(ExportError::EmptyDataSet.exit_code(), 66);
+    }
+
+    #[cfg(not(any(feature = "serde", feature = "bundle", feature = "rayon")))]
+    #[test]
+    fn export_capabilities_default() {
+        let caps = capabilities();
+        assert!(!caps.serde && !caps.bundle && !caps.parallel);
+        let path = std::env::temp_dir().join("voltcraft_try_export_unsupported.bundle");
+        let result = try_export(ExportFormat::Bundle, path.to_str().unwrap(), &events());
+        assert!(matches!(
+            result,
+            Err(ExportError::Unsupported {
+                format: ExportFormat::Bundle
+            })
+        ));
+        assert!(!path.exists());
+    }
+
+    #[cfg(all(feature = "serde", feature = "bundle", feature = "rayon"))]
+    #[test]
+    fn export_capabilities_all_features() {
+        let caps = capabilities();
+        assert!(caps.serde && caps.bundle && caps.parallel);
+        let path = std::env::temp_dir().join("voltcraft_try_export.bundle");
+        try_export(ExportFormat::Bundle, path.to_str().unwrap(), &events()).unwrap();
+        assert!(crate::bundle::load(&path).is_ok());
+    }
+
+    #[test]
+    fn export_to_csv() {
+        // A data block starting 2014-09-11 18:43 with two power records
+        let raw = vec![
+            0xE0, 0xC5, 0xEA, 0x09, 0x0B, 0x0E, 0x12, 0x2B, 0x08, 0xC6, 0x01, 0xBE, 0x57, 0x08,
+            0xFC, 0x05, 0x1A, 0x64, 0xFF, 0xFF, 0xFF, 0xFF,
+        ];
+        let events = VoltcraftData::from_raw(raw).parse().unwrap();
+        let mut csv = Vec::new();
+        to_csv(&events, &mut csv).unwrap();
+        let (first, second) = (events[0].timestamp, events[1].timestamp);
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            format!(
+                "timestamp,voltage,current,power_factor,power_kw,apparent_power_kva\n\
+                 {},224.6,0.446,0.87,0.087149292,0.1001716\n\
+                 {},230,1.306,1,0.30038,0.30038\n",
+                first.to_rfc3339(),
+                second.to_rfc3339()
+            )
+        );
+
+        let days = VoltcraftStatistics::new(&events).daily_stats();
+        let mut csv = Vec::new();
+        daily_stats_to_csv(&days, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            format!(
+                "date,duration_min,energy_kwh,avg_power_kw,max_power_kw,max_power_timestamp,\
+                 apparent_energy_kvah,avg_apparent_power_kva,avg_voltage,min_voltage,max_voltage\n\
+                 2014-09-11,2,0.006458821533333333,0.19376464599999998,0.30038,{},\
+                 0.0066758600000000005,0.2002758,227.3,224.6,230\n",
+                second.to_rfc3339()
+            )
+        );
+    }
+
+    #[test]
+    fn export_to_line_protocol() {
+        // The two power records of export_to_csv
+        let raw = vec![
+            0xE0, 0xC5, 0xEA, 0x09, 0x0B, 0x0E, 0x12, 0x2B, 0x08, 0xC6, 0x01, 0xBE, 0x57, 0x08,
+            0xFC, 0x05, 0x1A, 0x64, 0xFF, 0xFF, 0xFF, 0xFF,
+        ];
+        let events = VoltcraftData::from_raw(raw).parse().unwrap();
+        let mut lines = Vec::new();
+        let tags = [("site", "Home, garage"), ("meter=id", "4000F")];
+        to_line_protocol(&events, "power events", &tags, &mut lines).unwrap();
+        let nanos = events[0].timestamp.timestamp() * 1_000_000_000;
+        assert_eq!(
+            String::from_utf8(lines).unwrap(),
+            format!(
+                "power\\ events,site=Home\\,\\ garage,meter\\=id=4000F \
+                 voltage=224.6,current=0.446,power_factor=0.87,power=0.087149292,\
+                 apparent_power=0.1001716 {}\n\
+                 power\\ events,site=Home\\,\\ garage,meter\\=id=4000F \
+                 voltage=230,current=1.306,power_factor=1,power=0.30038,apparent_power=0.30038 {}\n",
+                nanos,
+                nanos + 60_000_000_000
+            )
+        );
+
+        let blackouts = [PowerBlackout {
+            timestamp: events[1].timestamp,
+            duration: Duration::minutes(90),
+        }];
+        let mut lines = Vec::new();
+        blackouts_to_line_protocol(&blackouts, "blackouts", &[], &mut lines).unwrap();
+        assert_eq!(
+            String::from_utf8(lines).unwrap(),
+            format!(
+                "blackouts duration_seconds=5400i {}\n",
+                nanos + 60_000_000_000
+            )
+        );
+
+        // Without the values that are not finite and the empty tags
+        let broken = [
+            PowerEvent {
+                power_factor: f64::NAN,
+                power: f64::INFINITY,
+                ..events[1]
+            },
+            PowerEvent {
+                voltage: f64::NAN,
+                current: f64::NAN,
+                power_factor: f64::NAN,
+                power: f64::NEG_INFINITY,
+                apparent_power: f64::NAN,
+                ..events[1]
+            },
+        ];
+        let mut lines = Vec::new();
+        let tags = [("site", ""), ("", "garage"), ("meter", "4000F")];
+        to_line_protocol(&broken, "power", &tags, &mut lines).unwrap();
+        assert_eq!(
+            String::from_utf8(lines).unwrap(),
+            format!(
+                "power,meter=4000F voltage=230,current=1.306,apparent_power=0.30038 {}\n",
+                nanos + 60_000_000_000
+            )
+        );
+        let mut lines = Vec::new();
+        blackouts_to_line_protocol(&blackouts, "blackouts", &[("site", "")], &mut lines).unwrap();
+        assert!(String::from_utf8(lines)
+            .unwrap()
+            .starts_with("blackouts duration_seconds=5400i "));
     }
 }
@@ -0,0 +1,333 @@
+// Number formatting shared by the exporters and reports.
+//
+// Values are rounded from their shortest decimal representation (the one `Display` prints), so
+// that arithmetic artifacts such as 0.30000000000000004 never reach an output file and a value
+// that reads 2.675 always becomes 2.68. Ties are rounded half away from zero and the output
+// never uses scientific notation.
+
+use crate::locale::Locale;
+
+/// Unit of `format_power`, `Auto` taking the smallest of W, kW and MW under 1000.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum PowerUnit {
+    #[default]
+    Auto,
+    Watt,
+    Kilowatt,
+    Megawatt,
+}
+
+/// Unit of `format_energy`, `Auto` taking the smallest of Wh, kWh and MWh under 1000.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum EnergyUnit {
+    #[default]
+    Auto,
+    WattHour,
+    KilowattHour,
+    MegawattHour,
+}
+
+// Factor from kW (kWh), decimals and symbol of the units, from the smallest: whole watts, the
+// resolution of the logger, and two decimals above
+const POWER_UNITS: [(f64, usize, &str); 3] = [(1000.0, 0, "W"), (1.0, 2, "kW"), (0.001, 2, "MW")];
+const ENERGY_UNITS: [(f64, usize, &str); 3] =
+    [(1000.0, 0, "Wh"), (1.0, 2, "kWh"), (0.001, 2, "MWh")];
+
+/// Format `value` with exactly `decimals` decimals.
+pub fn format_fixed(value: f64, decimals: usize) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    let repr = value.abs().to_string();
+    let (integer, fraction) = repr.split_once('.').unwrap_or((&repr, ""));
+    let mut digits: Vec<u8> = integer
+        .bytes()
+        .chain(
+            fraction
+                .bytes()
+                .chain(std::iter::repeat(b'0'))
+                .take(decimals),
+        )
+        .collect();
+    if fraction
+        .as_bytes()
+        .get(decimals)
+        .is_some_and(|d| *d >= b'5')
+    {
+        // Propagate the carry, growing the integer part if all digits were nines
+        let mut carry = true;
+        for d in digits.iter_mut().rev() {
+            if *d == b'9' {
+                *d = b'0';
+            } else {
+                *d += 1;
+                carry = false;
+                break;
+            }
+        }
+        if carry {
+            digits.insert(0, b'1');
+        }
+    }
+    let integer_len = digits.len() - decimals;
+    // Values that round to zero are printed without a sign
+    let mut result = String::new();
+    if value < 0.0 && digits.iter().any(|d| *d != b'0') {
+        result.push('-');
+    }
+    result.extend(digits[..integer_len].iter().map(|d| *d as char));
+    if decimals > 0 {
+        result.push('.');
+        result.extend(digits[integer_len..].iter().map(|d| *d as char));
+    }
+    result
+}
+
+/// Round `value` to `decimals` decimals using the same rule as `format_fixed`
+/// (half away from zero on the shortest decimal representation).
+pub fn round_fixed(value: f64, decimals: usize) -> f64 {
+    format_fixed(value, decimals).parse().unwrap_or(value)
+}
+
+/// Format `value` with at most `decimals` decimals, dropping trailing zeros.
+pub fn format_trimmed(value: f64, decimals: usize) -> String {
+    let formatted = format_fixed(value, decimals);
+    if formatted.contains('.') {
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    } else {
+        formatted
+    }
+}
+
+/// Duration as days, hours and minutes, e.g. `02d:03h:05m`, `03h:05m` or `05m`; seconds are
+/// dropped and negative durations are shown as `00m`.
+pub fn humanize_duration(duration: chrono::Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let minutes = (seconds / 60) % 60;
+    let hours = (seconds / 3600) % 24;
+    let days = seconds / 86400;
+    if days > 0 {
+        format!("{:0>2}d:{:0>2}h:{:0>2}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{:0>2}h:{:0>2}m", hours, minutes)
+    } else {
+        format!("{:0>2}m", minutes)
+    }
+}
+
+/// Power given in kW with its unit, e.g. `450 W`, `2.30 kW` or `1.25 MW`.
+pub fn format_power(kw: f64, unit: PowerUnit) -> String {
+    format_power_in(kw, unit, Locale::En)
+}
+
+/// Same as `format_power`, with the decimal separator of the locale (`2,30 kW` in German).
+pub fn format_power_in(kw: f64, unit: PowerUnit, locale: Locale) -> String {
+    let index = match unit {
+        PowerUnit::Auto => None,
+        PowerUnit::Watt => Some(0),
+        PowerUnit::Kilowatt => Some(1),
+        PowerUnit::Megawatt => Some(2),
+    };
+    format_scaled(kw, &POWER_UNITS, index, locale)
+}
+
+/// Energy given in kWh with its unit, e.g. `870 Wh`, `12.40 kWh` or `1.08 MWh`.
+pub fn format_energy(kwh: f64, unit: EnergyUnit) -> String {
+    format_energy_in(kwh, unit, Locale::En)
+}
+
+/// Same as `format_energy`, with the decimal separator of the locale (`12,40 kWh` in German).
+pub fn format_energy_in(kwh: f64, unit: EnergyUnit, locale: Locale) -> String {
+    let index = match unit {
+        EnergyUnit::Auto => None,
+        EnergyUnit::WattHour => Some(0),
+        EnergyUnit::KilowattHour => Some(1),
+        EnergyUnit::MegawattHour => Some(2),
+    };
+    format_scaled(kwh, &ENERGY_UNITS, index, locale)
+}
+
+// Value in the unit at the index, or else in the smallest unit where it rounds below 1000
+fn format_scaled(
+    value: f64,
+    units: &[(f64, usize, &str)],
+    index: Option<usize>,
+    locale: Locale,
+) -> String {
+    let index = index.unwrap_or_else(|| {
+        units
+            .iter()
+            .position(|(factor, decimals, _)| round_fixed(value * factor, *decimals).abs() < 1000.0)
+            .unwrap_or(units.len() - 1)
+    });
+    let (factor, decimals, symbol) = units[index];
+    format!(
+        "{} {}",
+        locale.format_number(value * factor, decimals),
+        symbol
+    )
+}
+
+/// Duration for people, e.g. `2d 3h 14m`, `1h 5m` or `2d`: the units at zero are left out and
+/// the seconds dropped. Durations under a minute are `<1m`, and zero or negative ones `0m`.
+pub fn format_duration(duration: chrono::Duration) -> String {
+    if duration <= chrono::Duration::zero() {
+        return "0m".to_string();
+    }
+    let seconds = duration.num_seconds();
+    if seconds < 60 {
+        return "<1m".to_string();
+    }
+    [
+        (seconds / 86400, 'd'),
+        ((seconds / 3600) % 24, 'h'),
+        ((seconds / 60) % 60, 'm'),
+    ]
+    .iter()
+    .filter(|(n, _)| *n > 0)
+    .map(|(n, unit)| format!("{}{}", n, unit))
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{
+        format_duration, format_energy, format_energy_in, format_fixed, format_power,
+        format_power_in, format_trimmed, humanize_duration, EnergyUnit, PowerUnit,
+    };
+    use crate::locale::Locale;
+
+    // Reference rounding of n / 10^scale to the given decimals, done in integer arithmetic
+    fn reference(n: i64, scale: u32, decimals: u32) -> String {
+        let divisor = 10i64.pow(scale - decimals);
+        let rounded = (n.abs() + divisor / 2) / divisor;
+        let unit = 10i64.pow(decimals);
+        let sign = if n < 0 && rounded != 0 { "-" } else { "" };
+        if decimals == 0 {
+            format!("{}{}", sign, rounded)
+        } else {
+            format!(
+                "{}{}.{:0>width$}",
+                sign,
+                rounded / unit,
+                rounded % unit,
+                width = decimals as usize
+            )
+        }
+    }
+
+    #[test]
+    fn format_fixed_matches_reference() {
+        // Deterministic pseudo-random values, plus every tie at three decimals below 10
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut values: Vec<i64> = (0..20_000)
+            .map(|_| {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (seed >> 24) as i64 % 100_000_000 - 50_000_000
+            })
+            .collect();
+        values.extend((0..10_000).filter(|n| n % 10 == 5));
+        for n in values {
+            for scale in 3..=6 {
+                let value = n as f64 / 10f64.powi(scale as i32);
+                for decimals in 0..scale {
+                    assert_eq!(
+                        format_fixed(value, decimals as usize),
+                        reference(n, scale, decimals),
+                        "{} to {} decimals",
+                        value,
+                        decimals
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn format_fixed_edge_cases() {
+        assert_eq!(format_fixed(0.1 + 0.2, 2), "0.30");
+        assert_eq!(format_fixed(0.1 + 0.2, 20), "0.30000000000000004000");
+        assert_eq!(format_fixed(2.675, 2), "2.68");
+        assert_eq!(format_fixed(-2.5, 0), "-3");
+        assert_eq!(format_fixed(9.995, 2), "10.00");
+        assert_eq!(format_fixed(-0.004, 2), "0.00");
+        assert_eq!(format_fixed(1e-7, 8), "0.00000010");
+        assert_eq!(format_fixed(1e21, 1), "1000000000000000000000.0");
+        assert_eq!(format_fixed(f64::NAN, 2), "NaN");
+        assert_eq!(format_trimmed(0.1 + 0.2, 9), "0.3");
+        assert_eq!(format_trimmed(230.0, 1), "230");
+        assert_eq!(format_trimmed(-0.0001, 3), "0");
+    }
+
+    #[test]
+    fn humanize_duration_units() {
+        use chrono::Duration;
+        assert_eq!(humanize_duration(Duration::seconds(59)), "00m");
+        assert_eq!(humanize_duration(Duration::minutes(5)), "05m");
+        assert_eq!(humanize_duration(Duration::minutes(185)), "03h:05m");
+        assert_eq!(
+            humanize_duration(Duration::days(2) + Duration::minutes(60)),
+            "02d:01h:00m"
+        );
+        assert_eq!(humanize_duration(Duration::days(123)), "123d:00h:00m");
+        assert_eq!(humanize_duration(Duration::minutes(-5)), "00m");
+    }
+
+    #[test]
+    fn format_units() {
+        assert_eq!(format_power(0.45, PowerUnit::Auto), "450 W");
+        assert_eq!(format_power(2.3, PowerUnit::Auto), "2.30 kW");
+        assert_eq!(format_power(1250.0, PowerUnit::Auto), "1.25 MW");
+        assert_eq!(format_power(0.0, PowerUnit::Auto), "0 W");
+        assert_eq!(format_power(-0.087, PowerUnit::Auto), "-87 W");
+        // Rounding to 1000 moves to the next unit
+        assert_eq!(format_power(0.9996, PowerUnit::Auto), "1.00 kW");
+        assert_eq!(format_power(999.999, PowerUnit::Auto), "1.00 MW");
+        assert_eq!(format_power(0.45, PowerUnit::Kilowatt), "0.45 kW");
+        assert_eq!(format_power(2.3, PowerUnit::Watt), "2300 W");
+        assert_eq!(format_power(2.3, PowerUnit::Megawatt), "0.00 MW");
+        assert_eq!(format_power_in(2.3, PowerUnit::Auto, Locale::De), "2,30 kW");
+        assert_eq!(format_power_in(2.3, PowerUnit::Watt, Locale::De), "2.300 W");
+
+        assert_eq!(format_energy(0.87, EnergyUnit::Auto), "870 Wh");
+        assert_eq!(format_energy(12.4, EnergyUnit::Auto), "12.40 kWh");
+        assert_eq!(format_energy(1078.93, EnergyUnit::Auto), "1.08 MWh");
+        assert_eq!(
+            format_energy(1078.93, EnergyUnit::KilowattHour),
+            "1078.93 kWh"
+        );
+        assert_eq!(format_energy(0.0, EnergyUnit::MegawattHour), "0.00 MWh");
+        assert_eq!(
+            format_energy_in(12.4, EnergyUnit::Auto, Locale::De),
+            "12,40 kWh"
+        );
+        assert_eq!(format_energy(0.0125, EnergyUnit::WattHour), "13 Wh");
+    }
+
+    #[test]
+    fn format_duration_units() {
+        use chrono::Duration;
+        let duration = Duration::days(2) + Duration::hours(3) + Duration::minutes(14);
+        assert_eq!(format_duration(duration), "2d 3h 14m");
+        assert_eq!(format_duration(Duration::minutes(83)), "1h 23m");
+        assert_eq!(
+            format_duration(Duration::days(2) + Duration::minutes(5)),
+            "2d 5m"
+        );
+        assert_eq!(format_duration(Duration::days(1)), "1d");
+        assert_eq!(format_duration(Duration::seconds(119)), "1m");
+        // Under a minute is not nothing, zero and negative durations are
+        assert_eq!(format_duration(Duration::seconds(59)), "<1m");
+        assert_eq!(format_duration(Duration::milliseconds(1)), "<1m");
+        assert_eq!(format_duration(Duration::zero()), "0m");
+        assert_eq!(format_duration(Duration::minutes(-5)), "0m");
+        assert_eq!(format_duration(Duration::MIN), "0m");
+        assert_eq!(format_duration(Duration::days(400)), "400d");
+    }
+}
@@ -0,0 +1,251 @@
+//! Import of power data exported by other tools.
+
+use crate::voltcraft::data::PowerEvent;
+use chrono::{Local, NaiveDateTime, TimeZone};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ImportError {
+    Io(io::Error),
+    Csv(csv::Error),
+    UnknownDialect {
+        headers: Vec<String>, // fields of the first line, to show what was found
+    },
+    InvalidValue {
+        line: usize, // line number in the file (starting at 1)
+        column: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "error reading the CSV file: {}", e),
+            ImportError::Csv(e) => write!(f, "invalid CSV file: {}", e),
+            ImportError::UnknownDialect { headers } => write!(
+                f,
+                "unknown CSV format, expected the columns of the Voltcraft software export \
+                 (Datum, Uhrzeit, Spannung, Strom, cos phi) but found: {}",
+                headers.join(", ")
+            ),
+            ImportError::InvalidValue {
+                line,
+                column,
+                value,
+            } => write!(f, "invalid {} '{}' on line {}", column, value, line),
+        }
+    }
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImportError::Io(e) => Some(e),
+            ImportError::Csv(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+impl From<csv::Error> for ImportError {
+    fn from(e: csv::Error) -> Self {
+        ImportError::Csv(e)
+    }
+}
+
+// Lines searched for the header, after the preamble the vendor software writes
+const MAX_HEADER_LINE: usize = 10;
+
+// Position of each needed column in the vendor export
+struct Columns {
+    date: usize,
+    time: Option<usize>, // None when the date column also holds the time
+    voltage: usize,
+    current: usize,
+    power_factor: usize,
+}
+
+impl Columns {
+    // Recognize the header of the vendor export, e.g. "Datum;Uhrzeit;Spannung [V];Strom [A];cos phi"
+    fn sniff(fields: &[String]) -> Option<Columns> {
+        // Lowercase letters only, so that units and punctuation do not matter
+        let names = fields
+            .iter()
+            .map(|f| {
+                f.chars()
+                    .filter(|c| c.is_alphabetic())
+                    .collect::<String>()
+                    .to_lowercase()
+            })
+            .collect::<Vec<_>>();
+        let find = |matches: &dyn Fn(&str) -> bool| names.iter().position(|n| matches(n));
+        let datetime = find(&|n| n == "datumuhrzeit" || n == "zeitstempel");
+        let date = datetime.or_else(|| find(&|n| n == "datum"))?;
+        let time = match datetime {
+            Some(_) => None,
+            None => Some(find(&|n| n == "uhrzeit" || n == "zeit")?),
+        };
+        Some(Columns {
+            date,
+            time,
+            voltage: find(&|n| n.starts_with("spannung"))?,
+            current: find(&|n| n.starts_with("strom"))?,
+            power_factor: find(&|n| n == "cosphi" || n.starts_with("leistungsfaktor"))?,
+        })
+    }
+}
+
+/// Import the CSV export of the Voltcraft Windows software: semicolon-separated, German headers,
+/// `DD.MM.YYYY` dates and decimal commas, possibly preceded by a few preamble lines.
+/// The active and apparent power are recomputed from the voltage, current and power factor.
+pub fn import_vendor_csv<R: Read>(mut r: R) -> Result<Vec<PowerEvent>, ImportError> {
+    let mut raw = Vec::new();
+    r.read_to_end(&mut raw)?;
+    // The vendor software may write Windows-1252 text; only the ASCII column names matter here
+    let text = String::from_utf8_lossy(&raw);
+    let text = text.trim_start_matches('\u{feff}');
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let mut records = rdr.records();
+    let mut columns = None;
+    for record in records.by_ref().take(MAX_HEADER_LINE) {
+        let fields = record?.iter().map(str::to_string).collect::<Vec<_>>();
+        columns = Columns::sniff(&fields);
+        if columns.is_some() {
+            break;
+        }
+    }
+    let columns = columns.ok_or_else(|| ImportError::UnknownDialect {
+        headers: first_line_fields(text),
+    })?;
+
+    let mut events = Vec::new();
+    for record in records {
+        let record = record?;
+        let line = record.position().map_or(0, |p| p.line() as usize);
+        let field = |index: usize| record.get(index).unwrap_or("").trim();
+        if record.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        let invalid = |column: &str, value: String| ImportError::InvalidValue {
+            line,
+            column: column.to_string(),
+            value,
+        };
+        let timestamp = match columns.time {
+            Some(time) => format!("{} {}", field(columns.date), field(time)),
+            None => field(columns.date).to_string(),
+        };
+        let timestamp = parse_timestamp(&timestamp).ok_or_else(|| invalid("date", timestamp))?;
+        let number = |column: &str, index: usize| {
+            parse_number(field(index)).ok_or_else(|| invalid(column, field(index).to_string()))
+        };
+        let event = PowerEvent {
+            timestamp,
+            voltage: number("voltage", columns.voltage)?,
+            current: number("current", columns.current)?,
+            power_factor: number("power factor", columns.power_factor)?,
+            power: 0.0,
+            apparent_power: 0.0,
+            frequency: None,
+        };
+        events.push(event.recomputed());
+    }
+    Ok(events)
+}
+
+// Fields of the first non-empty line, split on the most frequent usual delimiter
+fn first_line_fields(text: &str) -> Vec<String> {
+    let line = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    let delimiter = [';', ',', '\t']
+        .into_iter()
+        .max_by_key(|d| line.matches(*d).count())
+        .unwrap();
+    line.split(delimiter)
+        .map(|f| f.trim().to_string())
+        .collect()
+}
+
+// DD.MM.YYYY HH:MM, with or without seconds, in local time
+fn parse_timestamp(s: &str) -> Option<chrono::DateTime<Local>> {
+    let naive = NaiveDateTime::parse_from_str(s, "%d.%m.%Y %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%d.%m.%Y %H:%M"))
+        .ok()?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+// Decimal comma, with optional dots as thousands separators
+fn parse_number(s: &str) -> Option<f64> {
+    if s.contains(',') {
+        s.replace('.', "").replace(',', ".").parse().ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::import::{import_vendor_csv, ImportError};
+    use chrono::{Local, TimeZone};
+
+    const VENDOR_EXPORT: &str = include_str!("../tests/fixtures/vendor_export.csv");
+
+    #[test]
+    fn import_vendor_export() {
+        let events = import_vendor_csv(VENDOR_EXPORT.as_bytes()).unwrap();
+        assert_eq!(events.len(), 12);
+        let first = events[0];
+        assert_eq!(
+            first.timestamp,
+            Local.with_ymd_and_hms(2014, 11, 3, 22, 58, 0).unwrap()
+        );
+        assert_eq!(first.voltage, 229.5);
+        assert_eq!(first.current, 0.418);
+        assert_eq!(first.power_factor, 0.57);
+        assert_eq!(first.power, first.derived_power());
+        // The vendor rounds the power to 0.1 W
+        assert!((first.power * 1000.0 - 54.7).abs() < 0.05);
+        assert!((events[6].apparent_power * 1000.0 - 1490.6).abs() < 0.05);
+        assert!(events
+            .windows(2)
+            .all(|w| w[1].timestamp - w[0].timestamp == chrono::Duration::minutes(1)));
+    }
+
+    #[test]
+    fn import_unknown_dialect() {
+        let csv = "Timestamp,Voltage (V),Current (A)\n2014-11-03 22:58,229.5,0.418\n";
+        let e = import_vendor_csv(csv.as_bytes()).unwrap_err();
+        assert!(matches!(e, ImportError::UnknownDialect { .. }));
+        assert!(e
+            .to_string()
+            .ends_with("found: Timestamp, Voltage (V), Current (A)"));
+
+        let csv =
+            "Datum;Uhrzeit;Spannung [V];Strom [A];cos phi\n03.11.2014;22:58;229,5;0,4x;0,57\n";
+        match import_vendor_csv(csv.as_bytes()).unwrap_err() {
+            ImportError::InvalidValue {
+                line,
+                column,
+                value,
+            } => assert_eq!(
+                (line, column.as_str(), value.as_str()),
+                (2, "current", "0,4x")
+            ),
+            e => panic!("unexpected error {}", e),
+        }
+    }
+}
@@ -0,0 +1,14 @@
+#[cfg(feature = "bundle")]
+pub mod bundle;
+pub mod export;
+pub mod format;
+pub mod import;
+pub mod locale;
+pub mod prelude;
+pub mod report;
+pub mod sink;
+pub mod summary;
+pub mod voltcraft;
+
+// Re-exported so that downstream crates use the same chrono version as the public API
+pub use chrono;
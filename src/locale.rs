@@ -0,0 +1,206 @@
+use crate::format::format_fixed;
+use chrono::Weekday;
+
+/// Language and number/date conventions used when rendering reports.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+}
+
+// Label keys shared by all locales, in the same order in every table
+const EN_LABELS: [(&str, &str); 41] = [
+    ("heading_overall", "OVERALL STATISTICS"),
+    ("heading_daily", "DAILY STATISTICS"),
+    ("heading_blackouts", "BLACKOUT HISTORY"),
+    ("heading_active_power", "ACTIVE POWER"),
+    ("heading_apparent_power", "APPARENT POWER"),
+    ("heading_voltage", "VOLTAGE"),
+    ("power_statistics", "Power statistics"),
+    ("overall", "Overall"),
+    ("daily_statistics", "Daily statistics"),
+    ("blackouts", "Blackouts"),
+    ("interval", "Interval"),
+    ("average_consumption", "Average consumption"),
+    ("projected", "Projected"),
+    ("day", "day"),
+    ("month", "month"),
+    ("year", "year"),
+    ("or", "or"),
+    ("total_energy_consumption", "Total energy consumption"),
+    ("peak_power_was", "Peak power was"),
+    ("occurred_on", "and occured on"),
+    ("average_power", "Minute by minute average power"),
+    ("min_voltage_was", "Minimum voltage was"),
+    ("max_voltage_was", "Maximum voltage was"),
+    ("average_voltage", "Minute by minute average voltage"),
+    ("recorded_activity", "recorded activity"),
+    ("total_active_power", "Total active power"),
+    ("total_apparent_power", "Total apparent power"),
+    ("average", "Average"),
+    ("minimum", "Minimum"),
+    ("maximum", "Maximum"),
+    ("on", "on"),
+    ("voltage", "Voltage"),
+    ("blackouts_for_a_total_of", "blackout(s) for a total of"),
+    ("duration", "Duration"),
+    ("quantity", "Quantity"),
+    ("total", "Total"),
+    ("peak", "Peak"),
+    ("active_power", "Active power"),
+    ("apparent_power", "Apparent power"),
+    ("date", "Date"),
+    ("energy", "Energy"),
+];
+
+const DE_LABELS: [(&str, &str); 41] = [
+    ("heading_overall", "GESAMTSTATISTIK"),
+    ("heading_daily", "TAGESSTATISTIK"),
+    ("heading_blackouts", "STROMAUSFÄLLE"),
+    ("heading_active_power", "WIRKLEISTUNG"),
+    ("heading_apparent_power", "SCHEINLEISTUNG"),
+    ("heading_voltage", "SPANNUNG"),
+    ("power_statistics", "Verbrauchsstatistik"),
+    ("overall", "Gesamt"),
+    ("daily_statistics", "Tagesstatistik"),
+    ("blackouts", "Stromausfälle"),
+    ("interval", "Zeitraum"),
+    ("average_consumption", "Durchschnittlicher Verbrauch"),
+    ("projected", "Hochrechnung"),
+    ("day", "Tag"),
+    ("month", "Monat"),
+    ("year", "Jahr"),
+    ("or", "oder"),
+    ("total_energy_consumption", "Gesamtverbrauch"),
+    ("peak_power_was", "Die Spitzenleistung betrug"),
+    ("occurred_on", "am"),
+    ("average_power", "Durchschnittliche Leistung (minütlich)"),
+    ("min_voltage_was", "Die niedrigste Spannung betrug"),
+    ("max_voltage_was", "Die höchste Spannung betrug"),
+    ("average_voltage", "Durchschnittliche Spannung (minütlich)"),
+    ("recorded_activity", "aufgezeichnet"),
+    ("total_active_power", "Wirkleistung gesamt"),
+    ("total_apparent_power", "Scheinleistung gesamt"),
+    ("average", "Durchschnitt"),
+    ("minimum", "Minimum"),
+    ("maximum", "Maximum"),
+    ("on", "am"),
+    ("voltage", "Spannung"),
+    (
+        "blackouts_for_a_total_of",
+        "Stromausfall/-ausfälle mit einer Gesamtdauer von",
+    ),
+    ("duration", "Dauer"),
+    ("quantity", "Größe"),
+    ("total", "Gesamt"),
+    ("peak", "Spitze"),
+    ("active_power", "Wirkleistung"),
+    ("apparent_power", "Scheinleistung"),
+    ("date", "Datum"),
+    ("energy", "Energie"),
+];
+
+impl Locale {
+    // Label texts, keyed by label name
+    pub fn labels(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::En => &EN_LABELS,
+            Locale::De => &DE_LABELS,
+        }
+    }
+
+    pub fn label(&self, key: &str) -> Option<&'static str> {
+        self.labels()
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, text)| *text)
+    }
+
+    pub fn decimal_separator(&self) -> char {
+        match self {
+            Locale::En => '.',
+            Locale::De => ',',
+        }
+    }
+
+    // English reports keep ungrouped numbers, as they always had
+    pub fn thousands_separator(&self) -> Option<char> {
+        match self {
+            Locale::En => None,
+            Locale::De => Some('.'),
+        }
+    }
+
+    // chrono format string for dates
+    pub fn date_format(&self) -> &'static str {
+        match self {
+            Locale::En => "%Y-%m-%d",
+            Locale::De => "%d.%m.%Y",
+        }
+    }
+
+    // chrono format string for timestamps
+    pub fn datetime_format(&self) -> &'static str {
+        match self {
+            Locale::En => "%Y-%m-%d %H:%M",
+            Locale::De => "%d.%m.%Y %H:%M",
+        }
+    }
+
+    pub fn weekday_abbreviation(&self, weekday: Weekday) -> &'static str {
+        let index = weekday.num_days_from_monday() as usize;
+        match self {
+            Locale::En => ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"][index],
+            Locale::De => ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"][index],
+        }
+    }
+
+    // Format a number with the given number of decimals using the locale separators
+    pub fn format_number(&self, value: f64, decimals: usize) -> String {
+        let formatted = format_fixed(value, decimals);
+        let (sign, digits) = match formatted.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", formatted.as_str()),
+        };
+        let (integer, fraction) = match digits.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (digits, None),
+        };
+        let mut result = String::from(sign);
+        for (i, c) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                if let Some(separator) = self.thousands_separator() {
+                    result.push(separator);
+                }
+            }
+            result.push(c);
+        }
+        if let Some(fraction) = fraction {
+            result.push(self.decimal_separator());
+            result.push_str(fraction);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::locale::Locale;
+
+    #[test]
+    fn locale_labels_complete() {
+        let en = Locale::En.labels().iter().map(|(k, _)| *k);
+        let de = Locale::De.labels().iter().map(|(k, _)| *k);
+        assert!(en.eq(de));
+    }
+
+    #[test]
+    fn locale_numbers() {
+        assert_eq!(Locale::En.format_number(1234567.891, 2), "1234567.89");
+        assert_eq!(Locale::De.format_number(1234567.891, 2), "1.234.567,89");
+        assert_eq!(Locale::De.format_number(-1234.5, 1), "-1.234,5");
+        assert_eq!(Locale::De.format_number(999.0, 0), "999");
+        assert_eq!(Locale::De.format_number(0.125, 3), "0,125");
+    }
+}
@@ -1,25 +1,83 @@
-mod export;
-mod voltcraft;
-
 use colored::*;
 use glob::glob;
 use std::env;
 use std::fs;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
-use voltcraft::data::{PowerEvent, VoltcraftData};
-use voltcraft::stats::VoltcraftStatistics;
+use voltcraft_energy_analyzer::summary::QuickSummary;
+use voltcraft_energy_analyzer::voltcraft::data::{OverlapResolution, PowerEvent, VoltcraftData};
+use voltcraft_energy_analyzer::voltcraft::diagnostics;
+use voltcraft_energy_analyzer::voltcraft::error::VoltcraftError;
+use voltcraft_energy_analyzer::voltcraft::stats::VoltcraftStatistics;
 
-use export::{save_parameter_history_csv, save_parameter_history_txt, save_statistics};
+use voltcraft_energy_analyzer::export::{
+    save_parameter_history_csv, save_parameter_history_txt, save_statistics, ExportError,
+};
 
 const PARAMETER_HISTORY_FILE_TEXT: &str = "voltcraft_history.txt";
 const PARAMETER_HISTORY_FILE_CSV: &str = "voltcraft_history.csv";
 const STATS_FILE_TEXT: &str = "voltcraft_stats.txt";
+const JSON_SUMMARY_FLAG: &str = "--json-summary";
+const DEBUG_COMMAND: &str = "debug";
+const VERBOSE_FLAG: &str = "--verbose";
+
+// With --json-summary, stdout only carries the JSON summary and progress goes to stderr
+static JSON_SUMMARY: AtomicBool = AtomicBool::new(false);
+
+macro_rules! out {
+    ($($arg:tt)*) => {
+        if JSON_SUMMARY.load(Ordering::Relaxed) {
+            eprint!($($arg)*);
+        } else {
+            print!($($arg)*);
+        }
+    };
+}
+
+macro_rules! outln {
+    ($($arg:tt)*) => {
+        if JSON_SUMMARY.load(Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
 
 fn main() {
+    // Process command-line arguments
+    let mut args: Vec<String> = env::args().collect();
+    if let Some(i) = args.iter().position(|a| a == JSON_SUMMARY_FLAG) {
+        JSON_SUMMARY.store(true, Ordering::Relaxed);
+        args.remove(i);
+    }
+    // With --verbose, files that cannot be decoded come with an explanation of what to do
+    let verbose = match args.iter().position(|a| a == VERBOSE_FLAG) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    // Diagnostic dump of a single data file, printed alone so that it can be pasted as is
+    // (unless the command is the name of an input folder)
+    if args.len() == 3 && args[1] == DEBUG_COMMAND && !Path::new(DEBUG_COMMAND).is_dir() {
+        match VoltcraftData::from_file(&args[2]) {
+            Ok(vdf) => print!("{}", diagnostics::dump(&vdf)),
+            Err(e) => {
+                eprintln!("{} {}", e.to_string().red(), args[2].bright_red());
+                process::exit(match e {
+                    VoltcraftError::Io(_) => 66, // EX_NOINPUT
+                    _ => 65,                     // EX_DATAERR
+                });
+            }
+        }
+        return;
+    }
     // Print welcome text
     display_welcome();
-    // Process command-line arguments
-    let args: Vec<String> = env::args().collect();
 
     let (mut input_dir, mut output_dir) = {
         if args.len() == 3 {
@@ -43,7 +101,7 @@ fn main() {
 
     // Create output folder
     if fs::create_dir_all(&output_dir).is_err() {
-        println!(
+        outln!(
             "{} {}",
             "Failed to create folder".red(),
             output_dir.bright_red()
@@ -59,11 +117,11 @@ fn main() {
         output_dir.push('/');
     }
 
-    println!(
+    outln!(
         "Reading data files from folder '{}'.",
         input_dir.bright_white()
     );
-    println!(
+    outln!(
         "Writing statistics to folder '{}'.",
         output_dir.bright_white()
     );
@@ -79,90 +137,126 @@ fn main() {
     let mut file_count = 0;
     for e in glob(input_dir.as_str()).unwrap().filter_map(Result::ok) {
         let file = e.display().to_string();
-        print!("Processing file: {}...", file);
+        out!("Processing file: {}...", file);
         // Open the file
-        if let Ok(vdf) = VoltcraftData::from_file(&file) {
+        match VoltcraftData::from_file(&file) {
             // Parse data
-            if let Ok(mut pev) = vdf.parse() {
-                power_events.append(&mut pev);
-                file_count += 1;
-                println!(" {}", "Ok".green());
-            } else {
-                println!(" {}", "Invalid".red());
+            Ok(vdf) => match vdf.parse_sessions_with(|_| OverlapResolution::KeepBoth) {
+                Ok((sessions, summary)) => {
+                    for mut session in sessions {
+                        power_events.append(&mut session.events);
+                    }
+                    file_count += 1;
+                    outln!(" {}", "Ok".green());
+                    // Let the user know about sessions recorded after a clock reset
+                    for overlap in &summary.overlaps {
+                        outln!("  {}", overlap.to_string().yellow());
+                    }
+                }
+                Err(e) => {
+                    outln!(" {}", "Invalid".red());
+                    if verbose {
+                        outln!("  {}", e.explain().yellow());
+                    }
+                }
+            },
+            Err(e) => {
+                outln!(" {}", "Failed to open".red());
+                if verbose {
+                    outln!("  {}", e.explain().yellow());
+                }
             }
-        } else {
-            println!(" {}", "Failed to open".red());
         }
     }
 
     // Process power events accrued from the parsed data files
+    let mut exit_code = 0; // of the first failed export
     if !power_events.is_empty() {
         // Chronologically sort power items (we need this to spot power blackouts)
-        print!("Sorting power data...");
-        power_events.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
-        println!(" {}", "Done".green());
+        out!("Sorting power data...");
+        power_events.sort_by_key(|a| a.timestamp);
+        outln!(" {}", "Done".green());
         // Remove duplicate events based on timestamp
-        print!("Removing duplicates from power data...");
+        out!("Removing duplicates from power data...");
         power_events.dedup_by(|a, b| a.timestamp == b.timestamp);
-        println!(" {}", "Done".green());
+        outln!(" {}", "Done".green());
         // Write power events to text file
         let mut target_path = output_dir.clone();
         target_path.push_str(PARAMETER_HISTORY_FILE_TEXT);
-        print!(
+        out!(
             "Saving parameter history to text file {}...",
             PARAMETER_HISTORY_FILE_TEXT.bright_white()
         );
-        if save_parameter_history_txt(target_path.as_str(), &power_events).is_ok() {
-            println!(" {}", "Ok".green());
-        } else {
-            println!(" {}", "Failed".red());
-        }
+        check_export(
+            save_parameter_history_txt(target_path.as_str(), &power_events),
+            &mut exit_code,
+        );
         // Write power events to CSV file
         let mut target_path = output_dir.clone();
         target_path.push_str(PARAMETER_HISTORY_FILE_CSV);
-        print!(
+        out!(
             "Saving parameter history to CSV file {}...",
             PARAMETER_HISTORY_FILE_CSV.bright_white()
         );
-        if save_parameter_history_csv(target_path.as_str(), &power_events).is_ok() {
-            println!(" {}", "Ok".green());
-        } else {
-            println!(" {}", "Failed".red());
-        }
+        check_export(
+            save_parameter_history_csv(target_path.as_str(), &power_events),
+            &mut exit_code,
+        );
         // Compute statistics
         let mut target_path = output_dir.clone();
         target_path.push_str(STATS_FILE_TEXT);
-        let stats = VoltcraftStatistics::new(&mut power_events);
-        print!(
+        let stats = VoltcraftStatistics::new(&power_events);
+        out!(
             "Saving statistics to file {}...",
             STATS_FILE_TEXT.bright_white()
         );
-        if save_statistics(
-            target_path.as_str(),
-            &stats.overall_stats(),
-            &stats.daily_stats(),
-            &stats.blackout_stats(),
-        )
-        .is_ok()
-        {
-            println!(" {}", "Ok".green());
-        } else {
-            println!(" {}", "Failed".red());
-        }
+        let saved = stats
+            .overall_stats()
+            .ok_or(ExportError::EmptyDataSet)
+            .and_then(|overall| {
+                save_statistics(
+                    target_path.as_str(),
+                    &overall,
+                    &stats.daily_stats(),
+                    &stats.blackout_stats(),
+                )
+            });
+        check_export(saved, &mut exit_code);
     } else {
-        println!("{}", "No valid Voltcraft data files found.".yellow());
+        outln!("{}", "No valid Voltcraft data files found.".yellow());
     }
 
     let duration = start_time.elapsed();
 
     if file_count > 0 {
-        println!("Processed {} files in {:?}.", file_count, duration);
+        outln!("Processed {} files in {:?}.", file_count, duration);
+    }
+    outln!("{}", "Finished.".green());
+
+    if JSON_SUMMARY.load(Ordering::Relaxed) {
+        println!("{}", QuickSummary::new(&power_events, file_count).to_json());
+    }
+    if exit_code != 0 {
+        process::exit(exit_code);
+    }
+}
+
+// Report the outcome of an export, keeping the exit code of the first failure
+fn check_export(result: Result<(), ExportError>, exit_code: &mut i32) {
+    match result {
+        Ok(()) => outln!(" {}", "Ok".green()),
+        Err(e) => {
+            outln!(" {}", "Failed".red());
+            outln!("  {}", e.to_string().red());
+            if *exit_code == 0 {
+                *exit_code = e.exit_code();
+            }
+        }
     }
-    println!("{}", "Finished.".green());
 }
 
 fn display_welcome() {
-    println!(
+    outln!(
         "{} - {} {}\n{} | {}",
         "Analyzer for Voltcraft Energy Logger 4000"
             .bright_white()
@@ -172,7 +266,7 @@ fn display_welcome() {
         "Valer BOCAN, PhD, CSSLP".green(),
         "https://github.com/vbocan/voltcraft-energy-analyzer".blue()
     );
-    println!(
+    outln!(
         "Type {} | {} | {} to get help.\n",
         "/?".yellow(),
         "-h".yellow(),
@@ -181,12 +275,25 @@ fn display_welcome() {
 }
 
 fn display_help() {
-    println!("{} <input folder> <output folder>\n\t- Decode Voltcraft files from a folder and output statistics in different folder.",
+    outln!("{} <input folder> <output folder>\n\t- Decode Voltcraft files from a folder and output statistics in different folder.",
         "voltcraft_energy_analyzer".bright_white());
-    println!("{} <input folder>\n\t- Decode Voltcraft files from a folder and output statistics in the current folder.",
+    outln!("{} <input folder>\n\t- Decode Voltcraft files from a folder and output statistics in the current folder.",
         "voltcraft_energy_analyzer".bright_white());
-    println!(
+    outln!(
         "{}\n\t- Decode Voltcraft files from and place the statistics in the current folder.\n",
         "voltcraft_energy_analyzer".bright_white()
     );
+    outln!(
+        "Add {} to any of the above to print a JSON summary (total_kwh, peak_kw, blackout_count, ...) as the only output on stdout.\n",
+        JSON_SUMMARY_FLAG.yellow()
+    );
+    outln!(
+        "Add {} to explain why a data file could not be decoded and what to do about it.\n",
+        VERBOSE_FLAG.yellow()
+    );
+    outln!(
+        "{} {} <file>\n\t- Print a short diagnostic dump of a data file (header, first and last records, problems found) to paste into a bug report.\n",
+        "voltcraft_energy_analyzer".bright_white(),
+        DEBUG_COMMAND
+    );
 }
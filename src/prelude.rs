@@ -0,0 +1,46 @@
+//! Commonly used types, importable in one go.
+//!
+//! ```
+//! use voltcraft_energy_analyzer::prelude::*;
+//!
+//! // One data block starting 2014-09-11 18:43 with a single power record
+//! let raw = vec![
+//!     0xE0, 0xC5, 0xEA, 0x09, 0x0B, 0x0E, 0x12, 0x2B, 0x08, 0xC6, 0x01, 0xBE, 0x57, 0xFF, 0xFF,
+//!     0xFF, 0xFF,
+//! ];
+//! let events: Events = VoltcraftData::from_raw(raw).parse().unwrap();
+//! let stats = VoltcraftStatistics::new(&events);
+//! let overall = stats.overall_stats().unwrap();
+//! assert_eq!(overall.start.naive_local().date(), NaiveDate::from_ymd_opt(2014, 9, 11).unwrap());
+//! assert_eq!(overall.stats.max_voltage.voltage, 224.6);
+//! let days: Vec<PowerInterval> = stats.daily_stats();
+//! assert_eq!(days.len(), 1);
+//! ```
+
+pub use crate::voltcraft::annotations::{Annotation, Annotations};
+pub use crate::voltcraft::bucket::{bucket_of, Granularity};
+pub use crate::voltcraft::clean::{
+    apply_pf_zero_policy, clean, clean_outliers, flag_events, EventFlags, OutlierStrategy,
+    PfZeroPolicy, ValidationRules,
+};
+pub use crate::voltcraft::compare::{compare_stats, Change, StatsComparison};
+pub use crate::voltcraft::cumulative::{
+    cumulative_energy, daily_cumulative_energy, CumulativeEnergy,
+};
+pub use crate::voltcraft::data::{
+    decode_power_record, same_reading, Events, FormatVersion, ParseOptions, ParseOutcome,
+    PowerEvent, PowerLog, PowerReading, PowerSession, Termination, VoltcraftData, VoltcraftDataRef,
+};
+pub use crate::voltcraft::dataset::Dataset;
+pub use crate::voltcraft::distribution::{histogram, percentiles, Metric};
+pub use crate::voltcraft::incremental::IncrementalStats;
+pub use crate::voltcraft::info::VoltcraftInfo;
+pub use crate::voltcraft::resample::{resample, Aggregation};
+pub use crate::voltcraft::sorted::{normalize, validate_sequence, SequenceIssue, SortedEvents};
+pub use crate::voltcraft::stats::{
+    ActiveStats, ActivityPeriod, AverageBasis, BaselineStats, BlackoutInfo, BlackoutOptions,
+    BlackoutSummary, BoundaryDays, CoverageInfo, HourProfile, NumericMode, OverallPowerInfo,
+    PowerBlackout, PowerFactorWindow, PowerHourInterval, PowerInterval, PowerStats,
+    PrecisionPolicy, VoltcraftStatistics,
+};
+pub use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Timelike};
@@ -0,0 +1,1213 @@
+use crate::export::{
+    save_blackouts_csv, save_daily_stats_csv, save_parameter_history_csv, ExportError,
+};
+use crate::format::{format_duration, format_energy_in, format_power_in, EnergyUnit, PowerUnit};
+use crate::locale::Locale;
+use crate::voltcraft::annotations::Annotation;
+use crate::voltcraft::bucket::{bucket_of, first_instant, Granularity};
+use crate::voltcraft::data::PowerEvent;
+use crate::voltcraft::dataset::Dataset;
+use crate::voltcraft::stats::{
+    BlackoutInfo, OverallPowerInfo, PowerBlackout, PowerInterval, PowerStats, PrecisionPolicy,
+    VoltcraftStatistics,
+};
+use chrono::{DateTime, Datelike, Duration, IsoWeek, Local, NaiveDate, NaiveTime, Weekday};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Default templates, rendered through the same engine as user-supplied ones
+pub const TEXT_TEMPLATE: &str = include_str!("templates/statistics.txt");
+pub const MARKDOWN_TEMPLATE: &str = include_str!("templates/statistics.md");
+
+type Values = BTreeMap<String, String>;
+
+/// Built-in report layouts of `render_report`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,     // TEXT_TEMPLATE, aligned for a terminal or a plain-text email
+    Markdown, // MARKDOWN_TEMPLATE, GitHub-flavored Markdown tables
+}
+
+#[derive(Debug, Clone, Default)]
+struct Section {
+    fields: BTreeSet<String>, // placeholders available to each item
+    items: Vec<Values>,
+}
+
+/// Values available to report templates.
+///
+/// Placeholders are written as `{{name}}`; a section is repeated once per item with
+/// `{{#each name}}...{{/each}}`. Inside a section, the item placeholders take
+/// precedence over the top-level ones.
+///
+/// Top-level placeholders:
+/// - `start`, `end`, `duration`: recording interval and its length (see `format_duration`)
+/// - `total_kwh`, `avg_kw`, `peak_kw`, `peak_kw_time`: active power
+/// - `total_energy`, `avg_power`, `peak_power`: active energy and power with the unit that
+///   suits their magnitude, e.g. `870 Wh` or `1.08 MWh` (see `format::format_energy`)
+/// - `total_kvah`, `avg_kva`, `peak_kva`, `peak_kva_time`: apparent power
+/// - `avg_voltage`, `min_voltage`, `min_voltage_time`, `max_voltage`, `max_voltage_time`: voltage
+/// - `blackout_count`, `blackout_duration`: blackout totals
+///
+/// Sections:
+/// - `daily_average`: a single item with `kwh_per_day`, `kwh_per_month` and `kwh_per_year`
+///   when more than a day of data is available, no item otherwise
+/// - `days`: one item per day with `date`, `duration` (recorded activity), `coverage` (percent),
+///   `notes` (footnote references such as ` [1]`, empty for days without annotations)
+///   and the same power and voltage placeholders as above, computed for that day
+/// - `notes`: one item per annotation found on the days, with `number`, `label`, `start` and `end`
+/// - `blackouts`: one item per blackout with `time` and `duration`
+#[derive(Debug, Clone, Default)]
+pub struct ReportContext {
+    values: Values,
+    sections: BTreeMap<String, Section>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum TemplateError {
+    UnknownPlaceholder {
+        name: String,
+        line: usize,
+        column: usize,
+    },
+    UnknownSection {
+        name: String,
+        line: usize,
+        column: usize,
+    },
+    UnclosedSection {
+        name: String,
+        line: usize,
+        column: usize,
+    },
+    UnexpectedSectionEnd {
+        line: usize,
+        column: usize,
+    },
+    UnclosedTag {
+        line: usize,
+        column: usize,
+    },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateError::UnknownPlaceholder { name, line, column } => write!(
+                f,
+                "unknown placeholder '{}' at line {}, column {}",
+                name, line, column
+            ),
+            TemplateError::UnknownSection { name, line, column } => write!(
+                f,
+                "unknown section '{}' at line {}, column {}",
+                name, line, column
+            ),
+            TemplateError::UnclosedSection { name, line, column } => write!(
+                f,
+                "section '{}' opened at line {}, column {} is never closed",
+                name, line, column
+            ),
+            TemplateError::UnexpectedSectionEnd { line, column } => write!(
+                f,
+                "section end without a matching start at line {}, column {}",
+                line, column
+            ),
+            TemplateError::UnclosedTag { line, column } => {
+                write!(f, "unclosed tag at line {}, column {}", line, column)
+            }
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReportError {
+    Io(io::Error),
+    Template(TemplateError),
+    Export(ExportError), // a file of the bundle could not be written
+    MonthNotInDataset { year: i32, month: u32 },
+}
+
+impl fmt::Display for ReportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReportError::Io(e) => write!(f, "error writing the report: {}", e),
+            ReportError::Template(e) => write!(f, "invalid report template: {}", e),
+            ReportError::Export(e) => write!(f, "{}", e),
+            ReportError::MonthNotInDataset { year, month } => {
+                write!(f, "no power data for {}-{:02}", year, month)
+            }
+        }
+    }
+}
+
+impl Error for ReportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReportError::Io(e) => Some(e),
+            ReportError::Template(e) => Some(e),
+            ReportError::Export(e) => Some(e),
+            ReportError::MonthNotInDataset { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReportError {
+    fn from(e: io::Error) -> Self {
+        ReportError::Io(e)
+    }
+}
+
+impl From<ExportError> for ReportError {
+    fn from(e: ExportError) -> Self {
+        ReportError::Export(e)
+    }
+}
+
+impl From<TemplateError> for ReportError {
+    fn from(e: TemplateError) -> Self {
+        ReportError::Template(e)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ReportOptions {
+    pub locale: Locale,                   // labels, number and date formatting
+    pub labels: BTreeMap<String, String>, // label texts replacing the ones of the locale
+    pub precision: PrecisionPolicy,       // decimals shown for each class of value
+}
+
+/// Files written by `monthly_bundle`, named `<year>-<month>-<kind>.<extension>`.
+///
+/// Options may be added in minor releases, so they are built from the default with the
+/// `with_*` methods; struct literals do not compile outside the crate:
+///
+/// ```compile_fail
+/// # use voltcraft_energy_analyzer::report::BundleOptions;
+/// let opts = BundleOptions { events_csv: true, ..Default::default() };
+/// ```
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct BundleOptions {
+    pub report: ReportOptions,
+    pub markdown_report: bool, // <year>-<month>-report.md
+    pub text_report: bool,     // <year>-<month>-report.txt
+    pub daily_csv: bool,       // <year>-<month>-daily.csv
+    pub blackouts_csv: bool,   // <year>-<month>-blackouts.csv
+    pub events_csv: bool,      // <year>-<month>-events.csv
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub path: PathBuf,
+    pub size: u64, // bytes
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BundleManifest {
+    pub year: i32,
+    pub month: u32,
+    pub files: Vec<ManifestEntry>, // in the order they were written
+}
+
+impl ReportOptions {
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    // Replace the text of a label of the locale
+    pub fn with_label(mut self, key: &str, text: &str) -> Self {
+        self.labels.insert(key.to_string(), text.to_string());
+        self
+    }
+
+    pub fn with_precision(mut self, precision: PrecisionPolicy) -> Self {
+        self.precision = precision;
+        self
+    }
+}
+
+impl BundleOptions {
+    pub fn with_report(mut self, report: ReportOptions) -> Self {
+        self.report = report;
+        self
+    }
+
+    pub fn with_markdown_report(mut self, enabled: bool) -> Self {
+        self.markdown_report = enabled;
+        self
+    }
+
+    pub fn with_text_report(mut self, enabled: bool) -> Self {
+        self.text_report = enabled;
+        self
+    }
+
+    pub fn with_daily_csv(mut self, enabled: bool) -> Self {
+        self.daily_csv = enabled;
+        self
+    }
+
+    pub fn with_blackouts_csv(mut self, enabled: bool) -> Self {
+        self.blackouts_csv = enabled;
+        self
+    }
+
+    pub fn with_events_csv(mut self, enabled: bool) -> Self {
+        self.events_csv = enabled;
+        self
+    }
+}
+
+impl Default for BundleOptions {
+    fn default() -> Self {
+        BundleOptions {
+            report: ReportOptions::default(),
+            markdown_report: true,
+            text_report: false,
+            daily_csv: true,
+            blackouts_csv: true,
+            events_csv: false,
+        }
+    }
+}
+
+impl ReportContext {
+    pub fn new(
+        overall_stats: &OverallPowerInfo,
+        daily_stats: &[PowerInterval],
+        blackout_stats: &BlackoutInfo,
+    ) -> ReportContext {
+        ReportContext::with_options(
+            overall_stats,
+            daily_stats,
+            blackout_stats,
+            &ReportOptions::default(),
+        )
+    }
+
+    pub fn with_options(
+        overall_stats: &OverallPowerInfo,
+        daily_stats: &[PowerInterval],
+        blackout_stats: &BlackoutInfo,
+        options: &ReportOptions,
+    ) -> ReportContext {
+        let locale = options.locale;
+        let precision = &options.precision;
+        let mut ctx = ReportContext {
+            values: stats_values(&overall_stats.stats, locale, precision),
+            sections: BTreeMap::new(),
+        };
+        // Labels are available as label_<key>, user-supplied texts taking precedence
+        for (key, text) in locale.labels() {
+            ctx.insert(&format!("label_{}", key), text);
+        }
+        for (key, text) in &options.labels {
+            ctx.insert(&format!("label_{}", key), text);
+        }
+        ctx.insert(
+            "start",
+            overall_stats.start.format(locale.datetime_format()),
+        );
+        ctx.insert("end", overall_stats.end.format(locale.datetime_format()));
+        ctx.insert(
+            "duration",
+            format_duration(overall_stats.end - overall_stats.start),
+        );
+        ctx.insert("blackout_count", blackout_stats.blackout_count);
+        ctx.insert(
+            "blackout_duration",
+            format_duration(blackout_stats.total_blackout_duration),
+        );
+
+        let daily_average = overall_stats
+            .avg_daily_power_consumption
+            .map(|d| {
+                Values::from([
+                    (
+                        "kwh_per_day".to_string(),
+                        locale.format_number(d, precision.energy),
+                    ),
+                    (
+                        "kwh_per_month".to_string(),
+                        locale.format_number(d * 30.0, precision.energy),
+                    ),
+                    (
+                        "kwh_per_year".to_string(),
+                        locale.format_number(d * 365.0, precision.energy),
+                    ),
+                ])
+            })
+            .into_iter()
+            .collect();
+        ctx.insert_section(
+            "daily_average",
+            &["kwh_per_day", "kwh_per_month", "kwh_per_year"],
+            daily_average,
+        );
+
+        // Annotations are numbered in the order they first appear
+        let mut notes = Vec::<&Annotation>::new();
+        for annotation in daily_stats.iter().flat_map(|d| &d.annotations) {
+            if !notes.contains(&annotation) {
+                notes.push(annotation);
+            }
+        }
+
+        let days = daily_stats
+            .iter()
+            .map(|interval| {
+                let mut values = stats_values(&interval.stats, locale, precision);
+                values.insert(
+                    "notes".to_string(),
+                    interval
+                        .annotations
+                        .iter()
+                        .filter_map(|a| notes.iter().position(|n| *n == a))
+                        .map(|i| format!(" [{}]", i + 1))
+                        .collect(),
+                );
+                values.insert(
+                    "date".to_string(),
+                    interval.date.format(locale.date_format()).to_string(),
+                );
+                values.insert(
+                    "duration".to_string(),
+                    format_duration(interval.stats.total_duration),
+                );
+                values.insert(
+                    "coverage".to_string(),
+                    locale.format_number(
+                        interval.stats.total_duration.num_seconds() as f64 * 100.0 / 86400.0,
+                        1,
+                    ),
+                );
+                values
+            })
+            .collect::<Vec<_>>();
+        let mut day_fields = STATS_FIELDS.to_vec();
+        day_fields.extend(["date", "duration", "coverage", "notes"]);
+        ctx.insert_section("days", &day_fields, days);
+
+        let notes = notes
+            .iter()
+            .enumerate()
+            .map(|(i, a)| {
+                Values::from([
+                    ("number".to_string(), (i + 1).to_string()),
+                    ("label".to_string(), a.label.clone()),
+                    (
+                        "start".to_string(),
+                        a.start.format(locale.datetime_format()).to_string(),
+                    ),
+                    (
+                        "end".to_string(),
+                        a.end.format(locale.datetime_format()).to_string(),
+                    ),
+                ])
+            })
+            .collect::<Vec<_>>();
+        ctx.insert_section("notes", &["number", "label", "start", "end"], notes);
+
+        let blackouts = blackout_stats
+            .blackouts
+            .iter()
+            .map(|be| {
+                Values::from([
+                    (
+                        "time".to_string(),
+                        be.timestamp.format(locale.datetime_format()).to_string(),
+                    ),
+                    ("duration".to_string(), format_duration(be.duration)),
+                ])
+            })
+            .collect::<Vec<_>>();
+        ctx.insert_section("blackouts", &["time", "duration"], blackouts);
+        ctx
+    }
+
+    // Add a top-level value (or replace an existing one), e.g. an address block for the heading
+    pub fn insert(&mut self, name: &str, value: impl ToString) {
+        self.values.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|v| v.as_str())
+    }
+
+    fn insert_section(&mut self, name: &str, fields: &[&str], items: Vec<Values>) {
+        self.sections.insert(
+            name.to_string(),
+            Section {
+                fields: fields.iter().map(|f| f.to_string()).collect(),
+                items,
+            },
+        );
+    }
+}
+
+// Placeholders computed from PowerStats, shared by the overall and the daily statistics
+const STATS_FIELDS: [&str; 16] = [
+    "total_kwh",
+    "avg_kw",
+    "peak_kw",
+    "peak_kw_time",
+    "total_kvah",
+    "avg_kva",
+    "peak_kva",
+    "peak_kva_time",
+    "avg_voltage",
+    "min_voltage",
+    "min_voltage_time",
+    "max_voltage",
+    "max_voltage_time",
+    "total_energy",
+    "avg_power",
+    "peak_power",
+];
+
+fn stats_values(stats: &PowerStats, locale: Locale, precision: &PrecisionPolicy) -> Values {
+    let timestamp = |pe: &PowerEvent| pe.timestamp.format(locale.datetime_format()).to_string();
+    let energy = |v: f64| locale.format_number(v, precision.energy);
+    let power = |v: f64| locale.format_number(v, precision.power);
+    let voltage = |v: f64| locale.format_number(v, precision.voltage);
+    let values = [
+        energy(stats.total_active_power),
+        power(stats.avg_active_power),
+        power(stats.max_active_power.power),
+        timestamp(&stats.max_active_power),
+        energy(stats.total_apparent_power),
+        power(stats.avg_apparent_power),
+        power(stats.max_apparent_power.apparent_power),
+        timestamp(&stats.max_apparent_power),
+        voltage(stats.avg_voltage),
+        voltage(stats.min_voltage.voltage),
+        timestamp(&stats.min_voltage),
+        voltage(stats.max_voltage.voltage),
+        timestamp(&stats.max_voltage),
+        format_energy_in(stats.total_active_power, EnergyUnit::Auto, locale),
+        format_power_in(stats.avg_active_power, PowerUnit::Auto, locale),
+        format_power_in(stats.max_active_power.power, PowerUnit::Auto, locale),
+    ];
+    STATS_FIELDS
+        .iter()
+        .map(|f| f.to_string())
+        .zip(values)
+        .collect()
+}
+
+// Name and position of a section start tag
+type SectionStart = (String, usize, usize);
+
+// Parsed template
+enum Node {
+    Text(String),
+    Placeholder {
+        name: String,
+        line: usize,
+        column: usize,
+    },
+    Section {
+        name: String,
+        line: usize,
+        column: usize,
+        body: Vec<Node>,
+    },
+}
+
+/// Write the reports and CSV files of one month of the dataset into a directory.
+/// There is no chart exporter yet, so no chart is written.
+pub fn monthly_bundle(
+    dataset: &Dataset,
+    year: i32,
+    month: u32,
+    out_dir: &Path,
+    opts: &BundleOptions,
+) -> Result<BundleManifest, ReportError> {
+    let events = dataset
+        .events()
+        .iter()
+        .filter(|e| {
+            let start = bucket_of(e.timestamp, Granularity::Month).date();
+            (start.year(), start.month()) == (year, month)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let stats = VoltcraftStatistics::new(&events);
+    let Some(overall_stats) = stats.overall_stats() else {
+        return Err(ReportError::MonthNotInDataset { year, month });
+    };
+    let daily_stats = stats.daily_stats();
+    let blackout_stats = stats.blackout_stats();
+    let ctx =
+        ReportContext::with_options(&overall_stats, &daily_stats, &blackout_stats, &opts.report);
+
+    fs::create_dir_all(out_dir)?;
+    let mut manifest = BundleManifest {
+        year,
+        month,
+        files: Vec::new(),
+    };
+    let mut write = |name: &str, save: &dyn Fn(&str) -> Result<(), ReportError>| {
+        let path = out_dir.join(format!("{}-{:02}-{}", year, month, name));
+        save(&path.to_string_lossy())?;
+        let size = fs::metadata(&path)?.len();
+        manifest.files.push(ManifestEntry { path, size });
+        Ok::<(), ReportError>(())
+    };
+    let report = |template: &str, filename: &str| -> Result<(), ReportError> {
+        fs::write(filename, render(template, &ctx)?)?;
+        Ok(())
+    };
+    if opts.markdown_report {
+        write("report.md", &|f| report(MARKDOWN_TEMPLATE, f))?;
+    }
+    if opts.text_report {
+        write("report.txt", &|f| report(TEXT_TEMPLATE, f))?;
+    }
+    if opts.daily_csv {
+        write("daily.csv", &|f| Ok(save_daily_stats_csv(f, &daily_stats)?))?;
+    }
+    if opts.blackouts_csv {
+        write("blackouts.csv", &|f| {
+            Ok(save_blackouts_csv(f, &blackout_stats)?)
+        })?;
+    }
+    if opts.events_csv {
+        write("events.csv", &|f| {
+            Ok(save_parameter_history_csv(f, &events)?)
+        })?;
+    }
+    Ok(manifest)
+}
+
+// Decimals of the energy and power figures of the digest
+const DIGEST_DECIMALS: usize = 1;
+
+/// A few sentences summarizing one ISO week of the dataset, e.g.
+/// "This week: 52.3 kWh (+8% vs last week). Peak 3.4 kW on Thu 19:12. One 14-minute outage on Tue."
+///
+/// The comparison is left out when the prior week holds no power data. Blackouts are counted
+/// in the week they start. Only the locale of the options is used.
+pub fn digest(dataset: &Dataset, week: IsoWeek, opts: &ReportOptions) -> String {
+    let locale = opts.locale;
+    let de = locale == Locale::De;
+    let monday = NaiveDate::from_isoywd_opt(week.year(), week.week(), Weekday::Mon)
+        .expect("ISO weeks are valid dates");
+    let no_data = || match de {
+        true => "Diese Woche: keine Messdaten.".to_string(),
+        false => "This week: no power data.".to_string(),
+    };
+    // Start of the day, or of its first hour when daylight saving time skips midnight
+    let midnight = |day: NaiveDate| first_instant(&Local, day.and_time(NaiveTime::MIN));
+    let (Some(start), Some(end)) = (midnight(monday), midnight(monday + Duration::days(7))) else {
+        return no_data();
+    };
+    let events = dataset.sorted_events();
+    let range = |from, to| events.range(from, to);
+    let this_week = range(start, end);
+    let Some(stats) = PowerStats::from_events(this_week) else {
+        return no_data();
+    };
+    let energy = |v: f64| locale.format_number(v, DIGEST_DECIMALS);
+    let weekday = |t: DateTime<Local>| locale.weekday_abbreviation(t.weekday());
+
+    let mut digest = if de {
+        format!("Diese Woche: {} kWh", energy(stats.total_active_power))
+    } else {
+        format!("This week: {} kWh", energy(stats.total_active_power))
+    };
+    let prior = PowerStats::from_events(range(start - Duration::days(7), start))
+        .map(|s| s.total_active_power)
+        .filter(|kwh| *kwh > 0.0);
+    if let Some(prior) = prior {
+        let mut change = locale.format_number((stats.total_active_power / prior - 1.0) * 100.0, 0);
+        if !change.starts_with('-') {
+            change.insert(0, '+');
+        }
+        if de {
+            digest.push_str(&format!(" ({} % ggü. Vorwoche)", change));
+        } else {
+            digest.push_str(&format!(" ({}% vs last week)", change));
+        }
+    }
+    let peak = stats.max_active_power;
+    let peak_time = format!(
+        "{} {}",
+        weekday(peak.timestamp),
+        peak.timestamp.format("%H:%M")
+    );
+    if de {
+        digest.push_str(&format!(
+            ". Spitze {} kW am {}.",
+            energy(peak.power),
+            peak_time
+        ));
+    } else {
+        digest.push_str(&format!(
+            ". Peak {} kW on {}.",
+            energy(peak.power),
+            peak_time
+        ));
+    }
+
+    // The first event after the week closes a blackout starting at its end
+    let next = events.get(events.partition_point(|e| e.timestamp < end));
+    let window = this_week.iter().chain(next).cloned().collect::<Vec<_>>();
+    let blackouts = VoltcraftStatistics::new(&window)
+        .blackout_stats()
+        .blackouts
+        .into_iter()
+        .filter(|b| b.timestamp < end)
+        .collect::<Vec<_>>();
+    let total = blackouts.iter().map(|b| b.duration_minutes()).sum::<i64>();
+    let longest = blackouts.iter().max_by_key(|b| b.duration);
+    let outages = match (blackouts.len(), longest) {
+        (_, None) if de => " Keine Stromausfälle.".to_string(),
+        (_, None) => " No outages.".to_string(),
+        (1, Some(b)) if de => format!(
+            " Ein Stromausfall von {} Minuten am {}.",
+            total,
+            weekday(b.timestamp)
+        ),
+        (1, Some(b)) => format!(" One {}-minute outage on {}.", total, weekday(b.timestamp)),
+        (n, Some(b)) if de => format!(
+            " {} Stromausfälle, insgesamt {} Minuten, der längste {} Minuten am {}.",
+            n,
+            total,
+            b.duration_minutes(),
+            weekday(b.timestamp)
+        ),
+        (n, Some(b)) => format!(
+            " {} outages, {} minutes in total, the longest {} minutes on {}.",
+            n,
+            total,
+            b.duration_minutes(),
+            weekday(b.timestamp)
+        ),
+    };
+    digest.push_str(&outages);
+    digest
+}
+
+/// Render a template, replacing its placeholders with the values in the context.
+/// The whole template is checked first, so unknown placeholders are reported even
+/// inside sections without items.
+pub fn render(template: &str, ctx: &ReportContext) -> Result<String, TemplateError> {
+    let nodes = parse(template)?;
+    check(&nodes, ctx, None)?;
+    let mut output = String::with_capacity(template.len());
+    render_nodes(&nodes, ctx, None, &mut output);
+    Ok(output)
+}
+
+impl ReportFormat {
+    pub fn template(&self) -> &'static str {
+        match self {
+            ReportFormat::Text => TEXT_TEMPLATE,
+            ReportFormat::Markdown => MARKDOWN_TEMPLATE,
+        }
+    }
+}
+
+/// Report of the overall statistics, a table of the daily ones and the list of blackouts in
+/// one of the built-in layouts, with the default options (English, kWh to 2 decimals and
+/// volts to 1). Use `ReportContext` and `render` for another locale or a custom template.
+pub fn render_report(
+    overall_stats: &OverallPowerInfo,
+    daily_stats: &[PowerInterval],
+    blackouts: &[PowerBlackout],
+    format: ReportFormat,
+) -> String {
+    let blackout_stats = BlackoutInfo::new(blackouts.to_vec());
+    let ctx = ReportContext::new(overall_stats, daily_stats, &blackout_stats);
+    render(format.template(), &ctx).expect("the built-in templates are valid")
+}
+
+// Line and column (both starting at 1) of a byte offset in the template
+fn position(template: &str, offset: usize) -> (usize, usize) {
+    let before = &template[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line, column)
+}
+
+fn parse(template: &str) -> Result<Vec<Node>, TemplateError> {
+    // Stack of the sections currently open, the bottom one being the template itself
+    let mut stack: Vec<(Option<SectionStart>, Vec<Node>)> = vec![(None, Vec::new())];
+    let mut rest = 0;
+    while let Some(found) = template[rest..].find("{{") {
+        let open = rest + found;
+        let (line, column) = position(template, open);
+        let close = match template[open..].find("}}") {
+            Some(close) => open + close,
+            None => return Err(TemplateError::UnclosedTag { line, column }),
+        };
+        let nodes = &mut stack.last_mut().unwrap().1;
+        if open > rest {
+            nodes.push(Node::Text(template[rest..open].to_string()));
+        }
+        let tag = template[open + 2..close].trim();
+        if let Some(name) = tag.strip_prefix("#each ") {
+            stack.push((Some((name.trim().to_string(), line, column)), Vec::new()));
+        } else if tag == "/each" {
+            match stack.pop() {
+                Some((Some((name, line, column)), body)) => {
+                    stack.last_mut().unwrap().1.push(Node::Section {
+                        name,
+                        line,
+                        column,
+                        body,
+                    })
+                }
+                _ => return Err(TemplateError::UnexpectedSectionEnd { line, column }),
+            }
+        } else {
+            nodes.push(Node::Placeholder {
+                name: tag.to_string(),
+                line,
+                column,
+            });
+        }
+        rest = close + 2;
+    }
+    if rest < template.len() {
+        let nodes = &mut stack.last_mut().unwrap().1;
+        nodes.push(Node::Text(template[rest..].to_string()));
+    }
+    match stack.pop() {
+        Some((None, nodes)) => Ok(nodes),
+        Some((Some((name, line, column)), _)) => {
+            Err(TemplateError::UnclosedSection { name, line, column })
+        }
+        None => unreachable!(),
+    }
+}
+
+fn check(
+    nodes: &[Node],
+    ctx: &ReportContext,
+    section: Option<&Section>,
+) -> Result<(), TemplateError> {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Placeholder { name, line, column } => {
+                let in_section = section.is_some_and(|s| s.fields.contains(name));
+                if !in_section && !ctx.values.contains_key(name) {
+                    return Err(TemplateError::UnknownPlaceholder {
+                        name: name.clone(),
+                        line: *line,
+                        column: *column,
+                    });
+                }
+            }
+            Node::Section {
+                name,
+                line,
+                column,
+                body,
+            } => match ctx.sections.get(name) {
+                // Sections cannot be nested
+                Some(s) if section.is_none() => check(body, ctx, Some(s))?,
+                _ => {
+                    return Err(TemplateError::UnknownSection {
+                        name: name.clone(),
+                        line: *line,
+                        column: *column,
+                    })
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+fn render_nodes(nodes: &[Node], ctx: &ReportContext, item: Option<&Values>, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Placeholder { name, .. } => {
+                // Placeholders have been checked, so the value is always present
+                let value = item
+                    .and_then(|i| i.get(name))
+                    .or_else(|| ctx.values.get(name));
+                output.push_str(value.map(|v| v.as_str()).unwrap_or_default());
+            }
+            Node::Section { name, body, .. } => {
+                for item in &ctx.sections[name].items {
+                    render_nodes(body, ctx, Some(item), output);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::locale::Locale;
+    use crate::report::{
+        digest, render, render_report, ReportContext, ReportFormat, ReportOptions, TemplateError,
+        MARKDOWN_TEMPLATE, TEXT_TEMPLATE,
+    };
+    use crate::voltcraft::annotations::Annotations;
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::dataset::Dataset;
+    use crate::voltcraft::stats::{PrecisionPolicy, VoltcraftStatistics};
+    use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Weekday};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    // Three days at 0.5 kW with a 5 minute gap on the second day
+    fn context() -> ReportContext {
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..4320)
+            .filter(|m| !(2000..2005).contains(m))
+            .map(|m| event(start + Duration::minutes(m), 0.5))
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        ReportContext::new(
+            &stats.overall_stats().unwrap(),
+            &stats.daily_stats(),
+            &stats.blackout_stats(),
+        )
+    }
+
+    #[test]
+    fn template_custom_loop() {
+        let mut ctx = context();
+        ctx.insert("address", "Str. Exemplu 1, Cluj-Napoca");
+        let template =
+            "{{address}}\nTotal: {{total_kwh}} kWh\n{{#each days}}{{date}}: {{ total_kwh }} kWh\n{{/each}}{{#each blackouts}}Outage {{time}} ({{duration}}), {{blackout_count}} in total\n{{/each}}";
+        let report = render(template, &ctx).unwrap();
+        assert_eq!(
+            report,
+            "Str. Exemplu 1, Cluj-Napoca\n\
+             Total: 35.96 kWh\n\
+             2014-09-10: 12.00 kWh\n\
+             2014-09-11: 11.96 kWh\n\
+             2014-09-12: 12.00 kWh\n\
+             Outage 2014-09-11 09:20 (5m), 1 in total\n"
+        );
+    }
+
+    #[test]
+    fn template_unknown_placeholder() {
+        let ctx = context();
+        let err = render("Total:\n  {{#each days}}{{total_kwhh}}{{/each}}", &ctx).unwrap_err();
+        assert_eq!(
+            err,
+            TemplateError::UnknownPlaceholder {
+                name: "total_kwhh".to_string(),
+                line: 2,
+                column: 17
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "unknown placeholder 'total_kwhh' at line 2, column 17"
+        );
+    }
+
+    #[test]
+    fn template_malformed() {
+        let ctx = context();
+        assert!(matches!(
+            render("{{#each weeks}}{{/each}}", &ctx),
+            Err(TemplateError::UnknownSection { .. })
+        ));
+        assert!(matches!(
+            render("{{#each days}}{{date}}", &ctx),
+            Err(TemplateError::UnclosedSection { .. })
+        ));
+        assert!(matches!(
+            render("{{date}}", &ctx),
+            Err(TemplateError::UnknownPlaceholder { .. })
+        ));
+        assert!(matches!(
+            render("{{/each}}", &ctx),
+            Err(TemplateError::UnexpectedSectionEnd { line: 1, column: 1 })
+        ));
+        assert!(matches!(
+            render("{{total_kwh", &ctx),
+            Err(TemplateError::UnclosedTag { .. })
+        ));
+    }
+
+    #[test]
+    fn template_defaults() {
+        let ctx = context();
+        let text = render(TEXT_TEMPLATE, &ctx).unwrap();
+        assert!(text.contains("Total energy consumption: 35.96kWh."));
+        assert!(text.contains("[2014-09-11 09:20] Duration: 5m"));
+        let markdown = render(MARKDOWN_TEMPLATE, &ctx).unwrap();
+        assert!(markdown.contains("| 2014-09-12 | 1d (100.0%) | 12.00 | 0.50 | 0.50 | 230.0 |"));
+    }
+
+    // Three days around 15 kW with a daily peak at 10:00, a varying voltage and a 5 minute gap
+    fn golden_context(locale: Locale) -> ReportContext {
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..4320)
+            .filter(|m| !(2000..2005).contains(m))
+            .map(|m| {
+                let power = if m % 1440 == 600 { 18.5 } else { 15.0 };
+                PowerEvent {
+                    voltage: 230.0 - (m % 7) as f64,
+                    ..event(start + Duration::minutes(m), power)
+                }
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let options = ReportOptions {
+            locale,
+            ..Default::default()
+        };
+        ReportContext::with_options(
+            &stats.overall_stats().unwrap(),
+            &stats.daily_stats(),
+            &stats.blackout_stats(),
+            &options,
+        )
+    }
+
+    #[test]
+    fn report_golden_en() {
+        let ctx = golden_context(Locale::En);
+        assert_eq!(
+            render(TEXT_TEMPLATE, &ctx).unwrap(),
+            include_str!("../tests/golden/statistics_en.txt")
+        );
+        assert_eq!(
+            render(MARKDOWN_TEMPLATE, &ctx).unwrap(),
+            include_str!("../tests/golden/statistics_en.md")
+        );
+        // Values with the unit that suits them, for custom templates
+        assert_eq!(ctx.value("total_energy"), Some("1.08 MWh"));
+        assert_eq!(ctx.value("peak_power"), Some("18.50 kW"));
+    }
+
+    #[test]
+    fn report_render_formats() {
+        // Same power events as the golden context
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..4320)
+            .filter(|m| !(2000..2005).contains(m))
+            .map(|m| {
+                let power = if m % 1440 == 600 { 18.5 } else { 15.0 };
+                PowerEvent {
+                    voltage: 230.0 - (m % 7) as f64,
+                    ..event(start + Duration::minutes(m), power)
+                }
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let (overall, daily) = (stats.overall_stats().unwrap(), stats.daily_stats());
+        let blackouts = stats.blackout_stats().blackouts;
+        assert_eq!(
+            render_report(&overall, &daily, &blackouts, ReportFormat::Text),
+            include_str!("../tests/golden/statistics_en.txt")
+        );
+        let markdown = render_report(&overall, &daily, &blackouts, ReportFormat::Markdown);
+        assert_eq!(markdown, include_str!("../tests/golden/statistics_en.md"));
+        // Every row of a table has as many cells as its header
+        for table in markdown.split("\n\n").filter(|t| t.starts_with('|')) {
+            let cells = |row: &str| row.matches('|').count();
+            let header = cells(table.lines().next().unwrap());
+            assert!(table.lines().all(|row| cells(row) == header), "{}", table);
+        }
+    }
+
+    #[test]
+    fn report_golden_de() {
+        let ctx = golden_context(Locale::De);
+        assert_eq!(
+            render(TEXT_TEMPLATE, &ctx).unwrap(),
+            include_str!("../tests/golden/statistics_de.txt")
+        );
+        assert_eq!(
+            render(MARKDOWN_TEMPLATE, &ctx).unwrap(),
+            include_str!("../tests/golden/statistics_de.md")
+        );
+        assert_eq!(ctx.value("total_energy"), Some("1,08 MWh"));
+    }
+
+    #[test]
+    fn report_label_override() {
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = vec![event(start, 0.23)];
+        let stats = VoltcraftStatistics::new(&events);
+        let mut options = ReportOptions {
+            locale: Locale::De,
+            ..Default::default()
+        };
+        options
+            .labels
+            .insert("interval".to_string(), "Messzeitraum".to_string());
+        let ctx = ReportContext::with_options(
+            &stats.overall_stats().unwrap(),
+            &stats.daily_stats(),
+            &stats.blackout_stats(),
+            &options,
+        );
+        let report = render("{{label_interval}}: {{start}} ({{label_day}})", &ctx).unwrap();
+        assert_eq!(report, "Messzeitraum: 10.09.2014 00:00 (Tag)");
+    }
+
+    #[test]
+    fn report_precision() {
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = vec![PowerEvent {
+            voltage: 230.25,
+            current: 1.0,
+            ..event(start, 7.5)
+        }];
+        let stats = VoltcraftStatistics::new(&events);
+        let options = ReportOptions {
+            precision: PrecisionPolicy {
+                energy: 3,
+                power: 0,
+                voltage: 2,
+                power_factor: 2,
+            },
+            ..Default::default()
+        };
+        let ctx = ReportContext::with_options(
+            &stats.overall_stats().unwrap(),
+            &stats.daily_stats(),
+            &stats.blackout_stats(),
+            &options,
+        );
+        let report = render("{{total_kwh}}kWh {{peak_kw}}kW {{avg_voltage}}V", &ctx).unwrap();
+        assert_eq!(report, "0.125kWh 8kW 230.25V");
+    }
+
+    #[test]
+    fn report_annotations() {
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..5 * 1440)
+            .step_by(10)
+            .map(|m| event(start + Duration::minutes(m), 0.23))
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let mut annotations = Annotations::new();
+        annotations.add(
+            start + Duration::hours(36),
+            start + Duration::hours(90),
+            "away on holiday",
+        );
+        annotations.add(
+            start - Duration::days(30),
+            start - Duration::days(29),
+            "before the recording",
+        );
+        let ctx = ReportContext::new(
+            &stats.overall_stats().unwrap(),
+            &stats.daily_stats_with_annotations(&annotations),
+            &stats.blackout_stats(),
+        );
+        let report = render(
+            "{{#each days}}{{date}}{{notes}}\n{{/each}}{{#each notes}}[{{number}}] {{label}} ({{start}} - {{end}})\n{{/each}}",
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(
+            report,
+            "2014-09-10\n2014-09-11 [1]\n2014-09-12 [1]\n2014-09-13 [1]\n2014-09-14\n\
+             [1] away on holiday (2014-09-11 12:00 - 2014-09-13 18:00)\n"
+        );
+    }
+
+    // Constant load from Monday 2014-09-08 for the given number of days, with the given gaps
+    fn digest_dataset(days: i64, kw: impl Fn(i64) -> f64, gaps: &[(i64, i64)]) -> Dataset {
+        let start = Local.with_ymd_and_hms(2014, 9, 8, 0, 0, 0).unwrap();
+        let events = (0..days * 1440)
+            .filter(|m| !gaps.iter().any(|(from, to)| (*from..*to).contains(m)))
+            .map(|m| event(start + Duration::minutes(m), kw(m)))
+            .collect::<Vec<_>>();
+        Dataset::new(events)
+    }
+
+    #[test]
+    fn digest_weeks() {
+        let week = |w| {
+            NaiveDate::from_isoywd_opt(2014, w, Weekday::Mon)
+                .unwrap()
+                .iso_week()
+        };
+        // Two weeks, 0.3 kW then 0.33 kW, with a peak on Thursday of the second week
+        // and a 14 minute outage on its Tuesday
+        let peak = 7 * 1440 + 3 * 1440 + 19 * 60 + 12;
+        let outage = 7 * 1440 + 1440 + 600;
+        let dataset = digest_dataset(
+            14,
+            |m| match m {
+                m if m == peak => 3.4,
+                m if m < 7 * 1440 => 0.3,
+                _ => 0.33,
+            },
+            &[(outage, outage + 14)],
+        );
+        let opts = ReportOptions::default();
+        assert_eq!(
+            digest(&dataset, week(38), &opts),
+            "This week: 55.4 kWh (+10% vs last week). Peak 3.4 kW on Thu 19:12. \
+             One 14-minute outage on Tue."
+        );
+        assert_eq!(
+            digest(&dataset, week(38), &opts.clone().with_locale(Locale::De)),
+            "Diese Woche: 55,4 kWh (+10 % ggü. Vorwoche). Spitze 3,4 kW am Do 19:12. \
+             Ein Stromausfall von 14 Minuten am Di."
+        );
+        // No prior week: the comparison is left out
+        assert_eq!(
+            digest(&dataset, week(37), &opts),
+            "This week: 50.4 kWh. Peak 0.3 kW on Sun 23:59. No outages."
+        );
+        assert_eq!(
+            digest(&dataset, week(40), &opts),
+            "This week: no power data."
+        );
+    }
+
+    #[test]
+    fn digest_several_outages() {
+        let week = NaiveDate::from_ymd_opt(2014, 9, 8).unwrap().iso_week();
+        // Outages on Monday and Wednesday, the last one running into the following week
+        let dataset = digest_dataset(
+            8,
+            |_| 1.0,
+            &[
+                (60, 65),
+                (2 * 1440, 2 * 1440 + 30),
+                (7 * 1440 - 10, 7 * 1440 + 10),
+            ],
+        );
+        assert_eq!(
+            digest(&dataset, week, &ReportOptions::default()),
+            "This week: 167.3 kWh. Peak 1.0 kW on Sun 23:49. \
+             3 outages, 55 minutes in total, the longest 30 minutes on Wed."
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Output sinks receiving power events and alerts as they are produced.
+
+use crate::voltcraft::data::PowerEvent;
+use chrono::{DateTime, Local};
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Notable condition found while monitoring, e.g. a blackout or a demand peak.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Alert {
+    pub timestamp: DateTime<Local>, // when the condition started
+    pub message: String,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SinkError {
+    Io(io::Error),
+    Rejected { detail: String }, // the receiving end refused the data
+}
+
+/// Destination of power events and alerts, such as a message queue or a local database.
+pub trait Sink {
+    fn publish_events(&mut self, events: &[PowerEvent]) -> Result<(), SinkError>;
+    fn publish_alert(&mut self, alert: &Alert) -> Result<(), SinkError>;
+}
+
+/// Delivery counters of one sink of a `Sinks`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct SinkStatus {
+    pub events_published: usize, // power events delivered
+    pub alerts_published: usize, // alerts delivered
+    pub errors: usize,           // failed deliveries
+    pub last_error: Option<String>,
+}
+
+/// Sinks all receiving the same power events and alerts. A failing sink does not stop the
+/// delivery to the others; its failures are counted in its status.
+pub struct Sinks {
+    sinks: Vec<(Box<dyn Sink + Send>, SinkStatus)>,
+}
+
+impl Alert {
+    pub fn new(timestamp: DateTime<Local>, message: impl Into<String>) -> Self {
+        Alert {
+            timestamp,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SinkError::Io(e) => write!(f, "error writing to the sink: {}", e),
+            SinkError::Rejected { detail } => write!(f, "the sink rejected the data: {}", detail),
+        }
+    }
+}
+
+impl Error for SinkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SinkError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SinkError {
+    fn from(e: io::Error) -> Self {
+        SinkError::Io(e)
+    }
+}
+
+impl Sinks {
+    pub fn new(sinks: Vec<Box<dyn Sink + Send>>) -> Self {
+        Sinks {
+            sinks: sinks
+                .into_iter()
+                .map(|s| (s, SinkStatus::default()))
+                .collect(),
+        }
+    }
+
+    // Deliver the power events to every sink, returning the number of sinks that failed
+    pub fn publish_events(&mut self, events: &[PowerEvent]) -> usize {
+        self.deliver(
+            |sink| sink.publish_events(events),
+            |status| status.events_published += events.len(),
+        )
+    }
+
+    // Deliver the alert to every sink, returning the number of sinks that failed
+    pub fn publish_alert(&mut self, alert: &Alert) -> usize {
+        self.deliver(
+            |sink| sink.publish_alert(alert),
+            |status| status.alerts_published += 1,
+        )
+    }
+
+    // Delivery counters of each sink, in the order the sinks were given
+    pub fn status(&self) -> Vec<SinkStatus> {
+        self.sinks
+            .iter()
+            .map(|(_, status)| status.clone())
+            .collect()
+    }
+
+    fn deliver(
+        &mut self,
+        publish: impl Fn(&mut dyn Sink) -> Result<(), SinkError>,
+        delivered: impl Fn(&mut SinkStatus),
+    ) -> usize {
+        let mut failed = 0;
+        for (sink, status) in self.sinks.iter_mut() {
+            match publish(sink.as_mut()) {
+                Ok(()) => delivered(status),
+                Err(e) => {
+                    status.errors += 1;
+                    status.last_error = Some(e.to_string());
+                    failed += 1;
+                }
+            }
+        }
+        failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sink::{Alert, Sink, SinkError, Sinks};
+    use crate::voltcraft::data::PowerEvent;
+    use chrono::{DateTime, Duration, Local, TimeZone};
+    use std::sync::{Arc, Mutex};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    // Sink recording what it receives, failing every delivery if asked to
+    struct MockSink {
+        received: Arc<Mutex<Vec<String>>>,
+        fail: bool,
+    }
+
+    impl Sink for MockSink {
+        fn publish_events(&mut self, events: &[PowerEvent]) -> Result<(), SinkError> {
+            if self.fail {
+                return Err(SinkError::Rejected {
+                    detail: "queue full".to_string(),
+                });
+            }
+            let mut received = self.received.lock().unwrap();
+            received.push(format!("{} events", events.len()));
+            Ok(())
+        }
+
+        fn publish_alert(&mut self, alert: &Alert) -> Result<(), SinkError> {
+            if self.fail {
+                return Err(std::io::Error::other("disk full").into());
+            }
+            self.received.lock().unwrap().push(alert.message.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn sinks_isolate_failures() {
+        let start = Local.with_ymd_and_hms(2014, 9, 11, 18, 43, 0).unwrap();
+        let events = (0..3)
+            .map(|m| event(start + Duration::minutes(m), 0.23))
+            .collect::<Vec<_>>();
+        let failing = Arc::new(Mutex::new(Vec::new()));
+        let working = Arc::new(Mutex::new(Vec::new()));
+        let mut sinks = Sinks::new(vec![
+            Box::new(MockSink {
+                received: failing.clone(),
+                fail: true,
+            }),
+            Box::new(MockSink {
+                received: working.clone(),
+                fail: false,
+            }),
+        ]);
+
+        assert_eq!(sinks.publish_events(&events), 1);
+        assert_eq!(sinks.publish_alert(&Alert::new(start, "blackout")), 1);
+        assert_eq!(sinks.publish_events(&events[..1]), 1);
+        assert!(failing.lock().unwrap().is_empty());
+        assert_eq!(
+            *working.lock().unwrap(),
+            ["3 events", "blackout", "1 events"]
+        );
+
+        let status = sinks.status();
+        assert_eq!((status[0].errors, status[0].events_published), (3, 0));
+        assert_eq!(
+            status[0].last_error.as_deref(),
+            Some("the sink rejected the data: queue full")
+        );
+        assert_eq!(status[1].errors, 0);
+        assert_eq!(
+            (status[1].events_published, status[1].alerts_published),
+            (4, 1)
+        );
+        assert_eq!(status[1].last_error, None);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::format::format_trimmed;
+use crate::voltcraft::data::PowerEvent;
+use crate::voltcraft::stats::VoltcraftStatistics;
+use chrono::{DateTime, Local};
+use std::fmt::Write;
+
+/// Version of the JSON summary shape, bumped whenever a key is renamed, removed or changes meaning.
+pub const SUMMARY_SCHEMA_VERSION: u32 = 1;
+
+/// Short summary of an analysis, printed as JSON by the `--json-summary` command-line option.
+///
+/// Keys of the JSON object, always present (`null` when there is no power data, and for a
+/// value that is not a finite number):
+/// - `schema_version`: `SUMMARY_SCHEMA_VERSION`
+/// - `file_count`, `event_count`: data files decoded and power events found
+/// - `start`, `end`: first and last power event (RFC 3339)
+/// - `total_kwh`, `avg_kw`, `peak_kw`, `peak_time`: active power
+/// - `min_voltage`, `max_voltage`, `avg_voltage`: voltage (V)
+/// - `blackout_count`, `blackout_minutes`: blackouts
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct QuickSummary {
+    pub file_count: usize,
+    pub event_count: usize,
+    pub stats: Option<SummaryStats>, // None without power data
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct SummaryStats {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub total_kwh: f64,
+    pub avg_kw: f64,
+    pub peak_kw: f64,
+    pub peak_time: DateTime<Local>,
+    pub min_voltage: f64,
+    pub max_voltage: f64,
+    pub avg_voltage: f64,
+    pub blackout_count: usize,
+    pub blackout_minutes: i64,
+}
+
+// Keys of the JSON summary, in output order
+pub const SUMMARY_KEYS: [&str; 14] = [
+    "schema_version",
+    "file_count",
+    "event_count",
+    "start",
+    "end",
+    "total_kwh",
+    "avg_kw",
+    "peak_kw",
+    "peak_time",
+    "min_voltage",
+    "max_voltage",
+    "avg_voltage",
+    "blackout_count",
+    "blackout_minutes",
+];
+
+impl QuickSummary {
+    // Power events are expected in chronological order
+    pub fn new(power_events: &[PowerEvent], file_count: usize) -> QuickSummary {
+        QuickSummary::from_statistics(&VoltcraftStatistics::new(power_events), file_count)
+    }
+
+    // Summary of statistics configured by the caller, e.g. in reproducible numeric mode
+    pub fn from_statistics(stats: &VoltcraftStatistics, file_count: usize) -> QuickSummary {
+        let event_count = stats.event_count();
+        let summary = stats.overall_stats().map(|overall| {
+            let blackouts = stats.blackout_stats();
+            SummaryStats {
+                start: overall.start,
+                end: overall.end,
+                total_kwh: overall.stats.total_active_power,
+                avg_kw: overall.stats.avg_active_power,
+                peak_kw: overall.stats.max_active_power.power,
+                peak_time: overall.stats.max_active_power.timestamp,
+                min_voltage: overall.stats.min_voltage.voltage,
+                max_voltage: overall.stats.max_voltage.voltage,
+                avg_voltage: overall.stats.avg_voltage,
+                blackout_count: blackouts.blackout_count,
+                blackout_minutes: blackouts.total_blackout_duration.num_minutes(),
+            }
+        });
+        QuickSummary {
+            file_count,
+            event_count,
+            stats: summary,
+        }
+    }
+
+    // Single-line JSON object with the keys listed in SUMMARY_KEYS
+    pub fn to_json(&self) -> String {
+        let s = self.stats.as_ref();
+        let null = || "null".to_string();
+        let number = |v: Option<f64>, decimals| {
+            v.filter(|v| v.is_finite())
+                .map_or_else(null, |v| format_trimmed(v, decimals))
+        };
+        let time =
+            |t: Option<DateTime<Local>>| t.map_or_else(null, |t| format!("\"{}\"", t.to_rfc3339()));
+        let values = [
+            SUMMARY_SCHEMA_VERSION.to_string(),
+            self.file_count.to_string(),
+            self.event_count.to_string(),
+            time(s.map(|s| s.start)),
+            time(s.map(|s| s.end)),
+            number(s.map(|s| s.total_kwh), 3),
+            number(s.map(|s| s.avg_kw), 3),
+            number(s.map(|s| s.peak_kw), 3),
+            time(s.map(|s| s.peak_time)),
+            number(s.map(|s| s.min_voltage), 1),
+            number(s.map(|s| s.max_voltage), 1),
+            number(s.map(|s| s.avg_voltage), 1),
+            s.map_or_else(null, |s| s.blackout_count.to_string()),
+            s.map_or_else(null, |s| s.blackout_minutes.to_string()),
+        ];
+        let mut json = String::from("{");
+        for (i, (key, value)) in SUMMARY_KEYS.iter().zip(values).enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write!(json, "\"{}\":{}", key, value).unwrap();
+        }
+        json.push('}');
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::summary::{QuickSummary, SUMMARY_KEYS, SUMMARY_SCHEMA_VERSION};
+    use crate::voltcraft::data::PowerEvent;
+    use chrono::{DateTime, Duration, Local, TimeZone};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    fn keys(json: &str) -> serde_json::Map<String, serde_json::Value> {
+        match serde_json::from_str(json).unwrap() {
+            serde_json::Value::Object(map) => map,
+            _ => panic!("summary is not a JSON object"),
+        }
+    }
+
+    #[test]
+    fn summary_json_shape() {
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..120)
+            .filter(|m| !(60..70).contains(m))
+            .map(|m| event(start + Duration::minutes(m), 0.46))
+            .collect::<Vec<_>>();
+        let json = QuickSummary::new(&events, 2).to_json();
+        let map = keys(&json);
+        assert_eq!(map["schema_version"], SUMMARY_SCHEMA_VERSION);
+        assert!(SUMMARY_KEYS.iter().all(|k| map.contains_key(*k)));
+        assert_eq!(map.len(), SUMMARY_KEYS.len());
+        assert_eq!(map["file_count"], 2);
+        assert_eq!(map["event_count"], 110);
+        assert_eq!(map["total_kwh"], 0.843);
+        assert_eq!(map["blackout_count"], 1);
+        assert_eq!(map["blackout_minutes"], 10);
+        assert_eq!(map["start"], start.to_rfc3339());
+
+        // Without power data, every key is still present
+        let map = keys(&QuickSummary::new(&Vec::new(), 0).to_json());
+        assert_eq!(map["schema_version"], SUMMARY_SCHEMA_VERSION);
+        assert!(SUMMARY_KEYS.iter().all(|k| map.contains_key(*k)));
+        assert!(map["total_kwh"].is_null());
+
+        // A value that is not a finite number is null, the others are kept
+        let mut infinite = events.clone();
+        infinite[5].power = f64::INFINITY;
+        let map = keys(&QuickSummary::new(&infinite, 1).to_json());
+        assert!(map["total_kwh"].is_null());
+        assert!(map["avg_kw"].is_null());
+        assert!(map["peak_kw"].is_null());
+        assert_eq!(map["avg_voltage"], 230.0);
+        assert_eq!(map["event_count"], 110);
+    }
+}
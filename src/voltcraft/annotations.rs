@@ -0,0 +1,117 @@
+use chrono::{DateTime, Local};
+
+/// A user note attached to a time range, e.g. "away on holiday".
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Annotation {
+    pub start: DateTime<Local>, // start of the range
+    pub end: DateTime<Local>,   // end of the range (exclusive)
+    pub label: String,
+}
+
+/// User notes, kept in the order they were added.
+/// Annotations may overlap each other and may lie outside the recorded data; they are
+/// always kept, and `overlapping_pairs` and `outside` report these cases.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Annotations {
+    entries: Vec<Annotation>,
+}
+
+impl Annotation {
+    // True if the annotation shares at least a moment with the half-open range
+    pub fn overlaps(&self, start: DateTime<Local>, end: DateTime<Local>) -> bool {
+        self.start < end && start < self.end
+    }
+}
+
+impl Annotations {
+    pub fn new() -> Annotations {
+        Annotations::default()
+    }
+
+    pub fn add(&mut self, start: DateTime<Local>, end: DateTime<Local>, label: &str) {
+        self.entries.push(Annotation {
+            start,
+            end,
+            label: label.to_string(),
+        });
+    }
+
+    pub fn entries(&self) -> &[Annotation] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Annotations sharing at least a moment with the half-open range
+    pub fn overlapping(&self, start: DateTime<Local>, end: DateTime<Local>) -> Vec<&Annotation> {
+        self.entries
+            .iter()
+            .filter(|a| a.overlaps(start, end))
+            .collect()
+    }
+
+    // Indices of the annotations overlapping each other
+    pub fn overlapping_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for (i, a) in self.entries.iter().enumerate() {
+            for (j, b) in self.entries.iter().enumerate().skip(i + 1) {
+                if a.overlaps(b.start, b.end) {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    // Annotations entirely outside the recorded data (first and last power event timestamps)
+    pub fn outside(&self, first: DateTime<Local>, last: DateTime<Local>) -> Vec<&Annotation> {
+        self.entries
+            .iter()
+            .filter(|a| !a.overlaps(first, last + chrono::Duration::minutes(1)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::annotations::Annotations;
+    use chrono::{Duration, Local, TimeZone};
+
+    #[test]
+    fn annotations_flags() {
+        let day = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let mut annotations = Annotations::new();
+        annotations.add(day, day + Duration::days(3), "away on holiday");
+        annotations.add(
+            day + Duration::hours(36),
+            day + Duration::hours(38),
+            "dishwasher install",
+        );
+        annotations.add(
+            day - Duration::days(10),
+            day - Duration::days(9),
+            "old meter",
+        );
+        annotations.add(
+            day + Duration::days(3),
+            day + Duration::days(4),
+            "back home",
+        );
+
+        assert_eq!(annotations.len(), 4);
+        assert_eq!(annotations.overlapping_pairs(), vec![(0, 1)]);
+        let outside = annotations.outside(day, day + Duration::days(3) - Duration::minutes(1));
+        let labels = outside.iter().map(|a| a.label.as_str()).collect::<Vec<_>>();
+        assert_eq!(labels, vec!["old meter", "back home"]);
+        let overlapping = annotations.overlapping(day + Duration::days(1), day + Duration::days(2));
+        assert_eq!(overlapping.len(), 2);
+    }
+}
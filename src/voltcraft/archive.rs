@@ -0,0 +1,299 @@
+use crate::voltcraft::error::VoltcraftError;
+use flate2::read::{DeflateDecoder, MultiGzDecoder};
+use flate2::Crc;
+use std::io::Read;
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const ZIP_LOCAL_HEADER: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+const ZIP_CENTRAL_HEADER: [u8; 4] = [0x50, 0x4B, 0x01, 0x02];
+const ZIP_END_OF_DIRECTORY: [u8; 4] = [0x50, 0x4B, 0x05, 0x06];
+// Size of the end of central directory record without its comment, and the longest comment
+const ZIP_END_SIZE: usize = 22;
+const ZIP_MAX_COMMENT: usize = 0xFFFF;
+// Most bytes decompressed from an archive, far more than a century of power records (5 bytes
+// a minute), so that a corrupt or malicious size cannot exhaust the memory
+const MAX_DATA_SIZE: usize = 256 << 20;
+
+/// Contents of a gzip file, or the `.bin` entries of a zip archive concatenated in archive
+/// order (each a data file, so that their sessions follow each other), or the data itself if
+/// it is not compressed.
+pub(crate) fn decompress(raw: Vec<u8>) -> Result<Vec<u8>, VoltcraftError> {
+    decompress_within(raw, MAX_DATA_SIZE)
+}
+
+// Same as decompress, InvalidArchive if the contents are longer than the limit
+fn decompress_within(raw: Vec<u8>, limit: usize) -> Result<Vec<u8>, VoltcraftError> {
+    if raw.starts_with(&GZIP_MAGIC) {
+        let mut data = Vec::new();
+        MultiGzDecoder::new(raw.as_slice())
+            .take(limit as u64 + 1)
+            .read_to_end(&mut data)
+            .map_err(|e| invalid(format!("corrupt gzip stream: {}", e)))?;
+        if data.len() > limit {
+            return Err(invalid(format!("gzip stream longer than {} bytes", limit)));
+        }
+        Ok(data)
+    } else if raw.starts_with(&ZIP_LOCAL_HEADER) {
+        unzip(&raw, limit).map_err(invalid)
+    } else {
+        Ok(raw)
+    }
+}
+
+fn invalid(detail: String) -> VoltcraftError {
+    VoltcraftError::InvalidArchive { detail }
+}
+
+// Concatenated .bin entries of a zip archive, stored or deflated, at most limit bytes
+fn unzip(raw: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    let search_from = raw.len().saturating_sub(ZIP_END_SIZE + ZIP_MAX_COMMENT);
+    let end = (search_from..=raw.len().saturating_sub(ZIP_END_SIZE))
+        .rev()
+        .find(|&off| raw[off..].starts_with(&ZIP_END_OF_DIRECTORY))
+        .ok_or("zip archive without a central directory")?;
+    let entries = le(raw, end + 10, 2)?;
+    let mut entry = le(raw, end + 16, 4)?;
+    let mut data = Vec::new();
+    let mut found = false;
+    for _ in 0..entries {
+        if !raw[entry.min(raw.len())..].starts_with(&ZIP_CENTRAL_HEADER) {
+            return Err(format!("invalid zip directory entry at offset {}", entry));
+        }
+        let method = le(raw, entry + 10, 2)?;
+        let crc = le(raw, entry + 16, 4)? as u32;
+        let compressed_size = le(raw, entry + 20, 4)?;
+        let size = le(raw, entry + 24, 4)?;
+        let name_length = le(raw, entry + 28, 2)?;
+        let local = le(raw, entry + 42, 4)?;
+        let name = raw
+            .get(entry + 46..entry + 46 + name_length)
+            .ok_or("truncated zip archive")?;
+        let name = String::from_utf8_lossy(name).to_string();
+        entry += 46 + name_length + le(raw, entry + 30, 2)? + le(raw, entry + 32, 2)?;
+        if !name.to_ascii_lowercase().ends_with(".bin") {
+            continue;
+        }
+
+        if !raw[local.min(raw.len())..].starts_with(&ZIP_LOCAL_HEADER) {
+            return Err(format!("invalid zip entry {}", name));
+        }
+        let start = local + 30 + le(raw, local + 26, 2)? + le(raw, local + 28, 2)?;
+        let compressed = raw
+            .get(start..start + compressed_size)
+            .ok_or_else(|| format!("truncated zip entry {}", name))?;
+        // The sizes come from the archive, so they are checked before allocating
+        if size > limit - data.len() {
+            return Err(format!("zip entries longer than {} bytes", limit));
+        }
+        let mut contents = Vec::with_capacity(size);
+        match method {
+            0 => contents.extend_from_slice(compressed),
+            8 => {
+                DeflateDecoder::new(compressed)
+                    .take(size as u64 + 1)
+                    .read_to_end(&mut contents)
+                    .map_err(|e| format!("corrupt zip entry {}: {}", name, e))?;
+            }
+            _ => {
+                return Err(format!(
+                    "zip entry {} uses compression method {}",
+                    name, method
+                ))
+            }
+        }
+        let mut checksum = Crc::new();
+        checksum.update(&contents);
+        if contents.len() != size || checksum.sum() != crc {
+            return Err(format!("corrupt zip entry {}: checksum mismatch", name));
+        }
+        data.extend(contents);
+        found = true;
+    }
+    match found {
+        true => Ok(data),
+        false => Err("zip archive without a .bin data file".to_string()),
+    }
+}
+
+// Little endian number of `bytes` bytes at the offset
+fn le(raw: &[u8], offset: usize, bytes: usize) -> Result<usize, String> {
+    let field = raw
+        .get(offset..offset + bytes)
+        .ok_or("truncated zip archive")?;
+    Ok(field
+        .iter()
+        .rev()
+        .fold(0usize, |value, &b| (value << 8) | b as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::archive::{decompress_within, MAX_DATA_SIZE};
+    use crate::voltcraft::data::VoltcraftData;
+    use crate::voltcraft::error::VoltcraftError;
+    use flate2::write::{DeflateEncoder, GzEncoder};
+    use flate2::{Compression, Crc};
+    use std::io::Write;
+
+    // Two sessions of two power records each, as separate data files
+    const FIRST: [u8; 22] = [
+        0xE0, 0xC5, 0xEA, 0x09, 0x0B, 0x0E, 0x12, 0x2B, //
+        0x08, 0xC6, 0x01, 0xBE, 0x57, 0x08, 0xC6, 0x01, 0xBE, 0x57, //
+        0xFF, 0xFF, 0xFF, 0xFF,
+    ];
+    const SECOND: [u8; 22] = [
+        0xE0, 0xC5, 0xEA, 0x09, 0x0C, 0x0E, 0x08, 0x00, //
+        0x08, 0xFC, 0x00, 0x64, 0x5A, 0x08, 0xFC, 0x00, 0x64, 0x5A, //
+        0xFF, 0xFF, 0xFF, 0xFF,
+    ];
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    // Zip archive of the entries, deflated or stored
+    fn zip(entries: &[(&str, &[u8], bool)]) -> Vec<u8> {
+        let (mut archive, mut directory) = (Vec::new(), Vec::new());
+        for (name, data, deflate) in entries {
+            let stored = match deflate {
+                true => {
+                    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                    encoder.write_all(data).unwrap();
+                    encoder.finish().unwrap()
+                }
+                false => data.to_vec(),
+            };
+            let mut crc = Crc::new();
+            crc.update(data);
+            let method: u16 = if *deflate { 8 } else { 0 };
+            let offset = archive.len() as u32;
+            // Fields shared by the local header and the directory entry, from the version needed
+            let mut common = Vec::new();
+            common.extend_from_slice(&20u16.to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes()); // flags
+            common.extend_from_slice(&method.to_le_bytes());
+            common.extend_from_slice(&[0; 4]); // modification time and date
+            common.extend_from_slice(&crc.sum().to_le_bytes());
+            common.extend_from_slice(&(stored.len() as u32).to_le_bytes());
+            common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+
+            archive.extend_from_slice(&[0x50, 0x4B, 0x03, 0x04]);
+            archive.extend_from_slice(&common);
+            archive.extend_from_slice(name.as_bytes());
+            archive.extend_from_slice(&stored);
+
+            directory.extend_from_slice(&[0x50, 0x4B, 0x01, 0x02]);
+            directory.extend_from_slice(&20u16.to_le_bytes()); // version made by
+            directory.extend_from_slice(&common);
+            directory.extend_from_slice(&[0; 6]); // comment length, disk, internal attributes
+            directory.extend_from_slice(&[0; 4]); // external attributes
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+        let directory_offset = archive.len() as u32;
+        archive.extend_from_slice(&directory);
+        archive.extend_from_slice(&[0x50, 0x4B, 0x05, 0x06, 0, 0, 0, 0]);
+        archive.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        archive.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        archive.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        archive.extend_from_slice(&directory_offset.to_le_bytes());
+        archive.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        archive
+    }
+
+    fn read(raw: Vec<u8>) -> Result<VoltcraftData, VoltcraftError> {
+        VoltcraftData::from_reader(raw.as_slice())
+    }
+
+    #[test]
+    fn archive_gzip_and_zip() {
+        let expected = VoltcraftData::from_raw(FIRST).parse().unwrap();
+        let events = read(gzip(&FIRST)).unwrap().parse().unwrap();
+        assert_eq!(events, expected);
+        // Uncompressed data is read as is
+        assert_eq!(read(FIRST.to_vec()).unwrap().parse().unwrap(), expected);
+
+        // The data files of a zip archive follow each other, other entries are skipped
+        let archive = zip(&[
+            ("A0810702.BIN", &FIRST, true),
+            ("readme.txt", b"not a data file", true),
+            ("A0810703.bin", &SECOND, false),
+        ]);
+        let sessions = read(archive.clone()).unwrap().parse_sessions().unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].events, expected);
+        assert_eq!(sessions[1].events[0].voltage, 230.0);
+
+        let path = std::env::temp_dir().join("voltcraft_archive.bin.gz");
+        std::fs::write(&path, gzip(&FIRST)).unwrap();
+        let events = VoltcraftData::from_file(&path).unwrap().parse().unwrap();
+        assert_eq!(events, expected);
+
+        // Corrupt and unusable archives
+        let mut corrupt = gzip(&FIRST);
+        let n = corrupt.len();
+        corrupt[n - 8] ^= 0xFF; // checksum
+        let truncated = gzip(&FIRST)[..n - 10].to_vec();
+        let mut flipped = zip(&[("A0810702.BIN", &FIRST, false)]);
+        flipped[45] ^= 0xFF; // in the stored data file
+        for raw in [
+            corrupt,
+            truncated,
+            flipped,
+            archive[..archive.len() - 30].to_vec(),
+            zip(&[("readme.txt", b"not a data file", false)]),
+        ] {
+            match read(raw) {
+                Err(VoltcraftError::InvalidArchive { .. }) => {}
+                other => panic!("invalid archive expected, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn archive_size_limit() {
+        let limit = FIRST.len() + SECOND.len();
+        let two = zip(&[("A.BIN", &FIRST, true), ("B.BIN", &SECOND, false)]);
+        assert_eq!(decompress_within(two.clone(), limit).unwrap().len(), limit);
+        assert_eq!(decompress_within(gzip(&FIRST), FIRST.len()).unwrap(), FIRST);
+
+        // A size in the directory beyond the limit fails before allocating it, a deflated
+        // entry inflating past its size stops at it
+        let mut huge = zip(&[("A.BIN", &FIRST, true)]);
+        let directory = huge.len() - 22 - 51;
+        huge[directory + 24..directory + 28].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut short = zip(&[("A.BIN", &FIRST, true)]);
+        short[directory + 24..directory + 28].copy_from_slice(&4u32.to_le_bytes());
+        for (raw, limit, detail) in [
+            (
+                two,
+                limit - 1,
+                format!("zip entries longer than {} bytes", limit - 1),
+            ),
+            (
+                gzip(&FIRST),
+                21,
+                "gzip stream longer than 21 bytes".to_string(),
+            ),
+            (
+                huge,
+                MAX_DATA_SIZE,
+                format!("zip entries longer than {} bytes", MAX_DATA_SIZE),
+            ),
+            (
+                short,
+                MAX_DATA_SIZE,
+                "corrupt zip entry A.BIN: checksum mismatch".to_string(),
+            ),
+        ] {
+            match decompress_within(raw, limit) {
+                Err(VoltcraftError::InvalidArchive { detail: d }) => assert_eq!(d, detail),
+                other => panic!("invalid archive expected, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use crate::voltcraft::stats::{median, PowerStats};
+
+/// Typical month, made of the medians of previous months, to compare a new month against.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Baseline {
+    pub months: Vec<u32>,  // months the baseline was built from, e.g. 201409
+    pub total_energy: f64, // median total active energy (kWh)
+    pub peak_power: f64,   // median peak active power (kW)
+    pub blackout_count: Option<f64>, // median number of blackouts, if known
+    pub bands: ComparisonBands,
+}
+
+/// Relative difference (percent) up to which a value is considered similar to the baseline.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ComparisonBands {
+    pub energy: f64,
+    pub peak_power: f64,
+    pub blackouts: f64,
+}
+
+// Lower consumption, peaks and blackout counts are better
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Classification {
+    Better,
+    Similar,
+    Worse,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MetricComparison {
+    pub current: f64,
+    pub baseline: f64,
+    pub delta: f64,                 // current - baseline
+    pub delta_percent: Option<f64>, // delta relative to the baseline (None for a zero baseline)
+    pub classification: Classification,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BaselineComparison {
+    pub energy: MetricComparison,
+    pub peak_power: MetricComparison,
+    pub blackouts: Option<MetricComparison>, // only when both blackout counts are known
+}
+
+impl ComparisonBands {
+    pub fn new(energy: f64, peak_power: f64, blackouts: f64) -> Self {
+        ComparisonBands {
+            energy,
+            peak_power,
+            blackouts,
+        }
+    }
+}
+
+impl Default for ComparisonBands {
+    fn default() -> Self {
+        ComparisonBands {
+            energy: 10.0,
+            peak_power: 10.0,
+            blackouts: 50.0,
+        }
+    }
+}
+
+impl Baseline {
+    // Baseline from the statistics of previous months, as (month, statistics) pairs
+    pub fn from_monthly(stats: &[(u32, PowerStats)]) -> Baseline {
+        Baseline {
+            months: stats.iter().map(|(month, _)| *month).collect(),
+            total_energy: median(stats.iter().map(|(_, s)| s.total_active_power).collect()),
+            peak_power: median(
+                stats
+                    .iter()
+                    .map(|(_, s)| s.max_active_power.power)
+                    .collect(),
+            ),
+            blackout_count: None,
+            bands: ComparisonBands::default(),
+        }
+    }
+
+    // Blackout counts of the same months, in any order
+    pub fn with_blackout_counts(mut self, counts: &[(u32, usize)]) -> Baseline {
+        self.blackout_count = Some(median(counts.iter().map(|(_, c)| *c as f64).collect()));
+        self
+    }
+
+    pub fn with_bands(mut self, bands: ComparisonBands) -> Baseline {
+        self.bands = bands;
+        self
+    }
+}
+
+impl MetricComparison {
+    fn new(current: f64, baseline: f64, band: f64) -> MetricComparison {
+        let delta = current - baseline;
+        let delta_percent = (baseline != 0.0).then(|| delta * 100.0 / baseline);
+        let classification = match delta_percent {
+            Some(p) if p > band => Classification::Worse,
+            Some(p) if p < -band => Classification::Better,
+            Some(_) => Classification::Similar,
+            // Anything above nothing is worse
+            None if delta > 0.0 => Classification::Worse,
+            None => Classification::Similar,
+        };
+        MetricComparison {
+            current,
+            baseline,
+            delta,
+            delta_percent,
+            classification,
+        }
+    }
+}
+
+impl BaselineComparison {
+    // Add the blackout comparison, when the baseline knows its blackout count
+    pub fn with_blackouts(mut self, blackout_count: usize, baseline: &Baseline) -> Self {
+        self.blackouts = baseline
+            .blackout_count
+            .map(|b| MetricComparison::new(blackout_count as f64, b, baseline.bands.blackouts));
+        self
+    }
+}
+
+pub fn compare_to_baseline(current: &PowerStats, baseline: &Baseline) -> BaselineComparison {
+    BaselineComparison {
+        energy: MetricComparison::new(
+            current.total_active_power,
+            baseline.total_energy,
+            baseline.bands.energy,
+        ),
+        peak_power: MetricComparison::new(
+            current.max_active_power.power,
+            baseline.peak_power,
+            baseline.bands.peak_power,
+        ),
+        blackouts: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::baseline::{compare_to_baseline, Baseline, Classification};
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::stats::{CoverageInfo, PowerStats};
+    use chrono::{DateTime, Duration, Local, TimeZone};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    fn month(total_active_power: f64, peak: f64) -> PowerStats {
+        let peak = event(Local.with_ymd_and_hms(2014, 9, 10, 19, 0, 0).unwrap(), peak);
+        PowerStats {
+            total_active_power,
+            avg_active_power: total_active_power / 720.0,
+            max_active_power: peak,
+            total_apparent_power: total_active_power,
+            avg_apparent_power: total_active_power / 720.0,
+            max_apparent_power: peak,
+            min_voltage: peak,
+            max_voltage: peak,
+            avg_voltage: 230.0,
+            p95_voltage: 230.0,
+            min_current: peak,
+            max_current: peak,
+            avg_current: peak.current,
+            avg_power_factor: 1.0,
+            p95_power: peak.power,
+            worst_power_factor: Some(peak),
+            weighted_power_factor: 1.0,
+            total_reactive_energy: 0.0,
+            worst_sustained_power_factor: None,
+            total_duration: Duration::days(30),
+            active: None,
+            coverage: CoverageInfo::new(43200, 43200),
+        }
+    }
+
+    #[test]
+    fn baseline_comparison() {
+        let baseline = Baseline::from_monthly(&[
+            (201406, month(310.0, 4.2)),
+            (201407, month(290.0, 3.8)),
+            (201408, month(300.0, 4.0)),
+        ])
+        .with_blackout_counts(&[(201406, 1), (201407, 3), (201408, 2)]);
+        assert_eq!(baseline.total_energy, 300.0);
+        assert_eq!(baseline.peak_power, 4.0);
+        assert_eq!(baseline.blackout_count, Some(2.0));
+
+        let comparison =
+            compare_to_baseline(&month(345.0, 3.9), &baseline).with_blackouts(0, &baseline);
+        assert_eq!(comparison.energy.classification, Classification::Worse);
+        assert!((comparison.energy.delta_percent.unwrap() - 15.0).abs() < 1e-9);
+        assert_eq!(
+            comparison.peak_power.classification,
+            Classification::Similar
+        );
+        let blackouts = comparison.blackouts.unwrap();
+        assert_eq!(blackouts.delta, -2.0);
+        assert_eq!(blackouts.classification, Classification::Better);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn baseline_serde_round_trip() {
+        let baseline = Baseline::from_monthly(&[(201407, month(290.0, 3.8))])
+            .with_blackout_counts(&[(201407, 1)]);
+        let json = serde_json::to_string(&baseline).unwrap();
+        assert_eq!(serde_json::from_str::<Baseline>(&json).unwrap(), baseline);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::voltcraft::data::{PowerEvent, VoltcraftData};
+use crate::voltcraft::error::VoltcraftError;
+use crate::voltcraft::stats::VoltcraftStatistics;
+use chrono::{DateTime, Datelike, Duration, Local};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Summary of a single data file.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct FileAnalysis {
+    pub event_count: usize,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub total_energy: f64, // kWh
+    pub peak_power: f64,   // kW
+    pub blackout_count: usize,
+    pub blackout_duration: Duration,
+    pub energy_by_year: BTreeMap<i32, f64>, // kWh
+}
+
+/// Summary of a set of data files, merged from the per-file results.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ArchiveSummary {
+    pub file_count: usize,   // files analyzed
+    pub failed_count: usize, // files that could not be analyzed
+    pub event_count: usize,
+    pub start: Option<DateTime<Local>>,
+    pub end: Option<DateTime<Local>>,
+    pub energy_by_year: BTreeMap<i32, f64>, // kWh
+    pub availability: f64, // recorded minutes over the minutes between start and end (0 to 1)
+}
+
+pub type FileResult = (PathBuf, Result<FileAnalysis, VoltcraftError>);
+
+pub fn analyze_file(path: &Path) -> Result<FileAnalysis, VoltcraftError> {
+    let vdf = VoltcraftData::from_raw(fs::read(path)?);
+    let mut events = vdf.parse()?;
+    events.sort_by_key(|e| e.timestamp);
+    events.dedup_by(|a, b| a.timestamp == b.timestamp);
+
+    let energy_by_year = energy_by_year(&events);
+    let stats = VoltcraftStatistics::new(&events);
+    let overall = stats.overall_stats().ok_or(VoltcraftError::EmptyDataSet)?;
+    let blackouts = stats.blackout_stats();
+    Ok(FileAnalysis {
+        event_count: events.len(),
+        start: overall.start,
+        end: overall.end,
+        total_energy: overall.stats.total_active_power,
+        peak_power: overall.stats.max_active_power.power,
+        blackout_count: blackouts.blackout_count,
+        blackout_duration: blackouts.total_blackout_duration,
+        energy_by_year,
+    })
+}
+
+// Analyze the files one after the other, in the given order
+pub fn analyze_files(paths: &[PathBuf]) -> Vec<FileResult> {
+    paths.iter().map(|p| (p.clone(), analyze_file(p))).collect()
+}
+
+// Analyze the files concurrently on the global thread pool; results are in the given order
+#[cfg(feature = "rayon")]
+pub fn analyze_files_parallel(paths: &[PathBuf]) -> Vec<FileResult> {
+    use rayon::prelude::*;
+    paths
+        .par_iter()
+        .map(|p| (p.clone(), analyze_file(p)))
+        .collect()
+}
+
+// Same as analyze_files_parallel, using at most max_threads threads
+#[cfg(feature = "rayon")]
+pub fn analyze_files_parallel_with(
+    paths: &[PathBuf],
+    max_threads: usize,
+) -> Result<Vec<FileResult>, rayon::ThreadPoolBuildError> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(max_threads)
+        .build()?;
+    Ok(pool.install(|| analyze_files_parallel(paths)))
+}
+
+impl ArchiveSummary {
+    // Merge per-file results; overlapping files count their shared minutes twice
+    pub fn merge(results: &[FileResult]) -> ArchiveSummary {
+        let analyses = results
+            .iter()
+            .filter_map(|(_, r)| r.as_ref().ok())
+            .collect::<Vec<_>>();
+        let mut energy_by_year = BTreeMap::new();
+        for analysis in &analyses {
+            for (year, energy) in &analysis.energy_by_year {
+                *energy_by_year.entry(*year).or_insert(0.0) += energy;
+            }
+        }
+        let start = analyses.iter().map(|a| a.start).min();
+        let end = analyses.iter().map(|a| a.end).max();
+        let event_count = analyses.iter().map(|a| a.event_count).sum();
+        let availability = match (start, end) {
+            (Some(start), Some(end)) => {
+                let minutes = (end - start).num_minutes() + 1;
+                (event_count as f64 / minutes as f64).min(1.0)
+            }
+            _ => 0.0,
+        };
+        ArchiveSummary {
+            file_count: analyses.len(),
+            failed_count: results.len() - analyses.len(),
+            event_count,
+            start,
+            end,
+            energy_by_year,
+            availability,
+        }
+    }
+}
+
+fn energy_by_year(events: &[PowerEvent]) -> BTreeMap<i32, f64> {
+    let mut power_sums = BTreeMap::new();
+    for e in events {
+        *power_sums.entry(e.timestamp.year()).or_insert(0.0) += e.power;
+    }
+    power_sums.into_iter().map(|(y, p)| (y, p / 60.0)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::batch::{analyze_files, ArchiveSummary};
+    use crate::voltcraft::error::VoltcraftError;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // Single session starting at the given time, with a power record per minute
+    fn fixture(year: u8, month: u8, day: u8, minutes: usize) -> Vec<u8> {
+        let mut data = vec![0xE0, 0xC5, 0xEA, month, day, year, 23, 0];
+        for m in 0..minutes {
+            let current = 500 + (m as u16 * 37) % 4000;
+            data.extend_from_slice(&2301u16.to_be_bytes());
+            data.extend_from_slice(&current.to_be_bytes());
+            data.push(80 + (m % 20) as u8);
+        }
+        data.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF]);
+        data
+    }
+
+    fn fixtures(name: &str) -> Vec<PathBuf> {
+        let dir = std::env::temp_dir().join(format!("voltcraft_batch_{}", name));
+        fs::create_dir_all(&dir).unwrap();
+        let mut files = vec![
+            ("a.bin", fixture(14, 12, 31, 180)),
+            ("b.bin", fixture(15, 1, 2, 1440)),
+            ("corrupt.bin", vec![0x00, 0x01, 0x02, 0x03, 0x04]),
+            (
+                "empty.bin",
+                vec![0xE0, 0xC5, 0xEA, 1, 3, 15, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF],
+            ),
+        ];
+        // Voltage outside the plausible range
+        let mut low_voltage = fixture(15, 1, 4, 10);
+        low_voltage[8] = 0;
+        files.push(("low_voltage.bin", low_voltage));
+        for day in 5..25 {
+            files.push(("day", fixture(15, 2, day, 60 * day as usize)));
+        }
+        let mut paths = files
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, data))| {
+                let path = dir.join(format!("{:02}-{}", i, name));
+                fs::write(&path, data).unwrap();
+                path
+            })
+            .collect::<Vec<_>>();
+        paths.push(dir.join("missing.bin"));
+        paths
+    }
+
+    #[test]
+    fn batch_isolates_failures() {
+        let results = analyze_files(&fixtures("sequential"));
+        assert!(matches!(
+            results[2].1,
+            Err(VoltcraftError::InvalidMagicNumber)
+        ));
+        assert!(matches!(results[3].1, Err(VoltcraftError::EmptyDataSet)));
+        assert!(matches!(
+            results[4].1,
+            Err(VoltcraftError::InvalidPowerRecord { offset: 8 })
+        ));
+        assert!(matches!(
+            results.last().unwrap().1,
+            Err(VoltcraftError::Io(_))
+        ));
+
+        let summary = ArchiveSummary::merge(&results);
+        assert_eq!(summary.file_count, 22);
+        assert_eq!(summary.failed_count, 4);
+        // The first file runs from 23:00 on New Year's Eve into 2015
+        let first = results[0].1.as_ref().unwrap();
+        assert_eq!(first.energy_by_year.len(), 2);
+        assert!((first.energy_by_year.values().sum::<f64>() - first.total_energy).abs() < 1e-9);
+        assert_eq!(summary.energy_by_year.len(), 2);
+        assert!(summary.availability > 0.0 && summary.availability < 1.0);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn batch_parallel_matches_sequential() {
+        use crate::voltcraft::batch::{analyze_files_parallel, analyze_files_parallel_with};
+        let paths = fixtures("parallel");
+        let sequential = analyze_files(&paths);
+        let same = |results: &[crate::voltcraft::batch::FileResult]| {
+            results.len() == sequential.len()
+                && results.iter().zip(&sequential).all(|(a, b)| {
+                    a.0 == b.0
+                        && match (&a.1, &b.1) {
+                            (Ok(a), Ok(b)) => a == b,
+                            (Err(a), Err(b)) => a.to_string() == b.to_string(),
+                            _ => false,
+                        }
+                })
+        };
+        let parallel = analyze_files_parallel(&paths);
+        assert!(same(&parallel));
+        let capped = analyze_files_parallel_with(&paths, 2).unwrap();
+        assert!(same(&capped));
+        assert_eq!(
+            ArchiveSummary::merge(&capped),
+            ArchiveSummary::merge(&sequential)
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Calendar buckets shared by every function grouping power events by time.
+//!
+//! Buckets are half-open intervals of the local wall clock, `[start, start + length)`: a
+//! power event timestamped exactly on a boundary (00:00 for a day, 18:00 for the 18:00 hour)
+//! belongs to the bucket starting there, never to the one ending there. Weeks start on Monday
+//! (ISO 8601). Around a daylight saving time change the wall clock repeats or skips an hour,
+//! so a day has 23 or 25 hours of power events and the repeated hour is a single bucket.
+
+use chrono::{
+    DateTime, Datelike, Duration, Local, LocalResult, NaiveDateTime, NaiveTime, TimeZone, Timelike,
+};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Granularity {
+    Hour,
+    #[default]
+    Day,
+    Week,  // Monday to Sunday
+    Month, // calendar month
+}
+
+/// Local wall clock start of the bucket containing the timestamp.
+pub fn bucket_of(timestamp: DateTime<Local>, granularity: Granularity) -> NaiveDateTime {
+    let local = timestamp.naive_local();
+    let date = local.date();
+    match granularity {
+        Granularity::Hour => date.and_hms_opt(local.hour(), 0, 0).unwrap(),
+        Granularity::Day => date.and_time(NaiveTime::MIN),
+        Granularity::Week => (date - Duration::days(date.weekday().num_days_from_monday() as i64))
+            .and_time(NaiveTime::MIN),
+        Granularity::Month => date.with_day(1).unwrap().and_time(NaiveTime::MIN),
+    }
+}
+
+/// First instant at or after the local wall clock time in the zone: the earlier of a time
+/// occurring twice when daylight saving time ends, the first minute after the skipped hour
+/// for one that does not exist when it starts. None only far outside the supported dates.
+pub fn first_instant<Tz: TimeZone>(tz: &Tz, local: NaiveDateTime) -> Option<DateTime<Tz>> {
+    // Time zone transitions skip at most a few hours
+    (0..=24 * 60).find_map(|m| {
+        match tz.from_local_datetime(&(local + Duration::minutes(m))) {
+            LocalResult::Single(t) => Some(t),
+            // Not necessarily in chronological order
+            LocalResult::Ambiguous(a, b) => Some(a.min(b)),
+            LocalResult::None => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::bucket::{bucket_of, Granularity};
+    use chrono::{Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+
+    fn naive(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, mo, d)
+            .unwrap()
+            .and_hms_opt(h, mi, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn bucket_boundaries() {
+        let at = |y, mo, d, h, mi| Local.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap();
+        let last_second = |t| t - Duration::seconds(1);
+
+        // Exactly on the hour starts the bucket, the second before ends the previous one
+        let t = at(2014, 9, 11, 18, 0);
+        assert_eq!(bucket_of(t, Granularity::Hour), naive(2014, 9, 11, 18, 0));
+        assert_eq!(
+            bucket_of(last_second(t), Granularity::Hour),
+            naive(2014, 9, 11, 17, 0)
+        );
+        assert_eq!(
+            bucket_of(at(2014, 9, 11, 18, 59), Granularity::Hour),
+            naive(2014, 9, 11, 18, 0)
+        );
+
+        // Exactly at midnight belongs to the new day, week and month
+        let t = at(2014, 9, 1, 0, 0); // a Monday
+        assert_eq!(bucket_of(t, Granularity::Day), naive(2014, 9, 1, 0, 0));
+        assert_eq!(bucket_of(t, Granularity::Week), naive(2014, 9, 1, 0, 0));
+        assert_eq!(bucket_of(t, Granularity::Month), naive(2014, 9, 1, 0, 0));
+        let before = last_second(t);
+        assert_eq!(
+            bucket_of(before, Granularity::Day),
+            naive(2014, 8, 31, 0, 0)
+        );
+        assert_eq!(
+            bucket_of(before, Granularity::Week),
+            naive(2014, 8, 25, 0, 0)
+        );
+        assert_eq!(
+            bucket_of(before, Granularity::Month),
+            naive(2014, 8, 1, 0, 0)
+        );
+
+        // Weeks start on Monday, also across a year boundary
+        assert_eq!(
+            bucket_of(at(2015, 1, 4, 23, 59), Granularity::Week),
+            naive(2014, 12, 29, 0, 0)
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use crate::voltcraft::data::{decode_power_record, decode_start, PowerEvent};
+use crate::voltcraft::error::VoltcraftError;
+use chrono::{DateTime, Duration, Local};
+use std::io::{Read, Seek, SeekFrom};
+
+const MAGIC_NUMBER: [u8; 3] = [0xE0, 0xC5, 0xEA];
+const END_OF_DATA: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+
+/// Position of a `ChunkedParser` between two chunks, to persist and resume from later.
+/// It always lies between two records or headers, never inside one.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParserState {
+    offset: u64,                             // bytes decoded so far
+    session: Option<(DateTime<Local>, u64)>, // start of the current session and records decoded in it
+    done: bool,                              // end of data reached
+}
+
+impl ParserState {
+    // Bytes of the input decoded so far
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+/// Decoder of power events a chunk at a time, for inputs too large to hold in memory such as a
+/// concatenation of data files (each one with its end of data marker).
+///
+/// The events are the ones `VoltcraftData::parse` returns for each of the concatenated files.
+/// A failed chunk leaves the state where the chunk started.
+pub struct ChunkedParser<R> {
+    reader: R,
+    buffer: Vec<u8>, // bytes read from the input, starting at state.offset
+    state: ParserState,
+}
+
+impl<R: Read> ChunkedParser<R> {
+    pub fn new(reader: R) -> ChunkedParser<R> {
+        ChunkedParser {
+            reader,
+            buffer: Vec::new(),
+            state: ParserState::default(),
+        }
+    }
+
+    pub fn state(&self) -> ParserState {
+        self.state.clone()
+    }
+
+    /// Decode up to `max_records` power events (at least one), or None once all were decoded.
+    pub fn next_chunk(
+        &mut self,
+        max_records: usize,
+    ) -> Result<Option<Vec<PowerEvent>>, VoltcraftError> {
+        let mut state = self.state.clone();
+        let mut pos = 0; // bytes of the buffer decoded by this chunk
+        let mut events = Vec::new();
+        while !state.done && events.len() < max_records.max(1) {
+            self.fill(pos + 8)?;
+            let rest = &self.buffer[pos..];
+            let offset = (state.offset + pos as u64) as usize; // in the input
+            if rest.starts_with(&END_OF_DATA) {
+                // Another session or data file may follow, after the padding of the device
+                pos += 4;
+                loop {
+                    self.fill(pos + 1)?;
+                    match self.buffer.get(pos) {
+                        Some(0xFF) => pos += 1,
+                        _ => break,
+                    }
+                }
+                self.fill(pos + 3)?;
+                state.done = !self.buffer[pos..].starts_with(&MAGIC_NUMBER);
+                state.session = None;
+            } else if rest.starts_with(&MAGIC_NUMBER) {
+                let start = match rest.get(3..8) {
+                    Some(bytes) => decode_start(bytes.try_into().unwrap())
+                        .ok_or(VoltcraftError::InvalidTimestamp { offset })?,
+                    _ => return Err(VoltcraftError::TruncatedData { offset }),
+                };
+                pos += 8;
+                state.session = Some((start, 0));
+            } else if rest.is_empty() && state.offset + pos as u64 > 0 {
+                // Tolerate a missing end of data marker
+                state.done = true;
+            } else {
+                let (start, count) = state
+                    .session
+                    .as_mut()
+                    .ok_or(VoltcraftError::InvalidMagicNumber)?;
+                let record = rest
+                    .first_chunk::<5>()
+                    .ok_or(VoltcraftError::TruncatedData { offset })?;
+                let reading = decode_power_record(record);
+                if !reading.is_plausible() {
+                    return Err(VoltcraftError::InvalidPowerRecord { offset });
+                }
+                events.push(PowerEvent::from_reading(
+                    *start + Duration::minutes(*count as i64),
+                    reading,
+                ));
+                *count += 1;
+                pos += 5;
+            }
+        }
+        self.buffer.drain(..pos);
+        state.offset += pos as u64;
+        self.state = state;
+        Ok((!events.is_empty() || !self.state.done).then_some(events))
+    }
+
+    // Read until the buffer holds len bytes or the input ends
+    fn fill(&mut self, len: usize) -> Result<(), VoltcraftError> {
+        while self.buffer.len() < len {
+            let mut block = [0u8; 8192];
+            let n = self.reader.read(&mut block)?;
+            if n == 0 {
+                break;
+            }
+            self.buffer.extend_from_slice(&block[..n]);
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> ChunkedParser<R> {
+    // Continue decoding from a state returned by an earlier parser of the same input
+    pub fn resume(mut reader: R, state: ParserState) -> Result<ChunkedParser<R>, VoltcraftError> {
+        reader.seek(SeekFrom::Start(state.offset))?;
+        Ok(ChunkedParser {
+            reader,
+            buffer: Vec::new(),
+            state,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::chunked::{ChunkedParser, ParserState};
+    use crate::voltcraft::data::{PowerEvent, VoltcraftData};
+    use crate::voltcraft::error::VoltcraftError;
+    use std::io::Cursor;
+
+    // Data file with a session of the given length per start minute
+    fn data_file(sessions: &[(u8, usize)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (i, (minute, records)) in sessions.iter().enumerate() {
+            data.extend_from_slice(&[0xE0, 0xC5, 0xEA, 9, 11 + i as u8, 14, 18, *minute]);
+            for r in 0..*records {
+                data.extend_from_slice(&(2250 + (r % 100) as u16).to_be_bytes());
+                data.extend_from_slice(&(400 + (r * 13 % 3000) as u16).to_be_bytes());
+                data.push(50 + (r % 50) as u8);
+            }
+        }
+        data.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF]);
+        data
+    }
+
+    fn key(e: &PowerEvent) -> (i64, u64, u64, u64) {
+        (
+            e.timestamp.timestamp(),
+            e.voltage.to_bits(),
+            e.current.to_bits(),
+            e.power_factor.to_bits(),
+        )
+    }
+
+    #[test]
+    fn chunked_resume_matches_parse() {
+        // The first file ends with the padding of the device
+        let files = [
+            [
+                data_file(&[(43, 250), (10, 333), (0, 0), (59, 17)]),
+                vec![0xFF; 11],
+            ]
+            .concat(),
+            data_file(&[(5, 1000)]),
+        ];
+        let expected = files
+            .iter()
+            .flat_map(|f| VoltcraftData::from_raw(f.clone()).parse().unwrap())
+            .map(|e| key(&e))
+            .collect::<Vec<_>>();
+        let archive = files.concat();
+
+        // A new parser for every chunk of 100 records, resumed from the persisted state
+        let mut state = ParserState::default();
+        let mut events = Vec::new();
+        let mut chunks = 0;
+        loop {
+            let mut parser = ChunkedParser::resume(Cursor::new(&archive), state).unwrap();
+            let chunk = parser.next_chunk(100).unwrap();
+            state = parser.state();
+            #[cfg(feature = "serde")]
+            {
+                let json = serde_json::to_string(&state).unwrap();
+                state = serde_json::from_str(&json).unwrap();
+            }
+            match chunk {
+                Some(chunk) => {
+                    assert!(chunk.len() <= 100);
+                    events.extend(chunk.iter().map(key));
+                    chunks += 1;
+                }
+                None => break,
+            }
+        }
+        assert_eq!(events, expected);
+        assert_eq!(chunks, 16);
+        assert_eq!(state.offset(), archive.len() as u64);
+
+        // The same stream in one go from a single parser
+        let mut parser = ChunkedParser::new(archive.as_slice());
+        let mut events = Vec::new();
+        while let Some(chunk) = parser.next_chunk(usize::MAX).unwrap() {
+            events.extend(chunk.iter().map(key));
+        }
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn chunked_errors() {
+        let mut parser = ChunkedParser::new([0x00, 0x01, 0x02, 0x03, 0x04, 0x05].as_slice());
+        assert!(matches!(
+            parser.next_chunk(10),
+            Err(VoltcraftError::InvalidMagicNumber)
+        ));
+
+        // A truncated record fails its chunk and keeps the state before it
+        let mut data = data_file(&[(43, 150)]);
+        data.truncate(data.len() - 7);
+        let mut parser = ChunkedParser::new(data.as_slice());
+        assert_eq!(parser.next_chunk(100).unwrap().unwrap().len(), 100);
+        let state = parser.state();
+        assert_eq!(state.offset(), 8 + 500);
+        assert!(matches!(
+            parser.next_chunk(100),
+            Err(VoltcraftError::TruncatedData { offset: 753 }) // the last record
+        ));
+        assert_eq!(parser.state(), state);
+    }
+}
@@ -0,0 +1,493 @@
+use crate::voltcraft::consts::MAX_CURRENT;
+use crate::voltcraft::data::PowerEvent;
+use crate::voltcraft::stats::median;
+use std::fmt;
+use std::ops::{BitOr, BitOrAssign};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OutlierStrategy {
+    // Lower the power to the highest of the adjacent samples
+    ClampToNeighborMax,
+    // Replace the sample with the average of the adjacent samples
+    ReplaceWithInterpolation,
+    // Drop the sample
+    Remove,
+}
+
+// Samples compared against on each side of the candidate
+const WINDOW: usize = 15;
+// Minimum distance from the window median, in scaled median absolute deviations
+const MAX_DEVIATION: f64 = 10.0;
+// Lower bound of the deviation scale, so that flat data does not make every change an outlier (kW)
+const MIN_SCALE: f64 = 0.05;
+// A sample is isolated when it exceeds both adjacent samples by this factor
+const ISOLATION_FACTOR: f64 = 4.0;
+
+/// Repair single-sample power glitches, returning the indices (in the original events) of the
+/// samples touched.
+///
+/// A sample is an outlier when its active power is far above the median of the surrounding
+/// samples (measured in median absolute deviations) and much higher than both adjacent samples,
+/// so a genuine appliance spike lasting a few minutes is never flagged. Events are expected in
+/// chronological order; the events are a `Vec` so that the `Remove` strategy can drop samples.
+pub fn clean_outliers(events: &mut Vec<PowerEvent>, strategy: OutlierStrategy) -> Vec<usize> {
+    let outliers = (0..events.len())
+        .filter(|i| is_outlier(events, *i))
+        .collect::<Vec<_>>();
+    match strategy {
+        OutlierStrategy::ClampToNeighborMax => {
+            for &i in &outliers {
+                let (previous, next) = neighbors(events, i);
+                let e = &mut events[i];
+                e.power = previous.power.max(next.power);
+                e.apparent_power = previous.apparent_power.max(next.apparent_power);
+                e.current = e.apparent_power * 1000.0 / e.voltage;
+            }
+        }
+        OutlierStrategy::ReplaceWithInterpolation => {
+            for &i in &outliers {
+                let (previous, next) = neighbors(events, i);
+                let e = &mut events[i];
+                e.voltage = (previous.voltage + next.voltage) / 2.0;
+                e.current = (previous.current + next.current) / 2.0;
+                e.power_factor = (previous.power_factor + next.power_factor) / 2.0;
+                e.power = (previous.power + next.power) / 2.0;
+                e.apparent_power = (previous.apparent_power + next.apparent_power) / 2.0;
+            }
+        }
+        OutlierStrategy::Remove => {
+            let mut index = 0;
+            events.retain(|_| {
+                index += 1;
+                outliers.binary_search(&(index - 1)).is_err()
+            });
+        }
+    }
+    outliers
+}
+
+fn is_outlier(events: &[PowerEvent], i: usize) -> bool {
+    let (previous, next) = neighbors(events, i);
+    let power = events[i].power;
+    if events.len() < 3 || power <= ISOLATION_FACTOR * previous.power.max(next.power) {
+        return false;
+    }
+    let window = events[i.saturating_sub(WINDOW)..(i + WINDOW + 1).min(events.len())]
+        .iter()
+        .enumerate()
+        .filter(|(j, _)| *j + i.saturating_sub(WINDOW) != i)
+        .map(|(_, e)| e.power)
+        .collect::<Vec<_>>();
+    let center = median(window.clone());
+    let mad = median(window.iter().map(|p| (p - center).abs()).collect());
+    // 1.4826 makes the deviation comparable to a standard deviation for normal data
+    let scale = (1.4826 * mad).max(MIN_SCALE);
+    (power - center) / scale > MAX_DEVIATION
+}
+
+// Adjacent samples, the only one available being used twice at either end
+fn neighbors(events: &[PowerEvent], i: usize) -> (PowerEvent, PowerEvent) {
+    let previous = events[i.saturating_sub(1)];
+    let next = events[(i + 1).min(events.len() - 1)];
+    match (i == 0, i + 1 == events.len()) {
+        (true, _) => (next, next),
+        (_, true) => (previous, previous),
+        _ => (previous, next),
+    }
+}
+
+/// What to do with samples recording a zero power factor while current flows,
+/// which some loggers do for electronic loads and which makes the active power zero.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum PfZeroPolicy {
+    // Keep the samples as recorded
+    #[default]
+    Trust,
+    // Use a power factor of 1
+    AssumeUnity,
+    // Use the given power factor
+    AssumeValue(f64),
+    // Keep the samples as recorded, only listing them
+    Flag,
+}
+
+// Apply the policy to the samples with a zero power factor and a nonzero current, recomputing
+// their active power. Returns the indices of the samples changed (or listed, for Flag).
+pub fn apply_pf_zero_policy(events: &mut [PowerEvent], policy: PfZeroPolicy) -> Vec<usize> {
+    let power_factor = match policy {
+        PfZeroPolicy::Trust => return Vec::new(),
+        PfZeroPolicy::AssumeUnity => Some(1.0),
+        PfZeroPolicy::AssumeValue(pf) => Some(pf),
+        PfZeroPolicy::Flag => None,
+    };
+    let mut touched = Vec::new();
+    for (i, e) in events.iter_mut().enumerate() {
+        if e.power_factor == 0.0 && e.current > 0.0 {
+            if let Some(pf) = power_factor {
+                e.power_factor = pf;
+                e.power = e.derived_power();
+            }
+            touched.push(i);
+        }
+    }
+    touched
+}
+
+/// Reasons for which `flag_events` finds a power event suspicious, a set combined with `|`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventFlags(u8);
+
+impl EventFlags {
+    pub const NONE: EventFlags = EventFlags(0);
+    pub const VOLTAGE_OUT_OF_RANGE: EventFlags = EventFlags(1);
+    pub const CURRENT_OUT_OF_RANGE: EventFlags = EventFlags(1 << 1);
+    pub const POWER_FACTOR_SUSPECT: EventFlags = EventFlags(1 << 2);
+    pub const SPIKE: EventFlags = EventFlags(1 << 3);
+
+    const NAMES: [(EventFlags, &'static str); 4] = [
+        (EventFlags::VOLTAGE_OUT_OF_RANGE, "voltage out of range"),
+        (EventFlags::CURRENT_OUT_OF_RANGE, "current out of range"),
+        (EventFlags::POWER_FACTOR_SUSPECT, "power factor suspect"),
+        (EventFlags::SPIKE, "spike"),
+    ];
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    // True if every flag of `other` is set
+    pub fn contains(self, other: EventFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: EventFlags) {
+        self.0 |= other.0;
+    }
+}
+
+impl BitOr for EventFlags {
+    type Output = EventFlags;
+
+    fn bitor(self, other: EventFlags) -> EventFlags {
+        EventFlags(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for EventFlags {
+    fn bitor_assign(&mut self, other: EventFlags) {
+        self.insert(other);
+    }
+}
+
+impl fmt::Display for EventFlags {
+    // "voltage out of range, spike", "none" without any flag
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "none");
+        }
+        let names = EventFlags::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name);
+        write!(f, "{}", names.collect::<Vec<_>>().join(", "))
+    }
+}
+
+/// Bounds outside which `flag_events` finds a power event suspicious. The defaults suit EU
+/// mains (230 V) and the 16 A rating of the logger.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ValidationRules {
+    pub min_voltage: f64,      // volts
+    pub max_voltage: f64,      // volts
+    pub max_current: f64,      // ampers
+    pub min_power_factor: f64, // below this power factor while current flows
+    pub max_power_factor: f64, // above this power factor, whatever the current
+    pub spike_percent: f64,    // a spike differs from both adjacent samples by this percentage
+    pub min_spike_power: f64,  // ... and by this power at least (kW), so that standby noise is none
+}
+
+impl ValidationRules {
+    pub fn with_voltage_range(mut self, min_voltage: f64, max_voltage: f64) -> Self {
+        (self.min_voltage, self.max_voltage) = (min_voltage, max_voltage);
+        self
+    }
+
+    pub fn with_max_current(mut self, max_current: f64) -> Self {
+        self.max_current = max_current;
+        self
+    }
+
+    pub fn with_power_factor_range(mut self, min_power_factor: f64, max_power_factor: f64) -> Self {
+        (self.min_power_factor, self.max_power_factor) = (min_power_factor, max_power_factor);
+        self
+    }
+
+    // Spikes beyond the percentage and power (kW), f64::INFINITY never flagging any
+    pub fn with_spike(mut self, spike_percent: f64, min_spike_power: f64) -> Self {
+        (self.spike_percent, self.min_spike_power) = (spike_percent, min_spike_power);
+        self
+    }
+
+    // Flags of the power event at the index, judged against its adjacent samples
+    fn flags(&self, events: &[PowerEvent], i: usize) -> EventFlags {
+        let e = &events[i];
+        let mut flags = EventFlags::NONE;
+        if !(self.min_voltage..=self.max_voltage).contains(&e.voltage) {
+            flags |= EventFlags::VOLTAGE_OUT_OF_RANGE;
+        }
+        if e.current > self.max_current || e.current < 0.0 {
+            flags |= EventFlags::CURRENT_OUT_OF_RANGE;
+        }
+        if e.power_factor > self.max_power_factor
+            || (e.power_factor < self.min_power_factor && e.current > 0.0)
+        {
+            flags |= EventFlags::POWER_FACTOR_SUSPECT;
+        }
+        if i > 0 && i + 1 < events.len() && self.is_spike(&events[i - 1], e, &events[i + 1]) {
+            flags |= EventFlags::SPIKE;
+        }
+        flags
+    }
+
+    // Above or below both adjacent samples, by more than the spike percentage (of the lower
+    // of the two powers) and power
+    fn is_spike(&self, previous: &PowerEvent, e: &PowerEvent, next: &PowerEvent) -> bool {
+        let differs = |neighbor: &PowerEvent| {
+            let delta = (e.power - neighbor.power).abs();
+            let lower = e.power.min(neighbor.power);
+            delta > self.min_spike_power && delta * 100.0 > self.spike_percent * lower
+        };
+        let outside = (e.power > previous.power) == (e.power > next.power);
+        outside && differs(previous) && differs(next)
+    }
+}
+
+impl Default for ValidationRules {
+    // 230V -22% +13%, up to the 16 A of the logger, power factors up to 1 and a sample at
+    // least 5 times (or a fifth of) its neighbors by more than 1 kW
+    fn default() -> Self {
+        ValidationRules {
+            min_voltage: 180.0,
+            max_voltage: 260.0,
+            max_current: MAX_CURRENT,
+            min_power_factor: 0.0,
+            max_power_factor: 1.0,
+            spike_percent: 400.0,
+            min_spike_power: 1.0,
+        }
+    }
+}
+
+/// Each power event with the reasons it looks suspicious, empty for a plausible one, in the
+/// given (chronological) order. The first and last events are never spikes, having a single
+/// adjacent sample.
+pub fn flag_events(
+    events: &[PowerEvent],
+    rules: &ValidationRules,
+) -> Vec<(PowerEvent, EventFlags)> {
+    (0..events.len())
+        .map(|i| (events[i], rules.flags(events, i)))
+        .collect()
+}
+
+/// The power events without any flag of `flag_events`, in the given order.
+pub fn clean(events: &[PowerEvent], rules: &ValidationRules) -> Vec<PowerEvent> {
+    flag_events(events, rules)
+        .into_iter()
+        .filter(|(_, flags)| flags.is_empty())
+        .map(|(e, _)| e)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::clean::{
+        apply_pf_zero_policy, clean, clean_outliers, flag_events, EventFlags, OutlierStrategy,
+        PfZeroPolicy, ValidationRules,
+    };
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::stats::VoltcraftStatistics;
+    use chrono::{DateTime, Duration, Local, TimeZone};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    // A day with a varying base load, fridge cycles, a kettle, an oven and one corrupted record
+    fn day_with_glitch() -> Vec<PowerEvent> {
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        (0..1440)
+            .map(|m| {
+                let mut power = 0.25 + 0.1 * ((m as f64) / 200.0).sin();
+                if m % 60 < 20 {
+                    power += 0.12; // fridge compressor
+                }
+                match m {
+                    420..=423 => power += 2.2,   // kettle
+                    1080..=1124 => power += 3.0, // oven
+                    1200..=1201 => power += 1.8, // microwave, two minutes during a fridge cycle
+                    700 => power = 65.0,         // corrupted record
+                    _ => {}
+                }
+                event(start + Duration::minutes(m), power)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clean_outliers_touches_only_the_glitch() {
+        let original = day_with_glitch();
+
+        let mut events = original.clone();
+        assert_eq!(
+            clean_outliers(&mut events, OutlierStrategy::ClampToNeighborMax),
+            vec![700]
+        );
+        let neighbor_max = original[699].power.max(original[701].power);
+        assert_eq!(events[700].power, neighbor_max);
+        assert_eq!(events[700].apparent_power, neighbor_max);
+        assert!(events
+            .iter()
+            .zip(&original)
+            .enumerate()
+            .all(|(i, (a, b))| i == 700 || a.power == b.power));
+
+        let mut events = original.clone();
+        assert_eq!(
+            clean_outliers(&mut events, OutlierStrategy::ReplaceWithInterpolation),
+            vec![700]
+        );
+        let average = (original[699].power + original[701].power) / 2.0;
+        assert!((events[700].power - average).abs() < 1e-12);
+        assert_eq!(events[700].timestamp, original[700].timestamp);
+
+        let mut events = original.clone();
+        assert_eq!(
+            clean_outliers(&mut events, OutlierStrategy::Remove),
+            vec![700]
+        );
+        assert_eq!(events.len(), 1439);
+        assert_eq!(events[700].timestamp, original[701].timestamp);
+    }
+
+    #[test]
+    fn clean_outliers_keeps_clean_data() {
+        let mut events = day_with_glitch();
+        events[700].power = events[699].power;
+        assert!(clean_outliers(&mut events, OutlierStrategy::Remove).is_empty());
+        assert_eq!(events.len(), 1440);
+    }
+
+    #[test]
+    fn pf_zero_policies() {
+        // An hour at 2 A, a quarter of it recorded with a zero power factor
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..60)
+            .map(|m| {
+                let power_factor = if m % 4 == 0 { 0.0 } else { 0.9 };
+                PowerEvent {
+                    current: 2.0,
+                    power_factor,
+                    apparent_power: 0.46,
+                    ..event(start + Duration::minutes(m), 0.46 * power_factor)
+                }
+            })
+            .collect::<Vec<_>>();
+        let energy = |events: &[PowerEvent]| events.iter().map(|e| e.power).sum::<f64>() / 60.0;
+
+        let mut trusted = events.clone();
+        assert!(apply_pf_zero_policy(&mut trusted, PfZeroPolicy::Trust).is_empty());
+        assert!((energy(&trusted) - 0.46 * 0.9 * 0.75).abs() < 1e-9);
+
+        let mut flagged = events.clone();
+        let list = apply_pf_zero_policy(&mut flagged, PfZeroPolicy::Flag);
+        assert_eq!(list.len(), 15);
+        assert_eq!(list[1], 4);
+        assert_eq!(energy(&flagged), energy(&trusted));
+
+        let mut assumed = events.clone();
+        let changed = apply_pf_zero_policy(&mut assumed, PfZeroPolicy::AssumeValue(0.55));
+        assert_eq!(changed, list);
+        assert!((energy(&assumed) - 0.46 * (0.9 * 0.75 + 0.55 * 0.25)).abs() < 1e-9);
+        assert_eq!(assumed[0].power_factor, 0.55);
+    }
+
+    #[test]
+    fn flag_events_and_clean() {
+        let mut events = day_with_glitch();
+        events[100].voltage = 170.0;
+        events[200].current = 17.5;
+        events[300].power_factor = 1.27;
+        events[301].voltage = 265.0;
+        events[301].power_factor = 1.5;
+        let rules = ValidationRules::default();
+
+        let flagged = flag_events(&events, &rules);
+        assert_eq!(flagged.len(), events.len());
+        let suspect = flagged
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, flags))| !flags.is_empty())
+            .map(|(i, (_, flags))| (i, *flags))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            suspect,
+            vec![
+                (100, EventFlags::VOLTAGE_OUT_OF_RANGE),
+                (200, EventFlags::CURRENT_OUT_OF_RANGE),
+                (300, EventFlags::POWER_FACTOR_SUSPECT),
+                (
+                    301,
+                    EventFlags::VOLTAGE_OUT_OF_RANGE | EventFlags::POWER_FACTOR_SUSPECT
+                ),
+                (700, EventFlags::CURRENT_OUT_OF_RANGE | EventFlags::SPIKE),
+            ]
+        );
+        // The kettle, oven and microwave run for several minutes and are no spikes
+        assert_eq!(
+            suspect[3].1.to_string(),
+            "voltage out of range, power factor suspect"
+        );
+        assert!(suspect[3].1.contains(EventFlags::POWER_FACTOR_SUSPECT));
+        assert!(!suspect[3].1.contains(EventFlags::SPIKE));
+        assert_eq!(EventFlags::NONE.to_string(), "none");
+
+        // A one minute dip is a spike too, unless the spikes are disabled
+        events[1100].power = 0.3;
+        assert_eq!(flag_events(&events, &rules)[1100].1, EventFlags::SPIKE);
+        let no_spikes = rules.with_spike(f64::INFINITY, 0.0);
+        assert!(flag_events(&events, &no_spikes)[1100].1.is_empty());
+        let wide = rules
+            .with_voltage_range(160.0, 270.0)
+            .with_max_current(20.0);
+        assert!(flag_events(&events, &wide)[100].1.is_empty());
+        assert!(flag_events(&events, &wide)[200].1.is_empty());
+
+        let cleaned = clean(&events, &rules);
+        assert_eq!(cleaned.len(), events.len() - 6);
+        assert!(cleaned.iter().all(|e| e.power < 10.0));
+
+        // The statistics trust the logger unless told otherwise
+        let trusted = VoltcraftStatistics::new(&events);
+        assert_eq!(trusted.event_count(), 1440);
+        let overall = trusted.overall_stats().unwrap().stats;
+        assert_eq!(overall.max_active_power.power, 65.0);
+        let validated = VoltcraftStatistics::new(&events).with_validation(&rules);
+        assert_eq!(validated.event_count(), 1434);
+        let overall = validated.overall_stats().unwrap().stats;
+        assert!(overall.max_active_power.power < 4.0);
+        assert_eq!(overall.min_voltage.voltage, 230.0);
+    }
+}
@@ -0,0 +1,419 @@
+use crate::voltcraft::bucket::{bucket_of, Granularity};
+use crate::voltcraft::data::PowerEvent;
+use crate::voltcraft::distribution::nearest_rank;
+use crate::voltcraft::sorted::SortedEvents;
+use crate::voltcraft::stats::{CoverageInfo, OverallPowerInfo, PowerInterval, PowerStats};
+use chrono::{DateTime, Duration, Local, NaiveDate, Timelike};
+use itertools::Itertools;
+use std::error::Error;
+use std::fmt;
+
+/// Summary replacing the power events of a day in a `CompactedDataset`.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct CompactedDay {
+    pub summary: PowerInterval,
+    pub hourly_energy: [f64; 24], // active energy by hour of the day (kWh), for charting
+    pub first: DateTime<Local>,   // first power event of the day
+    pub last: DateTime<Local>,    // last power event of the day
+    // Sums over the power events of the day, so that combined totals are computed from the same
+    // sums as on the raw events
+    pub sample_count: usize,
+    pub power_sum: f64,          // kW
+    pub apparent_power_sum: f64, // kVA
+    pub voltage_sum: f64,        // V
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub current_sum: f64, // A
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub power_factor_sum: f64,
+}
+
+/// Power data with the older days replaced by daily summaries (see `compact`).
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct CompactedDataset {
+    pub days: Vec<CompactedDay>, // compacted days, in chronological order
+    pub events: Vec<PowerEvent>, // power events after the compacted days, in chronological order
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompactionError {
+    // The statistic needs the power events, but the range includes compacted days
+    InsufficientResolution { from: NaiveDate, to: NaiveDate }, // compacted days in the range
+}
+
+impl fmt::Display for CompactionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompactionError::InsufficientResolution { from, to } => write!(
+                f,
+                "only daily summaries are kept from {} to {}, power events are needed",
+                from, to
+            ),
+        }
+    }
+}
+
+impl Error for CompactionError {}
+
+/// Replace the days before the day of `older_than` with daily summaries, keeping the power
+/// events from that day on.
+pub fn compact(events: &SortedEvents, older_than: DateTime<Local>) -> CompactedDataset {
+    let day_of = |t: DateTime<Local>| bucket_of(t, Granularity::Day).date();
+    let cutoff = day_of(older_than);
+    let split = events.partition_point(|e| day_of(e.timestamp) < cutoff);
+    let days = events[..split]
+        .iter()
+        .group_by(|e| day_of(e.timestamp))
+        .into_iter()
+        .map(|(date, day)| CompactedDay::new(date, &day.cloned().collect::<Vec<_>>()))
+        .collect();
+    CompactedDataset {
+        days,
+        events: events[split..].to_vec(),
+    }
+}
+
+// Same as compact, for power events in any order
+pub fn compact_slice(events: &[PowerEvent], older_than: DateTime<Local>) -> CompactedDataset {
+    compact(&SortedEvents::from_vec(events.to_vec()), older_than)
+}
+
+impl CompactedDay {
+    // Summary of the power events of a day (at least one)
+    fn new(date: NaiveDate, events: &[PowerEvent]) -> CompactedDay {
+        let mut hourly_energy = [0.0; 24];
+        for e in events {
+            hourly_energy[e.timestamp.hour() as usize] += e.power / 60.0;
+        }
+        CompactedDay {
+            summary: PowerInterval::new(date, PowerStats::from_events(events).unwrap()),
+            hourly_energy,
+            first: events[0].timestamp,
+            last: events[events.len() - 1].timestamp,
+            sample_count: events.len(),
+            power_sum: events.iter().fold(0f64, |sum, x| sum + x.power),
+            apparent_power_sum: events.iter().fold(0f64, |sum, x| sum + x.apparent_power),
+            voltage_sum: events.iter().fold(0f64, |sum, x| sum + x.voltage),
+            current_sum: events.iter().fold(0f64, |sum, x| sum + x.current),
+            power_factor_sum: events.iter().fold(0f64, |sum, x| sum + x.power_factor),
+        }
+    }
+}
+
+impl CompactedDataset {
+    pub fn is_empty(&self) -> bool {
+        self.days.is_empty() && self.events.is_empty()
+    }
+
+    /// Overall statistics over the compacted days and the power events, as `overall_stats`
+    /// computes them on the original power events (None without data).
+    pub fn overall_stats(&self) -> Option<OverallPowerInfo> {
+        let raw = PowerStats::from_events(&self.events);
+        let start = self
+            .days
+            .first()
+            .map(|d| d.first)
+            .or(self.events.first().map(|e| e.timestamp))?;
+        let end = self
+            .events
+            .last()
+            .map(|e| e.timestamp)
+            .or(self.days.last().map(|d| d.last))?;
+
+        // Sums of the days and of the power events, with the extremes of both
+        let mut count = self.events.len();
+        let mut power_sum = self.events.iter().fold(0f64, |sum, x| sum + x.power);
+        let mut apparent_power_sum = self
+            .events
+            .iter()
+            .fold(0f64, |sum, x| sum + x.apparent_power);
+        let mut voltage_sum = self.events.iter().fold(0f64, |sum, x| sum + x.voltage);
+        let mut current_sum = self.events.iter().fold(0f64, |sum, x| sum + x.current);
+        let mut power_factor_sum = self.events.iter().fold(0f64, |sum, x| sum + x.power_factor);
+        let mut summaries = self
+            .days
+            .iter()
+            .map(|d| &d.summary.stats)
+            .collect::<Vec<_>>();
+        summaries.extend(raw.as_ref());
+        for day in &self.days {
+            count += day.sample_count;
+            power_sum += day.power_sum;
+            apparent_power_sum += day.apparent_power_sum;
+            voltage_sum += day.voltage_sum;
+            current_sum += day.current_sum;
+            power_factor_sum += day.power_factor_sum;
+        }
+        let extreme = |value: fn(&PowerStats) -> PowerEvent, key: fn(&PowerEvent) -> f64| {
+            summaries
+                .iter()
+                .map(|s| value(s))
+                .max_by(|a, b| key(a).partial_cmp(&key(b)).unwrap())
+                .unwrap()
+        };
+        let extreme_value = |value: fn(&PowerStats) -> f64| {
+            summaries.iter().map(|s| value(s)).fold(f64::MIN, f64::max)
+        };
+        let stats = PowerStats {
+            total_active_power: power_sum / 60f64,
+            avg_active_power: power_sum / count as f64,
+            max_active_power: extreme(|s| s.max_active_power, |e| e.power),
+            total_apparent_power: apparent_power_sum / 60f64,
+            avg_apparent_power: apparent_power_sum / count as f64,
+            max_apparent_power: extreme(|s| s.max_apparent_power, |e| e.apparent_power),
+            min_voltage: extreme(|s| s.min_voltage, |e| -e.voltage),
+            max_voltage: extreme(|s| s.max_voltage, |e| e.voltage),
+            avg_voltage: voltage_sum / count as f64,
+            // The power events of the compacted days are gone, so the highest percentile of any
+            // day is an upper bound of the overall one
+            p95_voltage: extreme_value(|s| s.p95_voltage),
+            min_current: extreme(|s| s.min_current, |e| -e.current),
+            max_current: extreme(|s| s.max_current, |e| e.current),
+            avg_current: current_sum / count as f64,
+            avg_power_factor: power_factor_sum / count as f64,
+            p95_power: extreme_value(|s| s.p95_power),
+            worst_power_factor: summaries
+                .iter()
+                .filter_map(|s| s.worst_power_factor)
+                .min_by(|a, b| a.power_factor.partial_cmp(&b.power_factor).unwrap()),
+            // Each day weighs with its apparent energy, as its power events would
+            weighted_power_factor: match apparent_power_sum > 0.0 {
+                true => {
+                    summaries
+                        .iter()
+                        .map(|s| s.weighted_power_factor * s.total_apparent_power)
+                        .sum::<f64>()
+                        * 60f64
+                        / apparent_power_sum
+                }
+                false => 0.0,
+            },
+            total_reactive_energy: summaries.iter().map(|s| s.total_reactive_energy).sum(),
+            // Windows running over midnight into a compacted day are not seen
+            worst_sustained_power_factor: summaries
+                .iter()
+                .filter_map(|s| s.worst_sustained_power_factor)
+                .min_by(|a, b| a.power_factor.partial_cmp(&b.power_factor).unwrap()),
+            total_duration: (end - start) + Duration::minutes(1),
+            active: None,
+            coverage: CoverageInfo::new(
+                count as i64,
+                ((end - start) + Duration::minutes(1)).num_minutes(),
+            ),
+        };
+        let total_duration = end - start;
+        let avg_daily_power_consumption = (total_duration >= Duration::days(1))
+            .then(|| stats.total_active_power / (total_duration.num_seconds() as f64 / 86400.0));
+        Some(OverallPowerInfo {
+            start,
+            end,
+            stats,
+            avg_daily_power_consumption,
+        })
+    }
+
+    /// Active power (kW) below which the given fraction (0 to 1) of the power events between
+    /// `from` and `to` (exclusive) fall, using the nearest rank (None without power events).
+    /// Fails if the range includes compacted days, whose power events are gone.
+    pub fn power_percentile(
+        &self,
+        fraction: f64,
+        from: DateTime<Local>,
+        to: DateTime<Local>,
+    ) -> Result<Option<f64>, CompactionError> {
+        let compacted = self
+            .days
+            .iter()
+            .filter(|d| d.first < to && d.last >= from)
+            .map(|d| d.summary.date)
+            .collect::<Vec<_>>();
+        if let (Some(first), Some(last)) = (compacted.first(), compacted.last()) {
+            return Err(CompactionError::InsufficientResolution {
+                from: *first,
+                to: *last,
+            });
+        }
+        let powers = self
+            .events
+            .iter()
+            .filter(|e| e.timestamp >= from && e.timestamp < to)
+            .map(|e| e.power)
+            .collect::<Vec<_>>();
+        Ok((!powers.is_empty()).then(|| nearest_rank(powers, fraction)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::compact::{compact, compact_slice, CompactionError};
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::sorted::SortedEvents;
+    use crate::voltcraft::stats::VoltcraftStatistics;
+    use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    // Ten days from 2014-09-10 00:00 with a load varying over the day and a few gaps.
+    // Loads are multiples of 1/8 kW, so that every sum is exact whatever its order.
+    fn events() -> SortedEvents {
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..10 * 1440)
+            .filter(|m| m % 997 > 3)
+            .map(|m| {
+                let power = ((m * 7) % 40) as f64 / 8.0;
+                let voltage = 224.0 + (m % 16) as f64;
+                PowerEvent {
+                    voltage,
+                    current: power * 1000.0 / voltage,
+                    apparent_power: power * 1.25,
+                    ..event(start + Duration::minutes(m), power)
+                }
+            })
+            .collect();
+        SortedEvents::try_from_vec(events).unwrap()
+    }
+
+    #[test]
+    fn compact_preserves_totals() {
+        let events = events();
+        let older_than = Local.with_ymd_and_hms(2014, 9, 17, 12, 0, 0).unwrap();
+        let compacted = compact(&events, older_than);
+        assert_eq!(compacted.days.len(), 7);
+        assert_eq!(
+            compacted.days[6].summary.date,
+            NaiveDate::from_ymd_opt(2014, 9, 16).unwrap()
+        );
+        assert_eq!(compacted.events.len(), 3 * 1440 - 16);
+        let day = &compacted.days[0];
+        assert_eq!(
+            day.hourly_energy.iter().sum::<f64>(),
+            day.summary.stats.total_active_power
+        );
+
+        let expected = VoltcraftStatistics::new(&events).overall_stats().unwrap();
+        let actual = compacted.overall_stats().unwrap();
+        assert_eq!(actual.start, expected.start);
+        assert_eq!(actual.end, expected.end);
+        assert_eq!(
+            actual.stats.total_active_power,
+            expected.stats.total_active_power
+        );
+        assert_eq!(
+            actual.stats.total_apparent_power,
+            expected.stats.total_apparent_power
+        );
+        assert_eq!(
+            actual.stats.avg_active_power,
+            expected.stats.avg_active_power
+        );
+        assert_eq!(actual.stats.avg_voltage, expected.stats.avg_voltage);
+        let (actual_pf, expected_pf) = (
+            actual.stats.weighted_power_factor,
+            expected.stats.weighted_power_factor,
+        );
+        assert!((actual_pf - expected_pf).abs() < 1e-12);
+        let reactive = expected.stats.total_reactive_energy;
+        assert!((actual.stats.total_reactive_energy - reactive).abs() < 1e-9);
+        // The percentiles of the compacted days can only be bounded
+        assert!(actual.stats.p95_voltage >= expected.stats.p95_voltage);
+        assert!(actual.stats.p95_power >= expected.stats.p95_power);
+        assert_eq!(
+            actual.stats.max_active_power.power,
+            expected.stats.max_active_power.power
+        );
+        assert_eq!(
+            actual.stats.min_voltage.voltage,
+            expected.stats.min_voltage.voltage
+        );
+        assert_eq!(
+            actual.stats.max_current.timestamp,
+            expected.stats.max_current.timestamp
+        );
+        assert_eq!(actual.stats.total_duration, expected.stats.total_duration);
+        assert_eq!(
+            actual.avg_daily_power_consumption,
+            expected.avg_daily_power_consumption
+        );
+
+        // Everything compacted, or nothing
+        let all = compact(&events, older_than + Duration::days(30));
+        assert!(all.events.is_empty());
+        assert_eq!(
+            all.overall_stats().unwrap().stats.total_active_power,
+            expected.stats.total_active_power
+        );
+        let none = compact(&events, older_than - Duration::days(30));
+        assert!(none.days.is_empty());
+        assert!(compact(&SortedEvents::default(), older_than)
+            .overall_stats()
+            .is_none());
+
+        // Events in any order through the slice fallback
+        let mut shuffled = events.to_vec();
+        shuffled.reverse();
+        let from_slice = compact_slice(&shuffled, older_than);
+        assert_eq!(from_slice.days.len(), 7);
+        assert_eq!(
+            from_slice.overall_stats().unwrap().stats.total_active_power,
+            expected.stats.total_active_power
+        );
+    }
+
+    #[test]
+    fn compact_percentile_needs_events() {
+        let events = events();
+        let older_than = Local.with_ymd_and_hms(2014, 9, 17, 12, 0, 0).unwrap();
+        let compacted = compact(&events, older_than);
+        let day = |d| Local.with_ymd_and_hms(2014, 9, d, 0, 0, 0).unwrap();
+        assert_eq!(
+            compacted.power_percentile(0.5, day(15), day(19)),
+            Err(CompactionError::InsufficientResolution {
+                from: NaiveDate::from_ymd_opt(2014, 9, 15).unwrap(),
+                to: NaiveDate::from_ymd_opt(2014, 9, 16).unwrap(),
+            })
+        );
+        assert_eq!(
+            compacted.power_percentile(1.0, day(17), day(19)),
+            Ok(Some(4.875))
+        );
+        assert_eq!(
+            compacted.power_percentile(0.0, day(17), day(19)),
+            Ok(Some(0.0))
+        );
+        assert_eq!(compacted.power_percentile(0.5, day(25), day(26)), Ok(None));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn compact_serde_round_trip() {
+        let older_than = Local.with_ymd_and_hms(2014, 9, 17, 12, 0, 0).unwrap();
+        let compacted = compact(&events(), older_than);
+        let json = serde_json::to_string(&compacted).unwrap();
+        let restored: crate::voltcraft::compact::CompactedDataset =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.days.len(), compacted.days.len());
+        assert_eq!(
+            restored.days[3].hourly_energy,
+            compacted.days[3].hourly_energy
+        );
+        assert_eq!(
+            restored.overall_stats().unwrap().stats.total_active_power,
+            compacted.overall_stats().unwrap().stats.total_active_power
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use crate::format::format_fixed;
+use crate::voltcraft::stats::PowerStats;
+use std::fmt;
+
+/// Change of a value from one recording period to another.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Change {
+    pub before: f64,
+    pub after: f64,
+    pub delta: f64,                 // after - before
+    pub delta_percent: Option<f64>, // delta relative to before (None when before is zero)
+}
+
+/// Changes of the main statistics between two recording periods, e.g. before and after
+/// replacing the lighting.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct StatsComparison {
+    pub total_energy: Change,         // total active energy (kWh)
+    pub daily_energy: Option<Change>, // active energy per day of the periods (kWh), None for an empty period
+    pub avg_power: Change,            // average active power (kW)
+    pub peak_power: Change,           // maximum active power (kW)
+    pub avg_voltage: Change,          // average voltage (V)
+}
+
+impl Change {
+    pub fn new(before: f64, after: f64) -> Self {
+        let delta = after - before;
+        Change {
+            before,
+            after,
+            delta,
+            delta_percent: (before != 0.0).then(|| delta * 100.0 / before),
+        }
+    }
+
+    // "84.2 kWh → 61.7 kWh (−26.7%)", the percentage left out when it is unknown
+    fn write(&self, f: &mut fmt::Formatter, unit: &str, decimals: usize) -> fmt::Result {
+        write!(
+            f,
+            "{} {unit} → {} {unit}",
+            format_fixed(self.before, decimals),
+            format_fixed(self.after, decimals)
+        )?;
+        match self.delta_percent {
+            Some(percent) => {
+                let sign = if percent < 0.0 { '−' } else { '+' };
+                write!(f, " ({}{}%)", sign, format_fixed(percent.abs(), 1))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Comparison of the statistics of the `after` period against the `before` one. The daily
+/// energy is the active energy over the duration of each period in days, so that periods of
+/// different lengths compare fairly.
+pub fn compare_stats(before: &PowerStats, after: &PowerStats) -> StatsComparison {
+    let per_day = |stats: &PowerStats| {
+        let days = stats.total_duration.num_seconds() as f64 / 86400.0;
+        (days > 0.0).then(|| stats.total_active_power / days)
+    };
+    StatsComparison {
+        total_energy: Change::new(before.total_active_power, after.total_active_power),
+        daily_energy: per_day(before)
+            .zip(per_day(after))
+            .map(|(before, after)| Change::new(before, after)),
+        avg_power: Change::new(before.avg_active_power, after.avg_active_power),
+        peak_power: Change::new(before.max_active_power.power, after.max_active_power.power),
+        avg_voltage: Change::new(before.avg_voltage, after.avg_voltage),
+    }
+}
+
+impl fmt::Display for StatsComparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "total energy: ")?;
+        self.total_energy.write(f, "kWh", 1)?;
+        if let Some(daily_energy) = self.daily_energy {
+            write!(f, "\ndaily energy: ")?;
+            daily_energy.write(f, "kWh", 2)?;
+        }
+        write!(f, "\naverage power: ")?;
+        self.avg_power.write(f, "kW", 3)?;
+        write!(f, "\npeak power: ")?;
+        self.peak_power.write(f, "kW", 3)?;
+        write!(f, "\naverage voltage: ")?;
+        self.avg_voltage.write(f, "V", 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::compare::{compare_stats, Change};
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::stats::VoltcraftStatistics;
+    use chrono::{DateTime, Duration, Local, TimeZone};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    // Days of constant load from the start, one power event per minute
+    fn period(start: DateTime<Local>, days: i64, power: f64) -> Vec<PowerEvent> {
+        (0..days * 1440)
+            .map(|m| event(start + Duration::minutes(m), power))
+            .collect()
+    }
+
+    #[test]
+    fn compare_periods() {
+        // 20 days at 0.15 kW after 31 days at 0.12 kW
+        let before = period(
+            Local.with_ymd_and_hms(2014, 2, 1, 0, 0, 0).unwrap(),
+            31,
+            0.12,
+        );
+        let after = period(
+            Local.with_ymd_and_hms(2014, 3, 10, 0, 0, 0).unwrap(),
+            20,
+            0.15,
+        );
+        let (before, after) = (
+            VoltcraftStatistics::new(&before),
+            VoltcraftStatistics::new(&after),
+        );
+        let comparison = before.compare(&after).unwrap();
+        assert!((comparison.total_energy.before - 89.28).abs() < 1e-9);
+        assert!((comparison.total_energy.after - 72.0).abs() < 1e-9);
+        assert!(comparison.total_energy.delta_percent.unwrap() < 0.0);
+        // Less energy in total, but more per day
+        let daily = comparison.daily_energy.unwrap();
+        assert!((daily.before - 2.88).abs() < 1e-9);
+        assert!((daily.after - 3.6).abs() < 1e-9);
+        assert!((daily.delta_percent.unwrap() - 25.0).abs() < 1e-9);
+        assert!((comparison.peak_power.delta - 0.03).abs() < 1e-12);
+        assert_eq!(comparison.avg_voltage.delta_percent, Some(0.0));
+        assert_eq!(
+            comparison.to_string(),
+            "total energy: 89.3 kWh → 72.0 kWh (−19.4%)\n\
+             daily energy: 2.88 kWh → 3.60 kWh (+25.0%)\n\
+             average power: 0.120 kW → 0.150 kW (+25.0%)\n\
+             peak power: 0.120 kW → 0.150 kW (+25.0%)\n\
+             average voltage: 230.0 V → 230.0 V (+0.0%)"
+        );
+
+        // No percentage from nothing
+        let none = period(Local.with_ymd_and_hms(2014, 4, 1, 0, 0, 0).unwrap(), 1, 0.0);
+        let none = VoltcraftStatistics::new(&none);
+        let stats = |s: &VoltcraftStatistics| s.overall_stats().unwrap().stats;
+        let comparison = compare_stats(&stats(&none), &stats(&after));
+        assert_eq!(comparison.total_energy.delta_percent, None);
+        assert!(comparison
+            .to_string()
+            .starts_with("total energy: 0.0 kWh → 72.0 kWh\n"));
+        assert_eq!(Change::new(2.0, 1.0).delta_percent, Some(-50.0));
+        assert!(before.compare(&VoltcraftStatistics::new(&[])).is_none());
+    }
+}
@@ -0,0 +1,7 @@
+// Measurement resolution of the Energy Logger 4000, i.e. the smallest step of each recorded value
+pub const VOLTAGE_QUANTUM: f64 = 0.1; // volts
+pub const CURRENT_QUANTUM: f64 = 0.001; // ampers
+pub const POWER_FACTOR_QUANTUM: f64 = 0.01; // cos(phi)
+
+// Rated current of the Energy Logger 4000, above which a reading cannot be a measurement
+pub const MAX_CURRENT: f64 = 16.0; // ampers
@@ -0,0 +1,154 @@
+use crate::voltcraft::bucket::{bucket_of, Granularity};
+use crate::voltcraft::data::PowerEvent;
+use crate::voltcraft::stats::{energy, CompensatedSum};
+use chrono::{DateTime, Duration, Local};
+
+/// Running total of the active energy of chronologically sorted power events, like the
+/// reading of a meter, with range queries answered from the totals.
+///
+/// The totals are compensated (Neumaier) sums, so that the last one is the total active
+/// energy of the reproducible statistics (`NumericMode::Reproducible`) and stays within a
+/// rounding error of the default ones over years of minutes. Blackouts add nothing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CumulativeEnergy {
+    points: Vec<(DateTime<Local>, f64)>, // active energy (kWh) up to each power event included
+}
+
+impl CumulativeEnergy {
+    // Running totals of the power events recorded every interval (a minute for the logger)
+    pub fn new(events: &[PowerEvent], interval: Duration) -> Self {
+        let mut power_sum = CompensatedSum::default();
+        CumulativeEnergy {
+            points: events
+                .iter()
+                .map(|pe| {
+                    power_sum = power_sum.add(pe.power);
+                    (pe.timestamp, energy(power_sum.value(), interval))
+                })
+                .collect(),
+        }
+    }
+
+    pub fn points(&self) -> &[(DateTime<Local>, f64)] {
+        &self.points
+    }
+
+    pub fn into_points(self) -> Vec<(DateTime<Local>, f64)> {
+        self.points
+    }
+
+    // Active energy of all the power events (kWh), 0 without any
+    pub fn total(&self) -> f64 {
+        self.points.last().map_or(0.0, |(_, kwh)| *kwh)
+    }
+
+    // Active energy (kWh) of the power events from `from` included to `to` excluded, found by
+    // binary search: the difference of the running totals at both ends
+    pub fn energy_between(&self, from: DateTime<Local>, to: DateTime<Local>) -> f64 {
+        let start = self.points.partition_point(|(t, _)| *t < from);
+        let end = self.points.partition_point(|(t, _)| *t < to).max(start);
+        self.total_before(end) - self.total_before(start)
+    }
+
+    // Running total before the point at the index
+    fn total_before(&self, index: usize) -> f64 {
+        index
+            .checked_sub(1)
+            .map_or(0.0, |previous| self.points[previous].1)
+    }
+}
+
+/// Active energy (kWh) consumed up to and including each power event, recorded every minute,
+/// in chronological order. The last value is the total active energy.
+pub fn cumulative_energy(events: &[PowerEvent]) -> Vec<(DateTime<Local>, f64)> {
+    CumulativeEnergy::new(events, Duration::minutes(1)).into_points()
+}
+
+/// Same as `cumulative_energy`, starting again from zero at every local midnight, so that
+/// the last value of each day is the energy of that day.
+pub fn daily_cumulative_energy(events: &[PowerEvent]) -> Vec<(DateTime<Local>, f64)> {
+    let mut day = None;
+    let mut power_sum = CompensatedSum::default();
+    events
+        .iter()
+        .map(|pe| {
+            let today = Some(bucket_of(pe.timestamp, Granularity::Day));
+            if today != day {
+                (day, power_sum) = (today, CompensatedSum::default());
+            }
+            power_sum = power_sum.add(pe.power);
+            (
+                pe.timestamp,
+                energy(power_sum.value(), Duration::minutes(1)),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::cumulative::{cumulative_energy, daily_cumulative_energy};
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::stats::{NumericMode, VoltcraftStatistics};
+    use chrono::{DateTime, Duration, Local, TimeZone};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    #[test]
+    fn cumulative_energy_totals() {
+        // A year of minutes with a varying load and a 3 hour blackout on the first day
+        let start = Local.with_ymd_and_hms(2014, 1, 1, 18, 0, 0).unwrap();
+        let events = (0..525_600)
+            .filter(|m| !(60..240).contains(m))
+            .map(|m| {
+                let power = 0.05 + (m % 97) as f64 / 100.0;
+                event(start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+
+        let series = cumulative_energy(&events);
+        assert_eq!(series.len(), events.len());
+        // The blackout adds nothing
+        assert!((series[60].1 - series[59].1 - events[60].power / 60.0).abs() < 1e-12);
+        let daily = daily_cumulative_energy(&events);
+        let powers = events.iter().map(|pe| pe.power).collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let total = stats.overall_stats().unwrap().stats.total_active_power;
+        assert!((series.last().unwrap().1 - total).abs() < 1e-6);
+        let reproducible = stats.with_numeric_mode(NumericMode::Reproducible);
+        let cumulative = reproducible.cumulative_energy();
+        let exact = reproducible.overall_stats().unwrap().stats;
+        assert_eq!(cumulative.total(), exact.total_active_power);
+
+        // Range queries from the totals
+        let (from, to) = (start + Duration::days(40), start + Duration::days(41));
+        let day = reproducible.stats_between(from, to).unwrap();
+        assert!((cumulative.energy_between(from, to) - day.total_active_power).abs() < 1e-6);
+        assert_eq!(cumulative.energy_between(to, from), 0.0);
+        assert_eq!(cumulative.energy_between(start, start), 0.0);
+        let all = cumulative.energy_between(start, start + Duration::days(400));
+        assert_eq!(all, cumulative.total());
+
+        // The daily curves end at the energy of each day
+        assert_eq!(daily[0].1, powers[0] / 60.0);
+        let midnight = series
+            .iter()
+            .position(|(t, _)| *t == Local.with_ymd_and_hms(2014, 1, 2, 0, 0, 0).unwrap())
+            .unwrap();
+        assert_eq!(daily[midnight].1, powers[midnight] / 60.0);
+        assert!((daily[midnight - 1].1 - series[midnight - 1].1).abs() < 1e-12);
+        let days = reproducible.daily_stats();
+        assert!((daily[midnight - 1].1 - days[0].stats.total_active_power).abs() < 1e-9);
+    }
+}
@@ -1,10 +1,28 @@
-use chrono::{Duration, Local, TimeZone};
+use crate::format::format_fixed;
+use crate::voltcraft::bucket::first_instant;
+use crate::voltcraft::consts::{
+    CURRENT_QUANTUM, MAX_CURRENT, POWER_FACTOR_QUANTUM, VOLTAGE_QUANTUM,
+};
+use crate::voltcraft::error::VoltcraftError;
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone, Timelike};
+use std::fmt;
 use std::fs;
-pub struct VoltcraftData {
-    raw_data: Vec<u8>,
+use std::io::Read;
+use std::path::Path;
+/// Contents of a data file, owned (`Vec<u8>`, from `from_file`, `from_reader` or `from_raw`)
+/// or borrowed from any byte buffer (`from_bytes`), e.g. a memory map of a large file, which
+/// is then decoded without being copied.
+pub struct VoltcraftData<B = Vec<u8>> {
+    raw_data: B,
 }
 
-#[derive(Debug, Copy, Clone)]
+// Data file decoded from a borrowed byte slice
+pub type VoltcraftDataRef<'a> = VoltcraftData<&'a [u8]>;
+
+/// Power events compare by timestamp first, then by their values, so that sorting them puts
+/// them in chronological order.
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PowerEvent {
     pub timestamp: chrono::DateTime<Local>, // timestamp
     pub voltage: f64,                       // volts
@@ -12,139 +30,1921 @@ pub struct PowerEvent {
     pub power_factor: f64,                  // cos(phi)
     pub power: f64,                         // kW
     pub apparent_power: f64,                // kVA
+    // Hertz, for record formats that carry it (none of the known formats so far)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub frequency: Option<f64>,
 }
 
-impl VoltcraftData {
-    pub fn from_file(filename: &str) -> Result<VoltcraftData, &'static str> {
-        let contents = fs::read(filename);
-        match contents {
-            Err(_) => return Err("File not found"),
-            Ok(raw_data) => return Ok(VoltcraftData { raw_data }),
-        };
+// Power events as returned by the parser
+pub type Events = Vec<PowerEvent>;
+
+/// Values of a single 5-byte power record, as decoded by `decode_power_record`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct PowerReading {
+    pub voltage: f64,        // volts
+    pub current: f64,        // ampers
+    pub power_factor: f64,   // cos(phi)
+    pub power: f64,          // kW, derived from the three values above
+    pub apparent_power: f64, // kVA, derived from the voltage and current
+}
+
+/// Power events of a logger configured to record at another interval than every minute, as
+/// returned by `VoltcraftData::parse_with_interval`. Analyze them with
+/// `VoltcraftStatistics::from_log`, which accounts for the interval.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PowerLog {
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::voltcraft::serde_helpers::duration_seconds")
+    )]
+    pub interval: Duration, // between two power events of a session
+    pub events: Vec<PowerEvent>,
+}
+
+/// A run of consecutive power events following one data block header.
+/// The device starts a new data block every time it resumes logging.
+#[derive(Debug, Clone)]
+pub struct PowerSession {
+    pub start: chrono::DateTime<Local>, // start time stored in the data block header
+    pub events: Vec<PowerEvent>,        // power events recorded during the session
+}
+
+/// A session whose header start time lies before the end of an earlier session,
+/// which is what happens when a device reset reverts the clock.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SessionOverlap {
+    pub session: usize,                        // index of the overlapping session
+    pub previous_session: usize,               // index of the session it overlaps
+    pub start: chrono::DateTime<Local>,        // start time of the overlapping session
+    pub previous_end: chrono::DateTime<Local>, // last event of the overlapped session
+}
+
+/// How to deal with a session overlapping the data recorded before it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OverlapResolution {
+    KeepBoth,                          // keep the session as recorded
+    DropOverlap,                       // drop the events recorded before the previous session ends
+    Reanchor(chrono::DateTime<Local>), // move the session to the supplied (correct) start time
+}
+
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ParseSummary {
+    pub session_count: usize,          // number of data blocks found
+    pub event_count: usize,            // number of power events kept
+    pub overlaps: Vec<SessionOverlap>, // sessions starting before a previous one ends
+    pub suspicious_start_time: bool,   // a data block header holds an implausible start time
+    pub anchored: bool,                // the start time was overridden by ParseOptions::anchor_time
+}
+
+/// How the power records of a data file end, e.g. when the SD card was pulled while the
+/// logger was recording.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Termination {
+    EndOfData,        // the end of data marker, as written by the device
+    MissingEndOfData, // the file ends right after a complete power record
+    // The file ends within a power record or data block header, or with a record that is not
+    // a reading (e.g. half overwritten), at the offset and of the length of the leftover bytes
+    TruncatedRecord { offset: usize, length: usize },
+    ShortPadding { offset: usize, length: usize }, // 1 to 3 0xff bytes instead of the marker
+}
+
+/// Power events of a data file along with how its power records end, as returned by
+/// `VoltcraftData::parse_outcome`.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ParseOutcome {
+    pub events: Vec<PowerEvent>,
+    pub termination: Termination,
+    // The data ends without the marker and the last power event is not a measurement (a
+    // current above MAX_CURRENT or a power factor above 1), e.g. the record in progress
+    pub suspect_last_event: bool,
+}
+
+impl ParseOutcome {
+    // The power events without the last one when it is suspect
+    pub fn trusted_events(&self) -> &[PowerEvent] {
+        let suspect = self.suspect_last_event as usize;
+        &self.events[..self.events.len() - suspect]
     }
+}
 
-    pub fn from_raw(raw_data: Vec<u8>) -> VoltcraftData {
-        VoltcraftData { raw_data }
+/// How `VoltcraftData::merge` keeps power events of different sets at the same minute whose
+/// readings differ.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    #[default]
+    KeepFirst, // keep the event of the set given first
+    KeepLast, // keep the event of the set given last
+    Error,    // fail the merge
+}
+
+/// Time range recorded by two of the merged sets, e.g. the same file imported twice.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct MergeOverlap {
+    pub set: usize,                     // index of the set
+    pub previous_set: usize,            // index of an earlier set recording the same time range
+    pub start: chrono::DateTime<Local>, // first minute recorded by both sets
+    pub end: chrono::DateTime<Local>,   // last minute recorded by both sets
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct MergeReport {
+    pub event_count: usize,          // number of power events kept
+    pub duplicates: usize,           // events dropped as the same reading at the same minute
+    pub conflicts: usize,            // events dropped as a different reading at the same minute
+    pub overlaps: Vec<MergeOverlap>, // pairs of sets recording the same time range
+}
+
+/// Two sets holding different readings at the same minute, with `DuplicatePolicy::Error`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct MergeConflict {
+    pub timestamp: chrono::DateTime<Local>,
+    pub set: usize,          // index of the set
+    pub previous_set: usize, // index of the earlier set with another reading at the same minute
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Sets are numbered from one in user-facing messages
+        write!(
+            f,
+            "sets {} and {} hold different readings at {}",
+            self.previous_set + 1,
+            self.set + 1,
+            self.timestamp.format("%Y-%m-%d %H:%M")
+        )
+    }
+}
+
+impl std::error::Error for MergeConflict {}
+
+/// Power events that `VoltcraftData::encode` cannot store in a data file.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum EncodeError {
+    // Not a whole minute of the years 2000 to 2255, which the header can hold
+    InvalidStartTime {
+        start: chrono::DateTime<Local>,
+    },
+    // The power event at the index is not the given number of minutes after the start
+    NotContiguous {
+        index: usize,
+    },
+    // A value of the power event at the index does not fit its field of the power record
+    OutOfRange {
+        index: usize,
+        field: &'static str,
+        value: f64,
+    },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EncodeError::InvalidStartTime { start } => {
+                write!(f, "start time {} cannot be stored in a header", start)
+            }
+            EncodeError::NotContiguous { index } => write!(
+                f,
+                "power event {} is not one minute after the one before it",
+                index
+            ),
+            EncodeError::OutOfRange {
+                index,
+                field,
+                value,
+            } => write!(
+                f,
+                "{} {} of power event {} does not fit a power record",
+                field, value, index
+            ),
+        }
     }
+}
 
-    pub fn parse(&self) -> Result<Vec<PowerEvent>, &'static str> {
-        let mut result = Vec::<PowerEvent>::new();
-        // The initial offset in the data block is zero
-        let mut offset = 0;
-        // Set the initial time somewhere in the past as it will be overwritten anyway
-        let mut start_time = chrono::Local.ymd(2000, 1, 1).and_hms(0, 0, 0);
-        // For each new power event we encounter, the timestamp is increased by one minute (the Voltcraft device records parameters each minute)
-        let mut minute_increment = 0;
+impl std::error::Error for EncodeError {}
 
-        // Check whether we have a valid data file (the data block header should be at the beginning of the file)
-        if !self.is_datablock(offset) {
-            return Err("Invalid data file, probably not a Voltcraft file");
+/// Layout of the power records of a data file, for `VoltcraftData::parse_with_format`. The
+/// header carries no format version, so the format cannot be detected from the file; only the
+/// layout described in docs/voltcraft_file_format.txt is known so far.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FormatVersion {
+    // 5-byte records of voltage, current and power factor, without frequency
+    #[default]
+    V1,
+}
+
+impl FormatVersion {
+    // Version byte of the format
+    pub fn version(&self) -> u8 {
+        match self {
+            FormatVersion::V1 => 1,
         }
+    }
+}
 
-        loop {
-            // If we encounter the beginning of a data block, decode and memorize the timestamp
-            if self.is_datablock(offset) {
-                offset += 3;
-                start_time = self.decode_timestamp(offset);
-                minute_increment = 0;
-                offset += 5;
+impl TryFrom<u8> for FormatVersion {
+    type Error = VoltcraftError;
+
+    // Format of the version byte, UnsupportedFormatVersion for unknown ones
+    fn try_from(version: u8) -> Result<Self, VoltcraftError> {
+        match version {
+            1 => Ok(FormatVersion::V1),
+            _ => Err(VoltcraftError::UnsupportedFormatVersion { version }),
+        }
+    }
+}
+
+/// Options of `VoltcraftData::parse_with_options`.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct ParseOptions {
+    pub anchor_time: Option<chrono::DateTime<Local>>, // start time replacing the one of the first header
+}
+
+impl ParseOptions {
+    // Use this start time for the first data block, e.g. when the device clock battery died and the
+    // headers say 2000-01-01 00:00. Later blocks keep their offset from the first one.
+    pub fn anchor_time(mut self, start: chrono::DateTime<Local>) -> Self {
+        self.anchor_time = Some(start);
+        self
+    }
+}
+
+// Start of every data block header
+const MAGIC_NUMBER: [u8; 3] = [0xE0, 0xC5, 0xEA];
+// End of the power records of a session
+const END_OF_DATA: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+// Headers further than this from today are considered wrong
+const SUSPICIOUS_YEARS: i32 = 20;
+
+// True for the start time of a device whose clock was never set (2000-01-01 00:00) or one
+// decades away from today
+pub(crate) fn is_suspicious_start(start: chrono::DateTime<Local>) -> bool {
+    let device_default = Local.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).single();
+    let this_year = Local::now().year();
+    Some(start) == device_default || (start.year() - this_year).abs() > SUSPICIOUS_YEARS
+}
+
+// Offset of the data block header of the session recorded after the end of data marker at
+// the offset (the device appends one every time it is powered on), skipping the 0xff padding
+// in between. None if no session follows.
+fn next_session(raw_data: &[u8], end_of_data: usize) -> Option<usize> {
+    let after = end_of_data + END_OF_DATA.len();
+    let padding = raw_data[after..].iter().take_while(|b| **b == 0xFF).count();
+    raw_data[after + padding..]
+        .starts_with(&MAGIC_NUMBER)
+        .then_some(after + padding)
+}
+
+/// Start time of a data block from its five header bytes (month, day, year since 2000, hour,
+/// minute), None if they are out of range. A local time occurring twice when daylight saving
+/// time ends is the earlier of the two; one skipped when it starts is the first minute after
+/// the skipped hour, as the device clock runs on.
+pub(crate) fn decode_start(bytes: &[u8; 5]) -> Option<chrono::DateTime<Local>> {
+    decode_start_in(bytes, &Local)
+}
+
+// Same as decode_start, for a device clock set to the time of the zone
+fn decode_start_in<Tz: TimeZone>(bytes: &[u8; 5], tz: &Tz) -> Option<chrono::DateTime<Tz>> {
+    let [month, day, year, hour, minute] = *bytes;
+    let date = NaiveDate::from_ymd_opt(year as i32 + 2000, month as u32, day as u32)?;
+    first_instant(tz, date.and_hms_opt(hour as u32, minute as u32, 0)?)
+}
+
+impl PowerEvent {
+    // Power event of the reading recorded at the timestamp
+    pub fn from_reading(timestamp: chrono::DateTime<Local>, reading: PowerReading) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: reading.voltage,
+            current: reading.current,
+            power_factor: reading.power_factor,
+            power: reading.power,
+            apparent_power: reading.apparent_power,
+            frequency: None,
+        }
+    }
+
+    // Active power computed from voltage, current and power factor (kW)
+    pub fn derived_power(&self) -> f64 {
+        self.voltage * self.current * self.power_factor / 1000.0
+    }
+
+    // Apparent power computed from voltage and current (kVA)
+    pub fn derived_apparent_power(&self) -> f64 {
+        self.voltage * self.current / 1000.0
+    }
+
+    // Reactive power from the stored active and apparent power (kvar), 0 rather than the root
+    // of a tiny negative number when rounding makes the active power the larger
+    pub fn reactive_power(&self) -> f64 {
+        (self.apparent_power.powi(2) - self.power.powi(2))
+            .max(0.0)
+            .sqrt()
+    }
+
+    // True if the stored active and apparent power match the derived ones within eps (kW/kVA)
+    pub fn is_consistent(&self, eps: f64) -> bool {
+        (self.power - self.derived_power()).abs() <= eps
+            && (self.apparent_power - self.derived_apparent_power()).abs() <= eps
+    }
+
+    // Copy with the active and apparent power recomputed from the measured values
+    pub fn recomputed(&self) -> PowerEvent {
+        PowerEvent {
+            power: self.derived_power(),
+            apparent_power: self.derived_apparent_power(),
+            ..*self
+        }
+    }
+}
+
+/// Decode a raw power record, e.g. one captured live from the serial port of the logger,
+/// into its voltage, current and power factor along with the active and apparent power
+/// derived from them. Unlike `parse`, implausible voltages are not rejected (see
+/// `PowerReading::is_plausible`).
+pub fn decode_power_record(record: &[u8; 5]) -> PowerReading {
+    // Decode voltage (2 bytes - Big Endian)
+    let voltage = u16::from_be_bytes([record[0], record[1]]);
+    let voltage: f64 = voltage as f64 / 10.0; // volts
+
+    // Decode current (2 bytes - Big Endian)
+    let current = u16::from_be_bytes([record[2], record[3]]);
+    let current: f64 = current as f64 / 1000.0; // ampers
+
+    // Decode power factor (1 byte)
+    let power_factor: u8 = record[4];
+    let power_factor: f64 = power_factor as f64 / 100.0; // cos phi
+
+    PowerReading {
+        voltage,
+        current,
+        power_factor,
+        power: voltage * current * power_factor / 1000.0, // kW
+        apparent_power: voltage * current / 1000.0,       // kVA
+    }
+}
+
+impl PowerReading {
+    // Whether the voltage is one the device can record, as parse requires (150 V to 250 V,
+    // both excluded); other records are corrupted or not power records at all
+    pub fn is_plausible(&self) -> bool {
+        self.voltage > 150.0 && self.voltage < 250.0
+    }
+}
+
+// The interval between two power events if it is a whole number of seconds, at least one, as
+// the statistics compute in seconds
+pub(crate) fn sample_interval(interval: Duration) -> Result<Duration, VoltcraftError> {
+    match interval >= Duration::seconds(1) && interval.subsec_nanos() == 0 {
+        true => Ok(interval),
+        false => Err(VoltcraftError::InvalidSampleInterval {
+            milliseconds: interval.num_milliseconds(),
+        }),
+    }
+}
+
+/// True if both power events hold the same measured voltage, current and power factor, each
+/// compared within half the device resolution so that values read back from CSV or JSON still match.
+/// Timestamps and the derived powers are not compared.
+pub fn same_reading(a: &PowerEvent, b: &PowerEvent) -> bool {
+    (a.voltage - b.voltage).abs() < VOLTAGE_QUANTUM / 2.0
+        && (a.current - b.current).abs() < CURRENT_QUANTUM / 2.0
+        && (a.power_factor - b.power_factor).abs() < POWER_FACTOR_QUANTUM / 2.0
+}
+
+impl fmt::Display for PowerEvent {
+    // One line at the resolution of the device, e.g.
+    // "2014-09-11 18:43 224.6 V 0.446 A PF 0.87 0.087 kW 0.100 kVA"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} V {} A PF {} {} kW {} kVA",
+            self.timestamp.format("%Y-%m-%d %H:%M"),
+            format_fixed(self.voltage, 1),
+            format_fixed(self.current, 3),
+            format_fixed(self.power_factor, 2),
+            format_fixed(self.power, 3),
+            format_fixed(self.apparent_power, 3)
+        )
+    }
+}
+
+impl fmt::Display for SessionOverlap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Sessions are numbered from one in user-facing messages
+        write!(
+            f,
+            "session {} starts before session {} ends",
+            self.session + 1,
+            self.previous_session + 1
+        )
+    }
+}
+
+/// Identity of the power records of a data file, to recognize copies of the same file.
+///
+/// The hash is the 64-bit FNV-1a hash of the raw power records, in file order, which will
+/// not change across releases. Only the records count: data block headers other than the
+/// first one's start time, and anything after the end of data marker, are ignored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FileFingerprint {
+    pub start: Option<chrono::DateTime<Local>>, // start time of the first data block
+    pub record_count: usize,                    // number of power records
+    pub hash: u64,                              // FNV-1a hash of the power records
+}
+
+impl fmt::Display for FileFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:016x}", self.hash)
+    }
+}
+
+/// Power events of a data file, decoded one at a time from the raw data as
+/// `VoltcraftData::parse` would. Iteration stops after the first error.
+pub struct PowerEventIter<'a, Tz: TimeZone = Local> {
+    data: VoltcraftDataRef<'a>,
+    tz: Tz,                                          // of the device clock
+    interval: Duration,                              // between two power events of a session
+    offset: usize,                                   // of the next header or record
+    session: Option<(chrono::DateTime<Local>, i64)>, // start of the current session and its events so far
+    done: bool,
+}
+
+// What the next bytes of a data file decode to
+enum Step {
+    Session(chrono::DateTime<Local>), // data block header with its start time
+    Event(PowerEvent),
+}
+
+/// Raw 5-byte power records of a data file, along with their byte offsets.
+/// Data block headers are skipped and iteration stops at the end of data marker.
+/// If the data ends without that marker, `malformed_tail` gives the offset of the
+/// leftover bytes once the iterator is exhausted.
+pub struct RawRecords<'a> {
+    raw_data: &'a [u8],
+    offset: usize,
+    malformed_tail: Option<usize>,
+    end_of_data: Option<usize>,
+    header_count: usize,
+    done: bool,
+}
+
+impl<'a> RawRecords<'a> {
+    pub fn malformed_tail(&self) -> Option<usize> {
+        self.malformed_tail
+    }
+
+    // Offset of the end of data marker, once reached
+    pub fn end_of_data(&self) -> Option<usize> {
+        self.end_of_data
+    }
+
+    // Data block headers skipped so far
+    pub fn header_count(&self) -> usize {
+        self.header_count
+    }
+}
+
+impl<'a> Iterator for RawRecords<'a> {
+    type Item = (usize, &'a [u8; 5]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let rest = &self.raw_data[self.offset..];
+            if rest.starts_with(&END_OF_DATA) {
+                match next_session(self.raw_data, self.offset) {
+                    Some(next) => self.offset = next,
+                    None => {
+                        self.done = true;
+                        self.end_of_data = Some(self.offset);
+                    }
+                }
+            } else if rest.starts_with(&MAGIC_NUMBER) && rest.len() >= 8 {
+                // Data block header followed by its start time
+                self.offset += 8;
+                self.header_count += 1;
+            } else if let Some(record) = rest.first_chunk::<5>() {
+                let offset = self.offset;
+                self.offset += 5;
+                return Some((offset, record));
+            } else {
+                self.done = true;
+                self.malformed_tail = Some(self.offset);
+            }
+        }
+        None
+    }
+}
+
+impl<'a, Tz: TimeZone> PowerEventIter<'a, Tz> {
+    fn new(raw_data: &'a [u8], tz: Tz) -> Self {
+        PowerEventIter {
+            data: VoltcraftData { raw_data },
+            tz,
+            interval: Duration::minutes(1),
+            offset: 0,
+            session: None,
+            done: false,
+        }
+    }
+
+    fn fail(&mut self, error: VoltcraftError) -> Option<Result<Step, VoltcraftError>> {
+        self.done = true;
+        Some(Err(error))
+    }
+
+    // Decode the next data block header or power record
+    fn step(&mut self) -> Option<Result<Step, VoltcraftError>> {
+        while !self.done {
+            let offset = self.offset;
+            let rest = &self.data.raw_data[offset..];
+            // A valid data file starts with a data block header
+            if offset == 0 && !self.data.is_datablock(0) {
+                return self.fail(if MAGIC_NUMBER.starts_with(rest) {
+                    VoltcraftError::TruncatedData { offset }
+                } else {
+                    VoltcraftError::InvalidMagicNumber
+                });
+            }
+            // If we encounter the beginning of a data block, decode the timestamp and start a new session
+            if self.data.is_datablock(offset) {
+                let Some(header) = rest.get(3..8) else {
+                    return self.fail(VoltcraftError::TruncatedData { offset });
+                };
+                let Some(start) = decode_start_in(header.try_into().unwrap(), &self.tz) else {
+                    return self.fail(VoltcraftError::InvalidTimestamp { offset });
+                };
+                let start = start.with_timezone(&Local);
+                self.session = Some((start, 0));
+                self.offset += 8;
+                return Some(Ok(Step::Session(start)));
+            }
+            // Check whether we have reached the end of the session, and whether another one follows
+            if rest.starts_with(&END_OF_DATA) {
+                match next_session(self.data.raw_data, offset) {
+                    Some(next) => self.offset = next,
+                    None => self.done = true,
+                }
                 continue;
             }
-            // Check whether we have reached the end of the Voltcraft data file
-            if self.is_endofdata(offset) {
-                break;
+            // The data ends in the middle of a power record, or without the end of data marker
+            if rest.len() < 5 {
+                return self.fail(VoltcraftError::TruncatedData { offset });
+            }
+            let Some(reading) = self.data.decode_power(offset) else {
+                return self.fail(VoltcraftError::InvalidPowerRecord { offset });
+            };
+            // Each power event is one interval after the previous one (the device records parameters
+            // each minute unless configured otherwise)
+            let (start, count) = self.session.as_mut().unwrap();
+            let timestamp = *start + self.interval * *count as i32;
+            *count += 1;
+            self.offset += 5;
+            return Some(Ok(Step::Event(PowerEvent::from_reading(
+                timestamp, reading,
+            ))));
+        }
+        None
+    }
+}
+
+impl<'a, Tz: TimeZone> Iterator for PowerEventIter<'a, Tz> {
+    type Item = Result<PowerEvent, VoltcraftError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.step()? {
+                Ok(Step::Session(_)) => continue,
+                Ok(Step::Event(event)) => return Some(Ok(event)),
+                Err(e) => return Some(Err(e)),
             }
-            let power_data = self.decode_power(offset);
-            let power_timestamp = start_time + Duration::minutes(minute_increment);
-            minute_increment += 1; // Increment the timestamp by 1 minute
-            offset += 5; // Increment byte offset
+        }
+    }
+
+    // At most one power event per record left in the data
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.done {
+            true => (0, Some(0)),
+            false => (0, Some((self.data.raw_data.len() - self.offset) / 5)),
+        }
+    }
+}
+
+impl VoltcraftData {
+    // With the compression feature, a gzip file (e.g. .bin.gz) is decompressed and the .bin
+    // entries of a zip archive are read one after the other, their sessions in archive order
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<VoltcraftData, VoltcraftError> {
+        let raw_data = fs::read(path)?;
+        VoltcraftData::from_read_bytes(raw_data)
+    }
+
+    // Read the whole data file from the reader, e.g. an upload or an entry of an archive,
+    // decompressing it as from_file does
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<VoltcraftData, VoltcraftError> {
+        let mut raw_data = Vec::new();
+        reader.read_to_end(&mut raw_data)?;
+        VoltcraftData::from_read_bytes(raw_data)
+    }
 
-            result.push(PowerEvent {
-                timestamp: power_timestamp,
-                voltage: power_data.0,
-                current: power_data.1,
-                power_factor: power_data.2,
-                power: power_data.3,
-                apparent_power: power_data.4,
-            });
+    fn from_read_bytes(raw_data: Vec<u8>) -> Result<VoltcraftData, VoltcraftError> {
+        #[cfg(feature = "compression")]
+        let raw_data = crate::voltcraft::archive::decompress(raw_data)?;
+        Ok(VoltcraftData { raw_data })
+    }
+
+    pub fn from_raw(raw_data: impl Into<Vec<u8>>) -> VoltcraftData {
+        VoltcraftData {
+            raw_data: raw_data.into(),
+        }
+    }
+}
+
+impl<B: AsRef<[u8]>> VoltcraftData<B> {
+    // Decode the bytes where they are, e.g. a &[u8] or a memory map, without copying them.
+    // Lazy decoding with events() then only allocates the power events kept.
+    pub fn from_bytes(raw_data: B) -> VoltcraftData<B> {
+        VoltcraftData { raw_data }
+    }
+
+    pub(crate) fn raw_data(&self) -> &[u8] {
+        self.raw_data.as_ref()
+    }
+
+    /// Iterate over the raw power records without decoding them.
+    pub fn raw_records(&self) -> RawRecords<'_> {
+        RawRecords {
+            raw_data: self.raw_data(),
+            offset: 0,
+            malformed_tail: None,
+            end_of_data: None,
+            header_count: 0,
+            done: false,
+        }
+    }
+
+    pub fn fingerprint(&self) -> FileFingerprint {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+        let mut hash = FNV_OFFSET_BASIS;
+        let mut record_count = 0;
+        for (_, record) in self.raw_records() {
+            for &b in record {
+                hash = (hash ^ b as u64).wrapping_mul(FNV_PRIME);
+            }
+            record_count += 1;
+        }
+        let start = match self.is_datablock(0) {
+            true => self.decode_timestamp(3),
+            false => None,
+        };
+        FileFingerprint {
+            start,
+            record_count,
+            hash,
+        }
+    }
+
+    /// Decode all power events, concatenating the sessions in file order.
+    pub fn parse(&self) -> Result<Vec<PowerEvent>, VoltcraftError> {
+        let events = self.events();
+        let mut result = Vec::with_capacity(events.size_hint().1.unwrap_or(0));
+        for event in events {
+            result.push(event?);
         }
         Ok(result)
     }
 
+    /// Decode all power events of a logger recording every `interval` (a whole number of
+    /// seconds, at least one, `InvalidSampleInterval` otherwise) instead of every minute.
+    pub fn parse_with_interval(&self, interval: Duration) -> Result<PowerLog, VoltcraftError> {
+        let interval = sample_interval(interval)?;
+        let events = PowerEventIter {
+            interval,
+            ..self.events()
+        };
+        Ok(PowerLog {
+            interval,
+            events: events.collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Decode all power events of a data file whose records have the given layout.
+    pub fn parse_with_format(
+        &self,
+        format: FormatVersion,
+    ) -> Result<Vec<PowerEvent>, VoltcraftError> {
+        match format {
+            FormatVersion::V1 => self.parse(),
+        }
+    }
+
+    /// Decode the power events lazily, e.g. to fold over a large data file without holding all
+    /// its events in memory.
+    pub fn events(&self) -> PowerEventIter<'_> {
+        PowerEventIter::new(self.raw_data(), Local)
+    }
+
+    /// Decode all power events of a device whose clock was set to the time of another zone
+    /// than the local one, e.g. `chrono::FixedOffset` or a zone of the `chrono-tz` crate for
+    /// data recorded abroad. Timestamps are the same instants whatever zone the decoder runs
+    /// in; `VoltcraftStatistics::daily_stats_in` groups them by the days of the recording site.
+    pub fn parse_in<Tz: TimeZone>(&self, tz: &Tz) -> Result<Vec<PowerEvent>, VoltcraftError> {
+        match self.decode_sessions_in(tz) {
+            (sessions, None) => Ok(sessions.into_iter().flat_map(|s| s.events).collect()),
+            (_, Some(e)) => Err(e),
+        }
+    }
+
+    /// Decode the power events up to where the data ends, e.g. in a file cut off while being
+    /// copied from the SD card. The error, if any, tells why decoding stopped early.
+    pub fn parse_lossy(&self) -> (Vec<PowerEvent>, Option<VoltcraftError>) {
+        let (sessions, error) = self.decode_sessions();
+        let events = sessions.into_iter().flat_map(|s| s.events).collect();
+        (events, error)
+    }
+
+    /// Decode all power events of a data file that may end abruptly, telling how it ends:
+    /// unlike `parse`, a file cut off within its last record or padded with fewer 0xff bytes
+    /// than the end of data marker is not an error. A last record that is not a reading
+    /// (an implausible voltage) is left out as part of the truncated tail; one with a current
+    /// or a power factor the device cannot measure is kept, but flagged as suspect.
+    pub fn parse_outcome(&self) -> Result<ParseOutcome, VoltcraftError> {
+        let raw = self.raw_data();
+        let (sessions, error) = self.decode_sessions();
+        let tail = |offset: usize| {
+            let length = raw.len() - offset;
+            match raw[offset..].iter().all(|b| *b == 0xFF) {
+                _ if length == 0 => Termination::MissingEndOfData,
+                true => Termination::ShortPadding { offset, length },
+                false => Termination::TruncatedRecord { offset, length },
+            }
+        };
+        let termination = match error {
+            None => Termination::EndOfData,
+            Some(VoltcraftError::TruncatedData { offset }) if offset > 0 => tail(offset),
+            // The last record, followed by less than a record and no marker
+            Some(VoltcraftError::InvalidPowerRecord { offset })
+                if raw.len() - offset < 10 && !raw[offset + 5..].starts_with(&END_OF_DATA) =>
+            {
+                Termination::TruncatedRecord {
+                    offset,
+                    length: raw.len() - offset,
+                }
+            }
+            Some(e) => return Err(e),
+        };
+        let events = sessions
+            .into_iter()
+            .flat_map(|s| s.events)
+            .collect::<Vec<_>>();
+        let suspect_last_event = termination != Termination::EndOfData
+            && events
+                .last()
+                .is_some_and(|pe| pe.current > MAX_CURRENT || pe.power_factor > 1.0);
+        Ok(ParseOutcome {
+            events,
+            termination,
+            suspect_last_event,
+        })
+    }
+
+    /// Decode the power events of each data block as a separate session.
+    pub fn parse_sessions(&self) -> Result<Vec<PowerSession>, VoltcraftError> {
+        match self.decode_sessions() {
+            (sessions, None) => Ok(sessions),
+            (_, Some(e)) => Err(e),
+        }
+    }
+
+    // Decode the sessions until the end of data marker, or until an error is found
+    fn decode_sessions(&self) -> (Vec<PowerSession>, Option<VoltcraftError>) {
+        self.decode_sessions_in(&Local)
+    }
+
+    // Same as decode_sessions, with the start times of the headers in the zone
+    fn decode_sessions_in<Tz: TimeZone>(
+        &self,
+        tz: &Tz,
+    ) -> (Vec<PowerSession>, Option<VoltcraftError>) {
+        let mut result = Vec::<PowerSession>::new();
+        let mut steps = PowerEventIter::new(self.raw_data(), tz.clone());
+        while let Some(step) = steps.step() {
+            match step {
+                Ok(Step::Session(start)) => result.push(PowerSession {
+                    start,
+                    events: Vec::new(),
+                }),
+                Ok(Step::Event(event)) => result.last_mut().unwrap().events.push(event),
+                Err(e) => return (result, Some(e)),
+            }
+        }
+        (result, None)
+    }
+
+    /// Decode the sessions, detecting sessions that start before an earlier one ends.
+    /// Each overlap is reported to `resolve`, which decides how the session is kept.
+    pub fn parse_sessions_with<F>(
+        &self,
+        mut resolve: F,
+    ) -> Result<(Vec<PowerSession>, ParseSummary), VoltcraftError>
+    where
+        F: FnMut(&SessionOverlap) -> OverlapResolution,
+    {
+        let mut sessions = self.parse_sessions()?;
+        let mut summary = ParseSummary {
+            session_count: sessions.len(),
+            ..Default::default()
+        };
+        // Latest event seen so far, along with the session it belongs to
+        let mut latest: Option<(usize, chrono::DateTime<Local>)> = None;
+
+        for (index, session) in sessions.iter_mut().enumerate() {
+            if let Some((previous_session, previous_end)) = latest {
+                if session.start <= previous_end && !session.events.is_empty() {
+                    let overlap = SessionOverlap {
+                        session: index,
+                        previous_session,
+                        start: session.start,
+                        previous_end,
+                    };
+                    match resolve(&overlap) {
+                        OverlapResolution::KeepBoth => {}
+                        OverlapResolution::DropOverlap => {
+                            session.events.retain(|e| e.timestamp > previous_end);
+                        }
+                        OverlapResolution::Reanchor(start) => {
+                            let shift = start - session.start;
+                            session.start = start;
+                            for e in session.events.iter_mut() {
+                                e.timestamp += shift;
+                            }
+                        }
+                    }
+                    summary.overlaps.push(overlap);
+                }
+            }
+            if let Some(last) = session.events.last() {
+                if latest.is_none_or(|(_, end)| last.timestamp > end) {
+                    latest = Some((index, last.timestamp));
+                }
+            }
+        }
+        summary.event_count = sessions.iter().map(|s| s.events.len()).sum();
+        summary.suspicious_start_time = sessions.iter().any(|s| is_suspicious_start(s.start));
+        Ok((sessions, summary))
+    }
+
+    /// Decode the sessions as recorded (overlapping ones included), applying the options.
+    pub fn parse_with_options(
+        &self,
+        options: &ParseOptions,
+    ) -> Result<(Vec<PowerSession>, ParseSummary), VoltcraftError> {
+        let (mut sessions, mut summary) =
+            self.parse_sessions_with(|_| OverlapResolution::KeepBoth)?;
+        if let (Some(anchor), Some(first)) = (options.anchor_time, sessions.first()) {
+            let shift = anchor - first.start;
+            for session in sessions.iter_mut() {
+                session.start += shift;
+                for e in session.events.iter_mut() {
+                    e.timestamp += shift;
+                }
+            }
+            summary.anchored = true;
+        }
+        Ok((sessions, summary))
+    }
+
     fn is_datablock(&self, off: usize) -> bool {
-        const MAGIC_NUMBER: [u8; 3] = [0xE0, 0xC5, 0xEA];
-        let header = &self.raw_data[off..off + 3];
-        header == MAGIC_NUMBER
+        self.raw_data()[off..].starts_with(&MAGIC_NUMBER)
     }
 
-    fn is_endofdata(&self, off: usize) -> bool {
-        const END_OF_DATA: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
-        let eod = &self.raw_data[off..off + 4];
-        eod == END_OF_DATA
+    fn decode_timestamp(&self, off: usize) -> Option<chrono::DateTime<Local>> {
+        decode_start(self.raw_data().get(off..off + 5)?.try_into().unwrap())
     }
 
-    fn decode_timestamp(&self, off: usize) -> chrono::DateTime<Local> {
-        let month: u8 = self.raw_data[off];
-        let day: u8 = self.raw_data[off + 1];
-        let year: u8 = self.raw_data[off + 2];
-        let hour: u8 = self.raw_data[off + 3];
-        let minute: u8 = self.raw_data[off + 4];
-        chrono::Local
-            .ymd(year as i32 + 2000, month as u32, day as u32)
-            .and_hms(hour as u32, minute as u32, 0)
+    // Decode the power record at the offset, None if its voltage is implausible
+    fn decode_power(&self, off: usize) -> Option<PowerReading> {
+        let record: &[u8; 5] = self.raw_data()[off..off + 5].try_into().unwrap();
+        let reading = decode_power_record(record);
+        reading.is_plausible().then_some(reading)
     }
+}
 
-    fn decode_power(&self, off: usize) -> (f64, f64, f64, f64, f64) {
-        // Decode voltage (2 bytes - Big Endian)
-        let voltage: [u8; 2] = self.raw_data[off..off + 2].try_into().unwrap();
-        let voltage = u16::from_be_bytes(voltage);
-        let voltage: f64 = voltage as f64 / 10.0; // volts
-        assert!(voltage > 150.0, "Low voltage at offset {}", off);
-        assert!(voltage < 250.0, "High voltage at offset {}", off);
+impl VoltcraftData {
+    /// Data file of a single session starting at `start` with a power event per minute, the
+    /// inverse of `parse`, e.g. to build test data. The values are rounded to the resolution of
+    /// the device; the active and apparent power are not stored, `parse` derives them again
+    /// (and rejects voltages outside 150 V to 250 V).
+    pub fn encode(
+        start: chrono::DateTime<Local>,
+        events: &[PowerEvent],
+    ) -> Result<Vec<u8>, EncodeError> {
+        let local = start.naive_local();
+        let year = local.year() - 2000;
+        if !(0..=255).contains(&year) || local.second() != 0 || local.nanosecond() != 0 {
+            return Err(EncodeError::InvalidStartTime { start });
+        }
+        let mut raw = MAGIC_NUMBER.to_vec();
+        raw.extend_from_slice(&[
+            local.month() as u8,
+            local.day() as u8,
+            year as u8,
+            local.hour() as u8,
+            local.minute() as u8,
+        ]);
+        for (index, pe) in events.iter().enumerate() {
+            if pe.timestamp != start + Duration::minutes(index as i64) {
+                return Err(EncodeError::NotContiguous { index });
+            }
+            // Number of quanta of the value, if the field holds it
+            let steps = |field: &'static str, value: f64, quantum: f64, max: f64| {
+                let steps = (value / quantum).round();
+                match steps >= 0.0 && steps <= max {
+                    true => Ok(steps),
+                    false => Err(EncodeError::OutOfRange {
+                        index,
+                        field,
+                        value,
+                    }),
+                }
+            };
+            let voltage = steps("voltage", pe.voltage, VOLTAGE_QUANTUM, u16::MAX as f64)?;
+            let current = steps("current", pe.current, CURRENT_QUANTUM, u16::MAX as f64)?;
+            let power_factor = steps(
+                "power factor",
+                pe.power_factor,
+                POWER_FACTOR_QUANTUM,
+                u8::MAX as f64,
+            )?;
+            raw.extend_from_slice(&(voltage as u16).to_be_bytes());
+            raw.extend_from_slice(&(current as u16).to_be_bytes());
+            raw.push(power_factor as u8);
+        }
+        raw.extend_from_slice(&END_OF_DATA);
+        Ok(raw)
+    }
 
-        // Decode current (2 bytes - Big Endian)
-        let current: [u8; 2] = self.raw_data[off + 2..off + 4].try_into().unwrap();
-        let current = u16::from_be_bytes(current);
-        let current: f64 = current as f64 / 1000.0; // ampers
+    /// Combine the power events of several sets (e.g. the weekly downloads of a device) into
+    /// chronological order with one event per minute, as `VoltcraftStatistics` expects. Events
+    /// at the same minute are deduplicated according to the policy, and the
+    /// sets recording the same time range are reported. Minutes between the sets that none of
+    /// them recorded are blackouts, like any other gap.
+    pub fn merge(
+        events: Vec<Vec<PowerEvent>>,
+        policy: DuplicatePolicy,
+    ) -> Result<(Vec<PowerEvent>, MergeReport), MergeConflict> {
+        let mut report = MergeReport::default();
+        let ranges = events
+            .iter()
+            .map(|set| {
+                let first = set.iter().map(|e| e.timestamp).min()?;
+                Some((first, set.iter().map(|e| e.timestamp).max()?))
+            })
+            .collect::<Vec<_>>();
+        for (set, range) in ranges.iter().enumerate() {
+            for (previous_set, previous) in ranges[..set].iter().enumerate() {
+                if let (Some((start, end)), Some((previous_start, previous_end))) =
+                    (range, previous)
+                {
+                    if start <= previous_end && previous_start <= end {
+                        report.overlaps.push(MergeOverlap {
+                            set,
+                            previous_set,
+                            start: *start.max(previous_start),
+                            end: *end.min(previous_end),
+                        });
+                    }
+                }
+            }
+        }
 
-        // Decode power factor (1 byte)
-        let power_factor: u8 = self.raw_data[off + 4];
-        let power_factor: f64 = power_factor as f64 / 100.0; // cos phi
+        // Stable sort, so the events of a minute are in the order of their sets
+        let mut tagged = events
+            .into_iter()
+            .enumerate()
+            .flat_map(|(set, events)| events.into_iter().map(move |e| (set, e)))
+            .collect::<Vec<_>>();
+        tagged.sort_by_key(|(_, e)| e.timestamp);
+        let mut merged: Vec<PowerEvent> = Vec::with_capacity(tagged.len());
+        let mut kept_set = 0; // set of the last merged event
+        for (set, event) in tagged {
+            match merged.last_mut() {
+                Some(last) if last.timestamp == event.timestamp => {
+                    if same_reading(last, &event) {
+                        report.duplicates += 1;
+                        continue;
+                    }
+                    report.conflicts += 1;
+                    match policy {
+                        DuplicatePolicy::KeepFirst => {}
+                        DuplicatePolicy::KeepLast => {
+                            *last = event;
+                            kept_set = set;
+                        }
+                        DuplicatePolicy::Error => {
+                            return Err(MergeConflict {
+                                timestamp: event.timestamp,
+                                set,
+                                previous_set: kept_set,
+                            })
+                        }
+                    }
+                }
+                _ => {
+                    merged.push(event);
+                    kept_set = set;
+                }
+            }
+        }
+        report.event_count = merged.len();
+        Ok((merged, report))
+    }
 
-        let power = voltage * current * power_factor / 1000.0; // kW
-        let apparent_power = voltage * current / 1000.0; // kVA
-        (voltage, current, power_factor, power, apparent_power)
+    /// Decode a raw power record into voltage (V), current (A), power factor,
+    /// active power (kW) and apparent power (kVA). Unlike `parse`, implausible voltages are not rejected.
+    #[deprecated(
+        since = "0.3.0",
+        note = "use decode_power_record, which names the values"
+    )]
+    pub fn decode_record(record: &[u8; 5]) -> (f64, f64, f64, f64, f64) {
+        let r = decode_power_record(record);
+        (
+            r.voltage,
+            r.current,
+            r.power_factor,
+            r.power,
+            r.apparent_power,
+        )
     }
 }
 
 #[cfg(test)]
-
 mod tests {
-    use crate::voltcraft::data::VoltcraftData;
-    use chrono::DateTime;
+    use crate::voltcraft::consts::{CURRENT_QUANTUM, POWER_FACTOR_QUANTUM, VOLTAGE_QUANTUM};
+    use crate::voltcraft::data::{
+        decode_power_record, same_reading, DuplicatePolicy, EncodeError, FormatVersion,
+        MergeConflict, MergeOverlap, OverlapResolution, ParseOptions, PowerEvent, Termination,
+        VoltcraftData, VoltcraftDataRef,
+    };
+    use crate::voltcraft::error::VoltcraftError;
+    use crate::voltcraft::stats::VoltcraftStatistics;
+    use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, TimeZone, Utc};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
     const TESTDATA: [u8; 17] = [
         // Header (magic number)
         0xE0, 0xC5, 0xEA, // Power data
         0x09, 0x0B, 0x0E, 0x12, 0x2B, 0x08, 0xC6, 0x01, 0xBE, 0x57, // End of power data
         0xFF, 0xFF, 0xFF, 0xFF,
     ];
+    // Two sessions, the second one starting (after a device reset) at 18:44 while the first runs until 18:45
+    const REVERTED_CLOCK: [u8; 55] = [
+        // Session 1: 2014-09-11 18:43, three records
+        0xE0, 0xC5, 0xEA, 0x09, 0x0B, 0x0E, 0x12, 0x2B, //
+        0x08, 0xC6, 0x01, 0xBE, 0x57, //
+        0x08, 0xC6, 0x01, 0xBE, 0x57, //
+        0x08, 0xC6, 0x01, 0xBE, 0x57, //
+        // Session 2: 2014-09-11 18:44, four records
+        0xE0, 0xC5, 0xEA, 0x09, 0x0B, 0x0E, 0x12, 0x2C, //
+        0x08, 0xC6, 0x01, 0xBE, 0x57, //
+        0x08, 0xC6, 0x01, 0xBE, 0x57, //
+        0x08, 0xC6, 0x01, 0xBE, 0x57, //
+        0x08, 0xC6, 0x01, 0xBE, 0x57, //
+        // End of power data
+        0xFF, 0xFF, 0xFF, 0xFF,
+    ];
+
+    fn at(hour: u32, minute: u32) -> chrono::DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2014, 9, 11, hour, minute, 0)
+            .unwrap()
+    }
 
     #[test]
     fn voltcraft_timestamp() {
         let vd = VoltcraftData::from_raw(TESTDATA.to_vec());
         let offset_timestamp = 3;
-        let ts = vd.decode_timestamp(offset_timestamp);
-        let expected = DateTime::parse_from_rfc3339("2014-09-11T18:43:00+03:00").unwrap();
-        assert_eq!(ts, expected);
+        let ts = vd.decode_timestamp(offset_timestamp).unwrap();
+        // The device records local wall-clock time
+        let expected = NaiveDate::from_ymd_opt(2014, 9, 11)
+            .unwrap()
+            .and_hms_opt(18, 43, 0)
+            .unwrap();
+        assert_eq!(ts.naive_local(), expected);
+    }
+
+    #[test]
+    fn data_power_event_display() {
+        let events = VoltcraftData::from_raw(TESTDATA.to_vec()).parse().unwrap();
+        assert_eq!(
+            events[0].to_string(),
+            "2014-09-11 18:43 224.6 V 0.446 A PF 0.87 0.087 kW 0.100 kVA"
+        );
+    }
+
+    #[test]
+    fn data_parse_with_format() {
+        let data = VoltcraftData::from_file("sample_data1/A04FC8D2.BIN").unwrap();
+        let events = data.parse_with_format(FormatVersion::V1).unwrap();
+        assert_eq!(events, data.parse().unwrap());
+        assert!(events.iter().all(|pe| pe.frequency.is_none()));
+
+        let version = FormatVersion::default().version();
+        assert_eq!(FormatVersion::try_from(version).unwrap(), FormatVersion::V1);
+        let unknown = FormatVersion::try_from(0x07).unwrap_err();
+        assert!(matches!(
+            unknown,
+            VoltcraftError::UnsupportedFormatVersion { version: 0x07 }
+        ));
+        assert_eq!(
+            unknown.to_string(),
+            "unsupported data file format version 0x07"
+        );
+    }
+
+    #[test]
+    fn data_decode_power_record() {
+        // 224.6 V, 0.446 A, cos phi 0.87, without a header or end of data marker
+        let reading = decode_power_record(&[0x08, 0xC6, 0x01, 0xBE, 0x57]);
+        assert_eq!(reading.voltage, 224.6);
+        assert_eq!(reading.current, 0.446);
+        assert_eq!(reading.power_factor, 0.87);
+        assert_eq!(reading.power, 224.6 * 0.446 * 0.87 / 1000.0);
+        assert_eq!(reading.apparent_power, 224.6 * 0.446 / 1000.0);
+        assert!(reading.is_plausible());
+        // The end of data marker is no power record
+        assert!(!decode_power_record(&[0xFF; 5]).is_plausible());
+        assert_eq!(decode_power_record(&[0x09, 0xC4, 0, 0, 0]).voltage, 250.0);
+        assert!(!decode_power_record(&[0x09, 0xC4, 0, 0, 0]).is_plausible());
+
+        let timestamp = at(18, 43);
+        let event = PowerEvent::from_reading(timestamp, reading);
+        let parsed = VoltcraftData::from_raw(TESTDATA).parse().unwrap();
+        assert_eq!(event, parsed[0]);
     }
 
     #[test]
     fn voltcraft_poweritem() {
         let vd = VoltcraftData::from_raw(TESTDATA.to_vec());
         let offset_poweritem = 8;
-        let pw = vd.decode_power(offset_poweritem);
-        assert_eq!(pw.0, 224.6);
-        assert_eq!(pw.1, 0.446);
-        assert_eq!(pw.2, 0.87);
+        let pw = vd.decode_power(offset_poweritem).unwrap();
+        assert_eq!(pw.voltage, 224.6);
+        assert_eq!(pw.current, 0.446);
+        assert_eq!(pw.power_factor, 0.87);
+    }
+
+    #[test]
+    fn voltcraft_sessions() {
+        let vd = VoltcraftData::from_raw(REVERTED_CLOCK.to_vec());
+        let sessions = vd.parse_sessions().unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].start, at(18, 43));
+        assert_eq!(sessions[0].events.len(), 3);
+        assert_eq!(sessions[1].start, at(18, 44));
+        assert_eq!(sessions[1].events.len(), 4);
+        // Plain parsing concatenates the sessions in file order
+        assert_eq!(vd.parse().unwrap().len(), 7);
+    }
+
+    #[test]
+    fn voltcraft_overlap_keep_both() {
+        let vd = VoltcraftData::from_raw(REVERTED_CLOCK.to_vec());
+        let (sessions, summary) = vd
+            .parse_sessions_with(|_| OverlapResolution::KeepBoth)
+            .unwrap();
+        assert_eq!(summary.session_count, 2);
+        assert_eq!(summary.event_count, 7);
+        assert_eq!(summary.overlaps.len(), 1);
+        assert_eq!(summary.overlaps[0].session, 1);
+        assert_eq!(summary.overlaps[0].previous_end, at(18, 45));
+        assert_eq!(
+            summary.overlaps[0].to_string(),
+            "session 2 starts before session 1 ends"
+        );
+        assert_eq!(sessions[1].events[0].timestamp, at(18, 44));
+    }
+
+    #[test]
+    fn voltcraft_overlap_drop() {
+        let vd = VoltcraftData::from_raw(REVERTED_CLOCK.to_vec());
+        let (sessions, summary) = vd
+            .parse_sessions_with(|_| OverlapResolution::DropOverlap)
+            .unwrap();
+        assert_eq!(summary.overlaps.len(), 1);
+        assert_eq!(summary.event_count, 5);
+        let kept = sessions[1]
+            .events
+            .iter()
+            .map(|e| e.timestamp)
+            .collect::<Vec<_>>();
+        assert_eq!(kept, vec![at(18, 46), at(18, 47)]);
+    }
+
+    #[test]
+    fn voltcraft_overlap_reanchor() {
+        let vd = VoltcraftData::from_raw(REVERTED_CLOCK.to_vec());
+        let (sessions, summary) = vd
+            .parse_sessions_with(|_| OverlapResolution::Reanchor(at(19, 0)))
+            .unwrap();
+        assert_eq!(summary.overlaps.len(), 1);
+        assert_eq!(summary.event_count, 7);
+        assert_eq!(sessions[1].start, at(19, 0));
+        assert_eq!(sessions[1].events[0].timestamp, at(19, 0));
+        assert_eq!(sessions[1].events[3].timestamp, at(19, 3));
+    }
+
+    #[test]
+    fn voltcraft_no_overlap() {
+        let vd = VoltcraftData::from_raw(TESTDATA.to_vec());
+        let (_, summary) = vd
+            .parse_sessions_with(|_| panic!("no overlap expected"))
+            .unwrap();
+        assert!(summary.overlaps.is_empty());
+        assert_eq!(summary.event_count, 1);
+    }
+
+    // Decoding each raw record gives the power event parsed from the same bytes
+    fn assert_raw_records_match(raw: &[u8]) {
+        let vdf = VoltcraftData::from_raw(raw.to_vec());
+        let events = vdf.parse().unwrap();
+        let mut records = vdf.raw_records();
+        let mut count = 0;
+        for ((offset, record), e) in records.by_ref().zip(&events) {
+            assert_eq!(&raw[offset..offset + 5], record);
+            let decoded = decode_power_record(record);
+            assert_eq!(PowerEvent::from_reading(e.timestamp, decoded), *e);
+            count += 1;
+        }
+        assert_eq!(count, events.len());
+        assert!(records.next().is_none());
+        assert_eq!(records.malformed_tail(), None);
+    }
+
+    #[test]
+    fn data_raw_records() {
+        assert_raw_records_match(&TESTDATA);
+        assert_raw_records_match(&REVERTED_CLOCK);
+        assert_raw_records_match(include_bytes!("../../sample_data1/A04FC8D2.BIN"));
+
+        let offsets = VoltcraftData::from_raw(REVERTED_CLOCK.to_vec())
+            .raw_records()
+            .map(|(offset, _)| offset)
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec![8, 13, 18, 31, 36, 41, 46]);
+        let vdf = VoltcraftData::from_raw(REVERTED_CLOCK.to_vec());
+        let mut records = vdf.raw_records();
+        records.by_ref().count();
+        assert_eq!(records.header_count(), 2);
+        assert_eq!(records.end_of_data(), Some(51));
+    }
+
+    #[test]
+    fn data_from_file_errors() {
+        let missing = std::env::temp_dir().join("voltcraft-missing.bin");
+        match VoltcraftData::from_file(missing.to_str().unwrap()) {
+            Err(VoltcraftError::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::NotFound),
+            _ => panic!("missing file expected"),
+        }
+        // Reading a folder fails for another reason than a missing file
+        match VoltcraftData::from_file(std::env::temp_dir().to_str().unwrap()) {
+            Err(VoltcraftError::Io(e)) => assert_ne!(e.kind(), std::io::ErrorKind::NotFound),
+            _ => panic!("read error expected"),
+        }
+    }
+
+    #[test]
+    fn data_sessions_after_end_marker() {
+        // A session on 2014-09-11, the padding of the device, and one recorded on 2014-10-02
+        // after the logger was powered on again
+        let mut raw = TESTDATA.to_vec();
+        raw.extend_from_slice(&[0xFF; 7]);
+        raw.extend_from_slice(&[0xE0, 0xC5, 0xEA, 0x0A, 0x02, 0x0E, 0x08, 0x00]);
+        raw.extend_from_slice(&TESTDATA[8..13].repeat(2));
+        raw.extend_from_slice(&[0xFF; 4 + 16]);
+        let vdf = VoltcraftData::from_raw(raw.clone());
+
+        let sessions = vdf.parse_sessions().unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].start, at(18, 43));
+        let october = Local.with_ymd_and_hms(2014, 10, 2, 8, 0, 0).unwrap();
+        assert_eq!(sessions[1].start, october);
+        assert_eq!(
+            sessions.iter().map(|s| s.events.len()).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(
+            vdf.parse().unwrap()[2].timestamp,
+            october + Duration::minutes(1)
+        );
+
+        let mut records = vdf.raw_records();
+        assert_eq!(
+            records
+                .by_ref()
+                .map(|(offset, _)| offset)
+                .collect::<Vec<_>>(),
+            [8, 32, 37]
+        );
+        assert_eq!(
+            (records.header_count(), records.end_of_data()),
+            (2, Some(42))
+        );
+        assert_eq!(vdf.fingerprint().record_count, 3);
+
+        // Padding alone after the end marker ends the file
+        raw.truncate(24);
+        assert_eq!(
+            VoltcraftData::from_raw(raw).parse_sessions().unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn data_invalid_timestamps() {
+        // All-zero start time, and hour 24
+        let mut zeroed = TESTDATA;
+        zeroed[3..8].fill(0);
+        assert!(matches!(
+            VoltcraftData::from_raw(zeroed.to_vec()).parse(),
+            Err(VoltcraftError::InvalidTimestamp { offset: 0 })
+        ));
+        let mut late = TESTDATA;
+        late[6] = 24;
+        assert!(matches!(
+            VoltcraftData::from_raw(late.to_vec()).parse(),
+            Err(VoltcraftError::InvalidTimestamp { offset: 0 })
+        ));
+        // A garbage header in the second data block keeps the first session
+        let mut raw = REVERTED_CLOCK;
+        raw[26..31].copy_from_slice(&[0x00, 0x20, 0x0E, 0x3C, 0x00]);
+        let vdf = VoltcraftData::from_raw(raw.to_vec());
+        assert_eq!(vdf.fingerprint().start, Some(at(18, 43)));
+        let (events, error) = vdf.parse_lossy();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(
+            error,
+            Some(VoltcraftError::InvalidTimestamp { offset: 23 })
+        ));
+        zeroed[..3].copy_from_slice(&[0xE0, 0xC5, 0xEA]);
+        assert_eq!(
+            VoltcraftData::from_raw(zeroed.to_vec()).fingerprint().start,
+            None
+        );
+    }
+
+    #[test]
+    fn data_truncated_files() {
+        let eof = |raw: &[u8]| match VoltcraftData::from_raw(raw.to_vec()).parse() {
+            Err(VoltcraftError::TruncatedData { offset }) => Some(offset),
+            _ => None,
+        };
+        // Shorter than the magic number, or cut off in the data block header
+        assert_eq!(eof(&[]), Some(0));
+        assert_eq!(eof(&TESTDATA[..2]), Some(0));
+        assert_eq!(eof(&TESTDATA[..6]), Some(0));
+        assert!(matches!(
+            VoltcraftData::from_raw(vec![0x12, 0x34]).parse(),
+            Err(VoltcraftError::InvalidMagicNumber)
+        ));
+        // In the middle of the power record starting at offset 8
+        for len in 9..13 {
+            assert_eq!(eof(&TESTDATA[..len]), Some(8));
+        }
+        // Without the end of data marker, and with a partial marker
+        assert_eq!(eof(&TESTDATA[..13]), Some(13));
+        assert_eq!(eof(&TESTDATA[..15]), Some(13));
+
+        // The events decoded before the data ends are kept
+        let (events, error) = VoltcraftData::from_raw(REVERTED_CLOCK[..49].to_vec()).parse_lossy();
+        assert_eq!(events.len(), 3 + 3);
+        assert!(matches!(
+            error,
+            Some(VoltcraftError::TruncatedData { offset: 46 })
+        ));
+        let (events, error) = VoltcraftData::from_raw(REVERTED_CLOCK.to_vec()).parse_lossy();
+        assert_eq!((events.len(), error.is_none()), (7, true));
+    }
+
+    #[test]
+    fn data_parse_outcome_tails() {
+        let outcome = |raw: &[u8]| VoltcraftData::from_raw(raw).parse_outcome();
+        let clean = outcome(include_bytes!("../../tests/fixtures/tail_end_of_data.bin")).unwrap();
+        assert_eq!(clean.termination, Termination::EndOfData);
+        assert_eq!((clean.events.len(), clean.suspect_last_event), (3, false));
+
+        let truncated = include_bytes!("../../tests/fixtures/tail_truncated_record.bin");
+        assert!(VoltcraftData::from_raw(truncated.to_vec()).parse().is_err());
+        let truncated = outcome(truncated).unwrap();
+        assert_eq!(
+            truncated.termination,
+            Termination::TruncatedRecord {
+                offset: 23,
+                length: 3
+            }
+        );
+        assert_eq!(truncated.events, clean.events);
+        let padded = outcome(include_bytes!(
+            "../../tests/fixtures/tail_short_padding.bin"
+        ))
+        .unwrap();
+        assert_eq!(
+            padded.termination,
+            Termination::ShortPadding {
+                offset: 23,
+                length: 3
+            }
+        );
+        assert_eq!(padded.events, clean.events);
+
+        // The record being written when the card was pulled decodes to 65.5 A
+        let raw = include_bytes!("../../tests/fixtures/tail_record_in_progress.bin");
+        let in_progress = outcome(raw).unwrap();
+        assert_eq!(in_progress.termination, Termination::MissingEndOfData);
+        assert_eq!(in_progress.events.len(), 4);
+        assert!(in_progress.suspect_last_event);
+        assert_eq!(in_progress.trusted_events(), clean.events.as_slice());
+        assert_eq!(clean.trusted_events(), clean.events.as_slice());
+
+        // A last record with an implausible voltage is part of the tail
+        let overwritten = clean.events.len() * 5 + 8;
+        let mut raw = raw[..overwritten].to_vec();
+        raw.extend_from_slice(&[0xFF, 0xFF, 0x01, 0xBE, 0x57, 0xFF]);
+        let tail = outcome(&raw).unwrap();
+        assert_eq!(
+            tail.termination,
+            Termination::TruncatedRecord {
+                offset: overwritten,
+                length: 6
+            }
+        );
+        assert_eq!(tail.events, clean.events);
+        // Anywhere else it is still an error, as are files cut off within the first header
+        let mut invalid = TESTDATA.to_vec();
+        invalid[8..10].copy_from_slice(&[0xFF, 0xFF]);
+        assert!(matches!(
+            outcome(&invalid),
+            Err(VoltcraftError::InvalidPowerRecord { offset: 8 })
+        ));
+        raw[8] = 0xFF;
+        assert!(outcome(&raw).is_err());
+        assert!(matches!(
+            outcome(&TESTDATA[..6]),
+            Err(VoltcraftError::TruncatedData { offset: 0 })
+        ));
+    }
+
+    #[test]
+    fn data_raw_records_malformed_tail() {
+        // Two and a half records, without the end of data marker
+        let vdf = VoltcraftData::from_raw(
+            TESTDATA[..13]
+                .iter()
+                .chain(&TESTDATA[8..16])
+                .cloned()
+                .collect::<Vec<_>>(),
+        );
+        let mut records = vdf.raw_records();
+        assert_eq!(records.by_ref().count(), 2);
+        assert_eq!(records.malformed_tail(), Some(18));
+    }
+
+    #[test]
+    fn data_anchor_time() {
+        // Header at the device default 2000-01-01 00:00, then a second block two minutes later
+        let mut raw = REVERTED_CLOCK.to_vec();
+        raw[3..8].copy_from_slice(&[0x01, 0x01, 0x00, 0x00, 0x00]);
+        raw[26..31].copy_from_slice(&[0x01, 0x01, 0x00, 0x00, 0x02]);
+        let vdf = VoltcraftData::from_raw(raw);
+        let (sessions, summary) = vdf.parse_with_options(&ParseOptions::default()).unwrap();
+        assert!(summary.suspicious_start_time);
+        assert!(!summary.anchored);
+        assert_eq!(
+            sessions[0].start.naive_local().date(),
+            NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()
+        );
+
+        let anchor = Local.with_ymd_and_hms(2014, 9, 11, 18, 43, 0).unwrap();
+        let options = ParseOptions::default().anchor_time(anchor);
+        let (sessions, summary) = vdf.parse_with_options(&options).unwrap();
+        assert!(summary.suspicious_start_time && summary.anchored);
+        assert_eq!(sessions[0].events[0].timestamp, anchor);
+        assert_eq!(sessions[1].start, anchor + chrono::Duration::minutes(2));
+
+        // A plausible header is not flagged
+        let vdf = VoltcraftData::from_raw(REVERTED_CLOCK.to_vec());
+        let (_, summary) = vdf.parse_with_options(&ParseOptions::default()).unwrap();
+        assert!(!summary.suspicious_start_time);
+    }
+
+    #[test]
+    fn data_fingerprint() {
+        let fingerprint = VoltcraftData::from_raw(REVERTED_CLOCK.to_vec()).fingerprint();
+        assert_eq!(fingerprint.record_count, 7);
+        assert_eq!(
+            fingerprint.start,
+            Some(Local.with_ymd_and_hms(2014, 9, 11, 18, 43, 0).unwrap())
+        );
+        assert_eq!(fingerprint.to_string().len(), 16);
+
+        // Same records, padded differently after the end of data marker
+        let mut padded = REVERTED_CLOCK.to_vec();
+        padded.extend_from_slice(&[0x00; 11]);
+        assert_eq!(VoltcraftData::from_raw(padded).fingerprint(), fingerprint);
+        let mut padded = REVERTED_CLOCK.to_vec();
+        padded.extend_from_slice(&[0xFF; 512]);
+        assert_eq!(VoltcraftData::from_raw(padded).fingerprint(), fingerprint);
+
+        let mut changed = REVERTED_CLOCK.to_vec();
+        changed[9] ^= 0x01;
+        let changed = VoltcraftData::from_raw(changed).fingerprint();
+        assert_ne!(changed, fingerprint);
+        assert_ne!(changed.to_string(), fingerprint.to_string());
+    }
+
+    #[test]
+    fn data_same_reading() {
+        let event = VoltcraftData::from_raw(TESTDATA.to_vec()).parse().unwrap()[0];
+        let mut perturbed = event;
+        perturbed.voltage += 1e-12;
+        perturbed.current -= 1e-12;
+        perturbed.power_factor += 1e-12;
+        assert!(same_reading(&event, &perturbed));
+        // Round trip through the text representation used by the CSV export
+        let mut parsed = event;
+        parsed.voltage = format!("{:.1}", event.voltage).parse().unwrap();
+        parsed.current = format!("{:.3}", event.current).parse().unwrap();
+        assert!(same_reading(&event, &parsed));
+
+        for (dv, di, dpf) in [
+            (VOLTAGE_QUANTUM, 0.0, 0.0),
+            (0.0, CURRENT_QUANTUM, 0.0),
+            (0.0, 0.0, -POWER_FACTOR_QUANTUM),
+        ] {
+            let mut changed = event;
+            changed.voltage += dv;
+            changed.current += di;
+            changed.power_factor += dpf;
+            assert!(!same_reading(&event, &changed));
+        }
+    }
+
+    #[test]
+    fn data_merge() {
+        let start = Local.with_ymd_and_hms(2014, 9, 11, 0, 0, 0).unwrap();
+        let event = VoltcraftData::from_raw(TESTDATA.to_vec()).parse().unwrap()[0];
+        let minutes = |from: i64, to: i64| {
+            (from..to)
+                .map(|m| PowerEvent {
+                    timestamp: start + Duration::minutes(m),
+                    ..event
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut conflicting = minutes(5, 6);
+        conflicting[0].voltage += 1.0;
+        // The second week first, the first week imported twice, then another reading of minute 5
+        let sets = vec![minutes(20, 30), minutes(0, 10), minutes(0, 10), conflicting];
+
+        let (merged, report) =
+            VoltcraftData::merge(sets.clone(), DuplicatePolicy::KeepFirst).unwrap();
+        assert_eq!(merged.len(), 20);
+        assert_eq!(report.event_count, 20);
+        assert!(merged.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+        assert_eq!(merged[5].voltage, event.voltage);
+        assert_eq!((report.duplicates, report.conflicts), (10, 1));
+        let at = |m| start + Duration::minutes(m);
+        assert_eq!(
+            report.overlaps,
+            [
+                MergeOverlap {
+                    set: 2,
+                    previous_set: 1,
+                    start: at(0),
+                    end: at(9)
+                },
+                MergeOverlap {
+                    set: 3,
+                    previous_set: 1,
+                    start: at(5),
+                    end: at(5)
+                },
+                MergeOverlap {
+                    set: 3,
+                    previous_set: 2,
+                    start: at(5),
+                    end: at(5)
+                },
+            ]
+        );
+        // Only the minutes between the two weeks are missing
+        let merged_events = merged;
+        let blackouts = VoltcraftStatistics::new(&merged_events).blackout_stats();
+        assert_eq!(blackouts.blackout_count, 1);
+        assert_eq!(blackouts.blackouts[0].timestamp, at(10));
+        assert_eq!(blackouts.blackouts[0].duration_minutes(), 10);
+
+        let (merged, _) = VoltcraftData::merge(sets.clone(), DuplicatePolicy::KeepLast).unwrap();
+        assert_eq!(merged[5].voltage, event.voltage + 1.0);
+        assert_eq!(
+            VoltcraftData::merge(sets, DuplicatePolicy::Error).unwrap_err(),
+            MergeConflict {
+                timestamp: at(5),
+                set: 3,
+                previous_set: 1
+            }
+        );
+        let (merged, report) =
+            VoltcraftData::merge(vec![Vec::new()], DuplicatePolicy::Error).unwrap();
+        assert!(merged.is_empty() && report.overlaps.is_empty());
+    }
+
+    #[test]
+    fn data_parse_in_time_zone() {
+        let vdf = VoltcraftData::from_raw(TESTDATA.to_vec());
+        let local = vdf.parse().unwrap();
+        let same = vdf.parse_in(&Local).unwrap();
+        assert_eq!(same[0].timestamp, local[0].timestamp);
+
+        // Recorded at 18:43 in a zone three hours ahead of UTC, decoded anywhere
+        let site = FixedOffset::east_opt(3 * 3600).unwrap();
+        let events = vdf.parse_in(&site).unwrap();
+        assert_eq!(
+            events[0].timestamp.naive_utc(),
+            NaiveDate::from_ymd_opt(2014, 9, 11)
+                .unwrap()
+                .and_hms_opt(15, 43, 0)
+                .unwrap()
+        );
+        assert_eq!(events[0].voltage, local[0].voltage);
+
+        // Two minutes around midnight at the site are two days there, a single one in UTC
+        let mut raw = TESTDATA[..13].to_vec();
+        raw[6..8].copy_from_slice(&[23, 59]);
+        raw.extend_from_slice(&TESTDATA[8..]);
+        let events = VoltcraftData::from_raw(raw).parse_in(&site).unwrap();
+        assert_eq!(events.len(), 2);
+        let stats = VoltcraftStatistics::new(&events);
+        let days = stats
+            .daily_stats_in(&site)
+            .iter()
+            .map(|d| d.date)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            days,
+            [
+                NaiveDate::from_ymd_opt(2014, 9, 11).unwrap(),
+                NaiveDate::from_ymd_opt(2014, 9, 12).unwrap()
+            ]
+        );
+        assert_eq!(stats.daily_stats_in(&Utc).len(), 1);
+    }
+
+    #[test]
+    fn data_events_iterator() {
+        let vdf = VoltcraftData::from_raw(REVERTED_CLOCK.to_vec());
+        let events = vdf.events();
+        assert_eq!(events.size_hint(), (0, Some(REVERTED_CLOCK.len() / 5)));
+        let energy = events.fold(0.0, |sum, e| sum + e.unwrap().power / 60.0);
+        let parsed = vdf.parse().unwrap();
+        assert_eq!(energy, parsed.iter().map(|e| e.power / 60.0).sum::<f64>());
+        let timestamps = vdf
+            .events()
+            .map(|e| e.unwrap().timestamp)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            timestamps,
+            parsed.iter().map(|e| e.timestamp).collect::<Vec<_>>()
+        );
+
+        // An error item ends the iteration
+        let mut truncated = REVERTED_CLOCK.to_vec();
+        truncated.truncate(20);
+        let items = VoltcraftData::from_raw(truncated)
+            .events()
+            .collect::<Vec<_>>();
+        assert_eq!(items.len(), 3);
+        assert!(items[..2].iter().all(|e| e.is_ok()));
+        assert!(matches!(
+            items[2],
+            Err(VoltcraftError::TruncatedData { offset: 18 })
+        ));
+        let invalid = VoltcraftData::from_raw(vec![0x00]);
+        let mut events = invalid.events();
+        assert!(matches!(
+            events.next(),
+            Some(Err(VoltcraftError::InvalidMagicNumber))
+        ));
+        assert!(events.next().is_none());
+        assert_eq!(events.size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    fn data_parse_with_interval() {
+        use crate::voltcraft::stats::VoltcraftStatistics;
+        // A logger recording every 5 minutes: an hour from 18:00, then half an hour from 19:30
+        let mut raw = vec![0xE0, 0xC5, 0xEA, 0x09, 0x0B, 0x0E, 0x12, 0x00];
+        raw.extend_from_slice(&TESTDATA[8..13].repeat(12));
+        raw.extend_from_slice(&[0xE0, 0xC5, 0xEA, 0x09, 0x0B, 0x0E, 0x13, 0x1E]);
+        raw.extend_from_slice(&TESTDATA[8..13].repeat(6));
+        raw.extend_from_slice(&[0xFF; 4]);
+        let vdf = VoltcraftData::from_raw(raw);
+
+        let log = vdf.parse_with_interval(Duration::minutes(5)).unwrap();
+        assert!(matches!(
+            vdf.parse_with_interval(Duration::zero()),
+            Err(VoltcraftError::InvalidSampleInterval { milliseconds: 0 })
+        ));
+        assert!(matches!(
+            vdf.parse_with_interval(Duration::milliseconds(500)),
+            Err(VoltcraftError::InvalidSampleInterval { milliseconds: 500 })
+        ));
+        assert!(vdf
+            .parse_with_interval(Duration::milliseconds(1500))
+            .is_err());
+        assert_eq!(log.interval, Duration::minutes(5));
+        assert_eq!(log.events.len(), 18);
+        assert_eq!(log.events[1].timestamp, at(18, 5));
+        assert_eq!(log.events[11].timestamp, at(18, 55));
+        assert_eq!(log.events[17].timestamp, at(19, 55));
+        let power = log.events[0].power;
+
+        let stats = VoltcraftStatistics::from_log(&log).unwrap();
+        let overall = stats.overall_stats().unwrap().stats;
+        assert!((overall.total_active_power - power * 18.0 / 12.0).abs() < 1e-12);
+        assert_eq!(overall.total_duration, Duration::minutes(120));
+        assert_eq!(overall.coverage.recorded_minutes, 90);
+        assert_eq!(overall.coverage.expected_minutes, 120);
+        // Only the gap between the sessions is a blackout, from the missing 19:00 record on
+        let blackouts = stats.blackout_stats().blackouts;
+        assert_eq!(blackouts.len(), 1);
+        assert_eq!(blackouts[0].timestamp, at(19, 0));
+        assert_eq!(blackouts[0].duration, Duration::minutes(30));
+        assert_eq!(stats.blackout_summary().availability, 75.0);
+
+        // The same file read as one minute records
+        let events = vdf.parse().unwrap();
+        assert_eq!(events[11].timestamp, at(18, 11));
+    }
+
+    #[test]
+    fn data_encode_round_trip() {
+        // Encoding the decoded power events gives the data file back
+        let events = VoltcraftData::from_raw(TESTDATA).parse().unwrap();
+        let raw = VoltcraftData::encode(events[0].timestamp, &events).unwrap();
+        assert_eq!(raw, TESTDATA);
+        let sessions = VoltcraftData::from_raw(REVERTED_CLOCK)
+            .parse_sessions()
+            .unwrap();
+        let raw = VoltcraftData::encode(sessions[1].start, &sessions[1].events).unwrap();
+        assert_eq!(raw[..], REVERTED_CLOCK[23..]);
+
+        // Two hours of varying values, rounded to the resolution of the device
+        let start = at(18, 0);
+        let events = (0..120)
+            .map(|m| {
+                let voltage = 220.0 + m as f64 * 0.17;
+                let current = m as f64 * 0.0913;
+                let power_factor = (m % 100) as f64 / 99.0;
+                PowerEvent {
+                    voltage,
+                    current,
+                    power_factor,
+                    ..event(start + Duration::minutes(m), 0.0)
+                }
+            })
+            .collect::<Vec<_>>();
+        let raw = VoltcraftData::encode(start, &events).unwrap();
+        let decoded = VoltcraftData::from_raw(raw).parse().unwrap();
+        assert_eq!(decoded.len(), events.len());
+        for (e, d) in events.iter().zip(&decoded) {
+            assert_eq!(d.timestamp, e.timestamp);
+            assert!((d.voltage - e.voltage).abs() <= VOLTAGE_QUANTUM / 2.0 + 1e-9);
+            assert!((d.current - e.current).abs() <= CURRENT_QUANTUM / 2.0 + 1e-9);
+            assert!((d.power_factor - e.power_factor).abs() <= POWER_FACTOR_QUANTUM / 2.0 + 1e-9);
+            assert!(d.is_consistent(1e-12));
+        }
+        // An empty session
+        let raw = VoltcraftData::encode(start, &[]).unwrap();
+        assert!(VoltcraftData::from_raw(raw).parse().unwrap().is_empty());
+
+        // Values larger than their field are not wrapped
+        let mut high = events[..3].to_vec();
+        high[2].current = 65.536;
+        assert_eq!(
+            VoltcraftData::encode(start, &high),
+            Err(EncodeError::OutOfRange {
+                index: 2,
+                field: "current",
+                value: 65.536
+            })
+        );
+        high[2].current = 1.0;
+        high[1].voltage = 6553.6;
+        assert!(matches!(
+            VoltcraftData::encode(start, &high),
+            Err(EncodeError::OutOfRange {
+                index: 1,
+                field: "voltage",
+                ..
+            })
+        ));
+        high[1].voltage = f64::NAN;
+        assert!(VoltcraftData::encode(start, &high).is_err());
+        // Timestamps come from the position of the power events
+        let mut gap = events[..3].to_vec();
+        gap[2].timestamp += Duration::minutes(1);
+        assert_eq!(
+            VoltcraftData::encode(start, &gap),
+            Err(EncodeError::NotContiguous { index: 2 })
+        );
+        assert_eq!(
+            VoltcraftData::encode(start + Duration::minutes(1), &events),
+            Err(EncodeError::NotContiguous { index: 0 })
+        );
+        let late = start + Duration::seconds(30);
+        assert_eq!(
+            VoltcraftData::encode(late, &[]),
+            Err(EncodeError::InvalidStartTime { start: late })
+        );
+    }
+
+    #[test]
+    fn data_from_borrowed_bytes() {
+        // Decoded straight from the static bytes, which are never copied
+        static RAW: [u8; 55] = REVERTED_CLOCK;
+        let owned = VoltcraftData::from_raw(REVERTED_CLOCK);
+        let borrowed: VoltcraftDataRef<'static> = VoltcraftData::from_bytes(&RAW[..]);
+        let expected = owned.parse().unwrap();
+        let events = borrowed.parse().unwrap();
+        assert_eq!(events, expected);
+        assert_eq!(borrowed.fingerprint(), owned.fingerprint());
+        assert_eq!(borrowed.raw_records().count(), owned.raw_records().count());
+        assert_eq!(borrowed.parse_sessions().unwrap().len(), 2);
+        // Lazily, only keeping the power events from 18:46 on
+        let kept = borrowed
+            .events()
+            .map(Result::unwrap)
+            .filter(|pe| pe.timestamp >= at(18, 46))
+            .collect::<Vec<_>>();
+        assert_eq!(kept.len(), 2);
+        // Any buffer of bytes, e.g. a memory map
+        let boxed = VoltcraftData::from_bytes(REVERTED_CLOCK.to_vec().into_boxed_slice());
+        assert_eq!(boxed.parse().unwrap(), expected);
+    }
+
+    #[test]
+    fn data_from_reader_and_path() {
+        let expected = VoltcraftData::from_raw(TESTDATA).parse().unwrap();
+        let same = |vdf: VoltcraftData| {
+            let events = vdf.parse().unwrap();
+            assert_eq!(events.len(), expected.len());
+            assert_eq!(events[0].timestamp, expected[0].timestamp);
+            assert_eq!(events[0].voltage, expected[0].voltage);
+        };
+        same(VoltcraftData::from_reader(std::io::Cursor::new(TESTDATA.to_vec())).unwrap());
+        same(VoltcraftData::from_raw(&TESTDATA[..]));
+
+        let path = std::env::temp_dir().join("voltcraft_from_path.bin");
+        std::fs::write(&path, TESTDATA).unwrap();
+        same(VoltcraftData::from_file(path.as_path()).unwrap());
+        same(VoltcraftData::from_file(&path).unwrap());
+        same(VoltcraftData::from_file(path.to_str().unwrap()).unwrap());
+
+        // Read errors are reported like those of files
+        struct Failing;
+        impl std::io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("connection reset"))
+            }
+        }
+        assert!(matches!(
+            VoltcraftData::from_reader(Failing),
+            Err(VoltcraftError::Io(_))
+        ));
     }
 }
@@ -0,0 +1,625 @@
+use crate::voltcraft::compact::{compact, CompactedDay};
+use crate::voltcraft::data::{FileFingerprint, ParseOptions, PowerEvent, VoltcraftData};
+use crate::voltcraft::error::VoltcraftError;
+use crate::voltcraft::presence::PresenceIndex;
+use crate::voltcraft::sorted::SortedEvents;
+use crate::voltcraft::stats::{OverallPowerInfo, VoltcraftStatistics};
+use chrono::{DateTime, Datelike, Duration, Local};
+use std::collections::BTreeSet;
+use std::mem;
+
+/// Chronologically sorted power events along with statistics computed on demand.
+/// Every change to the events bumps the generation and drops the cached statistics.
+pub struct Dataset {
+    events: SortedEvents,
+    generation: u64,
+    overall_stats: Option<OverallPowerInfo>,
+    presence: Option<PresenceIndex>,
+    fingerprints: Vec<FileFingerprint>, // data files added with add_file
+    summaries: Vec<CompactedDay>,       // daily summaries of pruned power events
+    retained_from: Option<DateTime<Local>>, // events before this were pruned
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct PruneReport {
+    pub removed: usize,                    // number of power events removed
+    pub summarized_days: usize,            // daily summaries added for the removed events
+    pub freed_bytes: usize,                // estimate of the memory freed, less the summaries added
+    pub earliest: Option<DateTime<Local>>, // first remaining power event
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct RetainReport {
+    pub removed: usize, // number of power events removed
+    pub removed_range: Option<(DateTime<Local>, DateTime<Local>)>, // first and last removed timestamps
+}
+
+/// Clock offset of the logger found by lining its energy up with an external reference series.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct AlignmentResult {
+    pub offset: Duration, // to add to the logger timestamps (positive when the logger clock is behind)
+    pub correlation: f64, // Pearson coefficient between the reference and the shifted logger energy
+    pub samples: usize,   // reference readings compared at the best offset
+    pub confident: bool,  // false for a degenerate reference or a poor fit; the offset is then zero
+}
+
+/// A power event whose stored power disagrees with the one derived from voltage, current and
+/// power factor, e.g. after editing or importing data.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Inconsistency {
+    pub index: usize, // position of the power event
+    pub timestamp: DateTime<Local>,
+    pub power: f64,                  // stored active power (kW)
+    pub derived_power: f64,          // active power derived from the measured values (kW)
+    pub apparent_power: f64,         // stored apparent power (kVA)
+    pub derived_apparent_power: f64, // apparent power derived from the measured values (kVA)
+}
+
+// Correlation below which an alignment is not trusted
+const MIN_ALIGNMENT_CORRELATION: f64 = 0.5;
+// Reference readings needed at the best offset for an alignment
+const MIN_ALIGNMENT_SAMPLES: usize = 4;
+
+// Pearson coefficient of the pairs, None if either side is (nearly) constant
+fn pearson(pairs: &[(f64, f64)]) -> Option<f64> {
+    let n = pairs.len() as f64;
+    let mean_a = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_b = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (a, b) in pairs {
+        let (da, db) = (a - mean_a, b - mean_b);
+        covariance += da * db;
+        variance_a += da * da;
+        variance_b += db * db;
+    }
+    let denominator = (variance_a * variance_b).sqrt();
+    (denominator > f64::EPSILON * n).then(|| (covariance / denominator).clamp(-1.0, 1.0))
+}
+
+// Power events whose stored power differs from the derived one by more than eps (kW/kVA)
+pub fn consistency_report(events: &[PowerEvent], eps: f64) -> Vec<Inconsistency> {
+    events
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| !e.is_consistent(eps))
+        .map(|(index, e)| Inconsistency {
+            index,
+            timestamp: e.timestamp,
+            power: e.power,
+            derived_power: e.derived_power(),
+            apparent_power: e.apparent_power,
+            derived_apparent_power: e.derived_apparent_power(),
+        })
+        .collect()
+}
+
+impl Dataset {
+    pub fn new(mut events: Vec<PowerEvent>) -> Dataset {
+        // Chronologically sort power items and remove duplicate events based on timestamp
+        events.sort_by_key(|e| e.timestamp);
+        events.dedup_by(|a, b| a.timestamp == b.timestamp);
+        Dataset {
+            events: SortedEvents::from_sorted_unchecked(events),
+            generation: 0,
+            overall_stats: None,
+            presence: None,
+            fingerprints: Vec::new(),
+            summaries: Vec::new(),
+            retained_from: None,
+        }
+    }
+
+    // Add the power events of a data file, unless a file with the same fingerprint was already
+    // added (e.g. a copy of the file under another name). Returns whether the file was added.
+    pub fn add_file(&mut self, data: &VoltcraftData) -> Result<bool, VoltcraftError> {
+        self.add_file_with(data, &ParseOptions::default())
+    }
+
+    // Same as add_file, with parse options. Files with a suspicious start time are refused
+    // unless anchored, as their timestamps would collide with (and be deduplicated against)
+    // the events of other files. Events before a pruning cutoff are not added back.
+    pub fn add_file_with(
+        &mut self,
+        data: &VoltcraftData,
+        options: &ParseOptions,
+    ) -> Result<bool, VoltcraftError> {
+        let fingerprint = data.fingerprint();
+        if self.fingerprints.contains(&fingerprint) {
+            return Ok(false);
+        }
+        let (sessions, summary) = data.parse_with_options(options)?;
+        if summary.suspicious_start_time && !summary.anchored {
+            return Err(VoltcraftError::SuspiciousStartTime);
+        }
+        let retained_from = self.retained_from;
+        self.events.0.extend(
+            sessions
+                .into_iter()
+                .flat_map(|s| s.events)
+                .filter(|e| retained_from.is_none_or(|cutoff| e.timestamp >= cutoff)),
+        );
+        self.events.0.sort_by_key(|e| e.timestamp);
+        self.events.0.dedup_by(|a, b| a.timestamp == b.timestamp);
+        self.fingerprints.push(fingerprint);
+        self.invalidate();
+        Ok(true)
+    }
+
+    // Fingerprints of the data files added, in the order they were added
+    pub fn fingerprints(&self) -> &[FileFingerprint] {
+        &self.fingerprints
+    }
+
+    pub fn events(&self) -> &[PowerEvent] {
+        &self.events
+    }
+
+    pub fn sorted_events(&self) -> &SortedEvents {
+        &self.events
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    // Calendar years with power events or daily summaries, in ascending order
+    pub fn years(&self) -> Vec<i32> {
+        self.summaries
+            .iter()
+            .map(|d| d.first.year())
+            .chain(self.events.iter().map(|e| e.timestamp.year()))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    // Incremented each time the events change
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    // Overall statistics, computed once per generation (None for an empty dataset)
+    pub fn overall_stats(&mut self) -> Option<&OverallPowerInfo> {
+        if self.overall_stats.is_none() {
+            let stats = VoltcraftStatistics::new(&self.events.0);
+            self.overall_stats = stats.overall_stats();
+        }
+        self.overall_stats.as_ref()
+    }
+
+    // Minutes with a power event, indexed once per generation for coverage and gap queries
+    pub fn presence(&mut self) -> &PresenceIndex {
+        self.presence
+            .get_or_insert_with(|| PresenceIndex::new(&self.events))
+    }
+
+    // Move every power event by the offset, e.g. the one found by align_to_reference
+    pub fn shift(&mut self, offset: Duration) {
+        if offset.is_zero() || self.events.is_empty() {
+            return;
+        }
+        for e in self.events.0.iter_mut() {
+            e.timestamp += offset;
+        }
+        self.retained_from = self.retained_from.map(|cutoff| cutoff + offset);
+        self.invalidate();
+    }
+
+    /// Find the clock offset of the logger against reference energy readings, e.g. the
+    /// 15-minute readings of a smart meter. Each reading is the energy (kWh) of the interval
+    /// starting at its timestamp; the interval length is the smallest step between readings.
+    /// The logger energy shifted by whole minutes within ±search_window is compared to the
+    /// readings, and the offset with the highest correlation wins (the earliest one on ties).
+    /// Only readings whose interval lies within the logger data are compared.
+    pub fn align_to_reference(
+        &self,
+        reference: &[(DateTime<Local>, f64)],
+        search_window: Duration,
+    ) -> AlignmentResult {
+        let mut result = AlignmentResult {
+            offset: Duration::zero(),
+            correlation: 0.0,
+            samples: 0,
+            confident: false,
+        };
+        let cadence = reference
+            .windows(2)
+            .map(|w| (w[1].0 - w[0].0).num_minutes())
+            .filter(|m| *m > 0)
+            .min();
+        let (Some(cadence), Some(first), Some(last)) =
+            (cadence, self.events.first(), self.events.last())
+        else {
+            return result;
+        };
+        // Energy of the logger per minute, as prefix sums (kWh)
+        let minutes = (last.timestamp - first.timestamp).num_minutes() + 1;
+        let mut energy = vec![0f64; minutes as usize + 1];
+        for e in self.events.iter() {
+            let minute = (e.timestamp - first.timestamp).num_minutes() as usize;
+            energy[minute + 1] += e.power / 60f64;
+        }
+        for m in 1..energy.len() {
+            energy[m] += energy[m - 1];
+        }
+
+        let search = search_window.num_minutes().abs();
+        for offset in -search..=search {
+            let pairs = reference
+                .iter()
+                .filter_map(|(t, kwh)| {
+                    // Start of the reading in logger time
+                    let seconds = (*t - first.timestamp).num_seconds() - offset * 60;
+                    let start = seconds.div_euclid(60);
+                    let end = start + cadence;
+                    (start >= 0 && end <= minutes)
+                        .then(|| (*kwh, energy[end as usize] - energy[start as usize]))
+                })
+                .collect::<Vec<_>>();
+            if pairs.len() < MIN_ALIGNMENT_SAMPLES {
+                continue;
+            }
+            if let Some(correlation) = pearson(&pairs) {
+                if correlation > result.correlation {
+                    result.offset = Duration::minutes(offset);
+                    result.correlation = correlation;
+                    result.samples = pairs.len();
+                }
+            }
+        }
+        result.confident = result.correlation >= MIN_ALIGNMENT_CORRELATION;
+        if !result.confident {
+            result.offset = Duration::zero();
+        }
+        result
+    }
+
+    // Keep only the power events matching the predicate
+    pub fn retain_events(&mut self, f: impl Fn(&PowerEvent) -> bool) -> RetainReport {
+        let mut report = RetainReport::default();
+        self.events.0.retain(|e| {
+            let keep = f(e);
+            if !keep {
+                report.removed += 1;
+                report.removed_range = match report.removed_range {
+                    None => Some((e.timestamp, e.timestamp)),
+                    Some((first, _)) => Some((first, e.timestamp)),
+                };
+            }
+            keep
+        });
+        if report.removed > 0 {
+            self.invalidate();
+        }
+        report
+    }
+
+    // Remove the power events in the half-open interval [from, to)
+    pub fn remove_range(&mut self, from: DateTime<Local>, to: DateTime<Local>) -> RetainReport {
+        self.retain_events(|e| e.timestamp < from || e.timestamp >= to)
+    }
+
+    // Remove the power events whose active power exceeds the given value (kW)
+    pub fn remove_outliers_above(&mut self, power_kw: f64) -> RetainReport {
+        self.retain_events(|e| e.power <= power_kw)
+    }
+
+    // Drop the power events before the cutoff, e.g. to bound the memory of a long-running process
+    pub fn prune_before(&mut self, cutoff: DateTime<Local>) -> PruneReport {
+        self.prune_before_with(cutoff, false)
+    }
+
+    /// Same as `prune_before`, keeping a daily summary (see `compact`) of the dropped events of
+    /// each day when `keep_summaries` is set. A day pruned in several steps gets one summary per
+    /// step. Data files added later do not bring the dropped events back.
+    pub fn prune_before_with(
+        &mut self,
+        cutoff: DateTime<Local>,
+        keep_summaries: bool,
+    ) -> PruneReport {
+        let split = self.events.partition_point(|e| e.timestamp < cutoff);
+        self.retained_from = self.retained_from.max(Some(cutoff));
+        let removed = SortedEvents::from_sorted_unchecked(self.events.0.drain(..split).collect());
+        let mut report = PruneReport {
+            removed: removed.len(),
+            earliest: self.events.first().map(|e| e.timestamp),
+            ..Default::default()
+        };
+        if keep_summaries {
+            // All the removed events lie before the day after the cutoff
+            let summaries = compact(&removed, cutoff + Duration::days(1)).days;
+            report.summarized_days = summaries.len();
+            self.summaries.extend(summaries);
+        }
+        report.freed_bytes = (report.removed * mem::size_of::<PowerEvent>())
+            .saturating_sub(report.summarized_days * mem::size_of::<CompactedDay>());
+        if report.removed > 0 {
+            self.invalidate();
+        }
+        report
+    }
+
+    // Daily summaries of the pruned power events, in chronological order
+    pub fn summaries(&self) -> &[CompactedDay] {
+        &self.summaries
+    }
+
+    pub fn consistency_report(&self, eps: f64) -> Vec<Inconsistency> {
+        consistency_report(&self.events, eps)
+    }
+
+    // Replace the stored power of the inconsistent power events with the derived one,
+    // so that the statistics no longer depend on it. Returns the number of events changed.
+    pub fn recompute_power(&mut self, eps: f64) -> usize {
+        let mut changed = 0;
+        for e in self.events.0.iter_mut().filter(|e| !e.is_consistent(eps)) {
+            *e = e.recomputed();
+            changed += 1;
+        }
+        if changed > 0 {
+            self.invalidate();
+        }
+        changed
+    }
+
+    fn invalidate(&mut self) {
+        self.generation += 1;
+        self.overall_stats = None;
+        self.presence = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::data::{ParseOptions, PowerEvent, VoltcraftData};
+    use crate::voltcraft::dataset::{consistency_report, Dataset};
+    use crate::voltcraft::error::VoltcraftError;
+    use chrono::{DateTime, Duration, Local, TimeZone};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    // Two days of one-minute samples at 0.5 kW, with a single 40 kW spike
+    fn dataset() -> Dataset {
+        let start = Local.with_ymd_and_hms(2014, 9, 11, 0, 0, 0).unwrap();
+        let events = (0..2880)
+            .map(|m| {
+                let power = if m == 100 { 40.0 } else { 0.5 };
+                event(start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+        Dataset::new(events)
+    }
+
+    #[test]
+    fn dataset_remove_outliers() {
+        let mut ds = dataset();
+        let peak = ds.overall_stats().unwrap().stats.max_active_power.power;
+        assert_eq!(peak, 40.0);
+        let report = ds.remove_outliers_above(10.0);
+        assert_eq!(report.removed, 1);
+        let spike = Local.with_ymd_and_hms(2014, 9, 11, 1, 40, 0).unwrap();
+        assert_eq!(report.removed_range, Some((spike, spike)));
+        assert_eq!(ds.generation(), 1);
+        let peak = ds.overall_stats().unwrap().stats.max_active_power.power;
+        assert_eq!(peak, 0.5);
+    }
+
+    #[test]
+    fn dataset_remove_range() {
+        let mut ds = dataset();
+        let total = ds.overall_stats().unwrap().stats.total_active_power;
+        let from = Local.with_ymd_and_hms(2014, 9, 12, 0, 0, 0).unwrap();
+        let to = Local.with_ymd_and_hms(2014, 9, 13, 0, 0, 0).unwrap();
+        let report = ds.remove_range(from, to);
+        assert_eq!(report.removed, 1440);
+        assert_eq!(
+            report.removed_range,
+            Some((from, to - Duration::minutes(1)))
+        );
+        assert_eq!(ds.len(), 1440);
+        let remaining = ds.overall_stats().unwrap().stats.total_active_power;
+        assert!((total - remaining - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn dataset_retain_nothing_removed() {
+        let mut ds = dataset();
+        let report = ds.retain_events(|_| true);
+        assert_eq!(report.removed, 0);
+        assert_eq!(report.removed_range, None);
+        // The cache stays valid when no event was removed
+        assert_eq!(ds.generation(), 0);
+    }
+
+    #[test]
+    fn dataset_consistency() {
+        let ds = dataset();
+        assert!(ds.consistency_report(1e-9).is_empty());
+
+        let mut events = ds.events().to_vec();
+        events[2000].power = 0.75;
+        let report = consistency_report(&events, 1e-6);
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].index, 2000);
+        assert_eq!(report[0].timestamp, events[2000].timestamp);
+        assert!((report[0].derived_power - 0.5).abs() < 1e-9);
+
+        let mut ds = Dataset::new(events);
+        assert_eq!(ds.recompute_power(1e-6), 1);
+        assert_eq!(ds.generation(), 1);
+        assert!(ds.consistency_report(1e-6).is_empty());
+        assert!((ds.events()[2000].power - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn dataset_align_to_reference() {
+        // Three days of loads changing every 7 minutes, and the 15-minute readings of a meter
+        let start = Local.with_ymd_and_hms(2014, 9, 11, 0, 0, 0).unwrap();
+        let mut seed = 3u64;
+        let mut power = 0.0;
+        let truth = (0..3 * 1440)
+            .map(|m| {
+                if m % 7 == 0 {
+                    seed = seed
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    power = (seed >> 33) as f64 % 3000.0 / 1000.0;
+                }
+                (start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+        let reference = truth
+            .chunks(15)
+            .map(|c| (c[0].0, c.iter().map(|(_, p)| p / 60.0).sum::<f64>()))
+            .collect::<Vec<_>>();
+        // The logger clock is 23 minutes behind
+        let events = truth
+            .iter()
+            .map(|&(t, power)| event(t - Duration::minutes(23), power))
+            .collect();
+        let mut ds = Dataset::new(events);
+        let alignment = ds.align_to_reference(&reference, Duration::hours(1));
+        assert!(alignment.confident);
+        assert!((alignment.offset.num_minutes() - 23).abs() <= 1);
+        assert!(alignment.correlation > 0.99);
+
+        ds.shift(alignment.offset);
+        assert_eq!(
+            ds.events()[0].timestamp,
+            start + alignment.offset - Duration::minutes(23)
+        );
+        let realigned = ds.align_to_reference(&reference, Duration::hours(1));
+        assert!(realigned.confident && realigned.offset.num_minutes().abs() <= 1);
+
+        // A constant reference cannot be lined up
+        let constant = reference.iter().map(|&(t, _)| (t, 1.0)).collect::<Vec<_>>();
+        let alignment = ds.align_to_reference(&constant, Duration::hours(1));
+        assert!(!alignment.confident);
+        assert_eq!(
+            (alignment.offset, alignment.correlation),
+            (Duration::zero(), 0.0)
+        );
+        assert!(!ds.align_to_reference(&[], Duration::hours(1)).confident);
+    }
+
+    #[test]
+    fn dataset_add_file_skips_copies() {
+        // One data block starting 2014-09-11 18:43 with two power records
+        let file = vec![
+            0xE0, 0xC5, 0xEA, 0x09, 0x0B, 0x0E, 0x12, 0x2B, 0x08, 0xC6, 0x01, 0xBE, 0x57, 0x08,
+            0xC6, 0x01, 0xBE, 0x57, 0xFF, 0xFF, 0xFF, 0xFF,
+        ];
+        let mut copy = file.clone();
+        copy.extend_from_slice(&[0xFF; 16]);
+        let mut other = file.clone();
+        other[7] = 0x2D; // starts two minutes later
+
+        let mut dataset = Dataset::new(Vec::new());
+        assert!(matches!(
+            dataset.add_file(&VoltcraftData::from_raw(file)),
+            Ok(true)
+        ));
+        let generation = dataset.generation();
+        assert!(matches!(
+            dataset.add_file(&VoltcraftData::from_raw(copy)),
+            Ok(false)
+        ));
+        assert_eq!(dataset.generation(), generation);
+        assert_eq!(dataset.len(), 2);
+        assert_eq!(dataset.presence().span_minutes(), 2);
+        assert!(matches!(
+            dataset.add_file(&VoltcraftData::from_raw(other)),
+            Ok(true)
+        ));
+        assert_eq!(dataset.fingerprints().len(), 2);
+        assert_eq!(dataset.len(), 4);
+        // The presence index follows the added files
+        assert_eq!(dataset.presence().present_minutes(), 4);
+        assert_eq!(dataset.presence().missing_minutes(), 0);
+    }
+
+    #[test]
+    fn dataset_refuses_unanchored_suspicious_files() {
+        // Two files with two power records each, both starting at the device default 2000-01-01 00:00
+        let file = |current: u8| {
+            VoltcraftData::from_raw(vec![
+                0xE0, 0xC5, 0xEA, 0x01, 0x01, 0x00, 0x00, 0x00, 0x08, 0xC6, 0x01, current, 0x57,
+                0x08, 0xC6, 0x01, current, 0x57, 0xFF, 0xFF, 0xFF, 0xFF,
+            ])
+        };
+        let mut dataset = Dataset::new(Vec::new());
+        assert!(matches!(
+            dataset.add_file(&file(0xBE)),
+            Err(VoltcraftError::SuspiciousStartTime)
+        ));
+        assert!(dataset.is_empty());
+
+        let start = Local.with_ymd_and_hms(2014, 9, 11, 18, 43, 0).unwrap();
+        let anchored = |t| ParseOptions::default().anchor_time(t);
+        assert!(matches!(
+            dataset.add_file_with(&file(0xBE), &anchored(start)),
+            Ok(true)
+        ));
+        let later = start + Duration::days(1);
+        assert!(matches!(
+            dataset.add_file_with(&file(0xBF), &anchored(later)),
+            Ok(true)
+        ));
+        assert_eq!(dataset.len(), 4);
+        assert_eq!(dataset.events()[2].timestamp, later);
+    }
+
+    #[test]
+    fn dataset_prune_before() {
+        let mut ds = dataset();
+        let start = ds.events()[0].timestamp;
+        let cutoff = start + Duration::hours(30);
+        let retained_total = ds.events()[1800..].iter().map(|e| e.power).sum::<f64>() / 60.0;
+        let total = ds.overall_stats().unwrap().stats.total_active_power;
+        let generation = ds.generation();
+
+        let report = ds.prune_before_with(cutoff, true);
+        assert_eq!(report.removed, 1800);
+        assert_eq!(report.earliest, Some(cutoff));
+        assert_eq!(report.summarized_days, 2);
+        assert!(report.freed_bytes > 0);
+        assert_eq!(ds.len(), 2880 - 1800);
+        assert!(ds.generation() > generation);
+        let retained = ds.overall_stats().unwrap().stats.total_active_power;
+        assert!((retained - retained_total).abs() < 1e-9);
+        // The summaries keep the energy of the pruned events, spike included
+        let summarized = ds
+            .summaries()
+            .iter()
+            .map(|d| d.summary.stats.total_active_power)
+            .sum::<f64>();
+        assert!((summarized + retained - total).abs() < 1e-9);
+        assert_eq!(ds.summaries()[0].summary.stats.max_active_power.power, 40.0);
+
+        // Pruning again at the same cutoff removes nothing
+        let report = ds.prune_before(cutoff);
+        assert_eq!((report.removed, report.summarized_days), (0, 0));
+        let report = ds.prune_before(cutoff + Duration::days(30));
+        assert_eq!((report.removed, report.earliest), (1080, None));
+        assert!(ds.is_empty());
+        // The years of the summaries remain
+        assert_eq!(ds.years(), [2014]);
+    }
+}
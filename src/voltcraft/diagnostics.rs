@@ -0,0 +1,209 @@
+use crate::format::format_fixed;
+use crate::voltcraft::data::{decode_power_record, is_suspicious_start, VoltcraftData};
+use chrono::{Local, NaiveDate, TimeZone};
+use std::fmt::Write;
+
+const MAGIC_NUMBER: [u8; 3] = [0xE0, 0xC5, 0xEA];
+const SHOWN_RECORDS: usize = 3; // records shown at each end of the file
+
+/// Bounded diagnostic report of a data file, to paste into a bug report: the file length, the
+/// first data block header, the first and last three power records in hex along with their
+/// decoded values, the record count and any problems found. The other records are not shown.
+///
+/// The report only depends on the file contents, so it can be compared against a snapshot.
+pub fn dump(data: &VoltcraftData) -> String {
+    let raw = data.raw_data();
+    let mut records = data.raw_records();
+    let all = records.by_ref().collect::<Vec<_>>();
+    let mut findings = Vec::new();
+
+    let mut out = String::from("Voltcraft data file diagnostics\n");
+    writeln!(out, "File length: {} bytes", raw.len()).unwrap();
+    let header = &raw[..raw.len().min(8)];
+    write!(out, "Header: {}", hex(header)).unwrap();
+    if raw.starts_with(&MAGIC_NUMBER) {
+        match raw.get(3..8) {
+            Some(&[month, day, year, hour, minute]) => {
+                let start = NaiveDate::from_ymd_opt(year as i32 + 2000, month as u32, day as u32)
+                    .and_then(|d| d.and_hms_opt(hour as u32, minute as u32, 0));
+                match start {
+                    Some(start) => {
+                        write!(out, " (start {})", start.format("%Y-%m-%d %H:%M")).unwrap();
+                        let local = Local.from_local_datetime(&start).earliest();
+                        if local.is_some_and(is_suspicious_start) {
+                            findings.push(
+                                "suspicious start time, the device clock was probably not set"
+                                    .to_string(),
+                            );
+                        }
+                    }
+                    None => findings
+                        .push("invalid start time in the first data block header".to_string()),
+                }
+            }
+            _ => findings.push("truncated first data block header".to_string()),
+        }
+    } else {
+        findings
+            .push("no data block header at offset 0, probably not a Voltcraft file".to_string());
+    }
+    out.push('\n');
+    writeln!(out, "Data blocks: {}", records.header_count()).unwrap();
+    writeln!(out, "Power records: {}", all.len()).unwrap();
+
+    // Every record once, even when there are fewer than twice the shown records
+    if all.len() <= 2 * SHOWN_RECORDS {
+        out.push_str("Records:\n");
+        all.iter()
+            .for_each(|(offset, record)| line(&mut out, *offset, record));
+    } else {
+        out.push_str("First records:\n");
+        all[..SHOWN_RECORDS]
+            .iter()
+            .for_each(|(offset, record)| line(&mut out, *offset, record));
+        out.push_str("Last records:\n");
+        all[all.len() - SHOWN_RECORDS..]
+            .iter()
+            .for_each(|(offset, record)| line(&mut out, *offset, record));
+    }
+    if all.is_empty() {
+        out.push_str("  none\n");
+    }
+
+    let implausible = all
+        .iter()
+        .filter(|(_, record)| !decode_power_record(record).is_plausible())
+        .map(|(offset, _)| *offset)
+        .collect::<Vec<_>>();
+    if let Some(first) = implausible.first() {
+        findings.push(format!(
+            "{} power records with an implausible voltage, the first at offset {}",
+            implausible.len(),
+            first
+        ));
+    }
+    match (records.end_of_data(), records.malformed_tail()) {
+        // The device pads its files with 0xff after the end of data marker
+        (Some(end), _) if raw[end + 4..].iter().any(|b| *b != 0xFF) => findings.push(format!(
+            "{} bytes other than padding after the end of data marker",
+            raw[end + 4..].iter().filter(|b| **b != 0xFF).count()
+        )),
+        (None, Some(tail)) => {
+            if tail < raw.len() {
+                findings.push(format!(
+                    "{} leftover bytes at offset {}, probably a truncated power record",
+                    raw.len() - tail,
+                    tail
+                ));
+            }
+            findings.push("no end of data marker".to_string());
+        }
+        _ => {}
+    }
+
+    out.push_str("Findings:\n");
+    if findings.is_empty() {
+        out.push_str("  none\n");
+    }
+    for finding in findings {
+        writeln!(out, "  - {}", finding).unwrap();
+    }
+    out
+}
+
+fn hex(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return "none".to_string();
+    }
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn line(out: &mut String, offset: usize, record: &[u8; 5]) {
+    let reading = decode_power_record(record);
+    writeln!(
+        out,
+        "  @{:<8} {}  U={}V I={}A cosPHI={} P={}kW S={}kVA",
+        offset,
+        hex(record),
+        format_fixed(reading.voltage, 1),
+        format_fixed(reading.current, 3),
+        format_fixed(reading.power_factor, 2),
+        format_fixed(reading.power, 3),
+        format_fixed(reading.apparent_power, 3)
+    )
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::data::VoltcraftData;
+    use crate::voltcraft::diagnostics::dump;
+
+    #[test]
+    fn diagnostics_dump_golden() {
+        let vdf =
+            VoltcraftData::from_raw(include_bytes!("../../sample_data1/A04FC8D2.BIN").to_vec());
+        assert_eq!(
+            dump(&vdf),
+            include_str!("../../tests/golden/diagnostics_dump.txt")
+        );
+    }
+
+    #[test]
+    fn diagnostics_dump_short_files() {
+        // Two records, the second one truncated, without the end of data marker
+        let vdf = VoltcraftData::from_raw(vec![
+            0xE0, 0xC5, 0xEA, 0x09, 0x0B, 0x0E, 0x12, 0x2B, //
+            0x08, 0xC6, 0x01, 0xBE, 0x57, //
+            0x08, 0xC6, 0x01,
+        ]);
+        let expected = "\
+Voltcraft data file diagnostics
+File length: 16 bytes
+Header: e0 c5 ea 09 0b 0e 12 2b (start 2014-09-11 18:43)
+Data blocks: 1
+Power records: 1
+Records:
+  @8        08 c6 01 be 57  U=224.6V I=0.446A cosPHI=0.87 P=0.087kW S=0.100kVA
+Findings:
+  - 3 leftover bytes at offset 13, probably a truncated power record
+  - no end of data marker
+";
+        assert_eq!(dump(&vdf), expected);
+
+        let vdf = VoltcraftData::from_raw(vec![0x00, 0x01, 0x02]);
+        let expected = "\
+Voltcraft data file diagnostics
+File length: 3 bytes
+Header: 00 01 02
+Data blocks: 0
+Power records: 0
+Records:
+  none
+Findings:
+  - no data block header at offset 0, probably not a Voltcraft file
+  - 3 leftover bytes at offset 0, probably a truncated power record
+  - no end of data marker
+";
+        assert_eq!(dump(&vdf), expected);
+
+        // Four records, one of them implausible, and a stray byte after the padding
+        let mut raw = vec![0xE0, 0xC5, 0xEA, 0x09, 0x0B, 0x0E, 0x12, 0x2B];
+        raw.extend([0x08, 0xC6, 0x01, 0xBE, 0x57].repeat(3));
+        raw.extend([0x00, 0x00, 0x01, 0xBE, 0x57]);
+        raw.extend([0xFF; 8]);
+        raw.push(0x00);
+        let report = dump(&VoltcraftData::from_raw(raw));
+        assert!(report.contains("Power records: 4\nRecords:\n"));
+        assert_eq!(report.matches("  @").count(), 4);
+        assert!(report.ends_with(
+            "Findings:\n  \
+            - 1 power records with an implausible voltage, the first at offset 23\n  \
+            - 1 bytes other than padding after the end of data marker\n"
+        ));
+    }
+}
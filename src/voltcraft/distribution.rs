@@ -0,0 +1,181 @@
+use crate::voltcraft::data::PowerEvent;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// Value of a power event whose distribution is computed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Metric {
+    Voltage,       // V
+    Current,       // A
+    PowerFactor,   // 0 to 1
+    Power,         // active power (kW)
+    ApparentPower, // kVA
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum DistributionError {
+    EmptyDataSet,            // a distribution needs at least one power event
+    InvalidBucketWidth(f64), // the histogram bucket width is not a positive number
+}
+
+impl fmt::Display for DistributionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DistributionError::EmptyDataSet => write!(f, "no power data for a distribution"),
+            DistributionError::InvalidBucketWidth(width) => {
+                write!(f, "invalid histogram bucket width {}", width)
+            }
+        }
+    }
+}
+
+impl Error for DistributionError {}
+
+impl Metric {
+    pub fn of(&self, pe: &PowerEvent) -> f64 {
+        match self {
+            Metric::Voltage => pe.voltage,
+            Metric::Current => pe.current,
+            Metric::PowerFactor => pe.power_factor,
+            Metric::Power => pe.power,
+            Metric::ApparentPower => pe.apparent_power,
+        }
+    }
+}
+
+/// Value of the metric at each of the fractions (0 to 1, e.g. 0.95 for the 95th percentile),
+/// using the nearest rank: the smallest value that at least the fraction of the power events
+/// do not exceed. Fractions outside 0 to 1 are clamped, 0 gives the minimum.
+pub fn percentiles(
+    events: &[PowerEvent],
+    metric: Metric,
+    fractions: &[f64],
+) -> Result<Vec<f64>, DistributionError> {
+    if events.is_empty() {
+        return Err(DistributionError::EmptyDataSet);
+    }
+    let values = sorted_values(events, metric);
+    Ok(fractions
+        .iter()
+        .map(|&fraction| rank_of(&values, fraction))
+        .collect())
+}
+
+/// Number of power events in each bucket of the metric, as (lower bound, count) in ascending
+/// order. Buckets are `width` wide from a multiple of it, lower bound included, and run from
+/// the one of the lowest value to the one of the highest, the empty ones in between included.
+pub fn histogram(
+    events: &[PowerEvent],
+    metric: Metric,
+    width: f64,
+) -> Result<Vec<(f64, usize)>, DistributionError> {
+    if !(width.is_finite() && width > 0.0) {
+        return Err(DistributionError::InvalidBucketWidth(width));
+    }
+    let mut counts = BTreeMap::<i64, usize>::new();
+    for pe in events {
+        *counts
+            .entry((metric.of(pe) / width).floor() as i64)
+            .or_default() += 1;
+    }
+    let (Some(&first), Some(&last)) = (counts.keys().next(), counts.keys().next_back()) else {
+        return Err(DistributionError::EmptyDataSet);
+    };
+    Ok((first..=last)
+        .map(|bucket| {
+            let count = counts.get(&bucket).copied().unwrap_or(0);
+            (bucket as f64 * width, count)
+        })
+        .collect())
+}
+
+// Value at the fraction (0 to 1) of the values, using the nearest rank (at least one value)
+pub(crate) fn nearest_rank(mut values: Vec<f64>, fraction: f64) -> f64 {
+    values.sort_by(f64::total_cmp);
+    rank_of(&values, fraction)
+}
+
+// Values of the metric in ascending order
+fn sorted_values(events: &[PowerEvent], metric: Metric) -> Vec<f64> {
+    let mut values = events.iter().map(|pe| metric.of(pe)).collect::<Vec<_>>();
+    values.sort_by(f64::total_cmp);
+    values
+}
+
+// Value at the fraction of the sorted values, at least one
+fn rank_of(sorted: &[f64], fraction: f64) -> f64 {
+    let rank = (fraction.clamp(0.0, 1.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.max(1) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::distribution::{histogram, percentiles, DistributionError, Metric};
+    use chrono::{DateTime, Duration, Local, TimeZone};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    #[test]
+    fn distribution_percentiles_and_histogram() {
+        // 100 minutes at 221 V to 240 V (5 minutes each) and a load of 0.01 kW to 1 kW
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..100)
+            .map(|m| PowerEvent {
+                voltage: 221.0 + (m / 5) as f64,
+                current: 1.0,
+                ..event(start + Duration::minutes(m), (m + 1) as f64 / 100.0)
+            })
+            .collect::<Vec<_>>();
+
+        let power = percentiles(&events, Metric::Power, &[0.0, 0.5, 0.95, 1.0]).unwrap();
+        assert_eq!(power, [0.01, 0.5, 0.95, 1.0]);
+        let voltage = percentiles(&events, Metric::Voltage, &[0.05, 0.051, 0.95]).unwrap();
+        assert_eq!(voltage, [221.0, 222.0, 239.0]);
+        // A single power event is every percentile
+        let single = percentiles(&events[..1], Metric::Voltage, &[0.0, 0.5, 1.0]).unwrap();
+        assert_eq!(single, [221.0, 221.0, 221.0]);
+        assert_eq!(
+            percentiles(&[], Metric::Power, &[0.5]),
+            Err(DistributionError::EmptyDataSet)
+        );
+
+        let buckets = histogram(&events, Metric::Voltage, 5.0).unwrap();
+        assert_eq!(
+            buckets,
+            [
+                (220.0, 20),
+                (225.0, 25),
+                (230.0, 25),
+                (235.0, 25),
+                (240.0, 5)
+            ]
+        );
+        // The empty buckets between the lowest and the highest value are kept
+        let gap = [events[0], events[99]];
+        let buckets = histogram(&gap, Metric::Voltage, 10.0).unwrap();
+        assert_eq!(buckets, [(220.0, 1), (230.0, 0), (240.0, 1)]);
+        assert_eq!(
+            histogram(&[], Metric::Voltage, 1.0),
+            Err(DistributionError::EmptyDataSet)
+        );
+        assert_eq!(
+            histogram(&events, Metric::Voltage, 0.0),
+            Err(DistributionError::InvalidBucketWidth(0.0))
+        );
+    }
+}
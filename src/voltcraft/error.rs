@@ -0,0 +1,322 @@
+use crate::locale::Locale;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum VoltcraftError {
+    Io(io::Error),      // the data file could not be read
+    InvalidMagicNumber, // the data file does not start with a data block header
+    // The start time of the data block header at the byte offset is not a valid date
+    InvalidTimestamp { offset: usize },
+    // The data ends before the end of data marker; offset of the cut off header or power record
+    TruncatedData { offset: usize },
+    // The voltage of the power record at the byte offset is implausible
+    InvalidPowerRecord { offset: usize },
+    SuspiciousStartTime, // the device clock was not set, see ParseOptions::anchor_time
+    EmptyDataSet,        // the data file holds no power events
+    // The data file is compressed (gzip or zip) but cannot be decompressed
+    InvalidArchive { detail: String },
+    // The version byte names a record format that is not decoded, see FormatVersion
+    UnsupportedFormatVersion { version: u8 },
+    // The interval between two power events is not a whole number of seconds, at least one
+    InvalidSampleInterval { milliseconds: i64 },
+    // The info file does not start with the header of the layout decoded, see VoltcraftInfo
+    UnsupportedInfoLayout { header: [u8; 5] },
+}
+
+impl fmt::Display for VoltcraftError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VoltcraftError::Io(e) => write!(f, "error reading the data file: {}", e),
+            VoltcraftError::InvalidMagicNumber => {
+                write!(f, "invalid data file, probably not a Voltcraft file")
+            }
+            VoltcraftError::InvalidTimestamp { offset } => {
+                write!(f, "invalid data block start time at offset {}", offset)
+            }
+            VoltcraftError::TruncatedData { offset } => {
+                write!(f, "unexpected end of data at offset {}", offset)
+            }
+            VoltcraftError::InvalidPowerRecord { offset } => {
+                write!(f, "invalid power record at offset {}", offset)
+            }
+            VoltcraftError::SuspiciousStartTime => write!(
+                f,
+                "suspicious start time in data file, set ParseOptions::anchor_time"
+            ),
+            VoltcraftError::EmptyDataSet => write!(f, "no power data"),
+            VoltcraftError::InvalidArchive { detail } => {
+                write!(f, "invalid compressed data file: {}", detail)
+            }
+            VoltcraftError::UnsupportedFormatVersion { version } => {
+                write!(f, "unsupported data file format version {:#04x}", version)
+            }
+            VoltcraftError::InvalidSampleInterval { milliseconds } => write!(
+                f,
+                "invalid sample interval of {} ms, it must be a whole number of seconds",
+                milliseconds
+            ),
+            VoltcraftError::UnsupportedInfoLayout { header } => {
+                write!(f, "unsupported info file layout, header {}", hex(header))
+            }
+        }
+    }
+}
+
+// Explanations of the errors for the end user (likely causes and what to do), in English and
+// German, keyed by kind
+const EXPLANATIONS: [(&str, &str, &str); 12] = [
+    (
+        "io",
+        "The data file could not be read: {detail}. Check that the file exists and may be read, \
+         and that the SD card is still inserted.",
+        "Die Datendatei konnte nicht gelesen werden: {detail}. Prüfen Sie, ob die Datei existiert \
+         und lesbar ist und ob die SD-Karte noch eingesteckt ist.",
+    ),
+    (
+        "file_not_found",
+        "The data file does not exist. Check the file name and the folder given on the command \
+         line.",
+        "Die Datendatei existiert nicht. Prüfen Sie den Dateinamen und den angegebenen Ordner.",
+    ),
+    (
+        "not_voltcraft",
+        "This file does not start with the Voltcraft header. If you exported it from the vendor \
+         software, use the CSV importer instead; otherwise copy the file again from the SD card \
+         of the Energy Logger 4000.",
+        "Diese Datei beginnt nicht mit der Voltcraft-Kennung. Wurde sie mit der Software des \
+         Herstellers exportiert, verwenden Sie stattdessen den CSV-Import; sonst kopieren Sie \
+         die Datei erneut von der SD-Karte des Energy Logger 4000.",
+    ),
+    (
+        "truncated",
+        "The file ends in the middle of the data, probably because it was cut off while being \
+         copied from the SD card. Copy the file again.",
+        "Die Datei endet mitten in den Daten, vermutlich wurde das Kopieren von der SD-Karte \
+         abgebrochen. Kopieren Sie die Datei erneut.",
+    ),
+    (
+        "invalid_record",
+        "The file holds a power record with an impossible voltage, so it is corrupted or not \
+         a data file of the Energy Logger 4000. Copy the file again, and run the debug command \
+         on it to include its diagnostic dump in a bug report if the problem persists.",
+        "Die Datei enthält einen Messwert mit einer unmöglichen Spannung; sie ist beschädigt \
+         oder keine Datendatei des Energy Logger 4000. Kopieren Sie die Datei erneut und fügen \
+         Sie, falls das Problem bleibt, die Ausgabe des Befehls debug einer Fehlermeldung bei.",
+    ),
+    (
+        "invalid_start",
+        "The start time recorded in the file is not a valid date, so the device clock is \
+         probably corrupted. Set the date and time of the Energy Logger 4000 again before the \
+         next recording.",
+        "Die in der Datei gespeicherte Startzeit ist kein gültiges Datum, die Uhr des Geräts ist \
+         vermutlich gestört. Stellen Sie Datum und Uhrzeit des Energy Logger 4000 vor der \
+         nächsten Aufzeichnung neu ein.",
+    ),
+    (
+        "empty",
+        "The file holds no power measurements. The logger was probably unplugged right after \
+         the recording started; this file can be deleted.",
+        "Die Datei enthält keine Messwerte. Der Logger wurde vermutlich direkt nach dem Start \
+         der Aufzeichnung ausgesteckt; die Datei kann gelöscht werden.",
+    ),
+    (
+        "suspicious_start",
+        "The file starts at the default date of the device (2000-01-01) or decades away from \
+         today, so the clock of the Energy Logger 4000 was not set for this recording. Give the \
+         actual start of the recording as the anchor time.",
+        "Die Datei beginnt am Standarddatum des Geräts (2000-01-01) oder Jahrzehnte von heute \
+         entfernt, die Uhr des Energy Logger 4000 war für diese Aufzeichnung nicht gestellt. \
+         Geben Sie den tatsächlichen Beginn der Aufzeichnung als Ankerzeit an.",
+    ),
+    (
+        "invalid_archive",
+        "The compressed data file cannot be unpacked ({detail}), so the archive is damaged. \
+         Compress the data file again from the copy of the SD card.",
+        "Die komprimierte Datendatei lässt sich nicht entpacken ({detail}), das Archiv ist \
+         beschädigt. Komprimieren Sie die Datendatei erneut aus der Kopie der SD-Karte.",
+    ),
+    (
+        "unsupported_format",
+        "The file uses a record format this version cannot decode (format version {detail}). \
+         Report it with the diagnostic dump of the debug command so that the format can be \
+         added.",
+        "Die Datei verwendet ein Datensatzformat, das diese Version nicht lesen kann \
+         (Formatversion {detail}). Melden Sie es mit der Ausgabe des Befehls debug, damit das \
+         Format ergänzt werden kann.",
+    ),
+    (
+        "invalid_interval",
+        "The sample interval given ({detail}) is not a whole number of seconds, at least one. \
+         Give the interval the Energy Logger 4000 was set to record at, one minute unless it was \
+         changed.",
+        "Das angegebene Messintervall ({detail}) ist keine ganze Zahl von Sekunden, mindestens \
+         eine. Geben Sie das Intervall an, in dem der Energy Logger 4000 aufzeichnet, eine \
+         Minute, sofern es nicht geändert wurde.",
+    ),
+    (
+        "unsupported_info",
+        "The info file starts with {detail} instead of the INFO: header, so its layout is not \
+         known. Report it with a copy of the file so that the layout can be added.",
+        "Die Infodatei beginnt mit {detail} statt mit der Kennung INFO:, ihr Aufbau ist nicht \
+         bekannt. Melden Sie sie mit einer Kopie der Datei, damit der Aufbau ergänzt werden \
+         kann.",
+    ),
+];
+
+// Bytes in hexadecimal, separated by spaces
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl VoltcraftError {
+    /// User-facing explanation of the error with its likely causes and what to do, in English.
+    pub fn explain(&self) -> String {
+        self.explain_in(Locale::En)
+    }
+
+    // Same as explain, in the language of the locale
+    pub fn explain_in(&self, locale: Locale) -> String {
+        let (kind, detail) = match self {
+            VoltcraftError::Io(e) if e.kind() == io::ErrorKind::NotFound => {
+                ("file_not_found", None)
+            }
+            VoltcraftError::Io(e) => ("io", Some(e.to_string())),
+            VoltcraftError::InvalidMagicNumber => ("not_voltcraft", None),
+            VoltcraftError::InvalidTimestamp { .. } => ("invalid_start", None),
+            VoltcraftError::TruncatedData { .. } => ("truncated", None),
+            VoltcraftError::InvalidPowerRecord { .. } => ("invalid_record", None),
+            VoltcraftError::SuspiciousStartTime => ("suspicious_start", None),
+            VoltcraftError::EmptyDataSet => ("empty", None),
+            VoltcraftError::InvalidArchive { detail } => ("invalid_archive", Some(detail.clone())),
+            VoltcraftError::UnsupportedFormatVersion { version } => {
+                ("unsupported_format", Some(format!("{:#04x}", version)))
+            }
+            VoltcraftError::InvalidSampleInterval { milliseconds } => {
+                ("invalid_interval", Some(format!("{} ms", milliseconds)))
+            }
+            VoltcraftError::UnsupportedInfoLayout { header } => {
+                ("unsupported_info", Some(hex(header)))
+            }
+        };
+        let (_, en, de) = EXPLANATIONS.iter().find(|(k, _, _)| *k == kind).unwrap();
+        let text = match locale {
+            Locale::En => en,
+            Locale::De => de,
+        };
+        text.replace("{detail}", detail.as_deref().unwrap_or_default())
+    }
+}
+
+impl Error for VoltcraftError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            VoltcraftError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for VoltcraftError {
+    fn from(e: io::Error) -> Self {
+        VoltcraftError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::locale::Locale;
+    use crate::voltcraft::error::VoltcraftError;
+    use std::io;
+
+    #[test]
+    fn error_explanations() {
+        let cases = [
+            (
+                VoltcraftError::InvalidMagicNumber,
+                "This file does not start with the Voltcraft header. If you exported it from the \
+                 vendor software, use the CSV importer instead; otherwise copy the file again from \
+                 the SD card of the Energy Logger 4000.",
+            ),
+            (
+                VoltcraftError::TruncatedData { offset: 13 },
+                "The file ends in the middle of the data, probably because it was cut off while \
+                 being copied from the SD card. Copy the file again.",
+            ),
+            (
+                VoltcraftError::InvalidPowerRecord { offset: 8 },
+                "The file holds a power record with an impossible voltage, so it is corrupted or \
+                 not a data file of the Energy Logger 4000. Copy the file again, and run the debug \
+                 command on it to include its diagnostic dump in a bug report if the problem \
+                 persists.",
+            ),
+            (
+                VoltcraftError::InvalidTimestamp { offset: 0 },
+                "The start time recorded in the file is not a valid date, so the device clock is \
+                 probably corrupted. Set the date and time of the Energy Logger 4000 again before \
+                 the next recording.",
+            ),
+            (
+                VoltcraftError::EmptyDataSet,
+                "The file holds no power measurements. The logger was probably unplugged right \
+                 after the recording started; this file can be deleted.",
+            ),
+            (
+                VoltcraftError::Io(io::Error::from(io::ErrorKind::NotFound)),
+                "The data file does not exist. Check the file name and the folder given on the \
+                 command line.",
+            ),
+            (
+                VoltcraftError::Io(io::Error::other("device not ready")),
+                "The data file could not be read: device not ready. Check that the file exists and \
+                 may be read, and that the SD card is still inserted.",
+            ),
+            (
+                VoltcraftError::InvalidArchive {
+                    detail: "corrupt gzip stream".to_string(),
+                },
+                "The compressed data file cannot be unpacked (corrupt gzip stream), so the archive \
+                 is damaged. Compress the data file again from the copy of the SD card.",
+            ),
+            (
+                VoltcraftError::SuspiciousStartTime,
+                "The file starts at the default date of the device (2000-01-01) or decades away \
+                 from today, so the clock of the Energy Logger 4000 was not set for this \
+                 recording. Give the actual start of the recording as the anchor time.",
+            ),
+            (
+                VoltcraftError::UnsupportedFormatVersion { version: 2 },
+                "The file uses a record format this version cannot decode (format version 0x02). \
+                 Report it with the diagnostic dump of the debug command so that the format can \
+                 be added.",
+            ),
+            (
+                VoltcraftError::InvalidSampleInterval { milliseconds: 500 },
+                "The sample interval given (500 ms) is not a whole number of seconds, at least \
+                 one. Give the interval the Energy Logger 4000 was set to record at, one minute \
+                 unless it was changed.",
+            ),
+            (
+                VoltcraftError::UnsupportedInfoLayout {
+                    header: [0xE0, 0xC5, 0xEA, 0x09, 0x0B],
+                },
+                "The info file starts with E0 C5 EA 09 0B instead of the INFO: header, so its \
+                 layout is not known. Report it with a copy of the file so that the layout can be \
+                 added.",
+            ),
+        ];
+        for (error, expected) in cases {
+            assert_eq!(error.explain(), expected);
+            assert_ne!(error.explain_in(Locale::De), expected);
+        }
+        assert!(VoltcraftError::Io(io::Error::from(io::ErrorKind::NotFound))
+            .explain_in(Locale::De)
+            .starts_with("Die Datendatei existiert nicht."));
+    }
+}
@@ -0,0 +1,425 @@
+use crate::voltcraft::data::{sample_interval, PowerEvent};
+use crate::voltcraft::error::VoltcraftError;
+use crate::voltcraft::stats::{
+    energy, worst_sustained_power_factor, CompensatedSum, CoverageInfo, PowerFactorWindow,
+    PowerStats,
+};
+use chrono::{DateTime, Duration, Local};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Power statistics folded in from batches of power events, e.g. a data file ingested every
+/// night, without keeping the events.
+///
+/// Batches can be added and accumulators merged in any order: `finalize` gives the statistics
+/// of all the power events as `PowerStats::from_events` computes them on the chronologically
+/// sorted events. Extremes are the same events (the latest one on a tie for the maxima, the
+/// earliest one for the minima) and the 95th percentiles are exact, from counts of the
+/// distinct values. Sums are compensated (Neumaier), so they agree with the sequential sums
+/// to within a rounding error, whatever the batches. The sustained power factor windows are
+/// those within a batch (chronological, recorded every interval); windows running over two
+/// batches are not seen. Batches are expected to hold distinct power events.
+///
+/// With the serde feature, the accumulator can be saved between runs.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IncrementalStats {
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::voltcraft::serde_helpers::duration_seconds")
+    )]
+    interval: Duration, // between two power events of the logger
+    count: usize,
+    first: Option<DateTime<Local>>, // earliest power event
+    last: Option<DateTime<Local>>,  // latest power event
+    power_sum: CompensatedSum,
+    apparent_power_sum: CompensatedSum,
+    voltage_sum: CompensatedSum,
+    current_sum: CompensatedSum,
+    power_factor_sum: CompensatedSum,
+    weighted_power_factor_sum: CompensatedSum, // power factors times the apparent power
+    reactive_power_sum: CompensatedSum,
+    max_active_power: Option<PowerEvent>,
+    max_apparent_power: Option<PowerEvent>,
+    min_voltage: Option<PowerEvent>,
+    max_voltage: Option<PowerEvent>,
+    min_current: Option<PowerEvent>,
+    max_current: Option<PowerEvent>,
+    worst_power_factor: Option<PowerEvent>, // with current flowing
+    worst_sustained_power_factor: Option<PowerFactorWindow>,
+    // Number of power events with each voltage and active power, keyed by total_order_key
+    voltage_counts: BTreeMap<i64, usize>,
+    power_counts: BTreeMap<i64, usize>,
+}
+
+impl Default for IncrementalStats {
+    fn default() -> Self {
+        IncrementalStats {
+            interval: Duration::minutes(1),
+            count: 0,
+            first: None,
+            last: None,
+            power_sum: CompensatedSum::default(),
+            apparent_power_sum: CompensatedSum::default(),
+            voltage_sum: CompensatedSum::default(),
+            current_sum: CompensatedSum::default(),
+            power_factor_sum: CompensatedSum::default(),
+            weighted_power_factor_sum: CompensatedSum::default(),
+            reactive_power_sum: CompensatedSum::default(),
+            max_active_power: None,
+            max_apparent_power: None,
+            min_voltage: None,
+            max_voltage: None,
+            min_current: None,
+            max_current: None,
+            worst_power_factor: None,
+            worst_sustained_power_factor: None,
+            voltage_counts: BTreeMap::new(),
+            power_counts: BTreeMap::new(),
+        }
+    }
+}
+
+impl IncrementalStats {
+    // No power events yet, recorded every minute
+    pub fn new() -> Self {
+        IncrementalStats::default()
+    }
+
+    // Interval between two power events of the logger, for the energy totals and durations
+    // (see VoltcraftStatistics::with_sample_interval), InvalidSampleInterval unless
+    // a whole number of seconds
+    pub fn with_sample_interval(mut self, interval: Duration) -> Result<Self, VoltcraftError> {
+        self.interval = sample_interval(interval)?;
+        Ok(self)
+    }
+
+    // Number of power events folded in
+    pub fn event_count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    // Fold in a batch of power events, in chronological order for the sustained power factor
+    pub fn add_events(&mut self, events: &[PowerEvent]) {
+        for pe in events {
+            self.count += 1;
+            self.first = Some(self.first.map_or(pe.timestamp, |t| t.min(pe.timestamp)));
+            self.last = Some(self.last.map_or(pe.timestamp, |t| t.max(pe.timestamp)));
+            self.power_sum = self.power_sum.add(pe.power);
+            self.apparent_power_sum = self.apparent_power_sum.add(pe.apparent_power);
+            self.voltage_sum = self.voltage_sum.add(pe.voltage);
+            self.current_sum = self.current_sum.add(pe.current);
+            self.power_factor_sum = self.power_factor_sum.add(pe.power_factor);
+            self.weighted_power_factor_sum = self
+                .weighted_power_factor_sum
+                .add(pe.power_factor * pe.apparent_power);
+            self.reactive_power_sum = self.reactive_power_sum.add(pe.reactive_power());
+            *self
+                .voltage_counts
+                .entry(total_order_key(pe.voltage))
+                .or_default() += 1;
+            *self
+                .power_counts
+                .entry(total_order_key(pe.power))
+                .or_default() += 1;
+        }
+        for pe in events.iter().copied() {
+            self.fold_extremes(pe);
+        }
+        let window = worst_sustained_power_factor(events, self.interval);
+        self.worst_sustained_power_factor =
+            lowest_window(self.worst_sustained_power_factor, window);
+    }
+
+    // Fold in the power events of another accumulator, recorded at the same interval
+    pub fn merge(&mut self, other: IncrementalStats) {
+        self.count += other.count;
+        self.first = self.first.into_iter().chain(other.first).min();
+        self.last = self.last.into_iter().chain(other.last).max();
+        self.power_sum = self.power_sum.merge(other.power_sum);
+        self.apparent_power_sum = self.apparent_power_sum.merge(other.apparent_power_sum);
+        self.voltage_sum = self.voltage_sum.merge(other.voltage_sum);
+        self.current_sum = self.current_sum.merge(other.current_sum);
+        self.power_factor_sum = self.power_factor_sum.merge(other.power_factor_sum);
+        self.weighted_power_factor_sum = self
+            .weighted_power_factor_sum
+            .merge(other.weighted_power_factor_sum);
+        self.reactive_power_sum = self.reactive_power_sum.merge(other.reactive_power_sum);
+        for pe in [
+            other.max_active_power,
+            other.max_apparent_power,
+            other.min_voltage,
+            other.max_voltage,
+            other.min_current,
+            other.max_current,
+            other.worst_power_factor,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            self.fold_extremes(pe);
+        }
+        self.worst_sustained_power_factor = lowest_window(
+            self.worst_sustained_power_factor,
+            other.worst_sustained_power_factor,
+        );
+        for (key, n) in other.voltage_counts {
+            *self.voltage_counts.entry(key).or_default() += n;
+        }
+        for (key, n) in other.power_counts {
+            *self.power_counts.entry(key).or_default() += n;
+        }
+    }
+
+    // Statistics of all the power events folded in, None without any
+    pub fn finalize(&self) -> Option<PowerStats> {
+        let (first, last) = (self.first?, self.last?);
+        let n = self.count as f64;
+        let apparent_power_sum = self.apparent_power_sum.value();
+        let duration = (last - first) + self.interval;
+        Some(PowerStats {
+            total_active_power: energy(self.power_sum.value(), self.interval),
+            avg_active_power: self.power_sum.value() / n,
+            max_active_power: self.max_active_power?,
+            total_apparent_power: energy(apparent_power_sum, self.interval),
+            avg_apparent_power: apparent_power_sum / n,
+            max_apparent_power: self.max_apparent_power?,
+            min_voltage: self.min_voltage?,
+            max_voltage: self.max_voltage?,
+            avg_voltage: self.voltage_sum.value() / n,
+            p95_voltage: nearest_rank(&self.voltage_counts, self.count, 0.95),
+            min_current: self.min_current?,
+            max_current: self.max_current?,
+            avg_current: self.current_sum.value() / n,
+            avg_power_factor: self.power_factor_sum.value() / n,
+            p95_power: nearest_rank(&self.power_counts, self.count, 0.95),
+            worst_power_factor: self.worst_power_factor,
+            weighted_power_factor: match apparent_power_sum > 0.0 {
+                true => self.weighted_power_factor_sum.value() / apparent_power_sum,
+                false => 0.0,
+            },
+            total_reactive_energy: energy(self.reactive_power_sum.value(), self.interval),
+            worst_sustained_power_factor: self.worst_sustained_power_factor,
+            total_duration: duration,
+            active: None,
+            coverage: CoverageInfo::from_seconds(
+                self.count as i64 * self.interval.num_seconds(),
+                duration.num_seconds(),
+            ),
+        })
+    }
+
+    // Keep the power event where it beats the extremes so far
+    fn fold_extremes(&mut self, pe: PowerEvent) {
+        extreme(
+            &mut self.max_active_power,
+            pe,
+            |e| e.power,
+            Ordering::Greater,
+        );
+        extreme(
+            &mut self.max_apparent_power,
+            pe,
+            |e| e.apparent_power,
+            Ordering::Greater,
+        );
+        extreme(&mut self.min_voltage, pe, |e| e.voltage, Ordering::Less);
+        extreme(&mut self.max_voltage, pe, |e| e.voltage, Ordering::Greater);
+        extreme(&mut self.min_current, pe, |e| e.current, Ordering::Less);
+        extreme(&mut self.max_current, pe, |e| e.current, Ordering::Greater);
+        if pe.current > 0.0 {
+            extreme(
+                &mut self.worst_power_factor,
+                pe,
+                |e| e.power_factor,
+                Ordering::Less,
+            );
+        }
+    }
+}
+
+// Replace the extreme with the candidate if it compares as wanted on the value, then on the
+// timestamp: the later of two equal maxima and the earlier of two equal minima
+fn extreme(
+    current: &mut Option<PowerEvent>,
+    candidate: PowerEvent,
+    value: fn(&PowerEvent) -> f64,
+    wanted: Ordering,
+) {
+    let replace = current.is_none_or(|current| {
+        value(&candidate)
+            .partial_cmp(&value(&current))
+            .unwrap()
+            .then(candidate.timestamp.cmp(&current.timestamp))
+            == wanted
+    });
+    if replace {
+        *current = Some(candidate);
+    }
+}
+
+// Lower of two power factor windows, the earlier one on a tie
+fn lowest_window(
+    a: Option<PowerFactorWindow>,
+    b: Option<PowerFactorWindow>,
+) -> Option<PowerFactorWindow> {
+    a.into_iter().chain(b).min_by(|a, b| {
+        a.power_factor
+            .partial_cmp(&b.power_factor)
+            .unwrap()
+            .then(a.start.cmp(&b.start))
+    })
+}
+
+// Integer ordered like the value under f64::total_cmp, from which the value is recovered
+fn total_order_key(value: f64) -> i64 {
+    let bits = value.to_bits() as i64;
+    bits ^ (((bits >> 63) as u64) >> 1) as i64
+}
+
+fn from_total_order_key(key: i64) -> f64 {
+    f64::from_bits((key ^ (((key >> 63) as u64) >> 1) as i64) as u64)
+}
+
+// Value at the fraction of the counted values using the nearest rank, as
+// distribution::nearest_rank does on the values themselves (at least one value)
+fn nearest_rank(counts: &BTreeMap<i64, usize>, total: usize, fraction: f64) -> f64 {
+    let rank = ((fraction.clamp(0.0, 1.0) * total as f64).ceil() as usize).max(1);
+    let mut seen = 0;
+    for (key, n) in counts {
+        seen += n;
+        if seen >= rank {
+            return from_total_order_key(*key);
+        }
+    }
+    unreachable!("rank beyond the counted values")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::incremental::IncrementalStats;
+    use crate::voltcraft::stats::PowerStats;
+    use chrono::{Duration, Local, TimeZone};
+
+    // Ten days with a varying load, a poor power factor at noon on the fourth day and gaps
+    fn events() -> Vec<PowerEvent> {
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        (0..10 * 1440)
+            .filter(|m| m % 997 > 3)
+            .map(|m| {
+                let current = ((m * 7) % 40) as f64 / 8.0;
+                let voltage = 224.0 + (m % 17) as f64 * 0.7;
+                let power_factor = match m {
+                    5040..=5070 => 0.4,
+                    _ => 0.6 + (m % 5) as f64 * 0.1,
+                };
+                PowerEvent {
+                    timestamp: start + Duration::minutes(m),
+                    voltage,
+                    current,
+                    power_factor,
+                    power: voltage * current * power_factor / 1000.0,
+                    apparent_power: voltage * current / 1000.0,
+                    frequency: None,
+                }
+            })
+            .collect()
+    }
+
+    fn assert_same(actual: &PowerStats, expected: &PowerStats) {
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * b.abs().max(1.0);
+        assert!(close(
+            actual.total_active_power,
+            expected.total_active_power
+        ));
+        assert!(close(actual.avg_active_power, expected.avg_active_power));
+        assert!(close(
+            actual.total_apparent_power,
+            expected.total_apparent_power
+        ));
+        assert!(close(actual.avg_voltage, expected.avg_voltage));
+        assert!(close(actual.avg_current, expected.avg_current));
+        assert!(close(actual.avg_power_factor, expected.avg_power_factor));
+        assert!(close(
+            actual.weighted_power_factor,
+            expected.weighted_power_factor
+        ));
+        assert!(close(
+            actual.total_reactive_energy,
+            expected.total_reactive_energy
+        ));
+        // The extremes, percentiles and durations are exact
+        assert_eq!(actual.max_active_power, expected.max_active_power);
+        assert_eq!(actual.max_apparent_power, expected.max_apparent_power);
+        assert_eq!(actual.min_voltage, expected.min_voltage);
+        assert_eq!(actual.max_voltage, expected.max_voltage);
+        assert_eq!(actual.min_current, expected.min_current);
+        assert_eq!(actual.max_current, expected.max_current);
+        assert_eq!(actual.worst_power_factor, expected.worst_power_factor);
+        assert_eq!(actual.p95_voltage, expected.p95_voltage);
+        assert_eq!(actual.p95_power, expected.p95_power);
+        assert_eq!(actual.total_duration, expected.total_duration);
+        assert_eq!(actual.coverage, expected.coverage);
+        let (window, expected_window) = (
+            actual.worst_sustained_power_factor.unwrap(),
+            expected.worst_sustained_power_factor.unwrap(),
+        );
+        assert_eq!(window.start, expected_window.start);
+        assert!(close(window.power_factor, expected_window.power_factor));
+    }
+
+    #[test]
+    fn incremental_matches_full_stats() {
+        let events = events();
+        let expected = PowerStats::from_events(&events).unwrap();
+
+        // One batch per day, folded in from the last one
+        let days = events.chunks(1440).collect::<Vec<_>>();
+        let mut stats = IncrementalStats::new();
+        for day in days.iter().rev() {
+            stats.add_events(day);
+        }
+        assert_eq!(stats.event_count(), events.len());
+        assert_same(&stats.finalize().unwrap(), &expected);
+
+        // Accumulators of odd and even days merged together
+        let (mut odd, mut even) = (IncrementalStats::new(), IncrementalStats::new());
+        for (i, day) in days.iter().enumerate() {
+            match i % 2 {
+                0 => even.add_events(day),
+                _ => odd.add_events(day),
+            }
+        }
+        odd.merge(even);
+        assert_same(&odd.finalize().unwrap(), &expected);
+        odd.merge(IncrementalStats::new());
+        assert_same(&odd.finalize().unwrap(), &expected);
+
+        let mut empty = IncrementalStats::new();
+        assert!(empty.finalize().is_none());
+        empty.add_events(&[]);
+        assert!(empty.is_empty());
+        assert!(IncrementalStats::new()
+            .with_sample_interval(Duration::zero())
+            .is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn incremental_resumes_from_saved_state() {
+        let events = events();
+        let (before, after) = events.split_at(6000);
+        let mut stats = IncrementalStats::new();
+        stats.add_events(before);
+        let saved = serde_json::to_string(&stats).unwrap();
+        let mut resumed: IncrementalStats = serde_json::from_str(&saved).unwrap();
+        resumed.add_events(after);
+        let expected = PowerStats::from_events(&events).unwrap();
+        assert_same(&resumed.finalize().unwrap(), &expected);
+    }
+}
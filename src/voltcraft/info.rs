@@ -0,0 +1,177 @@
+use crate::voltcraft::error::VoltcraftError;
+use crate::voltcraft::tariff::Tariff;
+use std::fs;
+use std::path::Path;
+
+/// Settings and totals of the device from the info file the logger writes next to the data
+/// files, see section 6 of the file format notes for the layout decoded.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct VoltcraftInfo {
+    pub unit_id: u8,
+    pub currency: String,    // as set on the device, e.g. "EUR"
+    pub tariffs: [f64; 2],   // price per kWh of tariff 1 and tariff 2
+    pub total_energy: f64,   // active energy recorded by the device (kWh)
+    pub recorded_hours: f64, // time recorded by the device (h)
+    pub on_hours: f64,       // time the load drew power (h)
+}
+
+// Start of the info file
+const INFO_MAGIC: [u8; 5] = *b"INFO:";
+// Byte offsets of the fields, and length of the info file
+const TOTAL_ENERGY: usize = 5;
+const RECORDED_HOURS: usize = 8;
+const ON_HOURS: usize = 11;
+const UNIT_ID: usize = 84;
+const TARIFF_1: usize = 85;
+const TARIFF_2: usize = 89;
+const CURRENCY: usize = 98;
+const INFO_LEN: usize = 101;
+
+impl VoltcraftInfo {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<VoltcraftInfo, VoltcraftError> {
+        VoltcraftInfo::from_bytes(&fs::read(path)?)
+    }
+
+    // Decode the info file, UnsupportedInfoLayout with its first bytes unless it starts with
+    // the info header, TruncatedData if it ends before the currency
+    pub fn from_bytes(raw: &[u8]) -> Result<VoltcraftInfo, VoltcraftError> {
+        let mut header = [0; INFO_MAGIC.len()];
+        let seen = raw.len().min(header.len());
+        header[..seen].copy_from_slice(&raw[..seen]);
+        if header != INFO_MAGIC {
+            return Err(VoltcraftError::UnsupportedInfoLayout { header });
+        }
+        if raw.len() < INFO_LEN {
+            return Err(VoltcraftError::TruncatedData { offset: raw.len() });
+        }
+        // Big endian unsigned value of the bytes at the offset
+        let value = |offset: usize, len: usize| {
+            raw[offset..offset + len]
+                .iter()
+                .fold(0u32, |value, &b| value << 8 | b as u32)
+        };
+        let currency = String::from_utf8_lossy(&raw[CURRENCY..INFO_LEN]);
+        Ok(VoltcraftInfo {
+            unit_id: raw[UNIT_ID],
+            currency: currency.trim_end_matches(['\0', ' ']).to_string(),
+            tariffs: [
+                value(TARIFF_1, 4) as f64 / 10000.0,
+                value(TARIFF_2, 4) as f64 / 10000.0,
+            ],
+            total_energy: value(TOTAL_ENERGY, 3) as f64 / 1000.0,
+            recorded_hours: value(RECORDED_HOURS, 3) as f64 / 100.0,
+            on_hours: value(ON_HOURS, 3) as f64 / 100.0,
+        })
+    }
+}
+
+// Flat price of tariff 1; the hours during which the device switches to tariff 2 are not
+// in the info file, add them with Tariff::with_band at tariffs[1]
+impl From<&VoltcraftInfo> for Tariff {
+    fn from(info: &VoltcraftInfo) -> Self {
+        Tariff::flat(info.tariffs[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::error::VoltcraftError;
+    use crate::voltcraft::info::{VoltcraftInfo, INFO_LEN};
+    use crate::voltcraft::stats::VoltcraftStatistics;
+    use crate::voltcraft::tariff::Tariff;
+    use chrono::{DateTime, Duration, Local, TimeZone};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    // Info file of unit 3 in EUR at 0.2875 and 0.1550 per kWh, having recorded 12.345 kWh
+    // in 250.5 hours, 40.25 of them with the load on
+    fn sample() -> Vec<u8> {
+        let mut raw = b"INFO:".to_vec();
+        raw.extend_from_slice(&[0x00, 0x30, 0x39]);
+        raw.extend_from_slice(&[0x00, 0x61, 0xDA]);
+        raw.extend_from_slice(&[0x00, 0x0F, 0xB9]);
+        raw.resize(84, 0);
+        raw.push(3);
+        raw.extend_from_slice(&2875u32.to_be_bytes());
+        raw.extend_from_slice(&1550u32.to_be_bytes());
+        raw.extend_from_slice(&[12, 30, 9, 10, 14]);
+        raw.extend_from_slice(b"EUR");
+        raw
+    }
+
+    #[test]
+    fn info_decode() {
+        let info = VoltcraftInfo::from_bytes(&sample()).unwrap();
+        assert_eq!(info.unit_id, 3);
+        assert_eq!(info.currency, "EUR");
+        assert_eq!(info.tariffs, [0.2875, 0.155]);
+        assert_eq!(info.total_energy, 12.345);
+        assert_eq!(info.recorded_hours, 250.5);
+        assert_eq!(info.on_hours, 40.25);
+
+        let mut padded = sample();
+        padded[98..].copy_from_slice(b"Ft\0");
+        let padded = VoltcraftInfo::from_bytes(&padded).unwrap();
+        assert_eq!(padded.currency, "Ft");
+
+        let path = std::env::temp_dir().join("voltcraft_info.inf");
+        std::fs::write(&path, sample()).unwrap();
+        assert_eq!(VoltcraftInfo::from_file(&path).unwrap(), info);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn info_unsupported_layout() {
+        let mut other = sample();
+        other[4] = b'2';
+        match VoltcraftInfo::from_bytes(&other) {
+            Err(VoltcraftError::UnsupportedInfoLayout { header }) => {
+                assert_eq!(&header, b"INFO2")
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match VoltcraftInfo::from_bytes(&[0xE0, 0xC5]) {
+            Err(VoltcraftError::UnsupportedInfoLayout { header }) => {
+                assert_eq!(header, [0xE0, 0xC5, 0, 0, 0])
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match VoltcraftInfo::from_bytes(&sample()[..INFO_LEN - 1]) {
+            Err(VoltcraftError::TruncatedData { offset: 100 }) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(
+            VoltcraftInfo::from_file(std::env::temp_dir().join("voltcraft-missing.inf")),
+            Err(VoltcraftError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn info_tariff() {
+        let info = VoltcraftInfo::from_bytes(&sample()).unwrap();
+        let tariff = Tariff::from(&info);
+        assert_eq!(tariff, Tariff::flat(0.2875));
+
+        // Two hours at 1 kW
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 12, 0, 0).unwrap();
+        let events = (0..120)
+            .map(|i| event(start + Duration::minutes(i), 1.0))
+            .collect::<Vec<_>>();
+        let cost = VoltcraftStatistics::new(&events).cost(&Tariff::from(&info));
+        assert!((cost.cost - 0.575).abs() < 1e-12);
+    }
+}
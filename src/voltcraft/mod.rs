@@ -1,2 +1,26 @@
+pub mod annotations;
+#[cfg(feature = "compression")]
+mod archive;
+pub mod baseline;
+pub mod batch;
+pub mod bucket;
+pub mod chunked;
+pub mod clean;
+pub mod compact;
+pub mod compare;
+pub mod consts;
+pub mod cumulative;
 pub mod data;
+pub mod dataset;
+pub mod diagnostics;
+pub mod distribution;
+pub mod error;
+pub mod incremental;
+pub mod info;
+pub mod presence;
+pub mod resample;
+#[cfg(feature = "serde")]
+pub(crate) mod serde_helpers;
+pub mod sorted;
 pub mod stats;
+pub mod tariff;
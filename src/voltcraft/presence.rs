@@ -0,0 +1,214 @@
+use crate::voltcraft::data::PowerEvent;
+use crate::voltcraft::stats::PowerBlackout;
+use chrono::{DateTime, Duration, Local};
+
+/// Which minutes between the first and the last power event have a power event, one bit per
+/// minute (about 64 KB for a year). Minutes are counted from the first power event, so the
+/// events are expected a whole number of minutes apart, as recorded by the device.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PresenceIndex {
+    start: Option<DateTime<Local>>, // first power event
+    minutes: usize,                 // minutes from the first to the last power event, both included
+    words: Vec<u64>,
+}
+
+impl PresenceIndex {
+    // Build the index from power events in any order
+    pub fn new(events: &[PowerEvent]) -> Self {
+        let (Some(first), Some(last)) = (
+            events.iter().map(|e| e.timestamp).min(),
+            events.iter().map(|e| e.timestamp).max(),
+        ) else {
+            return PresenceIndex::default();
+        };
+        let minutes = (last - first).num_minutes() as usize + 1;
+        let mut words = vec![0u64; minutes.div_ceil(64)];
+        for e in events {
+            let minute = (e.timestamp - first).num_minutes() as usize;
+            words[minute / 64] |= 1 << (minute % 64);
+        }
+        PresenceIndex {
+            start: Some(first),
+            minutes,
+            words,
+        }
+    }
+
+    pub fn start(&self) -> Option<DateTime<Local>> {
+        self.start
+    }
+
+    // Minutes from the first to the last power event, both included (the expected samples)
+    pub fn span_minutes(&self) -> usize {
+        self.minutes
+    }
+
+    // Minutes with a power event
+    pub fn present_minutes(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    // Minutes without a power event between the first and the last one
+    pub fn missing_minutes(&self) -> usize {
+        self.minutes - self.present_minutes()
+    }
+
+    // Whether the minute starting at the timestamp has a power event
+    pub fn has_minute(&self, timestamp: DateTime<Local>) -> bool {
+        match self.start {
+            Some(start) if timestamp >= start => {
+                let minute = (timestamp - start).num_minutes() as usize;
+                minute < self.minutes && self.is_set(minute)
+            }
+            _ => false,
+        }
+    }
+
+    // Minutes with a power event from `from` to `to` (exclusive)
+    pub fn count_in_range(&self, from: DateTime<Local>, to: DateTime<Local>) -> usize {
+        let (first, last) = (self.first_minute_from(from), self.first_minute_from(to));
+        if first >= last {
+            return 0;
+        }
+        let (first_word, last_word) = (first / 64, (last - 1) / 64);
+        let mut count = 0;
+        for (i, &word) in self.words[first_word..=last_word].iter().enumerate() {
+            let mut word = word;
+            if i == 0 {
+                word &= u64::MAX << (first % 64);
+            }
+            if first_word + i == last_word && last % 64 != 0 {
+                word &= u64::MAX >> (64 - last % 64);
+            }
+            count += word.count_ones() as usize;
+        }
+        count
+    }
+
+    // Runs of minutes without a power event, in chronological order (same as the blackouts
+    // found by comparing subsequent power events)
+    pub fn gaps(&self) -> Vec<PowerBlackout> {
+        let Some(start) = self.start else {
+            return Vec::new();
+        };
+        let mut gaps = Vec::new();
+        let mut gap_start = None;
+        let mut minute = 0;
+        while minute < self.minutes {
+            // Skip fully recorded words at once
+            if gap_start.is_none() && minute % 64 == 0 && self.words[minute / 64] == u64::MAX {
+                minute += 64;
+                continue;
+            }
+            match (self.is_set(minute), gap_start) {
+                (false, None) => gap_start = Some(minute),
+                (true, Some(first)) => {
+                    gaps.push(PowerBlackout {
+                        timestamp: start + Duration::minutes(first as i64),
+                        duration: Duration::minutes((minute - first) as i64),
+                    });
+                    gap_start = None;
+                }
+                _ => {}
+            }
+            minute += 1;
+        }
+        gaps
+    }
+
+    fn is_set(&self, minute: usize) -> bool {
+        self.words[minute / 64] & (1 << (minute % 64)) != 0
+    }
+
+    // Index of the first minute starting at or after the timestamp, clamped to the span
+    fn first_minute_from(&self, timestamp: DateTime<Local>) -> usize {
+        match self.start {
+            Some(start) if timestamp > start => {
+                let seconds = (timestamp - start).num_seconds() as usize;
+                seconds.div_ceil(60).min(self.minutes)
+            }
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::presence::PresenceIndex;
+    use crate::voltcraft::stats::VoltcraftStatistics;
+    use chrono::{DateTime, Duration, Local, TimeZone};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    #[test]
+    fn presence_matches_scan() {
+        let start = Local.with_ymd_and_hms(2014, 9, 1, 0, 0, 0).unwrap();
+        let mut seed = 7u64;
+        let mut random = move |n: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % n
+        };
+        for round in 0..20 {
+            // Runs of recorded minutes separated by gaps of 1 to 200 minutes
+            let mut minutes = Vec::new();
+            let mut minute = 0;
+            while minutes.len() < 5000 {
+                let run = if round % 2 == 0 {
+                    1 + random(10)
+                } else {
+                    1 + random(500)
+                };
+                minutes.extend(minute..minute + run);
+                minute += run + 1 + random(200);
+            }
+            let events = minutes
+                .iter()
+                .map(|&m| event(start + Duration::minutes(m as i64), 0.23))
+                .collect::<Vec<_>>();
+            let index = PresenceIndex::new(&events);
+            let last = *minutes.last().unwrap();
+            assert_eq!(index.span_minutes(), last as usize + 1);
+            assert_eq!(index.present_minutes(), minutes.len());
+
+            for _ in 0..100 {
+                let t = start + Duration::minutes(random(last + 100) as i64 - 50);
+                assert_eq!(index.has_minute(t), events.iter().any(|e| e.timestamp == t));
+                let from = start + Duration::seconds(random(last * 60) as i64 - 3000);
+                let to = from + Duration::seconds(random(20000 * 60) as i64);
+                let scan = events
+                    .iter()
+                    .filter(|e| e.timestamp >= from && e.timestamp < to)
+                    .count();
+                assert_eq!(index.count_in_range(from, to), scan);
+            }
+
+            let gaps = index.gaps();
+            let blackouts = VoltcraftStatistics::new(&events).blackout_stats();
+            assert_eq!(gaps.len(), blackouts.blackouts.len());
+            for (gap, blackout) in gaps.iter().zip(&blackouts.blackouts) {
+                assert_eq!(
+                    (gap.timestamp, gap.duration),
+                    (blackout.timestamp, blackout.duration)
+                );
+            }
+            let missing = gaps.iter().map(|g| g.duration_minutes()).sum::<i64>();
+            assert_eq!(index.missing_minutes(), missing as usize);
+        }
+        assert_eq!(PresenceIndex::new(&[]).count_in_range(start, start), 0);
+        assert!(PresenceIndex::new(&[]).gaps().is_empty());
+    }
+}
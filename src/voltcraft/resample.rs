@@ -0,0 +1,211 @@
+use crate::voltcraft::bucket::first_instant;
+use crate::voltcraft::data::PowerEvent;
+use chrono::{DateTime, Duration, Local, NaiveDateTime};
+use std::collections::BTreeMap;
+
+/// How the power events of a bucket are combined by `resample`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Aggregation {
+    // Average of the recorded minutes: the mean voltage, active and apparent power, with the
+    // current and power factor derived from them so that the values stay consistent
+    #[default]
+    Mean,
+    // The power event with the highest active power of the bucket (the first one on a tie)
+    Max,
+}
+
+/// One power event per bucket of `bucket` (e.g. 15 minutes, whole seconds) with power events,
+/// timestamped at the start of the bucket, in chronological order. A bucket shorter than a
+/// second, zero or negative, is taken as one second.
+///
+/// Buckets are aligned on the local wall clock at whole multiples of the bucket from
+/// 1970-01-01 00:00, so that a bucket dividing a day (15 minutes, an hour, 4 hours) starts at
+/// local midnight, like the calendar buckets of `bucket_of`; a longer one or one not dividing
+/// a day (7 minutes, 2 days) also crosses midnight. The repeated hour when daylight saving
+/// time ends is a single bucket.
+///
+/// With `Aggregation::Mean`, the active and apparent power are the means of the power events,
+/// so that the energy of a complete bucket is its power over its duration. The voltage is the
+/// mean voltage; the current and the power factor are derived from the mean powers rather
+/// than averaged, so that `PowerEvent::is_consistent` holds (the power factor is the ratio of
+/// the active to the apparent energy).
+pub fn resample(
+    events: &[PowerEvent],
+    bucket: Duration,
+    aggregation: Aggregation,
+) -> Vec<PowerEvent> {
+    // Whole seconds, at least one
+    let length = bucket.num_seconds().max(1);
+    let mut buckets = BTreeMap::<NaiveDateTime, Vec<&PowerEvent>>::new();
+    for pe in events {
+        let local = pe.timestamp.naive_local().and_utc().timestamp();
+        let start = DateTime::from_timestamp(local - local.rem_euclid(length), 0)
+            .unwrap()
+            .naive_utc();
+        buckets.entry(start).or_default().push(pe);
+    }
+    buckets
+        .into_iter()
+        .filter_map(|(start, events)| {
+            let timestamp = first_instant(&Local, start)?;
+            Some(match aggregation {
+                Aggregation::Mean => mean(timestamp, &events),
+                Aggregation::Max => PowerEvent {
+                    timestamp,
+                    ..**events
+                        .iter()
+                        .reduce(|max, pe| if pe.power > max.power { pe } else { max })
+                        .unwrap()
+                },
+            })
+        })
+        .collect()
+}
+
+// Mean of the power events of a bucket, at least one
+fn mean(timestamp: DateTime<Local>, events: &[&PowerEvent]) -> PowerEvent {
+    let n = events.len() as f64;
+    let average =
+        |value: fn(&PowerEvent) -> f64| events.iter().map(|pe| value(pe)).sum::<f64>() / n;
+    let voltage = average(|pe| pe.voltage);
+    let power = average(|pe| pe.power);
+    let apparent_power = average(|pe| pe.apparent_power);
+    PowerEvent {
+        timestamp,
+        voltage,
+        current: match voltage > 0.0 {
+            true => apparent_power * 1000.0 / voltage,
+            false => average(|pe| pe.current),
+        },
+        power_factor: match apparent_power > 0.0 {
+            true => power / apparent_power,
+            false => average(|pe| pe.power_factor),
+        },
+        power,
+        apparent_power,
+        frequency: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::resample::{resample, Aggregation};
+    use crate::voltcraft::stats::VoltcraftStatistics;
+    use chrono::{DateTime, Duration, Local, TimeZone};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    #[test]
+    fn resample_buckets() {
+        // Three hours from 18:40 with varying readings, without 20:00 to 20:14
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 18, 40, 0).unwrap();
+        let events = (0..180)
+            .filter(|m| !(80..95).contains(m))
+            .map(|m| {
+                let (voltage, current, power_factor) = (
+                    220.0 + (m % 7) as f64,
+                    (m % 13) as f64 * 0.5,
+                    0.5 + (m % 5) as f64 * 0.1,
+                );
+                PowerEvent {
+                    timestamp: start + Duration::minutes(m),
+                    voltage,
+                    current,
+                    power_factor,
+                    power: voltage * current * power_factor / 1000.0,
+                    apparent_power: voltage * current / 1000.0,
+                    frequency: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let quarters = resample(&events, Duration::minutes(15), Aggregation::Mean);
+        // 18:30 (partial) to 21:30 (partial), without the 20:00 bucket
+        assert_eq!(quarters.len(), 12);
+        assert_eq!(quarters[0].timestamp, start - Duration::minutes(10));
+        assert_eq!(quarters[6].timestamp, start + Duration::minutes(95));
+        assert!(quarters.iter().all(|pe| pe.is_consistent(1e-12)));
+
+        // The complete buckets keep their energy
+        let stats = VoltcraftStatistics::new(&events);
+        let from = start + Duration::minutes(5);
+        let to = start + Duration::minutes(170);
+        let expected = stats.stats_between(from, to).unwrap().total_active_power;
+        let energy = quarters
+            .iter()
+            .filter(|pe| pe.timestamp >= from && pe.timestamp < to)
+            .map(|pe| pe.power * 15.0 / 60.0)
+            .sum::<f64>();
+        assert!((energy - expected).abs() < 1e-9);
+        let apparent = stats.stats_between(from, to).unwrap().total_apparent_power;
+        let apparent_energy = quarters
+            .iter()
+            .filter(|pe| pe.timestamp >= from && pe.timestamp < to)
+            .map(|pe| pe.apparent_power * 15.0 / 60.0)
+            .sum::<f64>();
+        assert!((apparent_energy - apparent).abs() < 1e-9);
+
+        // The peak minute of each hour
+        let peaks = resample(&events, Duration::hours(1), Aggregation::Max);
+        assert_eq!(peaks.len(), 4);
+        assert_eq!(peaks[1].timestamp, start + Duration::minutes(20));
+        let hour = &events[20..80];
+        let peak = hour.iter().map(|pe| pe.power).fold(f64::MIN, f64::max);
+        assert_eq!(peaks[1].power, peak);
+        assert!(resample(&[], Duration::minutes(15), Aggregation::Mean).is_empty());
+    }
+
+    #[test]
+    fn resample_bucket_boundaries() {
+        // An event exactly at the start of a bucket and one a second earlier
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 12, 15, 0).unwrap();
+        let events = [
+            event(start - Duration::seconds(1), 0.23),
+            event(start, 0.23),
+        ];
+        let quarters = resample(&events, Duration::minutes(15), Aggregation::Mean);
+        assert_eq!(quarters.len(), 2);
+        assert_eq!(quarters[0].timestamp, start - Duration::minutes(15));
+        assert_eq!(quarters[1].timestamp, start);
+
+        // Buckets dividing a day start at local midnight, the others at multiples from 1970
+        let midnight = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let hours = resample(
+            &[event(midnight, 0.23)],
+            Duration::hours(4),
+            Aggregation::Max,
+        );
+        assert_eq!(hours[0].timestamp, midnight);
+        let sevens = resample(
+            &[event(midnight, 0.23)],
+            Duration::minutes(7),
+            Aggregation::Max,
+        );
+        assert_eq!(sevens[0].timestamp, midnight - Duration::minutes(2));
+
+        // Buckets shorter than a second are one second long
+        for bucket in [
+            Duration::zero(),
+            Duration::milliseconds(500),
+            -Duration::minutes(1),
+        ] {
+            let seconds = resample(&events, bucket, Aggregation::Mean);
+            assert_eq!(seconds.len(), 2);
+            assert_eq!(seconds[0].timestamp, events[0].timestamp);
+            assert_eq!(seconds[1].timestamp, start);
+        }
+    }
+}
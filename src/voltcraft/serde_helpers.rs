@@ -0,0 +1,18 @@
+// Serde representations for the chrono types lacking a suitable one of their own
+use chrono::Duration;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+// chrono::Duration as integer seconds
+pub mod duration_seconds {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_i64(duration.num_seconds())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        let seconds = i64::deserialize(d)?;
+        Duration::try_seconds(seconds).ok_or_else(|| D::Error::custom("duration out of range"))
+    }
+}
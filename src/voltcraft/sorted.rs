@@ -0,0 +1,304 @@
+use crate::voltcraft::data::PowerEvent;
+use chrono::{DateTime, Duration, Local};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::ops::Deref;
+
+/// Power events in chronological order (equal timestamps allowed).
+///
+/// Functions relying on the order (binary searches, single-pass grouping) take this type
+/// rather than a slice, so that events appended out of order cannot give wrong results
+/// silently:
+///
+/// ```compile_fail
+/// # use voltcraft_energy_analyzer::voltcraft::compact::compact;
+/// # use voltcraft_energy_analyzer::voltcraft::data::PowerEvent;
+/// # use chrono::Local;
+/// let events: Vec<PowerEvent> = Vec::new();
+/// compact(&events, Local::now()); // expects &SortedEvents
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SortedEvents(pub(crate) Vec<PowerEvent>);
+
+/// The first power event found earlier than the one before it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnsortedEvents {
+    pub index: usize, // position of the out of order power event
+}
+
+impl fmt::Display for UnsortedEvents {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "power event {} is earlier than the one before it",
+            self.index
+        )
+    }
+}
+
+impl Error for UnsortedEvents {}
+
+/// Problem with the order of a sequence of power events, found by `validate_sequence`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SequenceIssue {
+    // A power event with the same timestamp comes earlier in the sequence
+    Duplicate {
+        index: usize,
+        timestamp: DateTime<Local>,
+    },
+    // Earlier than the power event before it
+    OutOfOrder {
+        index: usize,
+        timestamp: DateTime<Local>,
+    },
+    // Not a whole number of minutes after the power event before it (blackouts are)
+    IrregularGap {
+        index: usize,
+        gap: Duration,
+    },
+}
+
+impl fmt::Display for SequenceIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SequenceIssue::Duplicate { index, timestamp } => {
+                write!(f, "power event {} repeats {}", index, timestamp)
+            }
+            SequenceIssue::OutOfOrder { index, timestamp } => write!(
+                f,
+                "power event {} at {} is earlier than the one before it",
+                index, timestamp
+            ),
+            SequenceIssue::IrregularGap { index, gap } => write!(
+                f,
+                "power event {} comes {} seconds after the one before it",
+                index,
+                gap.num_seconds()
+            ),
+        }
+    }
+}
+
+/// Duplicated timestamps, power events out of chronological order and gaps off the minute
+/// grid in a sequence of power events recorded every minute, e.g. after appending data files
+/// by hand (nothing for the events of a single data file). Each power event has at most one
+/// issue, a duplicate being reported as such wherever it is.
+pub fn validate_sequence(events: &[PowerEvent]) -> Vec<SequenceIssue> {
+    let mut seen = HashSet::new();
+    let mut issues = Vec::new();
+    for (index, pe) in events.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|i| events[i].timestamp);
+        if !seen.insert(pe.timestamp) {
+            issues.push(SequenceIssue::Duplicate {
+                index,
+                timestamp: pe.timestamp,
+            });
+        } else if let Some(previous) = previous {
+            let gap = pe.timestamp - previous;
+            if gap < Duration::zero() {
+                issues.push(SequenceIssue::OutOfOrder {
+                    index,
+                    timestamp: pe.timestamp,
+                });
+            } else if gap.num_milliseconds() % 60_000 != 0 {
+                issues.push(SequenceIssue::IrregularGap { index, gap });
+            }
+        }
+    }
+    issues
+}
+
+/// Power events sorted in chronological order, keeping the first one (in the given order) of
+/// the power events with the same timestamp. See `VoltcraftData::merge` to choose which one
+/// to keep or to reject different readings.
+pub fn normalize(mut events: Vec<PowerEvent>) -> Vec<PowerEvent> {
+    events.sort_by_key(|e| e.timestamp); // stable, so the first duplicate stays first
+    events.dedup_by_key(|e| e.timestamp);
+    events
+}
+
+impl SortedEvents {
+    // Power events known to be in chronological order, e.g. from Dataset::events; the order is
+    // only checked in debug builds
+    pub fn from_sorted_unchecked(events: Vec<PowerEvent>) -> SortedEvents {
+        debug_assert!(first_unsorted(&events).is_none());
+        SortedEvents(events)
+    }
+
+    // Sort the power events (stable, so events with equal timestamps keep their order)
+    pub fn from_vec(mut events: Vec<PowerEvent>) -> SortedEvents {
+        events.sort_by_key(|e| e.timestamp);
+        SortedEvents(events)
+    }
+
+    pub fn try_from_vec(events: Vec<PowerEvent>) -> Result<SortedEvents, UnsortedEvents> {
+        match first_unsorted(&events) {
+            Some(index) => Err(UnsortedEvents { index }),
+            None => Ok(SortedEvents(events)),
+        }
+    }
+
+    pub fn as_slice(&self) -> &[PowerEvent] {
+        &self.0
+    }
+
+    pub fn into_vec(self) -> Vec<PowerEvent> {
+        self.0
+    }
+
+    // Power events in the half-open interval [from, to), found by binary search
+    pub fn range(&self, from: DateTime<Local>, to: DateTime<Local>) -> &[PowerEvent] {
+        let start = self.0.partition_point(|e| e.timestamp < from);
+        let end = self.0.partition_point(|e| e.timestamp < to).max(start);
+        &self.0[start..end]
+    }
+}
+
+fn first_unsorted(events: &[PowerEvent]) -> Option<usize> {
+    events
+        .windows(2)
+        .position(|w| w[1].timestamp < w[0].timestamp)
+        .map(|i| i + 1)
+}
+
+impl Deref for SortedEvents {
+    type Target = [PowerEvent];
+
+    fn deref(&self) -> &[PowerEvent] {
+        &self.0
+    }
+}
+
+impl AsRef<[PowerEvent]> for SortedEvents {
+    fn as_ref(&self) -> &[PowerEvent] {
+        &self.0
+    }
+}
+
+impl TryFrom<Vec<PowerEvent>> for SortedEvents {
+    type Error = UnsortedEvents;
+
+    fn try_from(events: Vec<PowerEvent>) -> Result<Self, Self::Error> {
+        SortedEvents::try_from_vec(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::sorted::{
+        normalize, validate_sequence, SequenceIssue, SortedEvents, UnsortedEvents,
+    };
+    use chrono::{DateTime, Duration, Local, TimeZone};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    fn events(minutes: &[i64]) -> Vec<PowerEvent> {
+        let start = Local.with_ymd_and_hms(2014, 9, 11, 0, 0, 0).unwrap();
+        minutes
+            .iter()
+            .map(|m| PowerEvent {
+                current: 1.0,
+                ..event(start + Duration::minutes(*m), *m as f64)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sorted_try_from_vec() {
+        let sorted = SortedEvents::try_from_vec(events(&[0, 1, 1, 5])).unwrap();
+        assert_eq!(sorted.len(), 4);
+        assert_eq!(
+            SortedEvents::try_from_vec(events(&[0, 1, 3, 2, 4])).unwrap_err(),
+            UnsortedEvents { index: 3 }
+        );
+        assert!(SortedEvents::try_from(events(&[])).is_ok());
+
+        // Two data files appended in the wrong order
+        let mut appended = events(&[60, 61, 62]);
+        appended.extend(events(&[0, 1, 2]));
+        assert!(SortedEvents::try_from_vec(appended.clone()).is_err());
+        let sorted = SortedEvents::from_vec(appended);
+        let powers = sorted.iter().map(|e| e.power).collect::<Vec<_>>();
+        assert_eq!(powers, [0.0, 1.0, 2.0, 60.0, 61.0, 62.0]);
+    }
+
+    #[test]
+    fn sorted_range() {
+        let sorted = SortedEvents::from_vec(events(&[0, 1, 2, 10, 11, 20]));
+        let start = sorted[0].timestamp;
+        let range = sorted.range(start + Duration::minutes(1), start + Duration::minutes(11));
+        let powers = range.iter().map(|e| e.power).collect::<Vec<_>>();
+        assert_eq!(powers, [1.0, 2.0, 10.0]);
+        assert!(sorted.range(start + Duration::minutes(5), start).is_empty());
+    }
+
+    #[test]
+    fn sorted_validate_and_normalize() {
+        // Two hours shuffled, with minute 40 recorded twice (another reading the second time)
+        // and a blackout from minute 90 to 99
+        let mut minutes = (0..120)
+            .filter(|m| !(90..100).contains(m))
+            .collect::<Vec<_>>();
+        minutes.push(40);
+        let mut shuffled = events(&minutes);
+        shuffled.last_mut().unwrap().voltage = 231.0;
+        let n = shuffled.len();
+        for i in 0..n {
+            shuffled.swap(i, (i * 37 + 11) % n);
+        }
+        let issues = validate_sequence(&shuffled);
+        let duplicates = issues
+            .iter()
+            .filter_map(|issue| match issue {
+                SequenceIssue::Duplicate { index, timestamp } => Some((*index, *timestamp)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(duplicates.len(), 1);
+        let (index, timestamp) = duplicates[0];
+        assert_eq!(shuffled[index].timestamp, timestamp);
+        assert!(issues
+            .iter()
+            .any(|issue| matches!(issue, SequenceIssue::OutOfOrder { .. })));
+
+        let normalized = normalize(shuffled.clone());
+        assert_eq!(normalized.len(), 110);
+        assert!(validate_sequence(&normalized).is_empty());
+        assert!(SortedEvents::try_from_vec(normalized.clone()).is_ok());
+        // The first of the duplicates in the given order is kept
+        let first = shuffled
+            .iter()
+            .find(|pe| pe.timestamp == timestamp)
+            .unwrap();
+        assert_eq!(normalized[40], *first);
+        // The blackout is not an issue, a timestamp off the minute grid is
+        assert!(validate_sequence(&events(&[0, 1, 10, 11])).is_empty());
+        let mut off_grid = events(&[0, 1, 2]);
+        off_grid[2].timestamp += Duration::seconds(30);
+        assert_eq!(
+            validate_sequence(&off_grid),
+            [SequenceIssue::IrregularGap {
+                index: 2,
+                gap: Duration::seconds(90)
+            }]
+        );
+        // Power events sort chronologically
+        let mut reversed = events(&[2, 1, 0]);
+        reversed.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(reversed, events(&[0, 1, 2]));
+    }
+}
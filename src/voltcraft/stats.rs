@@ -1,13 +1,90 @@
-use crate::voltcraft::data::PowerEvent;
-use chrono::{Date, DateTime, Duration, Local};
+use crate::format::{format_duration, format_fixed, round_fixed};
+use crate::voltcraft::annotations::{Annotation, Annotations};
+use crate::voltcraft::bucket::{bucket_of, first_instant, Granularity};
+use crate::voltcraft::clean::{clean, ValidationRules};
+use crate::voltcraft::compare::{compare_stats, StatsComparison};
+use crate::voltcraft::cumulative::CumulativeEnergy;
+use crate::voltcraft::data::{sample_interval, PowerEvent, PowerLog};
+use crate::voltcraft::distribution::nearest_rank;
+use crate::voltcraft::error::VoltcraftError;
+use crate::voltcraft::presence::PresenceIndex;
+use crate::voltcraft::sorted::{validate_sequence, SequenceIssue};
+use crate::voltcraft::tariff::{BandDays, CostBreakdown, Tariff};
+use chrono::{
+    DateTime, Datelike, Days, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Timelike, Weekday,
+};
 use itertools::Itertools;
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
 
+/// Statistics over chronologically sorted power events (see `Dataset`, which sorts them).
+///
+/// Every function returning grouped results has a fixed order, whatever the history of the
+/// events: time buckets (days, weeks, windows, episodes, blackouts) are chronological and
+/// value bins are ascending, with ties broken by time. Groupings are built from ordered
+/// collections or explicit sorts, never from the iteration order of a hash map.
 pub struct VoltcraftStatistics<'a> {
-    power_data: &'a Vec<PowerEvent>,
+    power_data: Cow<'a, [PowerEvent]>,
+    boundary_days: BoundaryDays,
+    idle_threshold: Option<f64>,
+    numeric_mode: NumericMode,
+    average_basis: AverageBasis,
+    sample_interval: Duration,
 }
 
-#[derive(Debug, Copy, Clone)]
+// Default idle threshold for the active statistics (kW)
+pub const DEFAULT_IDLE_THRESHOLD: f64 = 0.01;
+
+// Default fraction of the power events at or below the standby baseline
+pub const DEFAULT_BASELINE_PERCENTILE: f64 = 0.05;
+
+/// Whether the first and last (usually partial) days of the data are analyzed by the per-day
+/// statistics (`daily_stats`), the voltage anomaly detection and the weekly quality report.
+/// Overall, blackout and rolling window statistics always use all the data.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum BoundaryDays {
+    #[default]
+    Include,
+    Exclude,
+    // Include a boundary day if its recorded activity covers more than this fraction of the day
+    IncludeIfCoverageAbove(f64),
+}
+
+/// How the statistics accumulate sums of power event values.
+///
+/// In `Reproducible` mode the overall, daily and active statistics are bit-identical across
+/// platforms (x86_64, aarch64, ...) for identical input:
+/// - sums are accumulated one event after the other in chronological order, never in parallel
+///   or as a tree reduction (the rayon feature only analyzes whole files or whole buckets of
+///   `stats_by` concurrently, and `ArchiveSummary::merge` adds the per-file results in the
+///   given order);
+/// - each sum is compensated (Neumaier), so it is also the closest to the exact sum;
+/// - the exporters format every number with `format::format_fixed`, which does not depend on
+///   the platform.
+///
+/// Timestamps are exported in the local time zone, which must be the same on both machines.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum NumericMode {
+    #[default]
+    Fast, // plain sequential sums
+    Reproducible,
+}
+
+/// Duration over which the average powers of the statistics are computed. With `Span`, the
+/// minutes missing from the recording (blackouts, skipped samples) count as zero power, so a
+/// day with a 6 hour outage shows a lower average than a fully recorded one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum AverageBasis {
+    #[default]
+    Recorded, // the recorded minutes only
+    Span, // every minute from the first to the last power event
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct PowerStats {
     pub total_active_power: f64,      // total active power (kWh)
     pub avg_active_power: f64,        // average active power (kW)
@@ -20,23 +97,201 @@ pub struct PowerStats {
     pub min_voltage: PowerEvent, // minimum voltage
     pub max_voltage: PowerEvent, // maximum voltage
     pub avg_voltage: f64,        // average voltage
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub p95_voltage: f64, // 95th percentile of the voltage (nearest rank)
+
+    pub min_current: PowerEvent, // minimum current
+    pub max_current: PowerEvent, // maximum current
+    pub avg_current: f64,        // average current (A)
 
+    pub avg_power_factor: f64, // average power factor of the samples, idle ones included
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub p95_power: f64, // 95th percentile of the active power (kW, nearest rank)
+    // Lowest power factor with current flowing (None if always idle)
+    pub worst_power_factor: Option<PowerEvent>,
+    // Power factor weighted by the apparent power, which leaves the idle minutes out: the
+    // ratio of the active to the apparent energy, as billed by utilities (0 if always idle)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub weighted_power_factor: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub total_reactive_energy: f64, // total reactive energy (kvarh)
+    // Lowest weighted power factor of the 15 minute windows recorded without a gap and under
+    // load throughout (None without any)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub worst_sustained_power_factor: Option<PowerFactorWindow>,
+
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::voltcraft::serde_helpers::duration_seconds")
+    )]
     pub total_duration: chrono::Duration, // total duration (in sec) of the interval for the current statistics
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub active: Option<ActiveStats>, // statistics of the non-idle samples, when requested
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub coverage: CoverageInfo, // minutes recorded out of the interval
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Minutes with a power event out of the minutes from the first to the last one. The totals
+/// only add up the recorded minutes, whatever the coverage.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct CoverageInfo {
+    pub recorded_minutes: i64, // minutes with a power event
+    pub expected_minutes: i64, // minutes from the first to the last power event, both included
+    pub coverage_ratio: f64,   // recorded out of expected minutes (0 to 1)
+}
+
+impl CoverageInfo {
+    // Coverage of the recorded seconds out of the expected ones, for sample intervals that are
+    // not whole minutes: the minutes are truncated, the ratio is not
+    pub(crate) fn from_seconds(recorded_seconds: i64, expected_seconds: i64) -> Self {
+        CoverageInfo {
+            coverage_ratio: CoverageInfo::new(recorded_seconds, expected_seconds).coverage_ratio,
+            ..CoverageInfo::new(recorded_seconds / 60, expected_seconds / 60)
+        }
+    }
+
+    pub fn new(recorded_minutes: i64, expected_minutes: i64) -> Self {
+        CoverageInfo {
+            recorded_minutes,
+            expected_minutes,
+            coverage_ratio: if expected_minutes > 0 {
+                recorded_minutes as f64 / expected_minutes as f64
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// Statistics restricted to the samples at or above the idle threshold.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ActiveStats {
+    pub idle_threshold: f64, // samples below this active power are idle (kW)
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::voltcraft::serde_helpers::duration_seconds")
+    )]
+    pub active_duration: chrono::Duration, // time spent at or above the threshold
+    pub active_energy: f64,  // active energy consumed while active (kWh)
+    pub avg_active_power: f64, // average active power while active (kW, 0 if never active)
+    pub active_fraction: f64, // fraction of the samples that are active (0 to 1)
+}
+
+/// Power factor over a window of power events (see `PowerStats::worst_sustained_power_factor`).
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct PowerFactorWindow {
+    pub start: DateTime<Local>, // first power event of the window
+    pub power_factor: f64,      // weighted by the apparent power
+}
+
+/// Number of decimals kept for each class of value by `PowerStats::rounded`.
+///
+/// Values are rounded half away from zero (0.125 kWh becomes 0.13 kWh with two decimals),
+/// starting from their shortest decimal representation, as done by `format::format_fixed`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PrecisionPolicy {
+    pub energy: usize,       // kWh and kVAh
+    pub power: usize,        // kW and kVA
+    pub voltage: usize,      // volts
+    pub power_factor: usize, // cos phi
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PowerBlackout {
     pub timestamp: chrono::DateTime<Local>, // start of blackout
-    pub duration: chrono::Duration,         // duration
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::voltcraft::serde_helpers::duration_seconds")
+    )]
+    pub duration: chrono::Duration, // duration
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct RollingWindow {
+    pub start: chrono::DateTime<Local>, // start of the window
+    pub end: chrono::DateTime<Local>,   // end of the window (exclusive)
+    pub avg_power: f64,                 // average active power over the samples present (kW)
+    pub sample_count: usize,            // number of samples present in the window
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DemandPeak {
+    pub start: chrono::DateTime<Local>, // start of the window
+    pub end: chrono::DateTime<Local>,   // end of the window (exclusive)
+    pub avg_power: f64,                 // average active power (kW)
+    pub min_voltage: f64,               // lowest voltage within the window
+    pub avg_power_factor: f64,          // average power factor within the window
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ConsumptionWindow {
+    pub start: chrono::DateTime<Local>, // start of the window
+    pub end: chrono::DateTime<Local>,   // end of the window (exclusive)
+    pub energy: f64,                    // active energy consumed within the window (kWh)
+    pub peak_power: PowerEvent,         // maximum active power within the window
+}
+
+/// Appliance activity: the active power above a threshold, over brief dips.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ActivityPeriod {
+    pub start: chrono::DateTime<Local>, // first minute above the threshold
+    pub end: chrono::DateTime<Local>,   // end of the last minute above the threshold (exclusive)
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::voltcraft::serde_helpers::duration_seconds")
+    )]
+    pub duration: chrono::Duration, // end - start, dips included
+    pub energy: f64,                    // active energy consumed from start to end (kWh)
+    pub peak_power: PowerEvent,         // maximum active power
+    pub open: bool, // still active when the data ends, so the period may last longer
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SustainedRun {
+    pub start: chrono::DateTime<Local>, // first minute of the run
+    pub duration: chrono::Duration,     // duration
+    pub avg_power: f64,                 // average active power during the run (kW)
 }
 
 #[derive(Debug)]
-pub struct DailyPowerInfo {
-    pub date: Date<Local>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct PowerInterval {
+    pub date: NaiveDate, // first day of the bucket, on the local wall clock
+    pub stats: PowerStats,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub annotations: Vec<Annotation>, // user notes overlapping the day
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub granularity: Granularity, // length of the bucket starting on the date
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub day_start: u32, // hour of the wall clock at which the bucket starts (0 to 23)
+}
+
+/// Statistics of an hour of the local wall clock (see bucket::bucket_of).
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct PowerHourInterval {
+    pub date: NaiveDate,
+    pub hour: u32, // 0 to 23
     pub stats: PowerStats,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct OverallPowerInfo {
     pub start: DateTime<Local>,
     pub end: DateTime<Local>,
@@ -45,34 +300,781 @@ pub struct OverallPowerInfo {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct BlackoutInfo {
     pub blackout_count: usize,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::voltcraft::serde_helpers::duration_seconds")
+    )]
     pub total_blackout_duration: chrono::Duration,
     pub blackouts: Vec<PowerBlackout>,
 }
 
+/// Average load at an hour of the day over all the recorded days (see `load_profile`).
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct HourProfile {
+    pub hour: u32,                       // from 0 to 23, local time
+    pub sample_count: usize,             // power events recorded at that hour
+    pub avg_power: Option<f64>,          // average active power (kW), None without samples
+    pub avg_apparent_power: Option<f64>, // average apparent power (kVA), None without samples
+    pub peak_power: Option<PowerEvent>,  // maximum active power
+}
+
+/// Totals and reliability figures of the blackouts between power events.
+#[derive(Debug, Copy, Clone)]
+#[non_exhaustive]
+pub struct BlackoutSummary {
+    pub count: usize,
+    pub total_duration: chrono::Duration,
+    pub longest: Option<PowerBlackout>, // the earliest one if several last as long
+    pub mean_time_between: Option<chrono::Duration>, // recorded time per blackout
+    pub availability: f64, // recorded minutes over recorded and blackout minutes (percent)
+}
+
+/// Standby (phantom) load, the power drawn when nothing is on, estimated as a low percentile of
+/// the active power of the recorded power events. Missing minutes are not counted as zero.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct BaselineStats {
+    pub percentile: f64, // fraction of the power events at or below the baseline (0 to 1)
+    pub power: f64,      // baseline active power (kW)
+    pub daily_energy: f64, // energy the baseline consumes in 24 hours (kWh)
+    pub share: f64,      // baseline energy over the recorded time out of the total (0 to 1)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AnomalyKind {
+    Sag,   // voltage below the low threshold
+    Swell, // voltage above the high threshold
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct VoltageAnomaly {
+    pub timestamp: chrono::DateTime<Local>, // start of the episode
+    pub duration: chrono::Duration,         // duration
+    pub kind: AnomalyKind,                  // sag or swell
+    pub extreme_voltage: f64,               // lowest (sag) or highest (swell) voltage reached
+}
+
+#[derive(Debug, Copy, Clone)]
+#[non_exhaustive]
+pub struct QualityThresholds {
+    pub nominal_voltage: f64, // volts
+    pub low_voltage: f64,     // sag below this voltage
+    pub high_voltage: f64,    // swell above this voltage
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DeltaBucket {
+    Hour(u32),        // hour of the day (0 to 23)
+    Weekday(Weekday), // day of the week
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BucketDelta {
+    pub bucket: DeltaBucket,
+    pub energy_a: f64, // energy consumed in the bucket during the first period (kWh)
+    pub energy_b: f64, // energy consumed in the bucket during the second period (kWh)
+    pub delta: f64,    // energy_b - energy_a (kWh)
+    pub share: Option<f64>, // fraction of the delta of all complete buckets (None if incomplete)
+    pub incomplete: bool, // no data for the bucket in one of the periods
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct DeltaAttribution {
+    pub energy_a: f64,              // total energy of the first period (kWh)
+    pub energy_b: f64,              // total energy of the second period (kWh)
+    pub hours: Vec<BucketDelta>,    // by hour of the day
+    pub weekdays: Vec<BucketDelta>, // by day of the week
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Streak {
+    pub start: NaiveDate,   // first day
+    pub end: NaiveDate,     // last day (inclusive)
+    pub days: usize,        // days with data in the streak
+    pub under_target: bool, // daily energy at or under the target, or above it
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StreakReport {
+    pub longest_under: Option<Streak>, // longest run of days at or under the target
+    pub longest_over: Option<Streak>,  // longest run of days above the target
+    pub current: Option<Streak>,       // run ending with the last day of data
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CurrentBin {
+    pub min_current: f64, // lowest current of the samples in the bin (A)
+    pub max_current: f64, // highest current of the samples in the bin (A)
+    pub avg_voltage: f64, // average voltage of the samples in the bin (V)
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct CorrelationReport {
+    pub samples: usize,
+    pub correlation: f64, // Pearson coefficient between current and voltage, 0 if either is constant
+    pub terciles: Vec<CurrentBin>, // samples split by increasing current into low, medium and high thirds
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct PacingReport {
+    pub as_of: NaiveDate,
+    pub covered: Option<(NaiveDate, NaiveDate)>, // days paced, from the first to the last (inclusive)
+    pub partial: bool,    // the data does not cover the whole year up to as_of
+    pub actual_kwh: f64,  // energy consumed during the covered days
+    pub target_kwh: f64,  // share of the annual target for the covered days
+    pub surplus_kwh: f64, // target_kwh - actual_kwh, negative when over budget
+    pub surplus_days: Option<f64>, // surplus in days of average consumption (None without consumption)
+}
+
+/// Options of `VoltcraftStatistics::detect_away_periods`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct AwayDetectorOptions {
+    pub min_days: usize,          // shortest period reported (days)
+    pub baseline_percentile: f64, // fraction of the samples at or below the standby baseline (0 to 1)
+    pub energy_factor: f64, // a day is unoccupied if its energy is at most the standby energy times this
+    pub spike_factor: f64,  // samples above the baseline times this are appliance cycles
+    pub max_spike_minutes: usize, // appliance cycle minutes tolerated per day (e.g. fridge defrost)
+}
+
+/// Options of `VoltcraftStatistics::blackout_stats_with`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BlackoutOptions {
+    pub min_gap: Duration, // a blackout is a gap between two power events longer than this
+    pub min_duration: Duration, // shorter blackouts are left out of the report
+    pub split_at_midnight: bool, // blackouts running over midnight are reported once per day
+}
+
+/// Consecutive days consuming about the standby baseline around the clock.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AwayPeriod {
+    pub start: NaiveDate,   // first day
+    pub end: NaiveDate,     // last day (inclusive)
+    pub avg_daily_kwh: f64, // average energy of the days of the period
+    pub savings_kwh: f64,   // energy below the typical day over the period
+}
+
+/// Battery of a peak shaving simulation, without losses. It starts fully charged.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct BatterySpec {
+    pub capacity_kwh: f64,
+    pub max_discharge_kw: f64,
+    pub max_charge_kw: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ShavingReport {
+    pub violations: usize, // episodes of consecutive minutes above the cap despite the battery
+    pub violation_minutes: usize, // minutes above the cap despite the battery
+    pub max_excess_kw: f64, // highest power above the cap despite the battery (kW)
+    pub shifted_kwh: f64,  // energy supplied by the battery
+    pub equivalent_full_cycles: f64, // shifted energy over the battery capacity
+}
+
+/// Generation curve of a solar what-if simulation.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum SolarProfile {
+    // Half a sine wave between sunrise and sunset (hours of the local day, January first)
+    ClearSky {
+        peak_kw: f64,
+        daylight: [(f64, f64); 12],
+    },
+    // Average generation (kW) for each hour of the day, midnight first
+    Hourly([f64; 24]),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct SolarMonth {
+    pub year: i32,
+    pub month: u32,
+    pub consumption_kwh: f64,
+    pub generation_kwh: f64,
+    pub self_consumed_kwh: f64, // generation used by the load, by which the grid import drops
+    pub surplus_kwh: f64,       // generation exceeding the load, exported
+    pub estimated_minutes: usize, // minutes without a power event, given the average load of their day
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct SolarReport {
+    pub months: Vec<SolarMonth>, // in chronological order
+    pub self_consumed_kwh: f64,
+    pub surplus_kwh: f64,
+    pub import_reduction: f64, // fraction of the consumption covered by the generation (0 to 1)
+}
+
+/// Logger energy over a period against an external reading (e.g. the utility meter).
+///
+/// The Display output labels the gap energy as an estimate.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Reconciliation {
+    pub from: DateTime<Local>,            // start of the period
+    pub to: DateTime<Local>,              // end of the period (exclusive)
+    pub external_kwh: f64,                // reading to reconcile with
+    pub logger_kwh: f64,                  // energy of the power events in the period
+    pub discrepancy_kwh: f64,             // external_kwh - logger_kwh
+    pub discrepancy_percent: Option<f64>, // discrepancy relative to external_kwh (None if 0)
+    pub coverage: f64, // minutes with a power event over the minutes of the period (0 to 1)
+    pub estimated_gap_kwh: f64, // estimate: minutes without a power event × average power
+    pub unexplained_kwh: f64, // discrepancy_kwh - estimated_gap_kwh
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct WeeklyQuality {
+    pub year: i32,                         // ISO year
+    pub week: u32,                         // ISO week number
+    pub start: NaiveDate,                  // Monday of the week
+    pub sags: usize,                       // voltage sags starting during the week
+    pub swells: usize,                     // voltage swells starting during the week
+    pub blackouts: usize,                  // blackouts starting during the week
+    pub outage_duration: chrono::Duration, // total duration of these blackouts
+    pub worst_voltage: Option<PowerEvent>, // voltage farthest from nominal (None if no data)
+}
+
+impl PrecisionPolicy {
+    pub fn new(energy: usize, power: usize, voltage: usize, power_factor: usize) -> Self {
+        PrecisionPolicy {
+            energy,
+            power,
+            voltage,
+            power_factor,
+        }
+    }
+}
+
+impl Default for PrecisionPolicy {
+    // Precision used by the reports
+    fn default() -> Self {
+        PrecisionPolicy {
+            energy: 2,
+            power: 2,
+            voltage: 1,
+            power_factor: 2,
+        }
+    }
+}
+
+impl PowerStats {
+    // Statistics of the given power events, e.g. imported by an adapter (None for no events)
+    pub fn from_events(power_events: &[PowerEvent]) -> Option<PowerStats> {
+        (!power_events.is_empty()).then(|| {
+            VoltcraftStatistics::compute_stats(
+                power_events,
+                NumericMode::Fast,
+                Duration::minutes(1),
+            )
+        })
+    }
+
+    /// Copy of the statistics with every value rounded according to the policy.
+    /// The statistics themselves are always computed at full precision.
+    pub fn rounded(&self, policy: &PrecisionPolicy) -> PowerStats {
+        let event = |pe: &PowerEvent| PowerEvent {
+            voltage: round_fixed(pe.voltage, policy.voltage),
+            power_factor: round_fixed(pe.power_factor, policy.power_factor),
+            power: round_fixed(pe.power, policy.power),
+            apparent_power: round_fixed(pe.apparent_power, policy.power),
+            ..*pe
+        };
+        PowerStats {
+            total_active_power: round_fixed(self.total_active_power, policy.energy),
+            avg_active_power: round_fixed(self.avg_active_power, policy.power),
+            max_active_power: event(&self.max_active_power),
+            total_apparent_power: round_fixed(self.total_apparent_power, policy.energy),
+            avg_apparent_power: round_fixed(self.avg_apparent_power, policy.power),
+            max_apparent_power: event(&self.max_apparent_power),
+            min_voltage: event(&self.min_voltage),
+            max_voltage: event(&self.max_voltage),
+            avg_voltage: round_fixed(self.avg_voltage, policy.voltage),
+            p95_voltage: round_fixed(self.p95_voltage, policy.voltage),
+            min_current: event(&self.min_current),
+            max_current: event(&self.max_current),
+            avg_current: self.avg_current, // no decimals set for currents
+            avg_power_factor: round_fixed(self.avg_power_factor, policy.power_factor),
+            p95_power: round_fixed(self.p95_power, policy.power),
+            worst_power_factor: self.worst_power_factor.as_ref().map(event),
+            weighted_power_factor: round_fixed(self.weighted_power_factor, policy.power_factor),
+            total_reactive_energy: round_fixed(self.total_reactive_energy, policy.energy),
+            worst_sustained_power_factor: self.worst_sustained_power_factor.map(|w| {
+                PowerFactorWindow {
+                    power_factor: round_fixed(w.power_factor, policy.power_factor),
+                    ..w
+                }
+            }),
+            total_duration: self.total_duration,
+            active: self.active.map(|a| ActiveStats {
+                active_energy: round_fixed(a.active_energy, policy.energy),
+                avg_active_power: round_fixed(a.avg_active_power, policy.power),
+                ..a
+            }),
+            coverage: self.coverage,
+        }
+    }
+}
+
+impl QualityThresholds {
+    pub fn new(nominal_voltage: f64, low_voltage: f64, high_voltage: f64) -> Self {
+        QualityThresholds {
+            nominal_voltage,
+            low_voltage,
+            high_voltage,
+        }
+    }
+
+    // Sags and swells beyond the tolerance (percent) of the nominal voltage, e.g. 230V ±10%
+    pub fn around(nominal_voltage: f64, tolerance_percent: f64) -> Self {
+        let deviation = nominal_voltage * tolerance_percent / 100.0;
+        QualityThresholds {
+            nominal_voltage,
+            low_voltage: nominal_voltage - deviation,
+            high_voltage: nominal_voltage + deviation,
+        }
+    }
+}
+
+impl PowerInterval {
+    // Statistics of a day, without annotations
+    pub fn new(date: NaiveDate, stats: PowerStats) -> Self {
+        PowerInterval {
+            date,
+            stats,
+            annotations: Vec::new(),
+            granularity: Granularity::Day,
+            day_start: 0,
+        }
+    }
+
+    // First instant of the bucket: local midnight of the date (or the day start hour), or the
+    // first minute after it when daylight saving time starts then in the local time zone
+    pub fn start(&self) -> DateTime<Local> {
+        let time = NaiveTime::from_hms_opt(self.day_start, 0, 0).unwrap_or(NaiveTime::MIN);
+        first_instant(&Local, self.date.and_time(time)).unwrap()
+    }
+
+    // Local wall clock end of the bucket (excluded), None for an hour
+    pub fn end(&self) -> Option<NaiveDate> {
+        let date = self.date;
+        match self.granularity {
+            Granularity::Hour => None,
+            Granularity::Day => date.succ_opt(),
+            Granularity::Week => date.checked_add_days(Days::new(7)),
+            Granularity::Month => date.checked_add_months(Months::new(1)),
+        }
+    }
+}
+
+impl PowerBlackout {
+    /// The duration as a `std::time::Duration`, which cannot be negative. Blackouts found by
+    /// the statistics always last at least a minute; a negative duration (only possible for a
+    /// hand-built blackout) saturates to zero.
+    pub fn duration_std(&self) -> std::time::Duration {
+        self.duration.to_std().unwrap_or(std::time::Duration::ZERO)
+    }
+
+    // Whole minutes of the duration, as exported to CSV and shown in the reports
+    pub fn duration_minutes(&self) -> i64 {
+        self.duration.num_minutes()
+    }
+}
+
+impl fmt::Display for PowerBlackout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({})",
+            self.timestamp.format("%Y-%m-%d %H:%M"),
+            format_duration(self.duration)
+        )
+    }
+}
+
+impl BlackoutInfo {
+    // Blackout totals of the given blackouts
+    pub fn new(blackouts: Vec<PowerBlackout>) -> Self {
+        BlackoutInfo {
+            blackout_count: blackouts.len(),
+            total_blackout_duration: blackouts
+                .iter()
+                .fold(Duration::zero(), |sum, x| sum + x.duration),
+            blackouts,
+        }
+    }
+}
+
+impl BlackoutSummary {
+    // Summary of the blackouts found between the given number of recorded minutes. Without
+    // any minute recorded or missing the availability is 100%.
+    pub fn new(blackouts: &[PowerBlackout], recorded_minutes: usize) -> Self {
+        let total_duration = blackouts
+            .iter()
+            .fold(Duration::zero(), |sum, b| sum + b.duration);
+        let recorded = Duration::minutes(recorded_minutes as i64);
+        let span_minutes = (recorded + total_duration).num_minutes();
+        BlackoutSummary {
+            count: blackouts.len(),
+            total_duration,
+            longest: blackouts
+                .iter()
+                .copied()
+                .reduce(|a, b| if b.duration > a.duration { b } else { a }),
+            mean_time_between: (!blackouts.is_empty()).then(|| recorded / blackouts.len() as i32),
+            availability: if span_minutes > 0 {
+                recorded_minutes as f64 * 100.0 / span_minutes as f64
+            } else {
+                100.0
+            },
+        }
+    }
+}
+
+impl fmt::Display for Reconciliation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kwh = |v: f64| format_fixed(v, 2);
+        writeln!(
+            f,
+            "Period: {} - {}",
+            self.from.format("%Y-%m-%d %H:%M"),
+            self.to.format("%Y-%m-%d %H:%M")
+        )?;
+        writeln!(f, "External reading: {} kWh", kwh(self.external_kwh))?;
+        writeln!(f, "Logger total: {} kWh", kwh(self.logger_kwh))?;
+        match self.discrepancy_percent {
+            Some(percent) => writeln!(
+                f,
+                "Discrepancy: {} kWh ({}%)",
+                kwh(self.discrepancy_kwh),
+                format_fixed(percent, 1)
+            )?,
+            None => writeln!(f, "Discrepancy: {} kWh", kwh(self.discrepancy_kwh))?,
+        }
+        writeln!(f, "Coverage: {}%", format_fixed(self.coverage * 100.0, 1))?;
+        writeln!(
+            f,
+            "Estimated energy during gaps: {} kWh (estimate: gap duration × average power)",
+            kwh(self.estimated_gap_kwh)
+        )?;
+        write!(f, "Unexplained: {} kWh", kwh(self.unexplained_kwh))
+    }
+}
+
+impl BatterySpec {
+    pub fn new(capacity_kwh: f64, max_discharge_kw: f64, max_charge_kw: f64) -> Self {
+        BatterySpec {
+            capacity_kwh,
+            max_discharge_kw,
+            max_charge_kw,
+        }
+    }
+}
+
+impl AwayDetectorOptions {
+    pub fn with_min_days(mut self, min_days: usize) -> Self {
+        self.min_days = min_days;
+        self
+    }
+
+    pub fn with_baseline_percentile(mut self, baseline_percentile: f64) -> Self {
+        self.baseline_percentile = baseline_percentile;
+        self
+    }
+
+    pub fn with_energy_factor(mut self, energy_factor: f64) -> Self {
+        self.energy_factor = energy_factor;
+        self
+    }
+
+    pub fn with_spike_factor(mut self, spike_factor: f64) -> Self {
+        self.spike_factor = spike_factor;
+        self
+    }
+
+    pub fn with_max_spike_minutes(mut self, max_spike_minutes: usize) -> Self {
+        self.max_spike_minutes = max_spike_minutes;
+        self
+    }
+}
+
+impl Default for AwayDetectorOptions {
+    fn default() -> Self {
+        AwayDetectorOptions {
+            min_days: 2,
+            baseline_percentile: 0.1,
+            energy_factor: 1.5,
+            spike_factor: 3.0,
+            max_spike_minutes: 60,
+        }
+    }
+}
+
+impl BlackoutOptions {
+    pub fn with_min_gap(mut self, min_gap: Duration) -> Self {
+        self.min_gap = min_gap;
+        self
+    }
+
+    pub fn with_min_duration(mut self, min_duration: Duration) -> Self {
+        self.min_duration = min_duration;
+        self
+    }
+
+    pub fn with_split_at_midnight(mut self, split_at_midnight: bool) -> Self {
+        self.split_at_midnight = split_at_midnight;
+        self
+    }
+}
+
+impl Default for BlackoutOptions {
+    // A single missing one minute sample (e.g. an SD card write delay) is not a blackout
+    fn default() -> Self {
+        BlackoutOptions {
+            min_gap: Duration::minutes(2),
+            min_duration: Duration::zero(),
+            split_at_midnight: false,
+        }
+    }
+}
+
+impl Default for QualityThresholds {
+    // 230V ±10%
+    fn default() -> Self {
+        QualityThresholds {
+            nominal_voltage: 230.0,
+            low_voltage: 207.0,
+            high_voltage: 253.0,
+        }
+    }
+}
+
 impl<'a> VoltcraftStatistics<'a> {
-    pub fn new(power_data: &mut Vec<PowerEvent>) -> VoltcraftStatistics {
-        VoltcraftStatistics { power_data }
+    pub fn new(power_data: &'a [PowerEvent]) -> VoltcraftStatistics<'a> {
+        VoltcraftStatistics {
+            power_data: Cow::Borrowed(power_data),
+            boundary_days: BoundaryDays::Include,
+            idle_threshold: None,
+            numeric_mode: NumericMode::Fast,
+            average_basis: AverageBasis::Recorded,
+            sample_interval: Duration::minutes(1),
+        }
     }
 
-    pub fn daily_stats(&self) -> Vec<DailyPowerInfo> {
-        // First we need the individual days in the interval
-        let days = self.distinct_days();
-        days.into_iter()
-            .map(|d| (d, self.filter_power_data(&d))) // Filter the power items corresponding to the current date
-            .map(|(d, e)| (d, VoltcraftStatistics::compute_stats(&e))) // Compute statistics on the filtered power items
-            .map(|(d, r)| DailyPowerInfo { date: d, stats: r }) // And finally build a structure to hold both the date and computed statistics
-            .collect::<Vec<_>>()
+    // Statistics of the power events of a log, recorded at its interval (InvalidSampleInterval
+    // unless a whole number of seconds)
+    pub fn from_log(log: &'a PowerLog) -> Result<VoltcraftStatistics<'a>, VoltcraftError> {
+        VoltcraftStatistics::new(&log.events).with_sample_interval(log.interval)
+    }
+
+    // Duplicated, out of order or misaligned power events, which the statistics do not expect
+    // (see sorted::normalize)
+    pub fn sequence_issues(&self) -> Vec<SequenceIssue> {
+        validate_sequence(&self.power_data)
     }
 
-    pub fn overall_stats(&self) -> OverallPowerInfo {
+    // Interval between two power events of the logger (one minute by default, otherwise a
+    // whole number of seconds, InvalidSampleInterval if not), for the energy totals, costs,
+    // durations and coverage of the power and active statistics, the blackout detection, the
+    // runs of consecutive power events, the voltage anomalies and the trailing windows. The
+    // other analyses expect a power event per minute.
+    pub fn with_sample_interval(
+        mut self,
+        interval: Duration,
+    ) -> Result<VoltcraftStatistics<'a>, VoltcraftError> {
+        self.sample_interval = sample_interval(interval)?;
+        Ok(self)
+    }
+
+    // Also compute the statistics of the samples at or above the idle threshold (kW),
+    // in the `active` field of the overall and daily statistics
+    pub fn with_active_stats(mut self, idle_threshold: f64) -> Self {
+        self.idle_threshold = Some(idle_threshold);
+        self
+    }
+
+    pub fn with_boundary_days(mut self, boundary_days: BoundaryDays) -> Self {
+        self.boundary_days = boundary_days;
+        self
+    }
+
+    // See NumericMode::Reproducible for identical results across platforms
+    pub fn with_numeric_mode(mut self, numeric_mode: NumericMode) -> Self {
+        self.numeric_mode = numeric_mode;
+        self
+    }
+
+    // Analyze only the power events without any flag of the rules (see clean::flag_events),
+    // as if the data had been cleaned with clean::clean: the flagged minutes count as missing
+    pub fn with_validation(mut self, rules: &ValidationRules) -> Self {
+        self.power_data = Cow::Owned(clean(&self.power_data, rules));
+        self
+    }
+
+    // See AverageBasis::Span to count the missing minutes as zero power in the averages
+    pub fn with_average_basis(mut self, average_basis: AverageBasis) -> Self {
+        self.average_basis = average_basis;
+        self
+    }
+
+    // Number of power events analyzed
+    pub fn event_count(&self) -> usize {
+        self.power_data.len()
+    }
+
+    // Statistics of each day with data, in chronological order. A day runs from 00:00 included
+    // to the next 00:00 excluded (see bucket::bucket_of)
+    pub fn daily_stats(&self) -> Vec<PowerInterval> {
+        self.stats_by(Granularity::Day)
+    }
+
+    // Statistics of each bucket with data (see bucket::bucket_of), in chronological order. The
+    // first and last buckets hold the power events recorded in them, less the boundary days
+    // when excluded. Hours are only told apart by hourly_stats. The power events are grouped
+    // in a single pass; with the rayon feature, the buckets are then analyzed concurrently.
+    pub fn stats_by(&self, granularity: Granularity) -> Vec<PowerInterval> {
+        let mut buckets = BTreeMap::<_, Vec<PowerEvent>>::new();
+        for pe in self.analyzed_data() {
+            buckets
+                .entry(bucket_of(pe.timestamp, granularity))
+                .or_default()
+                .push(*pe);
+        }
+        let interval = |(start, events): (NaiveDateTime, Vec<PowerEvent>)| PowerInterval {
+            date: start.date(),
+            stats: self.stats_of(&events),
+            annotations: Vec::new(),
+            granularity,
+            day_start: 0,
+        };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            buckets.into_par_iter().map(interval).collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        buckets.into_iter().map(interval).collect()
+    }
+
+    // Daily statistics with days starting at the given hour of the local wall clock (0 to 23,
+    // taken modulo 24), e.g. 6 for days from 06:00 included to the next 06:00 excluded. Each
+    // day is dated with the calendar day on which it starts, so the minutes after midnight
+    // count toward the previous date. Every power event is in exactly one day: with the
+    // boundary days included, the days add up to the overall statistics.
+    pub fn daily_stats_with_day_start(&self, hour: u32) -> Vec<PowerInterval> {
+        let hour = hour % 24;
+        let day_of = |t: &DateTime<Local>| (t.naive_local() - Duration::hours(hour as i64)).date();
+        self.analyzed_data_by(day_of)
+            .iter()
+            .group_by(|pe| day_of(&pe.timestamp))
+            .into_iter()
+            .map(|(day, events)| {
+                let events = events.cloned().collect::<Vec<_>>();
+                PowerInterval {
+                    day_start: hour,
+                    ..PowerInterval::new(day, self.stats_of(&events))
+                }
+            })
+            .collect()
+    }
+
+    /// Daily statistics with the days of another time zone than the local one, e.g.
+    /// `chrono::Utc` or a zone of the `chrono-tz` crate. The power events are instants, so the
+    /// same events can be grouped for any zone; days of a daylight saving time change last 23
+    /// or 25 hours of that zone. Each interval is dated with the calendar day of the zone.
+    pub fn daily_stats_in<Tz: TimeZone>(&self, tz: &Tz) -> Vec<PowerInterval> {
+        let day_of = |t: &DateTime<Local>| t.with_timezone(tz).date_naive();
+        self.analyzed_data_by(day_of)
+            .iter()
+            .group_by(|pe| day_of(&pe.timestamp))
+            .into_iter()
+            .map(|(day, events)| {
+                let events = events.cloned().collect::<Vec<_>>();
+                PowerInterval::new(day, self.stats_of(&events))
+            })
+            .collect()
+    }
+
+    // Statistics of each hour with power events, in chronological order. The first and last
+    // hours cover the minutes recorded, whatever the boundary days setting.
+    pub fn hourly_stats(&self) -> Vec<PowerHourInterval> {
+        self.power_data
+            .iter()
+            .group_by(|pe| bucket_of(pe.timestamp, Granularity::Hour))
+            .into_iter()
+            .map(|(start, events)| {
+                let events = events.cloned().collect::<Vec<_>>();
+                PowerHourInterval {
+                    date: start.date(),
+                    hour: start.hour(),
+                    stats: self.stats_of(&events),
+                }
+            })
+            .collect()
+    }
+
+    // Cost of all the power events with the tariff
+    pub fn cost(&self, tariff: &Tariff) -> CostBreakdown {
+        tariff.cost(&self.power_data, self.sample_interval)
+    }
+
+    // Cost of each day with power events, in chronological order, whatever the boundary days
+    // setting (the daily costs add up to the total cost)
+    pub fn daily_cost(&self, tariff: &Tariff) -> Vec<(NaiveDate, CostBreakdown)> {
+        self.power_data
+            .iter()
+            .group_by(|pe| bucket_of(pe.timestamp, Granularity::Day).date())
+            .into_iter()
+            .map(|(day, events)| {
+                let events = events.cloned().collect::<Vec<_>>();
+                (day, tariff.cost(&events, self.sample_interval))
+            })
+            .collect()
+    }
+
+    // Daily statistics, each day carrying the annotations overlapping it
+    pub fn daily_stats_with_annotations(&self, annotations: &Annotations) -> Vec<PowerInterval> {
+        let mut days = self.daily_stats();
+        for day in days.iter_mut() {
+            let start = day.start();
+            let end = first_instant(
+                &Local,
+                day.date.succ_opt().unwrap().and_time(NaiveTime::MIN),
+            )
+            .unwrap();
+            day.annotations = annotations
+                .overlapping(start, end)
+                .into_iter()
+                .cloned()
+                .collect();
+        }
+        days
+    }
+
+    // Statistics of all the power events, None without any
+    pub fn overall_stats(&self) -> Option<OverallPowerInfo> {
+        let (first, last) = (self.power_data.first()?, self.power_data.last()?);
         let mut avg_daily_power_consumption = Option::None;
-        let power_stats = VoltcraftStatistics::compute_stats(self.power_data);
+        let power_stats = self.stats_of(&self.power_data);
 
         // Compute the start and end of the power data
-        let start = self.power_data.first().unwrap().timestamp;
-        let end = self.power_data.last().unwrap().timestamp;
+        let start = first.timestamp;
+        let end = last.timestamp;
         // Determine the average daily consumption
         let total_duration = end - start;
         if total_duration >= Duration::days(1) {
@@ -81,66 +1083,1095 @@ impl<'a> VoltcraftStatistics<'a> {
                 power_stats.total_active_power / (total_duration.num_seconds() as f64 / 86400.0),
             );
         }
-        OverallPowerInfo {
+        Some(OverallPowerInfo {
             start,
             end,
             stats: power_stats,
             avg_daily_power_consumption,
-        }
+        })
     }
 
-    pub fn blackout_stats(&self) -> BlackoutInfo {
-        let blackouts = &VoltcraftStatistics::compute_blackouts(self.power_data);
-        let blackout_count = blackouts.len();
-        let total_blackout_duration = blackouts
-            .iter()
-            .fold(Duration::zero(), |sum, x| sum + x.duration);
-        BlackoutInfo {
-            blackout_count,
-            total_blackout_duration,
-            blackouts: blackouts.to_vec(),
-        }
+    // Comparison of the overall statistics of another recording period against these ones
+    // (see compare_stats), None when either has no power events
+    pub fn compare(&self, other: &VoltcraftStatistics) -> Option<StatsComparison> {
+        let (before, after) = (self.overall_stats()?, other.overall_stats()?);
+        Some(compare_stats(&before.stats, &after.stats))
     }
 
-    fn distinct_days(&self) -> Vec<Date<Local>> {
-        let mut days = self
-            .power_data
-            .iter()
-            .map(|d| d.timestamp.date())
-            .collect::<HashSet<_>>()
-            .into_iter()
-            .collect::<Vec<_>>();
-        days.sort();
-        days
+    // Running total of the active energy up to each power event, ending at the total active
+    // energy of the overall statistics (see CumulativeEnergy)
+    pub fn cumulative_energy(&self) -> CumulativeEnergy {
+        CumulativeEnergy::new(&self.power_data, self.sample_interval)
     }
 
-    fn filter_power_data(&self, day: &Date<Local>) -> Vec<PowerEvent> {
-        let filtered_data = self
-            .power_data
-            .iter()
-            .filter(|d| *day == d.timestamp.date())
-            .cloned()
-            .collect::<Vec<_>>();
-        filtered_data
+    // Blackouts in chronological order: every gap longer than the sample interval
+    pub fn blackout_stats(&self) -> BlackoutInfo {
+        BlackoutInfo::new(VoltcraftStatistics::compute_blackouts(
+            &self.power_data,
+            self.sample_interval,
+        ))
     }
 
-    // Compute power stats on the given power events
-    fn compute_stats(power_items: &[PowerEvent]) -> PowerStats {
-        // Total active power (in kWh) = (sum of instantaneous powers) / 60
-        let power_sum = power_items.iter().fold(0f64, |sum, x| sum + x.power);
-        let total_active_power = power_sum / 60f64; // Total active power consumption (kWh)
-        let avg_active_power = power_sum / power_items.len() as f64; // Average power (kW)
-        let max_active_power = power_items
-            .iter()
-            .max_by(|a, b| a.power.partial_cmp(&b.power).unwrap())
-            .unwrap(); // Maximum active power (kW)
-
-        // Total apparent power (in kVAh) = (sum of instantaneous apparent powers) / 60
-        let apparent_power_sum = power_items
-            .iter()
-            .fold(0f64, |sum, x| sum + x.apparent_power);
-        let total_apparent_power = apparent_power_sum / 60f64; // Total apparent power consumption (kVAh)
-        let avg_apparent_power = apparent_power_sum / power_items.len() as f64; // Average power (kVA)
+    // Blackouts in chronological order, from the gaps longer than the minimum gap. A blackout
+    // still starts a sample interval after the last power event before the gap. Blackouts
+    // shorter than the minimum duration are dropped before being split at midnight, so that
+    // the end of a long blackout is kept on the next day however short it is there.
+    pub fn blackout_stats_with(&self, options: BlackoutOptions) -> BlackoutInfo {
+        let interval = self.sample_interval;
+        let next_midnight = |t: DateTime<Local>| {
+            first_instant(&Local, t.date_naive().succ_opt()?.and_time(NaiveTime::MIN))
+        };
+        let mut blackouts = Vec::new();
+        for (pe1, pe2) in self.power_data.iter().tuple_windows() {
+            let gap = pe2.timestamp - pe1.timestamp;
+            if gap <= options.min_gap.max(interval) || gap - interval < options.min_duration {
+                continue;
+            }
+            let (mut timestamp, end) = (pe1.timestamp + interval, pe2.timestamp);
+            while let Some(midnight) =
+                next_midnight(timestamp).filter(|&m| options.split_at_midnight && m < end)
+            {
+                blackouts.push(PowerBlackout {
+                    timestamp,
+                    duration: midnight - timestamp,
+                });
+                timestamp = midnight;
+            }
+            blackouts.push(PowerBlackout {
+                timestamp,
+                duration: end - timestamp,
+            });
+        }
+        BlackoutInfo::new(blackouts)
+    }
+
+    // Average and peak load at each hour of the day (local time) over all the power events of
+    // the given days of the week. Hours without power events are kept, with no averages.
+    pub fn load_profile(&self, days: BandDays) -> [HourProfile; 24] {
+        let mut sums = [(0usize, 0f64, 0f64, None::<PowerEvent>); 24];
+        for pe in self.power_data.iter() {
+            if !days.contains(pe.timestamp.weekday()) {
+                continue;
+            }
+            let (count, power, apparent_power, peak) = &mut sums[pe.timestamp.hour() as usize];
+            *count += 1;
+            *power += pe.power;
+            *apparent_power += pe.apparent_power;
+            if peak.is_none_or(|p| pe.power > p.power) {
+                *peak = Some(*pe);
+            }
+        }
+        std::array::from_fn(|hour| {
+            let (count, power, apparent_power, peak) = sums[hour];
+            HourProfile {
+                hour: hour as u32,
+                sample_count: count,
+                avg_power: (count > 0).then(|| power / count as f64),
+                avg_apparent_power: (count > 0).then(|| apparent_power / count as f64),
+                peak_power: peak,
+            }
+        })
+    }
+
+    // Statistics of the power events of each day of the week over the whole recording, Monday
+    // first as in ISO 8601 (days as in daily_stats). None for a day of the week without data.
+    pub fn weekday_stats(&self) -> [Option<PowerStats>; 7] {
+        let mut weekdays: [Vec<PowerEvent>; 7] = Default::default();
+        for pe in self.analyzed_data() {
+            weekdays[pe.timestamp.weekday().num_days_from_monday() as usize].push(*pe);
+        }
+        weekdays.map(|events| (!events.is_empty()).then(|| self.stats_of(&events)))
+    }
+
+    // Active energy (kWh) of each day of the week, Monday first, divided by the number of dates
+    // of that day of the week with power events, so that a day of the week recorded twice
+    // (e.g. over nine days) does not weigh more. None for a day of the week without data.
+    pub fn weekday_energy_average(&self) -> [Option<f64>; 7] {
+        let mut dates: [BTreeSet<NaiveDate>; 7] = Default::default();
+        for pe in self.analyzed_data() {
+            let day = pe.timestamp.date_naive();
+            dates[day.weekday().num_days_from_monday() as usize].insert(day);
+        }
+        let weekdays = self.weekday_stats();
+        std::array::from_fn(|i| {
+            weekdays[i]
+                .as_ref()
+                .map(|s| s.total_active_power / dates[i].len() as f64)
+        })
+    }
+
+    // Statistics of the power events from `from` included to `to` excluded, None without any
+    pub fn stats_between(&self, from: DateTime<Local>, to: DateTime<Local>) -> Option<PowerStats> {
+        let events = self.events_between(from, to);
+        (!events.is_empty()).then(|| self.stats_of(events))
+    }
+
+    // Blackouts between the power events from `from` included to `to` excluded, in
+    // chronological order. A gap running over either end of the range is left out.
+    pub fn blackouts_between(&self, from: DateTime<Local>, to: DateTime<Local>) -> BlackoutInfo {
+        BlackoutInfo::new(VoltcraftStatistics::compute_blackouts(
+            self.events_between(from, to),
+            self.sample_interval,
+        ))
+    }
+
+    pub fn blackout_summary(&self) -> BlackoutSummary {
+        let blackouts =
+            VoltcraftStatistics::compute_blackouts(&self.power_data, self.sample_interval);
+        BlackoutSummary::new(&blackouts, self.recorded_minutes(self.power_data.len()))
+    }
+
+    // Standby baseline of all the power events at DEFAULT_BASELINE_PERCENTILE, None without any
+    pub fn baseline(&self) -> Option<BaselineStats> {
+        self.baseline_with(DEFAULT_BASELINE_PERCENTILE)
+    }
+
+    // Same as baseline, at the given fraction (0 to 1) of the power events
+    pub fn baseline_with(&self, percentile: f64) -> Option<BaselineStats> {
+        (!self.power_data.is_empty()).then(|| self.baseline_of(&self.power_data, percentile))
+    }
+
+    // Standby baseline of each day with power events at the given fraction, in chronological
+    // order (days as in daily_stats), e.g. to spot the day a standby appliance was unplugged
+    pub fn daily_baseline(&self, percentile: f64) -> Vec<(NaiveDate, BaselineStats)> {
+        let mut days = BTreeMap::<NaiveDate, Vec<PowerEvent>>::new();
+        for pe in self.analyzed_data() {
+            days.entry(bucket_of(pe.timestamp, Granularity::Day).date())
+                .or_default()
+                .push(*pe);
+        }
+        days.into_iter()
+            .map(|(day, events)| (day, self.baseline_of(&events, percentile)))
+            .collect()
+    }
+
+    // Blackout summary of each day with power events or the start of a blackout, in
+    // chronological order (days as in daily_stats). A blackout counts for the day it starts.
+    pub fn daily_blackout_summary(&self) -> Vec<(NaiveDate, BlackoutSummary)> {
+        let data = self.analyzed_data();
+        let day_of = |t| bucket_of(t, Granularity::Day).date();
+        let mut days = BTreeMap::<NaiveDate, (usize, Vec<PowerBlackout>)>::new();
+        for pe in data {
+            days.entry(day_of(pe.timestamp)).or_default().0 += 1;
+        }
+        for b in VoltcraftStatistics::compute_blackouts(data, self.sample_interval) {
+            days.entry(day_of(b.timestamp)).or_default().1.push(b);
+        }
+        days.into_iter()
+            .map(|(day, (recorded, blackouts))| {
+                (
+                    day,
+                    BlackoutSummary::new(&blackouts, self.recorded_minutes(recorded)),
+                )
+            })
+            .collect()
+    }
+
+    // Average active power over a trailing window ending with each power event.
+    // Power events are expected in chronological order; windows spanning gaps are averaged over the samples present.
+    // A window shorter than the sample interval holds no power event: there are none then.
+    pub fn rolling_avg(&self, window: Duration) -> Vec<RollingWindow> {
+        // Prefix sums of the active power, so each window average is computed in constant time
+        let mut prefix = Vec::with_capacity(self.power_data.len() + 1);
+        prefix.push(0f64);
+        for pe in self.power_data.iter() {
+            prefix.push(prefix.last().unwrap() + pe.power);
+        }
+        self.trailing_windows(window)
+            .into_iter()
+            .enumerate()
+            .map(|(last, first)| {
+                // Each power event covers the sample interval starting at its timestamp
+                let end = self.power_data[last].timestamp + self.sample_interval;
+                let sample_count = last + 1 - first;
+                RollingWindow {
+                    start: end - window,
+                    end,
+                    avg_power: (prefix[last + 1] - prefix[first]) / sample_count as f64,
+                    sample_count,
+                }
+            })
+            .collect()
+    }
+
+    // The n windows with the highest average power, highest first, along with the voltage and
+    // power factor seen during each of them. Reported windows never overlap, so a long peak is
+    // reported once rather than as several shifted copies.
+    pub fn demand_peaks(&self, window: Duration, n: usize) -> Vec<DemandPeak> {
+        let firsts = self.trailing_windows(window);
+        let mut windows = self
+            .rolling_avg(window)
+            .into_iter()
+            .enumerate()
+            .collect::<Vec<_>>();
+        // Stable sort, so the earliest window comes first among equal averages
+        windows.sort_by(|(_, a), (_, b)| b.avg_power.total_cmp(&a.avg_power));
+        let mut peaks = Vec::<DemandPeak>::new();
+        for (last, w) in windows {
+            if peaks.len() == n {
+                break;
+            }
+            if peaks.iter().any(|p| w.start < p.end && p.start < w.end) {
+                continue;
+            }
+            let samples = &self.power_data[firsts[last]..=last];
+            peaks.push(DemandPeak {
+                start: w.start,
+                end: w.end,
+                avg_power: w.avg_power,
+                min_voltage: samples.iter().map(|pe| pe.voltage).fold(f64::MAX, f64::min),
+                avg_power_factor: samples.iter().map(|pe| pe.power_factor).sum::<f64>()
+                    / samples.len() as f64,
+            });
+        }
+        peaks
+    }
+
+    // The n windows of the given duration consuming the most energy, highest first. As with
+    // demand_peaks, reported windows never overlap; fewer are returned when the data holds less
+    // than n disjoint windows. Missing minutes consume nothing.
+    pub fn top_consumption(&self, n: usize, window: Duration) -> Vec<ConsumptionWindow> {
+        let firsts = self.trailing_windows(window);
+        let mut windows = self
+            .rolling_avg(window)
+            .into_iter()
+            .enumerate()
+            .map(|(last, w)| {
+                let energy = energy(w.avg_power * w.sample_count as f64, self.sample_interval);
+                (last, w, energy)
+            })
+            .collect::<Vec<_>>();
+        // Stable sort, so the earliest window comes first among equal energies
+        windows.sort_by(|(_, _, a), (_, _, b)| b.total_cmp(a));
+        let mut top = Vec::<ConsumptionWindow>::new();
+        for (last, w, energy) in windows {
+            if top.len() == n {
+                break;
+            }
+            if top.iter().any(|t| w.start < t.end && t.start < w.end) {
+                continue;
+            }
+            let samples = &self.power_data[firsts[last]..=last];
+            top.push(ConsumptionWindow {
+                start: w.start,
+                end: w.end,
+                energy,
+                peak_power: *samples
+                    .iter()
+                    .max_by(|a, b| a.power.total_cmp(&b.power))
+                    .unwrap(),
+            });
+        }
+        top
+    }
+
+    // Running maximum of the trailing window average, for every power event
+    pub fn rolling_max_avg(&self, window: Duration) -> Vec<(DateTime<Local>, f64)> {
+        let mut max = f64::MIN;
+        self.rolling_avg(window)
+            .into_iter()
+            .map(|w| {
+                max = max.max(w.avg_power);
+                (w.end - self.sample_interval, max)
+            })
+            .collect::<Vec<_>>()
+    }
+
+    // The window with the highest average power (the earliest one if several are tied)
+    pub fn worst_window(&self, window: Duration) -> Option<RollingWindow> {
+        self.rolling_avg(window)
+            .into_iter()
+            .fold(None, |worst: Option<RollingWindow>, w| match worst {
+                Some(m) if m.avg_power >= w.avg_power => Some(m),
+                _ => Some(w),
+            })
+    }
+
+    // Longest run of consecutive minutes with the active power above the threshold (kW),
+    // the earliest one among equally long runs. A gap in the data ends a run.
+    pub fn sustained_load(&self, threshold_kw: f64) -> Option<SustainedRun> {
+        // First and last index of the longest run, and first index of the run being built
+        let mut longest: Option<(usize, usize)> = None;
+        let mut first = None;
+        for (i, pe) in self.power_data.iter().enumerate() {
+            if pe.power <= threshold_kw || (i > 0 && !self.follows_previous(i)) {
+                first = None;
+            }
+            if pe.power > threshold_kw {
+                let first = *first.get_or_insert(i);
+                if longest.is_none_or(|(f, l)| i - first > l - f) {
+                    longest = Some((first, i));
+                }
+            }
+        }
+        longest.map(|(first, last)| {
+            let samples = &self.power_data[first..=last];
+            SustainedRun {
+                start: samples[0].timestamp,
+                duration: self.sample_interval * samples.len() as i32,
+                avg_power: samples.iter().map(|pe| pe.power).sum::<f64>() / samples.len() as f64,
+            }
+        })
+    }
+
+    // Periods with the active power above the threshold (kW) lasting at least min_duration, in
+    // chronological order, e.g. the runs of a washing machine. Any dip below the threshold or
+    // gap in the data ends a period (see activity_periods_with).
+    pub fn activity_periods(
+        &self,
+        threshold_kw: f64,
+        min_duration: Duration,
+    ) -> Vec<ActivityPeriod> {
+        self.activity_periods_with(threshold_kw, min_duration, Duration::zero())
+    }
+
+    // Same as activity_periods, with the dips below the threshold (or gaps in the data) lasting
+    // at most max_gap within a period, e.g. the pauses of a wash cycle. The energy and peak of
+    // a period include its dips.
+    pub fn activity_periods_with(
+        &self,
+        threshold_kw: f64,
+        min_duration: Duration,
+        max_gap: Duration,
+    ) -> Vec<ActivityPeriod> {
+        let data = &self.power_data;
+        let interval = self.sample_interval;
+        let Some(data_end) = data.last().map(|pe| pe.timestamp + interval) else {
+            return Vec::new();
+        };
+        let period = |first: usize, last: usize| {
+            let samples = &data[first..=last];
+            let (start, end) = (
+                samples[0].timestamp,
+                samples[last - first].timestamp + interval,
+            );
+            ActivityPeriod {
+                start,
+                end,
+                duration: end - start,
+                energy: energy(
+                    sum(samples.iter().map(|pe| pe.power), self.numeric_mode),
+                    interval,
+                ),
+                peak_power: *samples
+                    .iter()
+                    .reduce(|max, pe| if pe.power > max.power { pe } else { max })
+                    .unwrap(),
+                open: data_end - end <= max_gap,
+            }
+        };
+        let mut periods = Vec::new();
+        // First and last index above the threshold of the period being built
+        let mut current: Option<(usize, usize)> = None;
+        for (i, pe) in data
+            .iter()
+            .enumerate()
+            .filter(|(_, pe)| pe.power > threshold_kw)
+        {
+            match current {
+                Some((first, last))
+                    if pe.timestamp - (data[last].timestamp + interval) <= max_gap =>
+                {
+                    current = Some((first, i))
+                }
+                _ => {
+                    periods.extend(current.map(|(first, last)| period(first, last)));
+                    current = Some((i, i));
+                }
+            }
+        }
+        periods.extend(current.map(|(first, last)| period(first, last)));
+        periods.retain(|p| p.duration >= min_duration);
+        periods
+    }
+
+    // Highest active power (kW) sustained for the whole duration, i.e. the largest P such that
+    // the power stays at or above P during some gap-free window of that length.
+    // None if no gap-free run of data lasts that long.
+    pub fn max_sustained_for(&self, duration: Duration) -> Option<f64> {
+        // Number of power events in the duration, rounded up
+        let interval = self.sample_interval.num_seconds();
+        let n = (duration.num_seconds().max(1) + interval - 1) / interval;
+        let n = n as usize;
+        let data = &self.power_data;
+        let mut best: Option<f64> = None;
+        // Rolling minimum: indices of the current window, with increasing power
+        let mut minima = VecDeque::<usize>::new();
+        let mut run_start = 0;
+        for (i, pe) in data.iter().enumerate() {
+            if i > 0 && !self.follows_previous(i) {
+                run_start = i;
+                minima.clear();
+            }
+            while minima.back().is_some_and(|&j| data[j].power >= pe.power) {
+                minima.pop_back();
+            }
+            minima.push_back(i);
+            if i + 1 - run_start >= n {
+                while minima[0] + n <= i {
+                    minima.pop_front();
+                }
+                let min = data[minima[0]].power;
+                best = Some(best.map_or(min, |b| b.max(min)));
+            }
+        }
+        best
+    }
+
+    // True if the power event was recorded a sample interval after the previous one
+    fn follows_previous(&self, i: usize) -> bool {
+        self.power_data[i].timestamp - self.power_data[i - 1].timestamp == self.sample_interval
+    }
+
+    // Episodes of consecutive power events with the voltage outside the given range, in chronological
+    // order. An episode lasting until the end of the data ends with the last power event.
+    pub fn voltage_anomalies(
+        &self,
+        low_threshold: f64,
+        high_threshold: f64,
+    ) -> Vec<VoltageAnomaly> {
+        let mut anomalies = Vec::<VoltageAnomaly>::new();
+        // Timestamp of the last event of the episode being built
+        let mut last_timestamp = None;
+        for pe in self.analyzed_data().iter() {
+            let kind = if pe.voltage < low_threshold {
+                AnomalyKind::Sag
+            } else if pe.voltage > high_threshold {
+                AnomalyKind::Swell
+            } else {
+                last_timestamp = None;
+                continue;
+            };
+            match anomalies.last_mut() {
+                // Extend the current episode if it continues with the next power event
+                Some(a)
+                    if a.kind == kind
+                        && last_timestamp == Some(pe.timestamp - self.sample_interval) =>
+                {
+                    a.duration = pe.timestamp - a.timestamp + self.sample_interval;
+                    a.extreme_voltage = match kind {
+                        AnomalyKind::Sag => a.extreme_voltage.min(pe.voltage),
+                        AnomalyKind::Swell => a.extreme_voltage.max(pe.voltage),
+                    };
+                }
+                _ => anomalies.push(VoltageAnomaly {
+                    timestamp: pe.timestamp,
+                    duration: self.sample_interval,
+                    kind,
+                    extreme_voltage: pe.voltage,
+                }),
+            }
+            last_timestamp = Some(pe.timestamp);
+        }
+        anomalies
+    }
+
+    // Runs of consecutive days at or under, and above, a daily energy target (kWh).
+    // A day without data ends a streak.
+    pub fn streaks(&self, daily_target_kwh: f64) -> StreakReport {
+        self.streaks_with(daily_target_kwh, false)
+    }
+
+    // Same as streaks, optionally keeping a streak going over a single day without data
+    pub fn streaks_with(
+        &self,
+        daily_target_kwh: f64,
+        bridge_single_day_gaps: bool,
+    ) -> StreakReport {
+        let max_step = if bridge_single_day_gaps { 2 } else { 1 };
+        let mut streaks = Vec::<Streak>::new();
+        for day in self.daily_stats() {
+            let date = day.date;
+            let under_target = day.stats.total_active_power <= daily_target_kwh;
+            match streaks.last_mut() {
+                Some(s)
+                    if s.under_target == under_target && (date - s.end).num_days() <= max_step =>
+                {
+                    s.end = date;
+                    s.days += 1;
+                }
+                _ => streaks.push(Streak {
+                    start: date,
+                    end: date,
+                    days: 1,
+                    under_target,
+                }),
+            }
+        }
+        // The earliest streak wins among equally long ones
+        let longest = |under_target: bool| {
+            streaks
+                .iter()
+                .filter(|s| s.under_target == under_target)
+                .fold(None, |longest: Option<Streak>, s| match longest {
+                    Some(l) if l.days >= s.days => Some(l),
+                    _ => Some(*s),
+                })
+        };
+        StreakReport {
+            longest_under: longest(true),
+            longest_over: longest(false),
+            current: streaks.last().cloned(),
+        }
+    }
+
+    // Energy consumed in each hour of the day and each day of the week during two periods
+    // (half-open ranges), with the change from the first to the second one per bucket.
+    // Complete buckets come first, sorted by decreasing absolute change; buckets without data
+    // in one of the periods are flagged as incomplete and listed last. Buckets with equal
+    // changes keep their natural order (hour of the day, Monday first). The buckets are profiles
+    // of each period as a whole, so a period spanning several years adds up its years.
+    pub fn attribute_delta(
+        &self,
+        period_a: (DateTime<Local>, DateTime<Local>),
+        period_b: (DateTime<Local>, DateTime<Local>),
+    ) -> DeltaAttribution {
+        // Energy and sample count per bucket
+        let buckets = |(from, to): (DateTime<Local>, DateTime<Local>)| {
+            let mut hours = [(0f64, 0usize); 24];
+            let mut weekdays = [(0f64, 0usize); 7];
+            for pe in self.power_data.iter() {
+                if pe.timestamp >= from && pe.timestamp < to {
+                    let hour = &mut hours[pe.timestamp.hour() as usize];
+                    hour.0 += pe.power / 60f64;
+                    hour.1 += 1;
+                    let day = &mut weekdays[pe.timestamp.weekday().num_days_from_monday() as usize];
+                    day.0 += pe.power / 60f64;
+                    day.1 += 1;
+                }
+            }
+            (hours, weekdays)
+        };
+        let (hours_a, weekdays_a) = buckets(period_a);
+        let (hours_b, weekdays_b) = buckets(period_b);
+        let deltas =
+            |a: &[(f64, usize)], b: &[(f64, usize)], bucket: &dyn Fn(usize) -> DeltaBucket| {
+                let mut deltas = a
+                    .iter()
+                    .zip(b)
+                    .enumerate()
+                    .map(|(i, (a, b))| BucketDelta {
+                        bucket: bucket(i),
+                        energy_a: a.0,
+                        energy_b: b.0,
+                        delta: b.0 - a.0,
+                        share: None,
+                        incomplete: a.1 == 0 || b.1 == 0,
+                    })
+                    .collect::<Vec<_>>();
+                let total = deltas
+                    .iter()
+                    .filter(|d| !d.incomplete)
+                    .map(|d| d.delta)
+                    .sum::<f64>();
+                for d in deltas.iter_mut().filter(|d| !d.incomplete && total != 0.0) {
+                    d.share = Some(d.delta / total);
+                }
+                deltas.sort_by(|a, b| {
+                    a.incomplete
+                        .cmp(&b.incomplete)
+                        .then(b.delta.abs().total_cmp(&a.delta.abs()))
+                });
+                deltas
+            };
+        DeltaAttribution {
+            energy_a: hours_a.iter().map(|h| h.0).sum(),
+            energy_b: hours_b.iter().map(|h| h.0).sum(),
+            hours: deltas(&hours_a, &hours_b, &|i| DeltaBucket::Hour(i as u32)),
+            weekdays: deltas(&weekdays_a, &weekdays_b, &|i| {
+                DeltaBucket::Weekday(Weekday::try_from(i as u8).unwrap())
+            }),
+        }
+    }
+
+    // How the voltage follows the current drawn: a strongly negative correlation points at
+    // sags caused by the own loads (e.g. wiring losses) rather than by the grid.
+    pub fn voltage_load_correlation(&self) -> CorrelationReport {
+        let data = self.analyzed_data();
+        let n = data.len() as f64;
+        let mean_current = data.iter().map(|e| e.current).sum::<f64>() / n;
+        let mean_voltage = data.iter().map(|e| e.voltage).sum::<f64>() / n;
+        let (mut covariance, mut current_variance, mut voltage_variance) = (0.0, 0.0, 0.0);
+        for e in data {
+            let (di, dv) = (e.current - mean_current, e.voltage - mean_voltage);
+            covariance += di * dv;
+            current_variance += di * di;
+            voltage_variance += dv * dv;
+        }
+        let denominator = (current_variance * voltage_variance).sqrt();
+        // Rounding noise on (nearly) constant values must not pass for a correlation
+        let correlation = if denominator > f64::EPSILON * n {
+            (covariance / denominator).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let mut by_current = data.iter().collect::<Vec<_>>();
+        by_current.sort_by(|a, b| a.current.total_cmp(&b.current));
+        let terciles = (0..3)
+            .map(|k| &by_current[by_current.len() * k / 3..by_current.len() * (k + 1) / 3])
+            .filter(|bin| !bin.is_empty())
+            .map(|bin| CurrentBin {
+                min_current: bin[0].current,
+                max_current: bin[bin.len() - 1].current,
+                avg_voltage: bin.iter().map(|e| e.voltage).sum::<f64>() / bin.len() as f64,
+                samples: bin.len(),
+            })
+            .collect();
+        CorrelationReport {
+            samples: data.len(),
+            correlation,
+            terciles,
+        }
+    }
+
+    // Consumption of the year of as_of up to that day against the pro-rated annual target (kWh).
+    // The target is spread evenly over the days of the year, or over the months according to
+    // the seasonal weights (January first) and evenly over the days of each month. Only the days
+    // between the first and the last power event are paced, which `partial` reports.
+    pub fn pacing(
+        &self,
+        annual_target_kwh: f64,
+        as_of: NaiveDate,
+        seasonal_weights: Option<[f64; 12]>,
+    ) -> PacingReport {
+        let year_start = NaiveDate::from_ymd_opt(as_of.year(), 1, 1).unwrap();
+        let days_in_month = |d: NaiveDate| {
+            let first = d.with_day(1).unwrap();
+            (first.checked_add_months(Months::new(1)).unwrap() - first).num_days() as f64
+        };
+        let days_in_year = (NaiveDate::from_ymd_opt(as_of.year() + 1, 1, 1).unwrap() - year_start)
+            .num_days() as f64;
+        let daily_target = |d: NaiveDate| match seasonal_weights {
+            Some(weights) => {
+                annual_target_kwh * weights[d.month0() as usize]
+                    / weights.iter().sum::<f64>()
+                    / days_in_month(d)
+            }
+            None => annual_target_kwh / days_in_year,
+        };
+
+        let covered = match (self.power_data.first(), self.power_data.last()) {
+            (Some(first), Some(last)) => {
+                let from = first.timestamp.date_naive().max(year_start);
+                let to = last.timestamp.date_naive().min(as_of);
+                (from <= to).then_some((from, to))
+            }
+            _ => None,
+        };
+        let (actual_kwh, target_kwh) = match covered {
+            Some((from, to)) => (
+                self.power_data
+                    .iter()
+                    .filter(|pe| (from..=to).contains(&pe.timestamp.date_naive()))
+                    .map(|pe| pe.power)
+                    .sum::<f64>()
+                    / 60f64,
+                from.iter_days()
+                    .take_while(|d| *d <= to)
+                    .map(daily_target)
+                    .sum::<f64>(),
+            ),
+            None => (0.0, 0.0),
+        };
+        let surplus_kwh = target_kwh - actual_kwh;
+        let surplus_days = covered
+            .filter(|_| actual_kwh > 0.0)
+            .map(|(from, to)| surplus_kwh / (actual_kwh / ((to - from).num_days() + 1) as f64));
+        PacingReport {
+            as_of,
+            covered,
+            partial: covered != Some((year_start, as_of)),
+            actual_kwh,
+            target_kwh,
+            surplus_kwh,
+            surplus_days,
+        }
+    }
+
+    // Periods of at least opts.min_days consecutive days where the house looks unoccupied: the
+    // day consumes little more than the standby baseline (a low percentile of the active power)
+    // around the clock, with at most a few minutes of appliance cycles. Savings are measured
+    // against the typical day, the median daily energy of the data.
+    pub fn detect_away_periods(&self, opts: &AwayDetectorOptions) -> Vec<AwayPeriod> {
+        let data = self.analyzed_data();
+        if data.is_empty() {
+            return Vec::new();
+        }
+        let baseline = nearest_rank(
+            data.iter().map(|e| e.power).collect(),
+            opts.baseline_percentile,
+        );
+        let max_daily_kwh = baseline * 24.0 * opts.energy_factor;
+
+        // (day, energy, unoccupied) in chronological order
+        let days = data
+            .iter()
+            .group_by(|e| e.timestamp.date_naive())
+            .into_iter()
+            .map(|(date, events)| {
+                let events = events.collect::<Vec<_>>();
+                let energy = events.iter().fold(0f64, |sum, x| sum + x.power) / 60f64;
+                let spikes = events
+                    .iter()
+                    .filter(|e| e.power > baseline * opts.spike_factor)
+                    .count();
+                let unoccupied = energy <= max_daily_kwh && spikes <= opts.max_spike_minutes;
+                (date, energy, unoccupied)
+            })
+            .collect::<Vec<_>>();
+        let typical_kwh = median(days.iter().map(|(_, energy, _)| *energy).collect());
+
+        let mut runs: Vec<Vec<(NaiveDate, f64)>> = Vec::new();
+        for (date, energy, unoccupied) in days {
+            match runs.last_mut() {
+                Some(run) if unoccupied && (date - run[run.len() - 1].0).num_days() == 1 => {
+                    run.push((date, energy))
+                }
+                _ if unoccupied => runs.push(vec![(date, energy)]),
+                _ => (),
+            }
+        }
+        runs.into_iter()
+            .filter(|run| run.len() >= opts.min_days)
+            .map(|run| {
+                let energy = run.iter().map(|(_, energy)| energy).sum::<f64>();
+                AwayPeriod {
+                    start: run[0].0,
+                    end: run[run.len() - 1].0,
+                    avg_daily_kwh: energy / run.len() as f64,
+                    savings_kwh: typical_kwh * run.len() as f64 - energy,
+                }
+            })
+            .collect()
+    }
+
+    // Simulate a battery keeping the load drawn from the grid at or under cap_kw: each minute
+    // above the cap the battery supplies the excess as far as its charge and discharge rate
+    // allow, and each minute under the cap it charges from the headroom up to its charge rate.
+    // Every power event stands for one minute; nothing happens during blackouts.
+    pub fn simulate_peak_shaving(&self, cap_kw: f64, battery: &BatterySpec) -> ShavingReport {
+        let mut report = ShavingReport {
+            violations: 0,
+            violation_minutes: 0,
+            max_excess_kw: 0.0,
+            shifted_kwh: 0.0,
+            equivalent_full_cycles: 0.0,
+        };
+        let mut charge = battery.capacity_kwh; // kWh
+        let mut violating = false;
+        for pe in self.power_data.iter() {
+            if pe.power > cap_kw {
+                let needed = pe.power - cap_kw;
+                let discharge = needed.min(battery.max_discharge_kw).min(charge * 60f64);
+                charge -= discharge / 60f64;
+                report.shifted_kwh += discharge / 60f64;
+                let excess = needed - discharge;
+                // Ignore the rounding left over by an exactly sufficient battery
+                if excess > 1e-9 {
+                    report.violation_minutes += 1;
+                    report.max_excess_kw = report.max_excess_kw.max(excess);
+                    if !violating {
+                        report.violations += 1;
+                    }
+                    violating = true;
+                    continue;
+                }
+            } else {
+                let headroom = cap_kw - pe.power;
+                let room = (battery.capacity_kwh - charge) * 60f64;
+                charge += headroom.min(battery.max_charge_kw).min(room) / 60f64;
+            }
+            violating = false;
+        }
+        if battery.capacity_kwh > 0.0 {
+            report.equivalent_full_cycles = report.shifted_kwh / battery.capacity_kwh;
+        }
+        report
+    }
+
+    // Overlay a generation curve on the consumption, minute by minute, over every day with power
+    // events. The minutes of a day without a power event are given the average load of the
+    // recorded minutes of that day, so that gaps do not count as exported generation.
+    pub fn simulate_solar(&self, profile: &SolarProfile) -> SolarReport {
+        let generation = |t: DateTime<Local>| match profile {
+            SolarProfile::ClearSky { peak_kw, daylight } => {
+                let (sunrise, sunset) = daylight[t.month0() as usize];
+                // Middle of the minute, in hours
+                let hour = t.hour() as f64 + (t.minute() as f64 + 0.5) / 60.0;
+                if hour > sunrise && hour < sunset {
+                    peak_kw * (std::f64::consts::PI * (hour - sunrise) / (sunset - sunrise)).sin()
+                } else {
+                    0.0
+                }
+            }
+            SolarProfile::Hourly(values) => values[t.hour() as usize],
+        };
+        let mut months = Vec::<SolarMonth>::new();
+        for (date, events) in &self
+            .analyzed_data()
+            .iter()
+            .group_by(|e| e.timestamp.date_naive())
+        {
+            // Start of the day, or of its first hour when daylight saving time skips midnight
+            let midnight = |d: NaiveDate| first_instant(&Local, d.and_time(NaiveTime::MIN));
+            let (Some(start), Some(end)) = (midnight(date), midnight(date + Duration::days(1)))
+            else {
+                continue;
+            };
+            let minutes = (end - start).num_minutes() as usize;
+            let mut load = vec![None; minutes];
+            for e in events {
+                load[(e.timestamp - start).num_minutes() as usize] = Some(e.power);
+            }
+            let recorded = load.iter().flatten().collect::<Vec<_>>();
+            let average = recorded.iter().copied().sum::<f64>() / recorded.len() as f64;
+            let estimated_minutes = minutes - recorded.len();
+
+            if months
+                .last()
+                .is_none_or(|m| (m.year, m.month) != (date.year(), date.month()))
+            {
+                months.push(SolarMonth {
+                    year: date.year(),
+                    month: date.month(),
+                    consumption_kwh: 0.0,
+                    generation_kwh: 0.0,
+                    self_consumed_kwh: 0.0,
+                    surplus_kwh: 0.0,
+                    estimated_minutes: 0,
+                });
+            }
+            let month = months.last_mut().unwrap();
+            month.estimated_minutes += estimated_minutes;
+            for (minute, power) in load.iter().enumerate() {
+                let power = power.unwrap_or(average);
+                let generated = generation(start + Duration::minutes(minute as i64));
+                month.consumption_kwh += power / 60f64;
+                month.generation_kwh += generated / 60f64;
+                month.self_consumed_kwh += power.min(generated) / 60f64;
+                month.surplus_kwh += (generated - power).max(0.0) / 60f64;
+            }
+        }
+        let consumption = months.iter().map(|m| m.consumption_kwh).sum::<f64>();
+        let self_consumed_kwh = months.iter().map(|m| m.self_consumed_kwh).sum::<f64>();
+        SolarReport {
+            self_consumed_kwh,
+            surplus_kwh: months.iter().map(|m| m.surplus_kwh).sum(),
+            import_reduction: if consumption > 0.0 {
+                self_consumed_kwh / consumption
+            } else {
+                0.0
+            },
+            months,
+        }
+    }
+
+    // Compare the energy of the power events between from and to (exclusive) with an external
+    // reading of the same period. Minutes without a power event are assumed to draw the average
+    // power of the recorded ones.
+    pub fn reconcile(
+        &self,
+        external_kwh: f64,
+        period: (DateTime<Local>, DateTime<Local>),
+    ) -> Reconciliation {
+        let (from, to) = period;
+        let events = self
+            .power_data
+            .iter()
+            .filter(|e| e.timestamp >= from && e.timestamp < to)
+            .collect::<Vec<_>>();
+        let power_sum = events.iter().fold(0f64, |sum, x| sum + x.power);
+        let logger_kwh = power_sum / 60f64;
+        let minutes = (to - from).num_minutes().max(0) as usize;
+        let present = PresenceIndex::new(&self.power_data).count_in_range(from, to);
+        let missing = minutes.saturating_sub(present);
+        let estimated_gap_kwh = match events.len() {
+            0 => 0.0,
+            n => missing as f64 * power_sum / n as f64 / 60f64,
+        };
+        let discrepancy_kwh = external_kwh - logger_kwh;
+        Reconciliation {
+            from,
+            to,
+            external_kwh,
+            logger_kwh,
+            discrepancy_kwh,
+            discrepancy_percent: (external_kwh != 0.0)
+                .then(|| discrepancy_kwh * 100.0 / external_kwh),
+            coverage: match minutes {
+                0 => 0.0,
+                m => (present as f64 / m as f64).min(1.0),
+            },
+            estimated_gap_kwh,
+            unexplained_kwh: discrepancy_kwh - estimated_gap_kwh,
+        }
+    }
+
+    // Supply quality per ISO week, for every week between the first and the last power event.
+    // Sags, swells and blackouts are counted in the week they start; weeks start on Monday 00:00.
+    pub fn weekly_quality_report(&self, thresholds: &QualityThresholds) -> Vec<WeeklyQuality> {
+        let (first, last) = match (self.analyzed_data().first(), self.analyzed_data().last()) {
+            (Some(first), Some(last)) => (first.timestamp, last.timestamp),
+            _ => return Vec::new(),
+        };
+        let monday = |t: DateTime<Local>| bucket_of(t, Granularity::Week).date();
+        let mut weeks = Vec::new();
+        let mut start = monday(first);
+        while start <= last.date_naive() {
+            let week = start.iso_week();
+            weeks.push(WeeklyQuality {
+                year: week.year(),
+                week: week.week(),
+                start,
+                sags: 0,
+                swells: 0,
+                blackouts: 0,
+                outage_duration: Duration::zero(),
+                worst_voltage: None,
+            });
+            start += Duration::days(7);
+        }
+        // Index of the week a timestamp falls into
+        let week_of = |t: DateTime<Local>| ((monday(t) - monday(first)).num_days() / 7) as usize;
+
+        for a in self.voltage_anomalies(thresholds.low_voltage, thresholds.high_voltage) {
+            match a.kind {
+                AnomalyKind::Sag => weeks[week_of(a.timestamp)].sags += 1,
+                AnomalyKind::Swell => weeks[week_of(a.timestamp)].swells += 1,
+            }
+        }
+        for b in VoltcraftStatistics::compute_blackouts(self.analyzed_data(), self.sample_interval)
+        {
+            let week = &mut weeks[week_of(b.timestamp)];
+            week.blackouts += 1;
+            week.outage_duration += b.duration;
+        }
+        for pe in self.analyzed_data().iter() {
+            let week = &mut weeks[week_of(pe.timestamp)];
+            let deviation = (pe.voltage - thresholds.nominal_voltage).abs();
+            if week
+                .worst_voltage
+                .is_none_or(|w| deviation > (w.voltage - thresholds.nominal_voltage).abs())
+            {
+                week.worst_voltage = Some(*pe);
+            }
+        }
+        weeks
+    }
+
+    // Power events analyzed by the per-day statistics, without the boundary days excluded by the policy
+    fn analyzed_data(&self) -> &[PowerEvent] {
+        self.analyzed_data_by(|t| bucket_of(*t, Granularity::Day).date())
+    }
+
+    // Same as analyzed_data, with the days given by day_of (e.g. in another time zone)
+    fn analyzed_data_by(&self, day_of: impl Fn(&DateTime<Local>) -> NaiveDate) -> &[PowerEvent] {
+        let data = &self.power_data;
+        let (first, last) = match (data.first(), data.last()) {
+            (Some(first), Some(last)) => (day_of(&first.timestamp), day_of(&last.timestamp)),
+            _ => return data,
+        };
+        let start = data.partition_point(|pe| day_of(&pe.timestamp) == first);
+        let end = data.partition_point(|pe| day_of(&pe.timestamp) < last);
+        let keep = |day: &[PowerEvent]| match self.boundary_days {
+            BoundaryDays::Include => true,
+            BoundaryDays::Exclude => false,
+            BoundaryDays::IncludeIfCoverageAbove(coverage) => {
+                let duration =
+                    day.last().unwrap().timestamp - day[0].timestamp + Duration::minutes(1);
+                duration.num_seconds() as f64 / 86400.0 > coverage
+            }
+        };
+        if first == last {
+            // A single day is both the first and the last one
+            return if keep(data) { data } else { &data[..0] };
+        }
+        let start = if keep(&data[..start]) { 0 } else { start };
+        let end = if keep(&data[end..]) { data.len() } else { end };
+        &data[start..end]
+    }
+
+    // Power stats on the given power events, with the active stats if requested
+    fn stats_of(&self, power_items: &[PowerEvent]) -> PowerStats {
+        let mut stats = VoltcraftStatistics::compute_stats(
+            power_items,
+            self.numeric_mode,
+            self.sample_interval,
+        );
+        if self.average_basis == AverageBasis::Span {
+            let hours = stats.total_duration.num_seconds() as f64 / 3600f64;
+            stats.avg_active_power = stats.total_active_power / hours;
+            stats.avg_apparent_power = stats.total_apparent_power / hours;
+        }
+        stats.active = self.idle_threshold.map(|threshold| {
+            let active = power_items
+                .iter()
+                .filter(|pe| pe.power >= threshold)
+                .collect::<Vec<_>>();
+            let power_sum = sum(active.iter().map(|pe| pe.power), self.numeric_mode);
+            ActiveStats {
+                idle_threshold: threshold,
+                active_duration: self.sample_interval * active.len() as i32,
+                active_energy: energy(power_sum, self.sample_interval),
+                avg_active_power: if active.is_empty() {
+                    0.0
+                } else {
+                    power_sum / active.len() as f64
+                },
+                active_fraction: active.len() as f64 / power_items.len() as f64,
+            }
+        });
+        stats
+    }
+
+    // Standby baseline of the given power events, at least one
+    fn baseline_of(&self, power_items: &[PowerEvent], percentile: f64) -> BaselineStats {
+        let power = nearest_rank(power_items.iter().map(|pe| pe.power).collect(), percentile);
+        let total = energy(
+            sum(power_items.iter().map(|pe| pe.power), self.numeric_mode),
+            self.sample_interval,
+        );
+        let standby = energy(power * power_items.len() as f64, self.sample_interval);
+        BaselineStats {
+            percentile: percentile.clamp(0.0, 1.0),
+            power,
+            daily_energy: power * 24f64,
+            share: if total > 0.0 { standby / total } else { 0.0 },
+        }
+    }
+
+    // Power events from `from` included to `to` excluded
+    fn events_between(&self, from: DateTime<Local>, to: DateTime<Local>) -> &[PowerEvent] {
+        let start = self.power_data.partition_point(|pe| pe.timestamp < from);
+        let end = self.power_data.partition_point(|pe| pe.timestamp < to);
+        &self.power_data[start..end.max(start)]
+    }
+
+    // Whole minutes recorded by the given number of power events
+    fn recorded_minutes(&self, events: usize) -> usize {
+        (events as i64 * self.sample_interval.num_seconds() / 60) as usize
+    }
+
+    // Compute power stats on the given power events, at least one, recorded every interval
+    fn compute_stats(
+        power_items: &[PowerEvent],
+        mode: NumericMode,
+        interval: Duration,
+    ) -> PowerStats {
+        // Total active power (in kWh) = (sum of instantaneous powers) * (minutes per sample) / 60
+        let power_sum = sum(power_items.iter().map(|x| x.power), mode);
+        let total_active_power = energy(power_sum, interval); // Total active power consumption (kWh)
+        let avg_active_power = power_sum / power_items.len() as f64; // Average power (kW)
+        let max_active_power = power_items
+            .iter()
+            .max_by(|a, b| a.power.partial_cmp(&b.power).unwrap())
+            .unwrap(); // Maximum active power (kW)
+
+        // Total apparent power (in kVAh) = (sum of instantaneous apparent powers) * (minutes per sample) / 60
+        let apparent_power_sum = sum(power_items.iter().map(|x| x.apparent_power), mode);
+        let total_apparent_power = energy(apparent_power_sum, interval); // Total apparent power consumption (kVAh)
+        let avg_apparent_power = apparent_power_sum / power_items.len() as f64; // Average power (kVA)
         let max_apparent_power = power_items
             .iter()
             .max_by(|a, b| a.apparent_power.partial_cmp(&b.apparent_power).unwrap())
@@ -155,7 +2186,35 @@ impl<'a> VoltcraftStatistics<'a> {
             .max_by(|a, b| a.voltage.partial_cmp(&b.voltage).unwrap())
             .unwrap(); // Maximum voltage (V)
         let avg_voltage =
-            power_items.iter().fold(0f64, |sum, x| sum + x.voltage) / power_items.len() as f64; // Average voltage (V)
+            sum(power_items.iter().map(|x| x.voltage), mode) / power_items.len() as f64; // Average voltage (V)
+
+        let min_current = power_items
+            .iter()
+            .min_by(|a, b| a.current.partial_cmp(&b.current).unwrap())
+            .unwrap(); // Minimum current (A)
+        let max_current = power_items
+            .iter()
+            .max_by(|a, b| a.current.partial_cmp(&b.current).unwrap())
+            .unwrap(); // Maximum current (A)
+        let avg_current =
+            sum(power_items.iter().map(|x| x.current), mode) / power_items.len() as f64; // Average current (A)
+
+        let avg_power_factor =
+            sum(power_items.iter().map(|x| x.power_factor), mode) / power_items.len() as f64; // Average power factor
+        let worst_power_factor = power_items
+            .iter()
+            .filter(|x| x.current > 0.0) // the idle minutes have no power factor to speak of
+            .min_by(|a, b| a.power_factor.partial_cmp(&b.power_factor).unwrap())
+            .copied(); // Lowest power factor under load
+        let weighted_power_factor = weighted_power_factor(power_items, mode);
+        let total_reactive_energy = energy(
+            sum(power_items.iter().map(|x| x.reactive_power()), mode),
+            interval,
+        ); // Total reactive energy (kvarh)
+        let worst_sustained_power_factor = worst_sustained_power_factor(power_items, interval);
+
+        let p95_voltage = nearest_rank(power_items.iter().map(|x| x.voltage).collect(), 0.95); // 95th percentile voltage (V)
+        let p95_power = nearest_rank(power_items.iter().map(|x| x.power).collect(), 0.95); // 95th percentile active power (kW)
 
         let start = power_items
             .iter()
@@ -177,22 +2236,2098 @@ impl<'a> VoltcraftStatistics<'a> {
             min_voltage: *min_voltage,
             max_voltage: *max_voltage,
             avg_voltage,
-            total_duration: (end - start) + Duration::minutes(1),
+            p95_voltage,
+            min_current: *min_current,
+            max_current: *max_current,
+            avg_current,
+            avg_power_factor,
+            p95_power,
+            worst_power_factor,
+            weighted_power_factor,
+            total_reactive_energy,
+            worst_sustained_power_factor,
+            total_duration: (end - start) + interval,
+            active: None,
+            coverage: CoverageInfo::from_seconds(
+                power_items.len() as i64 * interval.num_seconds(),
+                ((end - start) + interval).num_seconds(),
+            ),
+        }
+    }
+
+    // Index of the first power event of the trailing window ending with each power event,
+    // none for a window shorter than the sample interval
+    fn trailing_windows(&self, window: Duration) -> Vec<usize> {
+        if window < self.sample_interval {
+            return Vec::new();
         }
+        let mut first = 0;
+        self.power_data
+            .iter()
+            .enumerate()
+            .map(|(i, pe)| {
+                let start = pe.timestamp + self.sample_interval - window;
+                while first < i && self.power_data[first].timestamp < start {
+                    first += 1;
+                }
+                first
+            })
+            .collect()
     }
 
-    // Compute blackout stats on the given power events
-    fn compute_blackouts(power_items: &[PowerEvent]) -> Vec<PowerBlackout> {
+    // Compute blackout stats on the given power events, recorded every interval
+    fn compute_blackouts(power_items: &[PowerEvent], interval: Duration) -> Vec<PowerBlackout> {
         let mut blackouts = Vec::new();
         for (pe1, pe2) in power_items.iter().tuple_windows() {
-            // If the gap between two subsequent timestamps is more than a minute, we've detected a blackout
-            if pe2.timestamp - pe1.timestamp > Duration::minutes(1) {
+            // If the gap between two subsequent timestamps is more than the interval, we've detected a blackout
+            if pe2.timestamp - pe1.timestamp > interval {
                 blackouts.push(PowerBlackout {
-                    timestamp: pe1.timestamp + Duration::minutes(1),
-                    duration: (pe2.timestamp - pe1.timestamp) - Duration::minutes(1),
+                    timestamp: pe1.timestamp + interval,
+                    duration: (pe2.timestamp - pe1.timestamp) - interval,
                 })
             }
         }
         blackouts
     }
 }
+
+// Energy (kWh) of power events recorded every interval, from the sum of their powers (kW).
+// Same as dividing by 60 for one minute samples.
+pub(crate) fn energy(power_sum: f64, interval: Duration) -> f64 {
+    power_sum * (interval.num_seconds() as f64 / 60f64) / 60f64
+}
+
+// Median of the values (NaN for no values)
+pub(crate) fn median(mut values: Vec<f64>) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+// Window of the sustained power factor (minutes), the demand interval of most utilities
+const POWER_FACTOR_WINDOW: i64 = 15;
+
+// Power factor of the power events weighted by their apparent power, 0 without any load
+fn weighted_power_factor(power_items: &[PowerEvent], mode: NumericMode) -> f64 {
+    let apparent = sum(power_items.iter().map(|x| x.apparent_power), mode);
+    match apparent > 0.0 {
+        true => {
+            sum(
+                power_items
+                    .iter()
+                    .map(|x| x.power_factor * x.apparent_power),
+                mode,
+            ) / apparent
+        }
+        false => 0.0,
+    }
+}
+
+// Lowest weighted power factor of the windows of chronological power events recorded every
+// interval without a gap and with current flowing throughout, the first one on a tie
+pub(crate) fn worst_sustained_power_factor(
+    power_items: &[PowerEvent],
+    interval: Duration,
+) -> Option<PowerFactorWindow> {
+    let window = Duration::minutes(POWER_FACTOR_WINDOW);
+    let length = (window.num_seconds() / interval.num_seconds().max(1)).max(1) as usize;
+    power_items
+        .windows(length)
+        .filter(|w| {
+            w[length - 1].timestamp - w[0].timestamp == interval * (length as i32 - 1)
+                && w.iter().all(|x| x.current > 0.0)
+        })
+        .map(|w| PowerFactorWindow {
+            start: w[0].timestamp,
+            power_factor: weighted_power_factor(w, NumericMode::Fast),
+        })
+        .min_by(|a, b| a.power_factor.partial_cmp(&b.power_factor).unwrap())
+}
+
+// Sum of the values in iteration order, compensated (Neumaier) in reproducible mode
+fn sum(values: impl Iterator<Item = f64>, mode: NumericMode) -> f64 {
+    match mode {
+        NumericMode::Fast => values.fold(0f64, |sum, x| sum + x),
+        NumericMode::Reproducible => values
+            .fold(CompensatedSum::default(), CompensatedSum::add)
+            .value(),
+    }
+}
+
+// Running Neumaier sum, whose value can be read after each addition
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct CompensatedSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl CompensatedSum {
+    pub(crate) fn add(mut self, x: f64) -> Self {
+        let t = self.sum + x;
+        // Recover the low-order bits lost by the larger of the two operands
+        self.compensation += if self.sum.abs() >= x.abs() {
+            (self.sum - t) + x
+        } else {
+            (x - t) + self.sum
+        };
+        self.sum = t;
+        self
+    }
+
+    pub(crate) fn value(&self) -> f64 {
+        self.sum + self.compensation
+    }
+
+    // Sum of both sums, each keeping its compensation
+    pub(crate) fn merge(self, other: CompensatedSum) -> Self {
+        self.add(other.sum).add(other.compensation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::format_fixed;
+    use crate::voltcraft::bucket::{first_instant, Granularity};
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::error::VoltcraftError;
+    use crate::voltcraft::stats::{
+        sum, ActivityPeriod, AnomalyKind, AverageBasis, AwayDetectorOptions, BatterySpec,
+        BlackoutOptions, BoundaryDays, CoverageInfo, CurrentBin, DeltaBucket, NumericMode,
+        PowerBlackout, PowerFactorWindow, PowerHourInterval, PowerStats, PrecisionPolicy,
+        QualityThresholds, SolarProfile, Streak, SustainedRun, VoltcraftStatistics,
+        DEFAULT_BASELINE_PERCENTILE, DEFAULT_IDLE_THRESHOLD,
+    };
+    use crate::voltcraft::tariff::{BandDays, Tariff};
+    use chrono::{
+        DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone, Timelike, Weekday,
+    };
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    // Central European or US Eastern time with the 2014 daylight saving time rules, as a
+    // stand-in for the zones of a time zone database
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    enum TestZone {
+        Bucharest,
+        NewYork,
+    }
+
+    #[derive(Debug, Copy, Clone)]
+    struct TestOffset(TestZone, chrono::FixedOffset);
+
+    impl chrono::Offset for TestOffset {
+        fn fix(&self) -> chrono::FixedOffset {
+            self.1
+        }
+    }
+
+    impl TestZone {
+        // Standard offset (hours) and daylight saving time as UTC times
+        fn rules(&self) -> (i32, chrono::NaiveDateTime, chrono::NaiveDateTime) {
+            let utc = |m, d, h| {
+                NaiveDate::from_ymd_opt(2014, m, d)
+                    .unwrap()
+                    .and_hms_opt(h, 0, 0)
+                    .unwrap()
+            };
+            match self {
+                TestZone::Bucharest => (2, utc(3, 30, 1), utc(10, 26, 1)),
+                TestZone::NewYork => (-5, utc(3, 9, 7), utc(11, 2, 6)),
+            }
+        }
+    }
+
+    impl TimeZone for TestZone {
+        type Offset = TestOffset;
+
+        fn from_offset(offset: &TestOffset) -> Self {
+            offset.0
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> chrono::LocalResult<TestOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(
+            &self,
+            local: &chrono::NaiveDateTime,
+        ) -> chrono::LocalResult<TestOffset> {
+            let standard = self.rules().0;
+            let candidates = [standard, standard + 1]
+                .into_iter()
+                .map(|h| chrono::FixedOffset::east_opt(h * 3600).unwrap())
+                .filter(|o| {
+                    let utc = *local - Duration::seconds(o.local_minus_utc() as i64);
+                    self.offset_from_utc_datetime(&utc).1 == *o
+                })
+                .map(|o| TestOffset(*self, o))
+                .collect::<Vec<_>>();
+            match candidates[..] {
+                [a] => chrono::LocalResult::Single(a),
+                [a, b] => chrono::LocalResult::Ambiguous(a, b),
+                _ => chrono::LocalResult::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> TestOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &chrono::NaiveDateTime) -> TestOffset {
+            let (standard, dst_start, dst_end) = self.rules();
+            let hours = if (dst_start..dst_end).contains(utc) {
+                standard + 1
+            } else {
+                standard
+            };
+            TestOffset(*self, chrono::FixedOffset::east_opt(hours * 3600).unwrap())
+        }
+    }
+
+    // A day of one-minute samples, idle except for a 45-minute 3 kW block starting at 12:00
+    fn idle_day_with_block() -> Vec<PowerEvent> {
+        let midnight = Local.with_ymd_and_hms(2014, 9, 11, 0, 0, 0).unwrap();
+        (0..1440)
+            .map(|m| {
+                let power = if (720..765).contains(&m) { 3.0 } else { 0.0 };
+                event(midnight + Duration::minutes(m), power)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rolling_worst_window() {
+        let events = idle_day_with_block();
+        let stats = VoltcraftStatistics::new(&events);
+        let worst = stats.worst_window(Duration::hours(1)).unwrap();
+        // The whole block fits in the window: 45 minutes at 3 kW averaged over an hour
+        assert!((worst.avg_power - 2.25).abs() < 1e-9);
+        assert_eq!(worst.sample_count, 60);
+        assert_eq!(
+            worst.start,
+            Local.with_ymd_and_hms(2014, 9, 11, 11, 45, 0).unwrap()
+        );
+
+        // No window shorter than a sample
+        for window in [
+            Duration::zero(),
+            Duration::seconds(30),
+            Duration::minutes(-5),
+        ] {
+            assert!(stats.rolling_avg(window).is_empty());
+            assert!(stats.rolling_max_avg(window).is_empty());
+            assert!(stats.worst_window(window).is_none());
+        }
+    }
+
+    #[test]
+    fn rolling_max_is_monotonic() {
+        let events = idle_day_with_block();
+        let stats = VoltcraftStatistics::new(&events);
+        let series = stats.rolling_max_avg(Duration::hours(1));
+        assert_eq!(series.len(), 1440);
+        assert_eq!(series[719].1, 0.0);
+        assert!(series.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert!((series.last().unwrap().1 - 2.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rolling_window_over_gap() {
+        let mut events = idle_day_with_block();
+        // Remove half an hour of idle samples before the block
+        events.retain(|e| {
+            e.timestamp < Local.with_ymd_and_hms(2014, 9, 11, 11, 15, 0).unwrap()
+                || e.timestamp >= Local.with_ymd_and_hms(2014, 9, 11, 11, 45, 0).unwrap()
+        });
+        let stats = VoltcraftStatistics::new(&events);
+        let window = stats
+            .rolling_avg(Duration::hours(1))
+            .into_iter()
+            .find(|w| w.end == Local.with_ymd_and_hms(2014, 9, 11, 12, 15, 0).unwrap())
+            .unwrap();
+        // Only the 30 samples present are averaged: 15 idle and 15 at 3 kW
+        assert_eq!(window.sample_count, 30);
+        assert!((window.avg_power - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn weekly_quality_golden() {
+        // Four full ISO weeks (2023-05-01 is a Monday) at a steady 230V
+        let start = Local.with_ymd_and_hms(2023, 5, 1, 0, 0, 0).unwrap();
+        let mut events = (0..28 * 1440)
+            .map(|m| event(start + Duration::minutes(m), 0.23))
+            .collect::<Vec<_>>();
+        // Week 2: a three minute sag down to 195V
+        for (m, v) in [(12000, 200.0), (12001, 195.0), (12002, 201.0)] {
+            events[m].voltage = v;
+        }
+        // Week 4: a one minute swell and a 45 minute blackout
+        events[31000].voltage = 258.4;
+        events.drain(32000..32045);
+        let stats = VoltcraftStatistics::new(&events);
+        let report = stats
+            .weekly_quality_report(&QualityThresholds::default())
+            .iter()
+            .map(|w| {
+                format!(
+                    "{}-W{:02} {} sags={} swells={} blackouts={} outage={}m worst={}V",
+                    w.year,
+                    w.week,
+                    w.start,
+                    w.sags,
+                    w.swells,
+                    w.blackouts,
+                    w.outage_duration.num_minutes(),
+                    format_fixed(w.worst_voltage.unwrap().voltage, 1)
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            report,
+            vec![
+                "2023-W18 2023-05-01 sags=0 swells=0 blackouts=0 outage=0m worst=230.0V",
+                "2023-W19 2023-05-08 sags=1 swells=0 blackouts=0 outage=0m worst=195.0V",
+                "2023-W20 2023-05-15 sags=0 swells=0 blackouts=0 outage=0m worst=230.0V",
+                "2023-W21 2023-05-22 sags=0 swells=1 blackouts=1 outage=45m worst=258.4V",
+            ]
+        );
+    }
+
+    #[test]
+    fn power_stats_rounded() {
+        let timestamp = Local.with_ymd_and_hms(2014, 9, 10, 12, 0, 0).unwrap();
+        let event = PowerEvent {
+            timestamp,
+            voltage: 230.25,
+            current: 1.0,
+            power_factor: 0.625,
+            power: 0.14390625,
+            apparent_power: 0.23025,
+            frequency: None,
+        };
+        let stats = PowerStats {
+            total_active_power: 0.125,
+            avg_active_power: 2.5,
+            max_active_power: event,
+            total_apparent_power: -0.125,
+            avg_apparent_power: 0.23025,
+            max_apparent_power: event,
+            min_voltage: event,
+            max_voltage: event,
+            avg_voltage: 230.25,
+            p95_voltage: 230.25,
+            min_current: event,
+            max_current: event,
+            avg_current: 1.0,
+            avg_power_factor: 0.625,
+            p95_power: 0.14390625,
+            worst_power_factor: Some(event),
+            weighted_power_factor: 0.625,
+            total_reactive_energy: 0.005,
+            worst_sustained_power_factor: Some(PowerFactorWindow {
+                start: timestamp,
+                power_factor: 0.625,
+            }),
+            total_duration: Duration::minutes(1),
+            active: None,
+            coverage: CoverageInfo::new(1, 1),
+        };
+        let policy = PrecisionPolicy {
+            energy: 2,
+            power: 0,
+            voltage: 1,
+            power_factor: 2,
+        };
+        let rounded = stats.rounded(&policy);
+        // Ties go away from zero, where banker's rounding would give 0.12, 2, -0.12 and 230.2
+        assert_eq!(rounded.total_active_power, 0.13);
+        assert_eq!(rounded.avg_active_power, 3.0);
+        assert_eq!(rounded.total_apparent_power, -0.13);
+        assert_eq!(rounded.avg_voltage, 230.3);
+        assert_eq!(rounded.min_voltage.voltage, 230.3);
+        assert_eq!(rounded.max_active_power.power_factor, 0.63);
+        assert_eq!(rounded.max_active_power.power, 0.0);
+        assert_eq!(rounded.max_active_power.current, 1.0);
+        assert_eq!(rounded.max_active_power.timestamp, timestamp);
+        assert_eq!(rounded.weighted_power_factor, 0.63);
+        assert_eq!(rounded.total_reactive_energy, 0.01);
+        assert_eq!(
+            rounded.worst_sustained_power_factor.unwrap().power_factor,
+            0.63
+        );
+        // The original statistics keep their full precision
+        assert_eq!(stats.total_active_power, 0.125);
+    }
+
+    #[test]
+    fn demand_peaks_coincident_values() {
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..1440)
+            .map(|m| {
+                // 15 minutes at 3 kW from 08:00 with a voltage dip, 30 minutes at 2 kW from 19:00
+                let (power, voltage, power_factor) = match m {
+                    480..=494 => (3.0, 220.0 - (m % 3) as f64 * 2.5, 0.8),
+                    1140..=1169 => (2.0, 225.0, 0.95),
+                    _ => (0.1, 230.0, 0.9),
+                };
+                PowerEvent {
+                    timestamp: start + Duration::minutes(m),
+                    voltage,
+                    current: power * 1000.0 / voltage / power_factor,
+                    power_factor,
+                    power,
+                    apparent_power: power / power_factor,
+                    frequency: None,
+                }
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let peaks = stats.demand_peaks(Duration::minutes(15), 3);
+        assert_eq!(peaks.len(), 3);
+
+        assert_eq!(peaks[0].start, start + Duration::minutes(480));
+        assert_eq!(peaks[0].end, start + Duration::minutes(495));
+        assert!((peaks[0].avg_power - 3.0).abs() < 1e-9);
+        assert_eq!(peaks[0].min_voltage, 215.0);
+        assert!((peaks[0].avg_power_factor - 0.8).abs() < 1e-9);
+
+        assert_eq!(peaks[1].start, start + Duration::minutes(1140));
+        assert!((peaks[1].avg_power - 2.0).abs() < 1e-9);
+        assert_eq!(peaks[1].min_voltage, 225.0);
+        assert!((peaks[1].avg_power_factor - 0.95).abs() < 1e-9);
+
+        // The second half of the evening peak does not overlap the first one
+        assert_eq!(peaks[2].start, start + Duration::minutes(1155));
+        assert!((peaks[2].avg_power - 2.0).abs() < 1e-9);
+
+        // No peak in a window shorter than a sample
+        assert!(stats.demand_peaks(Duration::zero(), 3).is_empty());
+        assert!(stats.demand_peaks(Duration::seconds(30), 3).is_empty());
+    }
+
+    #[test]
+    fn top_consumption_windows() {
+        // 0.1 kW, with a kettle at 3 kW from 07:00 to 07:10 and an oven at 2 kW from 18:00 to 19:00
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..1440)
+            .map(|m| {
+                let power = match m {
+                    420..=429 => 3.0,
+                    1080..=1139 => 2.0,
+                    _ => 0.1,
+                };
+                event(start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let top = stats.top_consumption(3, Duration::hours(1));
+        assert_eq!(top.len(), 3);
+
+        assert_eq!(top[0].start, start + Duration::minutes(1080));
+        assert_eq!(top[0].end, start + Duration::minutes(1140));
+        assert!((top[0].energy - 2.0).abs() < 1e-9);
+        assert_eq!(top[0].peak_power.power, 2.0);
+
+        // The earliest hour holding the whole kettle cycle, then none of the shifted copies
+        assert_eq!(top[1].start, start + Duration::minutes(370));
+        assert!((top[1].energy - (0.5 + 50.0 * 0.1 / 60.0)).abs() < 1e-9);
+        assert_eq!(top[1].peak_power.power, 3.0);
+        assert!((top[2].energy - 0.1).abs() < 1e-9);
+
+        // Fewer disjoint hours than requested
+        let all = stats.top_consumption(100, Duration::hours(1));
+        assert!(all.len() < 100);
+        assert!(all.windows(2).all(|w| w[0].energy >= w[1].energy));
+        for (i, a) in all.iter().enumerate() {
+            assert!(all[i + 1..]
+                .iter()
+                .all(|b| a.end <= b.start || b.end <= a.start));
+        }
+        assert!(stats.top_consumption(0, Duration::hours(1)).is_empty());
+        assert!(stats.top_consumption(3, Duration::zero()).is_empty());
+        assert!(stats.top_consumption(3, Duration::seconds(30)).is_empty());
+    }
+
+    #[test]
+    fn boundary_days_policy() {
+        // From 18:00 on the first day to 19:59 on the third day, with a sag on the first evening
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 18, 0, 0).unwrap();
+        let events = (0..(6 + 24 + 20) * 60)
+            .map(|m| PowerEvent {
+                voltage: if m == 30 { 200.0 } else { 230.0 },
+                ..event(start + Duration::minutes(m), 0.23)
+            })
+            .collect::<Vec<_>>();
+        let days = |policy| {
+            let stats = VoltcraftStatistics::new(&events).with_boundary_days(policy);
+            let sags = stats.voltage_anomalies(207.0, 253.0).len();
+            let days = stats
+                .daily_stats()
+                .iter()
+                .map(|d| d.date.day())
+                .collect::<Vec<_>>();
+            (days, sags)
+        };
+        assert_eq!(days(BoundaryDays::Include), (vec![10, 11, 12], 1));
+        assert_eq!(days(BoundaryDays::Exclude), (vec![11], 0));
+        assert_eq!(
+            days(BoundaryDays::IncludeIfCoverageAbove(0.5)),
+            (vec![11, 12], 0)
+        );
+    }
+
+    #[test]
+    fn active_stats_of_mostly_idle_data() {
+        // Ten days, idle at 5 W except for 2.4 hours a day at 1.5 kW
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..10 * 1440)
+            .map(|m| {
+                let power = if m % 1440 >= 1140 && m % 1440 < 1284 {
+                    1.5
+                } else {
+                    0.005
+                };
+                event(start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+        assert!(VoltcraftStatistics::new(&events)
+            .overall_stats()
+            .unwrap()
+            .stats
+            .active
+            .is_none());
+
+        let stats = VoltcraftStatistics::new(&events).with_active_stats(DEFAULT_IDLE_THRESHOLD);
+        let overall = stats.overall_stats().unwrap().stats;
+        let active = overall.active.unwrap();
+        assert_eq!(active.active_duration, Duration::minutes(1440));
+        assert!((active.active_fraction - 0.1).abs() < 1e-9);
+        assert!((active.active_energy - 36.0).abs() < 1e-9);
+        assert!((active.avg_active_power - 1.5).abs() < 1e-9);
+        assert!(active.avg_active_power > 8.0 * overall.avg_active_power);
+
+        let day = &stats.daily_stats()[3];
+        assert_eq!(
+            day.stats.active.unwrap().active_duration,
+            Duration::minutes(144)
+        );
+    }
+
+    #[test]
+    fn attribute_delta_to_evening_hours() {
+        // Two weeks at 0.3 kW, the second one with an extra 1.2 kW from 18:00 to 22:00
+        // and no data between 03:00 and 04:00
+        let start = Local.with_ymd_and_hms(2014, 9, 1, 0, 0, 0).unwrap();
+        let events = (0..14 * 1440)
+            .filter(|m| m < &(7 * 1440) || m % 1440 / 60 != 3)
+            .map(|m| {
+                let evening = m >= 7 * 1440 && (18..22).contains(&(m % 1440 / 60));
+                let power = if evening { 1.5 } else { 0.3 };
+                event(start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let week = Duration::days(7);
+        let delta = stats.attribute_delta((start, start + week), (start + week, start + week * 2));
+
+        let top = &delta.hours[..4];
+        let mut hours = top
+            .iter()
+            .map(|d| match d.bucket {
+                DeltaBucket::Hour(h) => h,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        hours.sort();
+        assert_eq!(hours, vec![18, 19, 20, 21]);
+        for d in top {
+            // 1.2 kW for an hour on seven days
+            assert!((d.delta - 8.4).abs() < 1e-9);
+            assert!((d.share.unwrap() - 0.25).abs() < 1e-9);
+        }
+        let last = delta.hours.last().unwrap();
+        assert_eq!(last.bucket, DeltaBucket::Hour(3));
+        assert!(last.incomplete);
+        assert_eq!(last.share, None);
+        assert!(delta.hours[4..23].iter().all(|d| d.delta.abs() < 1e-9));
+        // Every weekday got the same increase (minus the missing hour)
+        assert!(delta.weekdays.iter().all(|d| !d.incomplete));
+        assert!(delta
+            .weekdays
+            .iter()
+            .all(|d| (d.delta - (4.8 - 0.3)).abs() < 1e-9));
+    }
+
+    #[test]
+    fn streaks_over_a_month() {
+        // September 2014: 0.5 kW (12 kWh a day) except 0.3 kW (7.2 kWh) from the 5th to the 13th,
+        // no data on the 20th and 0.3 kW again from the 19th to the 21st and on the 30th
+        let start = Local.with_ymd_and_hms(2014, 9, 1, 0, 0, 0).unwrap();
+        let events = (0..30 * 1440)
+            .filter(|m| m / 1440 + 1 != 20)
+            .map(|m| {
+                let day = m / 1440 + 1;
+                let power = match day {
+                    5..=13 | 19..=21 | 30 => 0.3,
+                    _ => 0.5,
+                };
+                event(start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let date = |day| NaiveDate::from_ymd_opt(2014, 9, day).unwrap();
+        let streak = |start, end, days, under_target| Streak {
+            start: date(start),
+            end: date(end),
+            days,
+            under_target,
+        };
+
+        let report = stats.streaks(10.0);
+        assert_eq!(report.longest_under, Some(streak(5, 13, 9, true)));
+        assert_eq!(report.longest_over, Some(streak(22, 29, 8, false)));
+        assert_eq!(report.current, Some(streak(30, 30, 1, true)));
+
+        // Bridging the missing 20th joins the 19th and the 21st, still shorter than 9 days
+        let report = stats.streaks_with(10.0, true);
+        assert_eq!(report.longest_under, Some(streak(5, 13, 9, true)));
+        let bridged = stats.streaks_with(7.0, true).longest_over;
+        assert_eq!(bridged, Some(streak(1, 30, 29, false)));
+    }
+
+    #[test]
+    fn stats_empty_and_single_event() {
+        let empty = Vec::new();
+        let stats = VoltcraftStatistics::new(&empty);
+        assert!(stats.overall_stats().is_none());
+        assert!(stats.daily_stats().is_empty());
+        assert!(stats.stats_by(Granularity::Month).is_empty());
+        assert!(stats.hourly_stats().is_empty());
+        assert_eq!(stats.blackout_stats().blackout_count, 0);
+
+        let timestamp = Local.with_ymd_and_hms(2014, 9, 10, 12, 0, 0).unwrap();
+        let single = vec![PowerEvent {
+            timestamp,
+            voltage: 231.5,
+            current: 2.0,
+            power_factor: 0.9,
+            power: 0.4167,
+            apparent_power: 0.463,
+            frequency: None,
+        }];
+        let stats = VoltcraftStatistics::new(&single);
+        let overall = stats.overall_stats().unwrap();
+        assert_eq!((overall.start, overall.end), (timestamp, timestamp));
+        assert!(overall.avg_daily_power_consumption.is_none());
+        let s = overall.stats;
+        assert_eq!(s.min_voltage.voltage, 231.5);
+        assert_eq!(s.max_voltage.voltage, 231.5);
+        assert_eq!(s.avg_voltage, 231.5);
+        assert_eq!((s.p95_voltage, s.p95_power), (231.5, 0.4167));
+        assert_eq!(s.avg_active_power, 0.4167);
+        assert_eq!(s.max_active_power.power, 0.4167);
+        assert_eq!(s.total_active_power, 0.4167 / 60.0);
+        assert_eq!(s.total_duration, Duration::minutes(1));
+        assert_eq!(stats.daily_stats().len(), 1);
+    }
+
+    #[test]
+    fn stats_current_and_power_factor() {
+        // An idle minute, then 2 A, 8 A and 4 A at decreasing then increasing power factors
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 12, 0, 0).unwrap();
+        let events = [(0.0, 0.0), (2.0, 0.875), (8.0, 0.5), (4.0, 0.75)]
+            .iter()
+            .zip(0..)
+            .map(|(&(current, power_factor), m)| PowerEvent {
+                current,
+                power_factor,
+                apparent_power: 0.23 * current,
+                ..event(start + Duration::minutes(m), 0.23 * current * power_factor)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let overall = stats.overall_stats().unwrap().stats;
+        assert_eq!(overall.min_current.timestamp, start);
+        assert_eq!(overall.max_current.current, 8.0);
+        assert_eq!(overall.avg_current, 3.5);
+        assert_eq!(overall.avg_power_factor, 0.53125);
+        // The idle minute has a power factor of 0 but is not the worst one
+        let worst = overall.worst_power_factor.unwrap();
+        assert_eq!(worst.timestamp, start + Duration::minutes(2));
+        assert_eq!(worst.power_factor, 0.5);
+
+        let day = &stats.daily_stats()[0];
+        assert_eq!(day.stats.avg_current, 3.5);
+        assert_eq!(
+            day.stats.max_current.timestamp,
+            overall.max_current.timestamp
+        );
+        assert_eq!(day.stats.worst_power_factor.unwrap().power_factor, 0.5);
+
+        // Always idle
+        let idle = PowerStats::from_events(&events[..1]).unwrap();
+        assert!(idle.worst_power_factor.is_none());
+        assert_eq!(idle.avg_power_factor, 0.0);
+    }
+
+    #[test]
+    fn stats_reactive_and_weighted_power_factor() {
+        // Every 5 minutes: idle, then 4 A, 2 A, 5 A and 1 A at 230 V
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 12, 0, 0).unwrap();
+        let events = [(0.0, 0.0), (4.0, 0.6), (2.0, 1.0), (5.0, 0.8), (1.0, 0.5)]
+            .iter()
+            .zip(0..)
+            .map(|(&(current, power_factor), i)| PowerEvent {
+                current,
+                power_factor,
+                apparent_power: 0.23 * current,
+                ..event(
+                    start + Duration::minutes(5 * i),
+                    0.23 * current * power_factor,
+                )
+            })
+            .collect::<Vec<_>>();
+        // 0.92 kVA at cos φ 0.6 is 0.552 kW and 0.736 kvar
+        assert!((events[1].reactive_power() - 0.736).abs() < 1e-12);
+        assert_eq!(events[2].reactive_power(), 0.0);
+        let rounded = PowerEvent {
+            power: 0.4600001,
+            ..events[2]
+        };
+        assert_eq!(rounded.reactive_power(), 0.0);
+
+        let stats = VoltcraftStatistics::new(&events)
+            .with_sample_interval(Duration::minutes(5))
+            .unwrap();
+        let overall = stats.overall_stats().unwrap().stats;
+        // The idle minute drags the plain average down, not the weighted one:
+        // (0.552 + 0.46 + 0.92 + 0.115) kW / (0.92 + 0.46 + 1.15 + 0.23) kVA
+        assert!((overall.avg_power_factor - 0.58).abs() < 1e-12);
+        assert!((overall.weighted_power_factor - 2.047 / 2.76).abs() < 1e-12);
+        assert!(
+            (overall.weighted_power_factor
+                - overall.total_active_power / overall.total_apparent_power)
+                .abs()
+                < 1e-12
+        );
+        // (0.736 + 0 + 0.69 + 0.23 × √0.75) kvar for 5 minutes each
+        let reactive = (0.736 + 0.69 + 0.23 * 0.75f64.sqrt()) * 5.0 / 60.0;
+        assert!((overall.total_reactive_energy - reactive).abs() < 1e-12);
+
+        // 15 minute windows of three events: the first one has an idle minute, the second one
+        // 1.932 kW / 2.53 kVA and the last one 1.495 kW / 1.84 kVA
+        let worst = overall.worst_sustained_power_factor.unwrap();
+        assert_eq!(worst.start, start + Duration::minutes(5));
+        assert!((worst.power_factor - 1.932 / 2.53).abs() < 1e-12);
+
+        // No window without a gap
+        let gapped = [events[0], events[1], events[3], events[4]];
+        let gapped = VoltcraftStatistics::new(&gapped)
+            .with_sample_interval(Duration::minutes(5))
+            .unwrap();
+        let gapped = gapped.overall_stats().unwrap().stats;
+        assert!(gapped.worst_sustained_power_factor.is_none());
+        let idle = PowerStats::from_events(&events[..1]).unwrap();
+        assert_eq!(idle.weighted_power_factor, 0.0);
+        assert_eq!(idle.total_reactive_energy, 0.0);
+    }
+
+    #[test]
+    fn stats_five_minute_interval() {
+        // Two hours of 5 minute samples at 1 kW from 12:00, sagging to 200 V
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 12, 0, 0).unwrap();
+        let events = (0..24)
+            .map(|i| PowerEvent {
+                voltage: 200.0,
+                current: 5.0,
+                ..event(start + Duration::minutes(5 * i), 1.0)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events)
+            .with_sample_interval(Duration::minutes(5))
+            .unwrap();
+        assert_eq!(stats.max_sustained_for(Duration::minutes(30)), Some(1.0));
+        assert_eq!(stats.max_sustained_for(Duration::minutes(118)), Some(1.0));
+        assert_eq!(stats.max_sustained_for(Duration::minutes(120)), Some(1.0));
+        assert_eq!(stats.max_sustained_for(Duration::minutes(121)), None);
+
+        let cost = stats.cost(&Tariff::flat(0.25));
+        assert!((cost.energy - 2.0).abs() < 1e-12);
+        assert!((cost.cost - 0.5).abs() < 1e-12);
+        let days = stats.daily_cost(&Tariff::flat(0.25));
+        assert_eq!(days.len(), 1);
+        assert!((days[0].1.energy - 2.0).abs() < 1e-12);
+
+        let anomalies = stats.voltage_anomalies(207.0, 253.0);
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].timestamp, start);
+        assert_eq!(anomalies[0].duration, Duration::hours(2));
+        assert_eq!(anomalies[0].kind, AnomalyKind::Sag);
+    }
+
+    #[test]
+    fn stats_sub_minute_interval() {
+        // An hour of 30 second samples at 0.6 kW from 12:00, without 12:20 to 12:29:30
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 12, 0, 0).unwrap();
+        let events = (0..120)
+            .filter(|i| !(40..60).contains(i))
+            .map(|i| event(start + Duration::seconds(30 * i), 0.6))
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events)
+            .with_sample_interval(Duration::seconds(30))
+            .unwrap();
+        let overall = stats.overall_stats().unwrap().stats;
+        assert!((overall.total_active_power - 0.5).abs() < 1e-12);
+        assert!((overall.avg_active_power - 0.6).abs() < 1e-12);
+        assert_eq!(overall.total_duration, Duration::minutes(60));
+        assert_eq!(overall.coverage, CoverageInfo::from_seconds(3000, 3600));
+        assert_eq!(
+            (
+                overall.coverage.recorded_minutes,
+                overall.coverage.expected_minutes
+            ),
+            (50, 60)
+        );
+        assert!((overall.coverage.coverage_ratio - 5.0 / 6.0).abs() < 1e-12);
+        // Over the whole hour, the missing ten minutes count as nothing
+        let span = VoltcraftStatistics::new(&events)
+            .with_sample_interval(Duration::seconds(30))
+            .unwrap()
+            .with_average_basis(AverageBasis::Span);
+        let span = span.overall_stats().unwrap().stats;
+        assert!((span.avg_active_power - 0.5).abs() < 1e-12);
+
+        // Runs and windows step by the sample interval
+        let blackouts = stats.blackout_stats().blackouts;
+        assert_eq!(blackouts.len(), 1);
+        assert_eq!(blackouts[0].duration, Duration::minutes(10));
+        let run = stats.sustained_load(0.5).unwrap();
+        assert_eq!(run.start, start + Duration::minutes(30));
+        assert_eq!(run.duration, Duration::minutes(30));
+        let windows = stats.rolling_avg(Duration::minutes(1));
+        assert_eq!(windows.len(), events.len());
+        assert_eq!(windows[1].sample_count, 2);
+        assert_eq!(windows[1].end, start + Duration::minutes(1));
+        assert_eq!(stats.rolling_avg(Duration::seconds(30))[5].sample_count, 1);
+        let top = stats.top_consumption(1, Duration::minutes(10));
+        assert!((top[0].energy - 0.1).abs() < 1e-12);
+
+        // Only positive intervals
+        for interval in [Duration::zero(), Duration::seconds(-30)] {
+            assert!(matches!(
+                VoltcraftStatistics::new(&events).with_sample_interval(interval),
+                Err(VoltcraftError::InvalidSampleInterval { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn stats_load_profile() {
+        // Monday 2014-09-08 to Sunday 2014-09-14, recorded from 06:00 to 22:00: 0.25 kW, with
+        // 1.25 kW at 07:00 on weekdays and 2 kW at 12:00 on weekends
+        let start = Local.with_ymd_and_hms(2014, 9, 8, 0, 0, 0).unwrap();
+        let events = (0..7 * 1440)
+            .map(|m| start + Duration::minutes(m))
+            .filter(|t| (6..22).contains(&t.hour()))
+            .map(|timestamp| {
+                let weekend = timestamp.weekday().number_from_monday() > 5;
+                let power = match timestamp.hour() {
+                    7 if !weekend => 1.25,
+                    12 if weekend => 2.0,
+                    _ => 0.25,
+                };
+                PowerEvent {
+                    power_factor: 0.8,
+                    apparent_power: power / 0.8,
+                    ..event(timestamp, power)
+                }
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+
+        let all = stats.load_profile(BandDays::All);
+        assert_eq!(all.len(), 24);
+        assert_eq!((all[3].hour, all[3].sample_count), (3, 0));
+        assert!(all[3].avg_power.is_none() && all[3].peak_power.is_none());
+        assert_eq!(all[7].sample_count, 7 * 60);
+        assert_eq!(format_fixed(all[7].avg_power.unwrap(), 4), "0.9643");
+        assert_eq!(
+            format_fixed(all[7].avg_apparent_power.unwrap(), 4),
+            "1.2054"
+        );
+        // The first event of the peak hour
+        let peak = all[12].peak_power.unwrap();
+        assert_eq!(
+            peak.timestamp,
+            Local.with_ymd_and_hms(2014, 9, 13, 12, 0, 0).unwrap()
+        );
+
+        let weekdays = stats.load_profile(BandDays::Weekdays);
+        assert_eq!(weekdays[7].avg_power, Some(1.25));
+        assert_eq!(weekdays[12].avg_power, Some(0.25));
+        let weekend = stats.load_profile(BandDays::Weekend);
+        assert_eq!(weekend[7].sample_count, 2 * 60);
+        assert_eq!(weekend[12].avg_power, Some(2.0));
+    }
+
+    #[test]
+    fn stats_baseline() {
+        // Two days at 0.05 kW of standby with 2 kW from 18:00 to 20:00, a 4 hour blackout on the
+        // first night and the standby down to 0.03 kW on the second day
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..2 * 1440)
+            .filter(|m| !(120..360).contains(m))
+            .map(|m| {
+                let power = match (m / 1440, m % 1440) {
+                    (_, 1080..=1199) => 2.0,
+                    (0, _) => 0.05,
+                    _ => 0.03,
+                };
+                event(start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+
+        // The blackout does not count as zero power
+        let baseline = stats.baseline().unwrap();
+        assert_eq!(baseline.percentile, DEFAULT_BASELINE_PERCENTILE);
+        assert_eq!(baseline.power, 0.03);
+        assert_eq!(format_fixed(baseline.daily_energy, 2), "0.72");
+        // 0.03 kW over 44 recorded hours, out of 8 kWh of appliances and 1.56 kWh of standby
+        assert_eq!(format_fixed(baseline.share, 4), "0.1381");
+        assert_eq!(stats.baseline_with(0.5).unwrap().power, 0.03);
+        assert_eq!(stats.baseline_with(0.1).unwrap().power, 0.03);
+        assert_eq!(stats.baseline_with(0.95).unwrap().power, 2.0);
+
+        let days = stats.daily_baseline(DEFAULT_BASELINE_PERCENTILE);
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].0, start.date_naive());
+        assert_eq!((days[0].1.power, days[1].1.power), (0.05, 0.03));
+        assert!(VoltcraftStatistics::new(&Vec::new()).baseline().is_none());
+    }
+
+    #[test]
+    fn stats_weekdays() {
+        // Nine days from Wednesday 2014-09-10, each at its own constant load (day + 1) / 8 kW,
+        // without the Tuesday, so that Wednesday and Thursday are recorded twice
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..9 * 1440)
+            .filter(|m| m / 1440 != 6)
+            .map(|m| {
+                let power = (m / 1440 + 1) as f64 / 8.0;
+                event(start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+
+        let weekdays = stats.weekday_stats();
+        // Monday first, 2014-09-15
+        let monday = weekdays[0].as_ref().unwrap();
+        assert_eq!(monday.max_active_power.timestamp.weekday(), Weekday::Mon);
+        assert_eq!(monday.total_active_power, 18.0);
+        assert!(weekdays[1].is_none());
+        let wednesday = weekdays[2].as_ref().unwrap();
+        assert_eq!(wednesday.total_active_power, 3.0 + 24.0);
+        assert_eq!(wednesday.total_duration, Duration::days(8));
+        assert_eq!(
+            weekdays[6].as_ref().unwrap().max_active_power.timestamp,
+            start + Duration::days(4) + Duration::minutes(1439)
+        );
+
+        // Wednesday and Thursday are averaged over both of their dates
+        let average = stats.weekday_energy_average();
+        assert_eq!(
+            average,
+            [
+                Some(18.0),
+                None,
+                Some(13.5),
+                Some(16.5),
+                Some(9.0),
+                Some(12.0),
+                Some(15.0)
+            ]
+        );
+        assert_eq!(
+            VoltcraftStatistics::new(&Vec::new()).weekday_energy_average(),
+            [None; 7]
+        );
+    }
+
+    #[test]
+    fn stats_between() {
+        // 1 kW from 12:00 to 16:00, nothing recorded from 13:00 to 13:30, 2 kW from 16:00 to 20:00
+        let start = Local.with_ymd_and_hms(2014, 3, 3, 12, 0, 0).unwrap();
+        let events = (0..480)
+            .filter(|m| !(60..90).contains(m))
+            .map(|m| PowerEvent {
+                current: 4.5,
+                apparent_power: 1.0,
+                ..event(
+                    start + Duration::minutes(m),
+                    if m < 240 { 1.0 } else { 2.0 },
+                )
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let at = |h, m| Local.with_ymd_and_hms(2014, 3, 3, h, m, 0).unwrap();
+
+        // Half-open: the 18:00 event is left out
+        let afternoon = stats.stats_between(at(14, 0), at(18, 0)).unwrap();
+        assert_eq!(afternoon.total_active_power, 6.0);
+        assert_eq!(afternoon.max_active_power.power, 2.0);
+        assert_eq!(afternoon.total_duration, Duration::hours(4));
+        assert!(stats.stats_between(at(20, 0), at(22, 0)).is_none());
+        assert!(stats.stats_between(at(18, 0), at(14, 0)).is_none());
+
+        let blackouts = stats.blackouts_between(at(12, 0), at(14, 0));
+        assert_eq!(blackouts.blackout_count, 1);
+        assert_eq!(blackouts.blackouts[0].timestamp, at(13, 0));
+        // A gap over the start of the range is not a blackout within it
+        assert_eq!(
+            stats
+                .blackouts_between(at(13, 10), at(14, 0))
+                .blackout_count,
+            0
+        );
+
+        // The overall statistics are unaffected
+        let overall = stats.overall_stats().unwrap();
+        assert_eq!(overall.stats.total_active_power, 11.5);
+    }
+
+    #[test]
+    fn stats_coverage() {
+        // 24 hours at 0.6 kW, with nothing recorded from 10:00 to 12:00
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..1440)
+            .filter(|m| !(600..720).contains(m))
+            .map(|m| PowerEvent {
+                current: 2.6,
+                ..event(start + Duration::minutes(m), 0.6)
+            })
+            .collect::<Vec<_>>();
+        let overall = VoltcraftStatistics::new(&events).overall_stats().unwrap();
+        let coverage = overall.stats.coverage;
+        assert_eq!(coverage.recorded_minutes, 1320);
+        assert_eq!(coverage.expected_minutes, 1440);
+        assert_eq!(format_fixed(coverage.coverage_ratio, 3), "0.917");
+        assert_eq!(format_fixed(overall.stats.total_active_power, 3), "13.200");
+        assert_eq!(format_fixed(overall.stats.avg_active_power, 3), "0.600");
+
+        // Over the span, the missing minutes count as zero power but the totals are unchanged
+        let stats = VoltcraftStatistics::new(&events).with_average_basis(AverageBasis::Span);
+        let overall = stats.overall_stats().unwrap();
+        assert_eq!(format_fixed(overall.stats.total_active_power, 3), "13.200");
+        assert_eq!(format_fixed(overall.stats.avg_active_power, 3), "0.550");
+        assert_eq!(format_fixed(overall.stats.avg_apparent_power, 3), "0.550");
+        assert_eq!(overall.stats.avg_voltage, 230.0);
+        let day = &stats.daily_stats()[0];
+        assert_eq!(format_fixed(day.stats.avg_active_power, 3), "0.550");
+        assert_eq!(day.stats.coverage, coverage);
+    }
+
+    #[test]
+    fn stats_blackout_summary() {
+        // Blackouts of 10 minutes at 01:00, 30 minutes at 05:00 and 30 minutes from 23:50
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..1560)
+            .filter(|m| {
+                !(60..70).contains(m) && !(300..330).contains(m) && !(1430..1460).contains(m)
+            })
+            .map(|m| PowerEvent {
+                current: 1.0,
+                ..event(start + Duration::minutes(m), 0.2)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let summary = stats.blackout_summary();
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.total_duration, Duration::minutes(70));
+        let longest = summary.longest.unwrap();
+        assert_eq!(longest.timestamp, start + Duration::minutes(300));
+        assert_eq!(
+            summary.mean_time_between,
+            Some(Duration::seconds(1490 * 60 / 3))
+        );
+        assert_eq!(format_fixed(summary.availability, 3), "95.513");
+
+        let days = stats.daily_blackout_summary();
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].0, start.date_naive());
+        assert_eq!(days[0].1.count, 3);
+        assert_eq!(format_fixed(days[0].1.availability, 3), "95.205");
+        assert_eq!(days[1].1.count, 0);
+        assert!(days[1].1.longest.is_none() && days[1].1.mean_time_between.is_none());
+        assert_eq!(days[1].1.availability, 100.0);
+
+        let empty = VoltcraftStatistics::new(&Vec::new()).blackout_summary();
+        assert_eq!((empty.count, empty.availability), (0, 100.0));
+        assert!(empty.longest.is_none());
+    }
+
+    #[test]
+    fn stats_voltage_anomalies() {
+        // A five minute sag down to 188.5V, a one minute swell, and a sag until the end
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 12, 0, 0).unwrap();
+        let voltages = [
+            230.0, 205.0, 196.0, 188.5, 199.0, 206.9, 229.0, 253.1, 231.0, 201.0, 203.0,
+        ];
+        let events = voltages
+            .iter()
+            .enumerate()
+            .map(|(m, &voltage)| PowerEvent {
+                voltage,
+                current: 1.0,
+                ..event(start + Duration::minutes(m as i64), 0.2)
+            })
+            .collect::<Vec<_>>();
+        let thresholds = QualityThresholds::around(230.0, 10.0);
+        assert_eq!(
+            (thresholds.low_voltage, thresholds.high_voltage),
+            (207.0, 253.0)
+        );
+        let anomalies = VoltcraftStatistics::new(&events)
+            .voltage_anomalies(thresholds.low_voltage, thresholds.high_voltage)
+            .iter()
+            .map(|a| {
+                (
+                    (a.timestamp - start).num_minutes(),
+                    a.duration.num_minutes(),
+                    a.kind,
+                    a.extreme_voltage,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            anomalies,
+            [
+                (1, 5, AnomalyKind::Sag, 188.5),
+                (7, 1, AnomalyKind::Swell, 253.1),
+                (9, 2, AnomalyKind::Sag, 201.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn stats_voltage_load_correlation() {
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        // The voltage drops 0.5 V per amp drawn
+        let drawing = |m: i64, current: f64, voltage: f64| PowerEvent {
+            voltage,
+            current,
+            ..event(start + Duration::minutes(m), voltage * current / 1000.0)
+        };
+        let events = (0..90)
+            .map(|m| {
+                let current = (m * 7 % 9) as f64;
+                drawing(m, current, 240.0 - 0.5 * current)
+            })
+            .collect::<Vec<_>>();
+        let report = VoltcraftStatistics::new(&events).voltage_load_correlation();
+        assert_eq!(report.samples, 90);
+        assert!(report.correlation < -0.999);
+        let bin = |min_current, max_current, avg_voltage| CurrentBin {
+            min_current,
+            max_current,
+            avg_voltage,
+            samples: 30,
+        };
+        assert_eq!(
+            report.terciles,
+            vec![
+                bin(0.0, 2.0, 239.5),
+                bin(3.0, 5.0, 238.0),
+                bin(6.0, 8.0, 236.5)
+            ]
+        );
+
+        // Constant voltage, then no data at all
+        let events = (0..90)
+            .map(|m| drawing(m, (m % 9) as f64, 230.1))
+            .collect::<Vec<_>>();
+        let report = VoltcraftStatistics::new(&events).voltage_load_correlation();
+        assert_eq!(report.correlation, 0.0);
+        assert!((report.terciles[2].avg_voltage - 230.1).abs() < 1e-9);
+        let report = VoltcraftStatistics::new(&Vec::new()).voltage_load_correlation();
+        assert_eq!(report.correlation, 0.0);
+        assert!(report.terciles.is_empty());
+    }
+
+    #[test]
+    fn stats_deterministic_order() {
+        // Three days with gaps, repeating power levels and voltage sags
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..3 * 1440)
+            .filter(|m| m % 700 > 5)
+            .map(|m| {
+                let power = [0.2, 1.5, 0.2, 3.0][(m / 45 % 4) as usize];
+                PowerEvent {
+                    voltage: if m % 300 < 3 { 200.0 } else { 230.0 },
+                    ..event(start + Duration::minutes(m), power)
+                }
+            })
+            .collect::<Vec<_>>();
+        // Shuffled clone, loaded the supported way
+        let mut shuffled = events.clone();
+        let mut seed = 12345u64;
+        for i in (1..shuffled.len()).rev() {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            shuffled.swap(i, (seed >> 33) as usize % (i + 1));
+        }
+        assert_ne!(
+            format!("{:?}", shuffled[..10].to_vec()),
+            format!("{:?}", events[..10].to_vec())
+        );
+        let shuffled = crate::voltcraft::dataset::Dataset::new(shuffled)
+            .events()
+            .to_vec();
+
+        let outputs = |events: Vec<PowerEvent>| {
+            let stats = VoltcraftStatistics::new(&events);
+            let day = Duration::days(1);
+            let histogram = crate::voltcraft::distribution::histogram(
+                &events,
+                crate::voltcraft::distribution::Metric::Power,
+                0.5,
+            );
+            format!(
+                "{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}",
+                stats.daily_stats(),
+                stats.hourly_stats(),
+                histogram,
+                stats.attribute_delta((start, start + day), (start + day, start + day * 3)),
+                stats.voltage_load_correlation(),
+                stats.voltage_anomalies(207.0, 253.0),
+                stats.weekly_quality_report(&QualityThresholds::default()),
+                stats.blackout_stats()
+            )
+        };
+        let expected = outputs(events.clone());
+        for _ in 0..2 {
+            assert_eq!(outputs(shuffled.clone()), expected);
+        }
+        let days = events.clone();
+        let days = VoltcraftStatistics::new(&days).daily_stats();
+        assert!(days.windows(2).all(|w| w[0].date < w[1].date));
+    }
+
+    #[test]
+    fn stats_from_slices() {
+        // Two hours with a 10 minute blackout, analyzed whole and in parts
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 8, 0, 0).unwrap();
+        let events = (0..120)
+            .filter(|m| !(50..60).contains(m))
+            .map(|m| PowerEvent {
+                current: 1.0,
+                ..event(start + Duration::minutes(m), 0.23 + m as f64 / 1000.0)
+            })
+            .collect::<Vec<_>>();
+        let shared = std::sync::Arc::new(events.clone());
+        let all = VoltcraftStatistics::new(&shared);
+        let second_hour = VoltcraftStatistics::new(&events[50..]);
+        assert_eq!(second_hour.event_count(), 60);
+        let hour = start + Duration::hours(1);
+        assert_eq!(
+            second_hour.overall_stats().unwrap().stats,
+            all.stats_between(hour, hour + Duration::hours(1)).unwrap()
+        );
+
+        let blackouts = all.blackout_stats().blackouts;
+        let first_hour = VoltcraftStatistics::new(&events[..60]).blackout_stats();
+        let unique = blackouts
+            .iter()
+            .chain(&first_hour.blackouts)
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(unique.len(), 1);
+        assert_eq!(blackouts[0].to_string(), "2014-09-10 08:50 (10m)");
+    }
+
+    #[test]
+    fn stats_activity_periods() {
+        // Wash cycles of 20 and 15 minutes with a 2 minute pause between them, a 5 minute pause,
+        // a 3 minute kettle, and a cycle still running when the data ends (after a gap)
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 8, 0, 0).unwrap();
+        let events = (0..120)
+            .filter(|m| !(95..98).contains(m))
+            .map(|m| {
+                let power = match m {
+                    10..=29 | 32..=46 => 2.0 + (m % 3) as f64 / 10.0,
+                    52..=53 => 1.8,
+                    70..=72 => 2.4,
+                    90..=94 | 98.. => 1.9,
+                    _ => 0.05,
+                };
+                event(start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+        let expected = events[10..47].iter().map(|pe| pe.power).sum::<f64>() / 60.0;
+        let stats = VoltcraftStatistics::new(&events);
+        let spans = |periods: Vec<ActivityPeriod>| {
+            periods
+                .iter()
+                .map(|p| {
+                    let minute = |t: chrono::DateTime<Local>| (t - start).num_minutes();
+                    (minute(p.start), minute(p.end), p.open)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let strict = stats.activity_periods(1.0, Duration::minutes(3));
+        assert_eq!(
+            spans(strict),
+            [
+                (10, 30, false),
+                (32, 47, false),
+                (70, 73, false),
+                (90, 95, false),
+                (98, 120, true)
+            ]
+        );
+        let cycles = stats.activity_periods_with(1.0, Duration::minutes(10), Duration::minutes(3));
+        assert_eq!(spans(cycles.clone()), [(10, 47, false), (90, 120, true)]);
+        let cycle = cycles[0];
+        assert_eq!(cycle.duration, Duration::minutes(37));
+        // The pause consumes the base load
+        assert!((cycle.energy - expected).abs() < 1e-12);
+        assert_eq!(cycle.peak_power.timestamp, start + Duration::minutes(11));
+        assert_eq!(cycle.peak_power.power, 2.2);
+        assert!(stats.activity_periods(3.0, Duration::zero()).is_empty());
+    }
+
+    #[test]
+    fn stats_sustained_load() {
+        // Base load with a 30 minute and a 10 minute plateau, and a 60 minute one cut by a gap
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+        let events = (0..600)
+            .filter(|m| *m != 400)
+            .map(|m| {
+                let power = match m {
+                    100..=129 => 1.5,
+                    200..=209 => 2.0,
+                    365..=424 => 1.2,
+                    _ => 0.2,
+                };
+                event(start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+
+        let run = stats.sustained_load(1.0).unwrap();
+        assert_eq!(
+            run,
+            SustainedRun {
+                start: start + Duration::minutes(365),
+                duration: Duration::minutes(35),
+                ..run
+            }
+        );
+        assert!((run.avg_power - 1.2).abs() < 1e-9);
+        let run = stats.sustained_load(1.8).unwrap();
+        assert_eq!(run.start, start + Duration::minutes(200));
+        assert_eq!(run.duration, Duration::minutes(10));
+        assert_eq!(stats.sustained_load(2.0), None);
+
+        assert_eq!(stats.max_sustained_for(Duration::minutes(10)), Some(2.0));
+        assert_eq!(stats.max_sustained_for(Duration::minutes(11)), Some(1.5));
+        assert_eq!(stats.max_sustained_for(Duration::minutes(30)), Some(1.5));
+        assert_eq!(stats.max_sustained_for(Duration::minutes(31)), Some(1.2));
+        assert_eq!(stats.max_sustained_for(Duration::minutes(36)), Some(0.2));
+        // The longest run without a gap covers the first 400 minutes
+        assert_eq!(stats.max_sustained_for(Duration::minutes(400)), Some(0.2));
+        assert_eq!(stats.max_sustained_for(Duration::minutes(401)), None);
+    }
+
+    #[test]
+    fn stats_pacing() {
+        // 0.5 kW around the clock (12 kWh a day) from the 1st of January
+        let events_from = |start: NaiveDate, days: i64| {
+            let start = Local
+                .from_local_datetime(&start.and_hms_opt(0, 0, 0).unwrap())
+                .unwrap();
+            (0..days * 1440)
+                .map(|m| event(start + Duration::minutes(m), 0.5))
+                .collect::<Vec<_>>()
+        };
+        let date = |m, d| NaiveDate::from_ymd_opt(2014, m, d).unwrap();
+        let events = events_from(date(1, 1), 20);
+        let stats = VoltcraftStatistics::new(&events);
+
+        let report = stats.pacing(2400.0, date(1, 10), None);
+        assert_eq!(report.covered, Some((date(1, 1), date(1, 10))));
+        assert!(!report.partial);
+        assert!((report.actual_kwh - 120.0).abs() < 1e-9);
+        assert!((report.target_kwh - 2400.0 * 10.0 / 365.0).abs() < 1e-9);
+        assert!((report.surplus_kwh - (2400.0 * 10.0 / 365.0 - 120.0)).abs() < 1e-9);
+        assert!((report.surplus_days.unwrap() - report.surplus_kwh / 12.0).abs() < 1e-9);
+
+        // Equal monthly weights give January a twelfth of the target
+        let report = stats.pacing(2400.0, date(1, 20), Some([1.0; 12]));
+        assert!(!report.partial);
+        assert!((report.target_kwh - 200.0 * 20.0 / 31.0).abs() < 1e-9);
+        assert!((report.surplus_kwh - (200.0 * 20.0 / 31.0 - 240.0)).abs() < 1e-9);
+
+        // No data after the 20th
+        let report = stats.pacing(2400.0, date(1, 31), None);
+        assert!(report.partial);
+        assert_eq!(report.covered, Some((date(1, 1), date(1, 20))));
+
+        // Data starting in June
+        let events = events_from(date(6, 1), 5);
+        let report = VoltcraftStatistics::new(&events).pacing(2400.0, date(6, 5), None);
+        assert!(report.partial);
+        assert_eq!(report.covered, Some((date(6, 1), date(6, 5))));
+        assert!((report.surplus_kwh - (2400.0 * 5.0 / 365.0 - 60.0)).abs() < 1e-9);
+
+        let report = VoltcraftStatistics::new(&Vec::new()).pacing(2400.0, date(6, 5), None);
+        assert_eq!(report.covered, None);
+        assert_eq!(report.surplus_days, None);
+    }
+
+    #[test]
+    fn stats_reconcile() {
+        // Ten days at a constant 1 kW, missing every tenth minute
+        let start = Local.with_ymd_and_hms(2014, 3, 1, 0, 0, 0).unwrap();
+        let events = (0..10 * 1440)
+            .filter(|m| m % 10 != 9)
+            .map(|m| event(start + Duration::minutes(m), 1.0))
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let end = start + Duration::days(10);
+        let r = stats.reconcile(240.0, (start, end));
+        assert_eq!(r.logger_kwh, 216.0);
+        assert_eq!(r.discrepancy_kwh, 24.0);
+        assert_eq!(r.discrepancy_percent, Some(10.0));
+        assert_eq!(r.coverage, 0.9);
+        // The gaps account for the whole discrepancy
+        assert!((r.estimated_gap_kwh - 24.0).abs() < 1e-9);
+        assert!(r.unexplained_kwh.abs() < 1e-9);
+        assert_eq!(
+            r.to_string(),
+            "Period: 2014-03-01 00:00 - 2014-03-11 00:00\n\
+             External reading: 240.00 kWh\n\
+             Logger total: 216.00 kWh\n\
+             Discrepancy: 24.00 kWh (10.0%)\n\
+             Coverage: 90.0%\n\
+             Estimated energy during gaps: 24.00 kWh (estimate: gap duration × average power)\n\
+             Unexplained: 0.00 kWh"
+        );
+
+        // A meter reading above the logger total plus the gaps leaves an unexplained part,
+        // and a period past the data has no coverage
+        let r = stats.reconcile(250.0, (start, end));
+        assert!((r.unexplained_kwh - 10.0).abs() < 1e-9);
+        let r = stats.reconcile(0.0, (end, end + Duration::days(1)));
+        assert_eq!(
+            (r.logger_kwh, r.coverage, r.estimated_gap_kwh),
+            (0.0, 0.0, 0.0)
+        );
+        assert_eq!(r.discrepancy_percent, None);
+    }
+
+    #[test]
+    fn stats_away_periods() {
+        // Two weeks from 2014-07-01 with 50 W of standby and 2 kW of appliances from 18:00 to
+        // 21:00, except for an away stretch from the 6th to the 10th and a single quiet day on
+        // the 13th. The fridge defrosts at 300 W for 20 minutes every day.
+        let start = Local.with_ymd_and_hms(2014, 7, 1, 0, 0, 0).unwrap();
+        let events = (0..14 * 1440)
+            .map(|m| {
+                let (day, minute) = (m / 1440, m % 1440);
+                let occupied = !(5..10).contains(&day) && day != 12;
+                let power = match minute {
+                    600..=619 => 0.3,
+                    1080..=1259 if occupied => 2.0,
+                    _ => 0.05,
+                };
+                event(start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let periods = stats.detect_away_periods(&AwayDetectorOptions::default());
+        assert_eq!(periods.len(), 1);
+        let away = periods[0];
+        let date = |d| NaiveDate::from_ymd_opt(2014, 7, d).unwrap();
+        assert_eq!((away.start, away.end), (date(6), date(10)));
+        // 1.2 kWh of standby and 0.083 kWh of defrost per day, 5.85 kWh of appliances on the typical day
+        assert!((away.avg_daily_kwh - (1.2 + 0.25 / 3.0)).abs() < 1e-9);
+        assert!((away.savings_kwh - 5.0 * 5.85).abs() < 1e-9);
+
+        // The quiet day alone is reported once single days are accepted
+        let periods = stats.detect_away_periods(&AwayDetectorOptions::default().with_min_days(1));
+        assert_eq!(periods.len(), 2);
+        assert_eq!((periods[1].start, periods[1].end), (date(13), date(13)));
+
+        // Without tolerance for the defrost, no day is unoccupied
+        let strict = AwayDetectorOptions::default().with_max_spike_minutes(0);
+        assert!(stats.detect_away_periods(&strict).is_empty());
+    }
+
+    #[test]
+    fn stats_peak_shaving() {
+        // Two days at 0.3 kW with a 4 kW peak from 14:00 to 16:00 each afternoon
+        let start = Local.with_ymd_and_hms(2014, 7, 1, 0, 0, 0).unwrap();
+        let events = (0..2 * 1440)
+            .map(|m| {
+                let power = if (840..960).contains(&(m % 1440)) {
+                    4.0
+                } else {
+                    0.3
+                };
+                event(start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+
+        // 4 kWh above the cap each afternoon, recharged overnight
+        let report = stats.simulate_peak_shaving(2.0, &BatterySpec::new(10.0, 3.0, 1.0));
+        assert_eq!((report.violations, report.violation_minutes), (0, 0));
+        assert!((report.shifted_kwh - 8.0).abs() < 1e-9);
+        assert!((report.equivalent_full_cycles - 0.8).abs() < 1e-9);
+
+        // A 3 kWh battery runs out after 90 minutes
+        let report = stats.simulate_peak_shaving(2.0, &BatterySpec::new(3.0, 3.0, 1.0));
+        assert_eq!((report.violations, report.violation_minutes), (2, 60));
+        assert!((report.max_excess_kw - 2.0).abs() < 1e-9);
+        assert!((report.shifted_kwh - 6.0).abs() < 1e-9);
+        assert!((report.equivalent_full_cycles - 2.0).abs() < 1e-9);
+
+        // A 1.5 kW discharge rate leaves 0.5 kW above the cap during each peak
+        let report = stats.simulate_peak_shaving(2.0, &BatterySpec::new(10.0, 1.5, 1.0));
+        assert_eq!((report.violations, report.violation_minutes), (2, 240));
+        assert!((report.max_excess_kw - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stats_solar() {
+        // A constant 1 kW load from 2014-06-29 to 2014-07-02, missing two hours of the night of
+        // the first of July
+        let start = Local.with_ymd_and_hms(2014, 6, 29, 0, 0, 0).unwrap();
+        let events = (0..4 * 1440)
+            .filter(|m| !(2 * 1440..2 * 1440 + 120).contains(m))
+            .map(|m| event(start + Duration::minutes(m), 1.0))
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+
+        // 2 kW from 10:00 to 14:00: 8 kWh generated a day, half of it consumed
+        let mut hourly = [0.0; 24];
+        hourly[10..14].fill(2.0);
+        let report = stats.simulate_solar(&SolarProfile::Hourly(hourly));
+        assert_eq!(report.months.len(), 2);
+        let (june, july) = (report.months[0], report.months[1]);
+        assert_eq!((june.year, june.month, july.month), (2014, 6, 7));
+        assert!((june.consumption_kwh - 48.0).abs() < 1e-9);
+        assert!((june.generation_kwh - 16.0).abs() < 1e-9);
+        assert!((june.self_consumed_kwh - 8.0).abs() < 1e-9);
+        assert!((june.surplus_kwh - 8.0).abs() < 1e-9);
+        // The gap is filled with the average load of its day
+        assert_eq!(july.estimated_minutes, 120);
+        assert!((july.consumption_kwh - 48.0).abs() < 1e-9);
+        assert!((july.self_consumed_kwh - 8.0).abs() < 1e-9);
+        assert!((report.self_consumed_kwh - 16.0).abs() < 1e-9);
+        assert!((report.surplus_kwh - 16.0).abs() < 1e-9);
+        assert!((report.import_reduction - 16.0 / 96.0).abs() < 1e-9);
+
+        // A 1 kW peak never exceeds the load; half a sine wave over 12 hours yields 24 / pi kWh
+        let report = stats.simulate_solar(&SolarProfile::ClearSky {
+            peak_kw: 1.0,
+            daylight: [(6.0, 18.0); 12],
+        });
+        assert!(report.surplus_kwh.abs() < 1e-9);
+        let expected = 4.0 * 24.0 / std::f64::consts::PI;
+        assert!((report.self_consumed_kwh - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn stats_daily_in_time_zone() {
+        // 0.5 kW from 2014-03-08 00:00 UTC to 2014-04-01 00:00 UTC, across both daylight saving
+        // time changes
+        let start = chrono::Utc.with_ymd_and_hms(2014, 3, 8, 0, 0, 0).unwrap();
+        let events = (0..24 * 1440)
+            .map(|m| event((start + Duration::minutes(m)).with_timezone(&Local), 0.5))
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let bucharest = stats.daily_stats_in(&TestZone::Bucharest);
+        let new_york = stats.daily_stats_in(&TestZone::NewYork);
+        let date = |m, d| NaiveDate::from_ymd_opt(2014, m, d).unwrap();
+        let hours = |days: &[super::PowerInterval], day| {
+            days.iter()
+                .find(|d| d.date == day)
+                .unwrap()
+                .stats
+                .total_duration
+                .num_hours()
+        };
+
+        // Bucharest starts at 02:00 on the 8th, New York at 19:00 on the 7th
+        assert_eq!(bucharest[0].date, date(3, 8));
+        assert_eq!(bucharest[0].stats.total_duration.num_hours(), 22);
+        assert_eq!(new_york[0].date, date(3, 7));
+        assert_eq!(new_york[0].stats.total_duration.num_hours(), 5);
+        assert_eq!((bucharest.len(), new_york.len()), (25, 25));
+        // Days of 23 hours in spring
+        assert_eq!(hours(&bucharest, date(3, 30)), 23);
+        assert_eq!(hours(&bucharest, date(3, 9)), 24);
+        assert_eq!(hours(&new_york, date(3, 9)), 23);
+        assert_eq!(hours(&new_york, date(3, 30)), 24);
+
+        let total = |days: &[super::PowerInterval]| {
+            days.iter().map(|d| d.stats.total_active_power).sum::<f64>()
+        };
+        let overall = stats.overall_stats().unwrap().stats.total_active_power;
+        assert!((total(&bucharest) - overall).abs() < 1e-9);
+        assert!((total(&new_york) - overall).abs() < 1e-9);
+        assert_eq!(overall, 288.0);
+    }
+
+    #[test]
+    fn stats_reproducible_sums() {
+        let values = [1e16, 1.0, -1e16, 0.1, 0.2, 0.3];
+        assert_eq!(
+            sum(values.into_iter(), NumericMode::Fast),
+            0.6000000000000001
+        );
+        assert_eq!(sum(values.into_iter(), NumericMode::Reproducible), 1.6);
+        assert_eq!(sum([0.1; 10].into_iter(), NumericMode::Reproducible), 1.0);
+
+        // A year of minutes at 0.1 kW is 876 kWh exactly only with compensated sums
+        let start = Local.with_ymd_and_hms(2014, 1, 1, 0, 0, 0).unwrap();
+        let events = (0..365 * 1440)
+            .map(|m| PowerEvent {
+                voltage: 230.1,
+                current: 0.1 / 0.2301,
+                ..event(start + Duration::minutes(m), 0.1)
+            })
+            .collect::<Vec<_>>();
+        let fast = VoltcraftStatistics::new(&events).overall_stats().unwrap();
+        let reproducible = VoltcraftStatistics::new(&events)
+            .with_numeric_mode(NumericMode::Reproducible)
+            .with_active_stats(DEFAULT_IDLE_THRESHOLD)
+            .overall_stats()
+            .unwrap();
+        assert_ne!(fast.stats.total_active_power, 876.0);
+        assert_eq!(reproducible.stats.total_active_power, 876.0);
+        assert_eq!(reproducible.stats.active.unwrap().active_energy, 876.0);
+        assert_eq!(reproducible.stats.avg_voltage, 230.1);
+    }
+
+    #[test]
+    fn stats_blackouts_at_every_position() {
+        let start = Local.with_ymd_and_hms(2014, 9, 11, 18, 43, 0).unwrap();
+        // Even and odd numbers of power events, with a three minute gap before each of them
+        for len in [6, 7] {
+            for gap_before in 1..len {
+                let events = (0..len)
+                    .map(|i| {
+                        let minute = if i < gap_before { i } else { i + 3 };
+                        event(start + Duration::minutes(minute), 0.23)
+                    })
+                    .collect::<Vec<_>>();
+                let blackouts = VoltcraftStatistics::new(&events).blackout_stats();
+                assert_eq!(
+                    blackouts.blackout_count, 1,
+                    "gap before event {}",
+                    gap_before
+                );
+                let blackout = blackouts.blackouts[0];
+                assert_eq!(blackout.timestamp, start + Duration::minutes(gap_before));
+                assert_eq!(blackout.duration, Duration::minutes(3));
+            }
+        }
+    }
+
+    #[test]
+    fn stats_daily_large_dataset() {
+        // 100000 minutes (71 days, partial first and last ones) with a varying load and gaps
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 18, 43, 0).unwrap();
+        let events = (0..100_000)
+            .filter(|m| m % 9973 >= 30)
+            .map(|m| PowerEvent {
+                voltage: 220.0 + (m % 17) as f64,
+                current: 1.0,
+                ..event(
+                    start + Duration::minutes(m),
+                    (m % 1440) as f64 / 1000.0 + (m % 31) as f64 / 100.0,
+                )
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let days = stats.daily_stats();
+        assert_eq!(days.len(), 71);
+        assert!(days
+            .windows(2)
+            .all(|d| d[0].date.succ_opt() == Some(d[1].date)));
+        // Same days as the ones grouped one after the other, and as the events of each day
+        let sequential = stats.daily_stats_in(&Local);
+        assert_eq!(sequential.len(), days.len());
+        for (day, other) in days.iter().zip(&sequential) {
+            assert_eq!(day.date, other.date);
+            let end = first_instant(&Local, day.end().unwrap().and_time(NaiveTime::MIN)).unwrap();
+            let s = stats.stats_between(day.start(), end).unwrap();
+            for stats in [&other.stats, &s] {
+                assert_eq!(day.stats.total_active_power, stats.total_active_power);
+                assert_eq!(day.stats.max_active_power, stats.max_active_power);
+                assert_eq!(day.stats.min_voltage, stats.min_voltage);
+                assert_eq!(day.stats.avg_voltage, stats.avg_voltage);
+                assert_eq!(day.stats.total_duration, stats.total_duration);
+                assert_eq!(day.stats.coverage, stats.coverage);
+            }
+        }
+        let total = days.iter().map(|d| d.stats.total_active_power).sum::<f64>();
+        let overall = stats.overall_stats().unwrap().stats.total_active_power;
+        assert!((total - overall).abs() < 1e-6);
+    }
+
+    #[test]
+    fn stats_blackout_options() {
+        // A skipped minute at 20:00 and 90 minutes without power from 23:01
+        let start = Local.with_ymd_and_hms(2014, 9, 11, 18, 43, 0).unwrap();
+        let outage = Local.with_ymd_and_hms(2014, 9, 11, 23, 1, 0).unwrap();
+        let events = (0..600)
+            .map(|m| start + Duration::minutes(m))
+            .filter(|t| t.hour() != 20 || t.minute() != 0)
+            .filter(|&t| t < outage || t >= outage + Duration::minutes(90))
+            .map(|timestamp| event(timestamp, 0.23))
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        assert_eq!(stats.blackout_stats().blackout_count, 2);
+
+        let blackouts = stats.blackout_stats_with(BlackoutOptions::default());
+        assert_eq!(blackouts.blackout_count, 1);
+        assert_eq!(blackouts.blackouts[0].timestamp, outage);
+        assert_eq!(blackouts.blackouts[0].duration, Duration::minutes(90));
+        // The skipped minute is kept with a one minute gap, short blackouts can be dropped
+        let all = BlackoutOptions::default().with_min_gap(Duration::minutes(1));
+        assert_eq!(stats.blackout_stats_with(all).blackout_count, 2);
+        let long = all.with_min_duration(Duration::minutes(91));
+        assert_eq!(stats.blackout_stats_with(long).blackout_count, 0);
+
+        // Split at midnight, the 31 minutes of the next day are kept even if short
+        let daily = BlackoutOptions::default()
+            .with_split_at_midnight(true)
+            .with_min_duration(Duration::minutes(60));
+        let blackouts = stats.blackout_stats_with(daily);
+        let durations = blackouts
+            .blackouts
+            .iter()
+            .map(|b| (b.timestamp, b.duration.num_minutes()))
+            .collect::<Vec<_>>();
+        let midnight = Local.with_ymd_and_hms(2014, 9, 12, 0, 0, 0).unwrap();
+        assert_eq!(durations, [(outage, 59), (midnight, 31)]);
+        assert_eq!(blackouts.total_blackout_duration, Duration::minutes(90));
+    }
+
+    #[test]
+    fn blackout_duration_conversions() {
+        let start = Local.with_ymd_and_hms(2014, 9, 11, 18, 43, 0).unwrap();
+        let blackout = |duration| PowerBlackout {
+            timestamp: start,
+            duration,
+        };
+        let long = blackout(Duration::minutes(1570) + Duration::seconds(30));
+        assert_eq!(
+            long.duration_std(),
+            std::time::Duration::from_secs(1570 * 60 + 30)
+        );
+        assert_eq!(long.duration_minutes(), 1570);
+        assert_eq!(long.to_string(), "2014-09-11 18:43 (1d 2h 10m)");
+
+        let negative = blackout(Duration::minutes(-3));
+        assert_eq!(negative.duration_std(), std::time::Duration::ZERO);
+        assert_eq!(negative.duration_minutes(), -3);
+        assert_eq!(
+            blackout(Duration::zero()).duration_std(),
+            std::time::Duration::ZERO
+        );
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_value(long).unwrap();
+            assert_eq!(json["duration"], 1570 * 60 + 30);
+            let back: PowerBlackout = serde_json::from_value(json).unwrap();
+            assert_eq!(back.duration, long.duration);
+            assert_eq!(back.timestamp, start);
+
+            let info = crate::voltcraft::stats::BlackoutInfo::new(vec![long, negative]);
+            let info = serde_json::to_value(info).unwrap();
+            assert_eq!(info["total_blackout_duration"], 1567 * 60 + 30);
+            let out_of_range = serde_json::json!({ "timestamp": start, "duration": i64::MAX });
+            assert!(serde_json::from_value::<PowerBlackout>(out_of_range).is_err());
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn stats_power_interval_serde() {
+        use crate::voltcraft::stats::PowerInterval;
+        let start = Local.with_ymd_and_hms(2014, 9, 11, 23, 58, 0).unwrap();
+        let events = (0..4)
+            .map(|m| PowerEvent {
+                timestamp: start + Duration::minutes(m),
+                voltage: 230.0 + m as f64,
+                current: 1.5,
+                power_factor: 0.9,
+                power: 0.31,
+                apparent_power: 0.345,
+                frequency: None,
+            })
+            .collect::<Vec<_>>();
+        let days = VoltcraftStatistics::new(&events)
+            .with_active_stats(0.1)
+            .daily_stats();
+        assert_eq!(days.len(), 2);
+        let json = serde_json::to_string(&days).unwrap();
+        let back: Vec<PowerInterval> = serde_json::from_str(&json).unwrap();
+        for (day, back) in days.iter().zip(&back) {
+            assert_eq!(day.date, back.date);
+            // The date is a calendar date, the duration whole seconds
+            let value = serde_json::to_value(day).unwrap();
+            assert_eq!(value["date"], day.date.to_string());
+            assert_eq!(value["stats"]["total_duration"], 120);
+            let (stats, back) = (&day.stats, &back.stats);
+            assert_eq!(stats.total_duration, back.total_duration);
+            // serde_json parses floats to within an ulp unless its float_roundtrip feature is on
+            assert!((stats.total_active_power - back.total_active_power).abs() < 1e-15);
+            assert_eq!(stats.max_voltage.timestamp, back.max_voltage.timestamp);
+            assert_eq!(stats.max_voltage.voltage, back.max_voltage.voltage);
+            assert_eq!(
+                stats.active.map(|a| a.active_duration),
+                back.active.map(|a| a.active_duration)
+            );
+        }
+    }
+
+    #[test]
+    fn stats_by_granularity() {
+        // From Wednesday 2014-08-27 13:00 to 2014-10-03, a changing load every minute
+        let start = Local.with_ymd_and_hms(2014, 8, 27, 13, 0, 0).unwrap();
+        let events = (0..37 * 1440)
+            .map(|m| PowerEvent {
+                current: 1.0,
+                apparent_power: 1.2,
+                ..event(start + Duration::minutes(m), 0.1 + (m % 97) as f64 / 100.0)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let total = stats.overall_stats().unwrap().stats.total_active_power;
+        let date = |m, d| NaiveDate::from_ymd_opt(2014, m, d).unwrap();
+        let spans = |g| {
+            stats
+                .stats_by(g)
+                .iter()
+                .map(|i| (i.date, i.end().unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        let months = stats.stats_by(Granularity::Month);
+        assert_eq!(
+            spans(Granularity::Month),
+            [
+                (date(8, 1), date(9, 1)),
+                (date(9, 1), date(10, 1)),
+                (date(10, 1), date(11, 1))
+            ]
+        );
+        let monthly_total = months
+            .iter()
+            .map(|m| m.stats.total_active_power)
+            .sum::<f64>();
+        assert!((monthly_total - total).abs() < 1e-9 * total);
+        assert_eq!(
+            months[0].stats.total_duration,
+            Duration::minutes(5 * 1440 - 13 * 60)
+        );
+
+        let weeks = spans(Granularity::Week);
+        assert_eq!(weeks.len(), 6);
+        assert_eq!(weeks[0], (date(8, 25), date(9, 1)));
+        assert!(weeks
+            .iter()
+            .all(|(d, _)| d.weekday() == chrono::Weekday::Mon));
+
+        let days = stats.daily_stats();
+        assert_eq!(days.len(), 38);
+        assert_eq!(days[1].end(), Some(date(8, 29)));
+        assert!(days.iter().all(|d| d.granularity == Granularity::Day));
+        let hours = stats.stats_by(Granularity::Hour);
+        assert_eq!(hours.len(), 37 * 24);
+        assert_eq!(hours[0].end(), None);
+    }
+
+    #[test]
+    fn stats_daily_with_day_start() {
+        // 2014-09-10 20:00 to 2014-09-12 10:00 with a heater on from 23:30 to 00:30 and
+        // blackouts from 05:55 to 06:04 and across the second midnight, 23:50 to 00:19
+        let start = Local.with_ymd_and_hms(2014, 9, 10, 20, 0, 0).unwrap();
+        let events = (0..38 * 60)
+            .filter(|m| !(595..605).contains(m) && !(1670..1700).contains(m))
+            .map(|m| {
+                let power = match m {
+                    210..=269 => 2.0,
+                    _ => 0.1 + (m % 13) as f64 / 100.0,
+                };
+                event(start + Duration::minutes(m), power)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+        let total = stats.overall_stats().unwrap().stats.total_active_power;
+        let date = |d| NaiveDate::from_ymd_opt(2014, 9, d).unwrap();
+
+        // Days from 06:00 to 06:00, dated with the day they start on
+        let days = stats.daily_stats_with_day_start(6);
+        let summary = days
+            .iter()
+            .map(|d| {
+                (
+                    d.date,
+                    d.stats.coverage.recorded_minutes,
+                    d.stats.total_duration.num_minutes(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (date(10), 595, 595),
+                (date(11), 1405, 1435),
+                (date(12), 240, 240)
+            ]
+        );
+        assert_eq!(
+            days[1].start(),
+            Local.with_ymd_and_hms(2014, 9, 11, 6, 0, 0).unwrap()
+        );
+        assert_eq!(days[1].end(), Some(date(12)));
+        // The heater cycle is a single day
+        assert_eq!(days[0].stats.max_active_power.power, 2.0);
+        assert!(days[1].stats.max_active_power.power < 2.0);
+
+        // Every minute counted once
+        let daily_total = days.iter().map(|d| d.stats.total_active_power).sum::<f64>();
+        assert!((daily_total - total).abs() < 1e-9 * total);
+        let midnight = stats.daily_stats_with_day_start(0);
+        let dates = |days: &[crate::voltcraft::stats::PowerInterval]| {
+            days.iter().map(|d| d.date).collect::<Vec<_>>()
+        };
+        assert_eq!(dates(&midnight), dates(&stats.daily_stats()));
+        assert_eq!(dates(&stats.daily_stats_with_day_start(30)), dates(&days));
+        let whole = VoltcraftStatistics::new(&events)
+            .with_boundary_days(BoundaryDays::Exclude)
+            .daily_stats_with_day_start(6);
+        assert_eq!(dates(&whole), [date(11)]);
+    }
+
+    #[test]
+    fn stats_hourly() {
+        // 22:30 to 01:15 across midnight, nothing recorded from 23:00 to 23:59
+        let start = Local.with_ymd_and_hms(2014, 9, 11, 22, 30, 0).unwrap();
+        let events = (0..166)
+            .filter(|m| !(30..90).contains(m))
+            .map(|m| PowerEvent {
+                current: 1.0,
+                apparent_power: 0.6,
+                ..event(
+                    start + Duration::minutes(m),
+                    if m == 100 { 2.4 } else { 0.6 },
+                )
+            })
+            .collect::<Vec<_>>();
+        let hours = VoltcraftStatistics::new(&events)
+            .with_boundary_days(BoundaryDays::Exclude)
+            .hourly_stats();
+        let summary = hours
+            .iter()
+            .map(|h: &PowerHourInterval| {
+                (
+                    h.date.day(),
+                    h.hour,
+                    h.stats.total_duration.num_minutes(),
+                    format_fixed(h.stats.total_active_power, 3),
+                    format_fixed(h.stats.max_active_power.power, 1),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (11, 22, 30, "0.300".to_string(), "0.6".to_string()),
+                (12, 0, 60, "0.630".to_string(), "2.4".to_string()),
+                (12, 1, 16, "0.160".to_string(), "0.6".to_string()),
+            ]
+        );
+        assert_eq!(
+            hours[1].stats.max_active_power.timestamp,
+            start + Duration::minutes(100)
+        );
+        assert!(VoltcraftStatistics::new(&Vec::new())
+            .hourly_stats()
+            .is_empty());
+    }
+
+    #[test]
+    fn stats_bucket_boundaries() {
+        // Sunday 23:59, then Monday 00:00 and 18:00 exactly
+        let at = |d, h, m| Local.with_ymd_and_hms(2014, 9, d, h, m, 0).unwrap();
+        let events = [at(7, 23, 59), at(8, 0, 0), at(8, 18, 0)]
+            .iter()
+            .map(|t| PowerEvent {
+                current: 1.0,
+                ..event(*t, 0.6)
+            })
+            .collect::<Vec<_>>();
+        let stats = VoltcraftStatistics::new(&events);
+
+        let days = stats.daily_stats();
+        let counts = days
+            .iter()
+            .map(|d| (d.date.day(), format_fixed(d.stats.total_active_power, 3)))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [(7, "0.010".to_string()), (8, "0.020".to_string())]);
+
+        let weeks = stats.weekly_quality_report(&QualityThresholds::default());
+        let weeks = weeks
+            .iter()
+            .map(|w| (w.week, w.start.day()))
+            .collect::<Vec<_>>();
+        assert_eq!(weeks, [(36, 1), (37, 8)]);
+
+        let delta = stats.attribute_delta((at(7, 0, 0), at(8, 0, 0)), (at(8, 0, 0), at(9, 0, 0)));
+        let energy = |hour| {
+            let bucket = delta
+                .hours
+                .iter()
+                .find(|b| b.bucket == DeltaBucket::Hour(hour));
+            let bucket = bucket.unwrap();
+            format!("{} {}", bucket.energy_a * 100.0, bucket.energy_b * 100.0)
+        };
+        assert_eq!(energy(23), "1 0");
+        assert_eq!(energy(0), "0 1");
+        assert_eq!(energy(17), "0 0");
+        assert_eq!(energy(18), "0 1");
+
+        let compacted = crate::voltcraft::compact::compact_slice(&events, at(8, 0, 0));
+        assert_eq!(compacted.days.len(), 1);
+        assert_eq!(compacted.events.len(), 2);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::voltcraft::data::PowerEvent;
+use crate::voltcraft::stats::energy;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, Weekday};
+
+/// Days of the week on which a tariff band starts, or of a `VoltcraftStatistics::load_profile`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BandDays {
+    #[default]
+    All,
+    Weekdays, // Monday to Friday
+    Weekend,  // Saturday and Sunday
+}
+
+/// Price of the energy consumed during part of the day, from `start` included to `end`
+/// excluded on the local wall clock. A band ending at or before its start crosses midnight
+/// and is counted as part of the day it starts on (22:00 until 07:00 on Friday night is a
+/// weekday band).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct TariffBand {
+    pub name: String,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub days: BandDays,
+    pub price: f64, // per kWh
+}
+
+/// Flat price per kWh, with optional time of use bands overriding it. When bands overlap, the
+/// first one added applies.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Tariff {
+    pub price: f64, // per kWh outside every band
+    pub bands: Vec<TariffBand>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandCost {
+    pub name: String,
+    pub energy: f64, // active energy consumed in the band (kWh)
+    pub cost: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostBreakdown {
+    pub energy: f64,          // total active energy (kWh)
+    pub cost: f64,            // total cost
+    pub bands: Vec<BandCost>, // one per band of the tariff, in the same order
+    pub base: BandCost,       // energy outside every band, at the flat price
+}
+
+// Name of the energy consumed outside every band
+const BASE_NAME: &str = "base";
+
+impl TariffBand {
+    pub fn new(name: &str, start: NaiveTime, end: NaiveTime, price: f64) -> Self {
+        TariffBand {
+            name: name.to_string(),
+            start,
+            end,
+            days: BandDays::All,
+            price,
+        }
+    }
+
+    pub fn with_days(mut self, days: BandDays) -> Self {
+        self.days = days;
+        self
+    }
+
+    // Whether the minute starting at the timestamp falls in the band
+    pub fn contains(&self, timestamp: DateTime<Local>) -> bool {
+        let local = timestamp.naive_local();
+        let (date, time) = (local.date(), local.time());
+        if self.start < self.end {
+            return self.start <= time && time < self.end && self.starts_on(date.weekday());
+        }
+        // Crossing midnight: the evening part of the day of the timestamp, or the morning part
+        // of a band started the day before
+        (time >= self.start && self.starts_on(date.weekday()))
+            || (time < self.end && self.starts_on((date - Duration::days(1)).weekday()))
+    }
+
+    fn starts_on(&self, day: Weekday) -> bool {
+        self.days.contains(day)
+    }
+}
+
+impl BandDays {
+    pub fn contains(&self, day: Weekday) -> bool {
+        let weekend = matches!(day, Weekday::Sat | Weekday::Sun);
+        match self {
+            BandDays::All => true,
+            BandDays::Weekdays => !weekend,
+            BandDays::Weekend => weekend,
+        }
+    }
+}
+
+impl Tariff {
+    pub fn flat(price: f64) -> Self {
+        Tariff {
+            price,
+            bands: Vec::new(),
+        }
+    }
+
+    pub fn with_band(mut self, band: TariffBand) -> Self {
+        self.bands.push(band);
+        self
+    }
+
+    // Index of the band applying to the minute starting at the timestamp, None for the flat price
+    pub fn band_of(&self, timestamp: DateTime<Local>) -> Option<usize> {
+        self.bands.iter().position(|b| b.contains(timestamp))
+    }
+
+    // Cost of the power events recorded every interval (a minute for the logger), each one
+    // standing for the interval at its active power
+    pub fn cost(&self, power_events: &[PowerEvent], interval: Duration) -> CostBreakdown {
+        let mut power_sums = vec![0.0; self.bands.len() + 1]; // the last one outside every band
+        for pe in power_events {
+            let band = self.band_of(pe.timestamp).unwrap_or(self.bands.len());
+            power_sums[band] += pe.power;
+        }
+        let mut energies = power_sums
+            .into_iter()
+            .map(|power_sum| energy(power_sum, interval))
+            .collect::<Vec<_>>();
+        let base_energy = energies.pop().unwrap();
+        let bands = self
+            .bands
+            .iter()
+            .zip(energies)
+            .map(|(band, energy)| BandCost {
+                name: band.name.clone(),
+                energy,
+                cost: energy * band.price,
+            })
+            .collect::<Vec<_>>();
+        let base = BandCost {
+            name: BASE_NAME.to_string(),
+            energy: base_energy,
+            cost: base_energy * self.price,
+        };
+        CostBreakdown {
+            energy: bands.iter().map(|b| b.energy).sum::<f64>() + base.energy,
+            cost: bands.iter().map(|b| b.cost).sum::<f64>() + base.cost,
+            bands,
+            base,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::format_fixed;
+    use crate::voltcraft::data::PowerEvent;
+    use crate::voltcraft::stats::VoltcraftStatistics;
+    use crate::voltcraft::tariff::{BandDays, Tariff, TariffBand};
+    use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone};
+
+    // Power event at 230 V with a power factor of 1
+    fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+        PowerEvent {
+            timestamp,
+            voltage: 230.0,
+            current: power * 1000.0 / 230.0,
+            power_factor: 1.0,
+            power,
+            apparent_power: power,
+            frequency: None,
+        }
+    }
+
+    #[test]
+    fn tariff_bands() {
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        // Peak on weekdays, off-peak on weekday nights, the flat price on weekends
+        let tariff = Tariff::flat(0.30)
+            .with_band(
+                TariffBand::new("peak", time(7), time(22), 0.40).with_days(BandDays::Weekdays),
+            )
+            .with_band(
+                TariffBand::new("night", time(22), time(7), 0.20).with_days(BandDays::Weekdays),
+            );
+        let at = |d, h, m| Local.with_ymd_and_hms(2014, 9, d, h, m, 0).unwrap();
+        // Friday 2014-09-12: a boundary belongs to the band starting there
+        assert_eq!(tariff.band_of(at(12, 6, 59)), Some(1));
+        assert_eq!(tariff.band_of(at(12, 7, 0)), Some(0));
+        assert_eq!(tariff.band_of(at(12, 21, 59)), Some(0));
+        assert_eq!(tariff.band_of(at(12, 22, 0)), Some(1));
+        // The Friday night band runs into Saturday morning, the weekend has the flat price
+        assert_eq!(tariff.band_of(at(13, 6, 59)), Some(1));
+        assert_eq!(tariff.band_of(at(13, 7, 0)), None);
+        assert_eq!(tariff.band_of(at(14, 23, 0)), None);
+        // Sunday night still has the flat price, Monday morning is off-peak
+        assert_eq!(tariff.band_of(at(15, 3, 0)), None);
+        assert_eq!(tariff.band_of(at(15, 22, 30)), Some(1));
+
+        // 1.2 kW from Friday 21:00 to Saturday 08:00
+        let events = (0..11 * 60)
+            .map(|m| PowerEvent {
+                current: 5.2,
+                ..event(at(12, 21, 0) + Duration::minutes(m), 1.2)
+            })
+            .collect::<Vec<_>>();
+        let cost = VoltcraftStatistics::new(&events).cost(&tariff);
+        let summary = cost
+            .bands
+            .iter()
+            .chain([&cost.base])
+            .map(|b| {
+                format!(
+                    "{} {} {}",
+                    b.name,
+                    format_fixed(b.energy, 3),
+                    format_fixed(b.cost, 2)
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            ["peak 1.200 0.48", "night 10.800 2.16", "base 1.200 0.36"]
+        );
+        assert_eq!(format_fixed(cost.energy, 3), "13.200");
+        assert_eq!(format_fixed(cost.cost, 2), "3.00");
+
+        let days = VoltcraftStatistics::new(&events).daily_cost(&tariff);
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].0, at(12, 0, 0).date_naive());
+        assert_eq!(format_fixed(days[0].1.cost, 2), "0.96");
+        assert_eq!(format_fixed(days[1].1.cost, 2), "2.04");
+
+        let flat = Tariff::flat(0.25).cost(&events, Duration::minutes(1));
+        assert!(flat.bands.is_empty());
+        assert_eq!(format_fixed(flat.cost, 2), "3.30");
+        assert_eq!(Tariff::flat(0.25).cost(&[], Duration::minutes(1)).cost, 0.0);
+    }
+}
@@ -0,0 +1,77 @@
+// The voltcraft binary, run on the sample data
+use std::process::Command;
+
+fn voltcraft(args: &[&str]) -> (Option<i32>, String) {
+    run(env!("CARGO_BIN_EXE_voltcraft"), args)
+}
+
+fn run(binary: &str, args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(binary).args(args).output().unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn cli_merges_files_chronologically() {
+    // Given in reverse order
+    let (code, csv) = voltcraft(&[
+        "decode",
+        "sample_data1/A04FC8D3.BIN",
+        "sample_data1/A04FC8D2.BIN",
+    ]);
+    assert_eq!(code, Some(0));
+    let timestamps = csv
+        .lines()
+        .skip(1)
+        .map(|l| l.split(',').next().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert!(!timestamps.is_empty());
+    assert!(timestamps.windows(2).all(|w| w[0] < w[1]));
+
+    let (code, json) = voltcraft(&["decode", "--format", "json", "sample_data1/A04FC8D2.BIN"]);
+    assert_eq!(code, Some(0));
+    assert!(json.starts_with("[\n  {\"timestamp\":"));
+    assert!(json.ends_with("}\n]\n"));
+
+    let (code, stats) = voltcraft(&["stats", "sample_data1/A04FC8D2.BIN"]);
+    assert_eq!(code, Some(0));
+    assert!(stats.starts_with("{\"schema_version\":1,\"file_count\":1,"));
+    let (_, daily) = voltcraft(&["stats", "--daily", "sample_data1/A04FC8D2.BIN"]);
+    assert!(daily.starts_with("date,duration_min,energy_kwh,"));
+    let (_, blackouts) = voltcraft(&["blackouts", "sample_data1/A04FC8D2.BIN"]);
+    assert!(blackouts.starts_with("start,duration_min\n"));
+}
+
+#[test]
+fn cli_exit_codes() {
+    assert_eq!(voltcraft(&["decode", "Cargo.toml"]).0, Some(65));
+    assert_eq!(voltcraft(&["decode", "missing.BIN"]).0, Some(66));
+    assert_eq!(voltcraft(&["decode"]).0, Some(64));
+    assert_eq!(voltcraft(&["convert", "Cargo.toml"]).0, Some(64));
+    assert_eq!(
+        voltcraft(&["stats", "--tz", "Mars", "sample_data1/A04FC8D2.BIN"]).0,
+        Some(64)
+    );
+}
+
+#[test]
+fn cli_debug_dump() {
+    let (code, dump) = voltcraft(&["debug", "sample_data1/A04FC8D2.BIN"]);
+    assert_eq!(code, Some(0));
+    assert!(!dump.is_empty());
+    assert_eq!(voltcraft(&["debug", "missing.BIN"]).0, Some(66));
+    let two = [
+        "debug",
+        "sample_data1/A04FC8D2.BIN",
+        "sample_data1/A04FC8D3.BIN",
+    ];
+    assert_eq!(voltcraft(&two).0, Some(64));
+
+    // The analyzer gives the same dump
+    let analyzer = env!("CARGO_BIN_EXE_voltcraft_energy_analyzer");
+    let (code, same) = run(analyzer, &["debug", "sample_data1/A04FC8D2.BIN"]);
+    assert_eq!((code, same), (Some(0), dump));
+    assert_eq!(run(analyzer, &["debug", "missing.BIN"]).0, Some(66));
+}
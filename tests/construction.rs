@@ -0,0 +1,81 @@
+// Construction paths supported for the non-exhaustive types, as seen from outside the crate
+use voltcraft_energy_analyzer::export::ExportError;
+use voltcraft_energy_analyzer::locale::Locale;
+use voltcraft_energy_analyzer::prelude::*;
+use voltcraft_energy_analyzer::report::{BundleOptions, ReportOptions};
+use voltcraft_energy_analyzer::voltcraft::baseline::ComparisonBands;
+use voltcraft_energy_analyzer::voltcraft::stats::QualityThresholds;
+
+// Power event at 230 V with a power factor of 1
+fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+    PowerEvent {
+        timestamp,
+        voltage: 230.0,
+        current: power * 1000.0 / 230.0,
+        power_factor: 1.0,
+        power,
+        apparent_power: power,
+        frequency: None,
+    }
+}
+
+fn events() -> Vec<PowerEvent> {
+    let start = Local.with_ymd_and_hms(2014, 9, 10, 0, 0, 0).unwrap();
+    (0..60)
+        .map(|m| event(start + Duration::minutes(m), 0.46))
+        .collect()
+}
+
+#[test]
+fn construct_statistics() {
+    let stats = PowerStats::from_events(&events()).unwrap();
+    assert!((stats.total_active_power - 0.46).abs() < 1e-9);
+    assert!(PowerStats::from_events(&[]).is_none());
+
+    let day = PowerInterval::new(NaiveDate::from_ymd_opt(2014, 9, 10).unwrap(), stats);
+    assert!(day.annotations.is_empty());
+    assert_eq!(day.stats.total_duration, stats.total_duration);
+
+    let blackouts = BlackoutInfo::new(vec![PowerBlackout {
+        timestamp: events()[10].timestamp,
+        duration: Duration::minutes(5),
+    }]);
+    assert_eq!(blackouts.blackout_count, 1);
+    assert_eq!(blackouts.total_blackout_duration, Duration::minutes(5));
+}
+
+#[test]
+fn construct_options() {
+    let precision = PrecisionPolicy::new(3, 3, 1, 2);
+    assert_eq!(precision.energy, 3);
+    let report = ReportOptions::default()
+        .with_locale(Locale::De)
+        .with_label("overall", "Gesamt")
+        .with_precision(precision);
+    assert_eq!(report.labels["overall"], "Gesamt");
+    let bundle = BundleOptions::default()
+        .with_report(report)
+        .with_text_report(true)
+        .with_events_csv(true)
+        .with_markdown_report(false)
+        .with_daily_csv(false)
+        .with_blackouts_csv(false);
+    assert!(bundle.text_report && bundle.events_csv && !bundle.markdown_report);
+    assert_eq!(bundle.report.locale, Locale::De);
+
+    let thresholds = QualityThresholds::new(230.0, 220.0, 240.0);
+    assert_eq!(thresholds.low_voltage, 220.0);
+    let bands = ComparisonBands::new(5.0, 5.0, 25.0);
+    assert_eq!(bands.blackouts, 25.0);
+}
+
+#[test]
+fn match_errors_with_wildcard() {
+    let describe = |e: &ExportError| match e {
+        ExportError::Io(_) => "io",
+        ExportError::EmptyDataSet => "empty",
+        // Variants may be added in minor releases
+        _ => "other",
+    };
+    assert_eq!(describe(&ExportError::EmptyDataSet), "empty");
+}
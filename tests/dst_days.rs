@@ -0,0 +1,34 @@
+// Days starting after a daylight saving time change at midnight in the local time zone
+use voltcraft_energy_analyzer::prelude::*;
+
+// Power event at 230 V with a power factor of 1
+fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+    PowerEvent {
+        timestamp,
+        voltage: 230.0,
+        current: power * 1000.0 / 230.0,
+        power_factor: 1.0,
+        power,
+        apparent_power: power,
+        frequency: None,
+    }
+}
+
+#[test]
+fn dst_day_starts() {
+    // Clocks went from 00:00 to 01:00 on 2014-10-19 in Sao Paulo
+    std::env::set_var("TZ", "America/Sao_Paulo");
+    let start = Local.with_ymd_and_hms(2014, 10, 18, 22, 0, 0).unwrap();
+    let events = (0..240)
+        .map(|m| event(start + Duration::minutes(m), 0.23))
+        .collect::<Vec<_>>();
+    let days = VoltcraftStatistics::new(&events).daily_stats();
+    assert_eq!(days.len(), 2);
+    assert_eq!(days[1].date, NaiveDate::from_ymd_opt(2014, 10, 19).unwrap());
+    // The day starts with its first minute, there is no midnight
+    let first_minute = Local.with_ymd_and_hms(2014, 10, 19, 1, 0, 0).unwrap();
+    assert_eq!(days[1].start(), first_minute);
+    assert_eq!(days[1].start().timestamp(), 1413687600);
+    assert_eq!(days[0].start().to_rfc3339(), "2014-10-18T00:00:00-03:00");
+    assert_eq!(days[1].stats.total_duration, Duration::minutes(120));
+}
@@ -0,0 +1,38 @@
+// Data block start times falling into a daylight saving time change of the local time zone
+use voltcraft_energy_analyzer::prelude::*;
+
+// One data block starting at the given local time (2014, Europe/Bucharest) with two records
+fn file(month: u8, day: u8, hour: u8, minute: u8) -> VoltcraftData {
+    VoltcraftData::from_raw(vec![
+        0xE0, 0xC5, 0xEA, month, day, 0x0E, hour, minute, 0x08, 0xC6, 0x01, 0xBE, 0x57, 0x08, 0xC6,
+        0x01, 0xBE, 0x57, 0xFF, 0xFF, 0xFF, 0xFF,
+    ])
+}
+
+#[test]
+fn dst_start_times() {
+    // Clocks go from 03:00 to 04:00 on March 30 and from 04:00 back to 03:00 on October 26
+    std::env::set_var("TZ", "Europe/Bucharest");
+
+    // Skipped time: the first minute after the gap
+    let events = file(3, 30, 3, 30).parse().unwrap();
+    assert_eq!(
+        events[0].timestamp.to_rfc3339(),
+        "2014-03-30T04:00:00+03:00"
+    );
+    assert_eq!(
+        events[1].timestamp.to_rfc3339(),
+        "2014-03-30T04:01:00+03:00"
+    );
+
+    // Repeated time: the earlier of the two (still summer time)
+    let events = file(10, 26, 3, 30).parse().unwrap();
+    assert_eq!(
+        events[0].timestamp.to_rfc3339(),
+        "2014-10-26T03:30:00+03:00"
+    );
+    assert_eq!(
+        file(10, 26, 3, 30).fingerprint().start,
+        Some(events[0].timestamp)
+    );
+}
@@ -0,0 +1,89 @@
+use std::fs;
+use voltcraft_energy_analyzer::prelude::*;
+use voltcraft_energy_analyzer::report::{monthly_bundle, BundleOptions, ReportError};
+
+// Power event at 230 V with a power factor of 1
+fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+    PowerEvent {
+        timestamp,
+        voltage: 230.0,
+        current: power * 1000.0 / 230.0,
+        power_factor: 1.0,
+        power,
+        apparent_power: power,
+        frequency: None,
+    }
+}
+
+// From 2014-08-25 to 2014-09-05, with a 30 minute blackout on 2014-09-02
+fn dataset() -> Dataset {
+    let start = Local.with_ymd_and_hms(2014, 8, 25, 0, 0, 0).unwrap();
+    let blackout = 8 * 1440 + 600..8 * 1440 + 630;
+    let events = (0..12 * 1440)
+        .filter(|m| !blackout.contains(m))
+        .map(|m| PowerEvent {
+            current: 2.0,
+            power_factor: 0.9,
+            apparent_power: 0.46,
+            ..event(start + Duration::minutes(m), 0.414)
+        })
+        .collect();
+    Dataset::new(events)
+}
+
+#[test]
+fn monthly_bundle_manifest_matches_files() {
+    let out_dir = std::env::temp_dir().join(format!("voltcraft-monthly-{}", std::process::id()));
+    let opts = BundleOptions::default().with_events_csv(true);
+    let manifest = monthly_bundle(&dataset(), 2014, 9, &out_dir, &opts).unwrap();
+
+    let names = manifest
+        .files
+        .iter()
+        .map(|f| f.path.file_name().unwrap().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "2014-09-report.md",
+            "2014-09-daily.csv",
+            "2014-09-blackouts.csv",
+            "2014-09-events.csv"
+        ]
+    );
+    for file in &manifest.files {
+        assert_eq!(fs::metadata(&file.path).unwrap().len(), file.size);
+    }
+    assert_eq!(
+        fs::read_dir(&out_dir).unwrap().count(),
+        manifest.files.len()
+    );
+
+    // Only September is included
+    let daily = fs::read_to_string(&manifest.files[1].path).unwrap();
+    assert_eq!(daily.lines().count(), 1 + 5);
+    assert!(daily
+        .lines()
+        .nth(1)
+        .unwrap()
+        .starts_with("2014-09-01,1440,"));
+    let blackouts = fs::read_to_string(&manifest.files[2].path).unwrap();
+    assert_eq!(blackouts, "Start,Duration (min)\n2014-09-02 10:00,30\n");
+    let events = fs::read_to_string(&manifest.files[3].path).unwrap();
+    assert_eq!(events.lines().count(), 1 + 5 * 1440 - 30);
+
+    fs::remove_dir_all(&out_dir).unwrap();
+}
+
+#[test]
+fn monthly_bundle_month_not_in_dataset() {
+    let out_dir = std::env::temp_dir().join("voltcraft-monthly-missing");
+    let result = monthly_bundle(&dataset(), 2014, 10, &out_dir, &BundleOptions::default());
+    match result {
+        Err(e @ ReportError::MonthNotInDataset { .. }) => {
+            assert_eq!(e.to_string(), "no power data for 2014-10")
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(!out_dir.exists());
+}
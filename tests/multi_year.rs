@@ -0,0 +1,128 @@
+// Groupings over a dataset spanning several years keep the same month of different years apart
+use voltcraft_energy_analyzer::chrono::Weekday;
+use voltcraft_energy_analyzer::prelude::*;
+use voltcraft_energy_analyzer::report::{digest, monthly_bundle, BundleOptions, ReportOptions};
+use voltcraft_energy_analyzer::voltcraft::compact::compact;
+use voltcraft_energy_analyzer::voltcraft::stats::{DeltaBucket, QualityThresholds, SolarProfile};
+
+// Power event at 230 V with a power factor of 1
+fn event(timestamp: DateTime<Local>, power: f64) -> PowerEvent {
+    PowerEvent {
+        timestamp,
+        voltage: 230.0,
+        current: power * 1000.0 / 230.0,
+        power_factor: 1.0,
+        power,
+        apparent_power: power,
+        frequency: None,
+    }
+}
+
+// An hour of power events at noon every day from November 2022 to December 2024 (26 months),
+// at 1 kW in 2022 and 2023 and 2 kW in 2024
+fn dataset() -> Dataset {
+    let first = NaiveDate::from_ymd_opt(2022, 11, 1).unwrap();
+    let last = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+    let events = first
+        .iter_days()
+        .take_while(|d| *d <= last)
+        .flat_map(|d| {
+            let noon = Local.from_local_datetime(&d.and_hms_opt(12, 0, 0).unwrap());
+            let noon = noon.unwrap();
+            let power = if d.year() == 2024 { 2.0 } else { 1.0 };
+            (0..60).map(move |m| event(noon + Duration::minutes(m), power))
+        })
+        .collect();
+    Dataset::new(events)
+}
+
+fn at(year: i32, month: u32, day: u32) -> DateTime<Local> {
+    Local.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap()
+}
+
+#[test]
+fn multi_year_distinct_buckets() {
+    let dataset = dataset();
+    assert_eq!(dataset.years(), [2022, 2023, 2024]);
+    let events = dataset.events().to_vec();
+    let stats = VoltcraftStatistics::new(&events);
+
+    // Days
+    let days = stats.daily_stats();
+    assert_eq!(days.len(), 30 + 31 + 365 + 366);
+    let energy_on = |d: NaiveDate| {
+        let day = days.iter().find(|i| i.date == d).unwrap();
+        day.stats.total_active_power
+    };
+    assert_eq!(
+        energy_on(NaiveDate::from_ymd_opt(2023, 1, 15).unwrap()),
+        1.0
+    );
+    assert_eq!(
+        energy_on(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()),
+        2.0
+    );
+
+    // ISO weeks
+    let weeks = stats.weekly_quality_report(&QualityThresholds::default());
+    let keys = weeks.iter().map(|w| (w.year, w.week)).collect::<Vec<_>>();
+    assert!(keys.windows(2).all(|w| w[0] < w[1]));
+    assert!(keys.contains(&(2023, 2)) && keys.contains(&(2024, 2)));
+    let opts = ReportOptions::default();
+    let week = |year| {
+        NaiveDate::from_isoywd_opt(year, 2, Weekday::Mon)
+            .unwrap()
+            .iso_week()
+    };
+    assert!(digest(&dataset, week(2023), &opts).contains("7.0 kWh"));
+    assert!(digest(&dataset, week(2024), &opts).contains("14.0 kWh"));
+
+    // Months
+    let solar = stats.simulate_solar(&SolarProfile::Hourly([0.0; 24]));
+    assert_eq!(solar.months.len(), 26);
+    let january = |year| {
+        let month = solar.months.iter().find(|m| (m.year, m.month) == (year, 1));
+        month.unwrap().consumption_kwh
+    };
+    // Minutes without data count at the average load of their day
+    assert!((january(2023) - 31.0 * 24.0).abs() < 1e-6);
+    assert!((january(2024) - 62.0 * 24.0).abs() < 1e-6);
+    let out_dir = std::env::temp_dir().join(format!("voltcraft-multi-year-{}", std::process::id()));
+    let opts = BundleOptions::default();
+    for (year, energy) in [(2023, "1.000"), (2024, "2.000")] {
+        let manifest = monthly_bundle(&dataset, year, 1, &out_dir, &opts).unwrap();
+        assert_eq!((manifest.year, manifest.month), (year, 1));
+        let daily = manifest
+            .files
+            .iter()
+            .find(|f| f.path.ends_with(format!("{}-01-daily.csv", year)));
+        let daily = std::fs::read_to_string(&daily.unwrap().path).unwrap();
+        assert_eq!(daily.lines().count(), 1 + 31);
+        let first = format!("{}-01-01,60,{},", year, energy);
+        assert!(daily.lines().nth(1).unwrap().starts_with(&first));
+    }
+    std::fs::remove_dir_all(&out_dir).unwrap();
+
+    // Hours of two periods a year apart
+    let delta = stats.attribute_delta(
+        (at(2023, 1, 1), at(2023, 2, 1)),
+        (at(2024, 1, 1), at(2024, 2, 1)),
+    );
+    let noon = delta
+        .hours
+        .iter()
+        .find(|b| b.bucket == DeltaBucket::Hour(12))
+        .unwrap();
+    assert!((noon.energy_a - 31.0).abs() < 1e-9 && (noon.energy_b - 62.0).abs() < 1e-9);
+
+    // Compacted days
+    let compacted = compact(dataset.sorted_events(), at(2025, 1, 1));
+    assert_eq!(compacted.days.len(), days.len());
+    let januaries = compacted
+        .days
+        .iter()
+        .filter(|d| d.first.month() == 1 && d.first.day() == 15)
+        .map(|d| (d.first.year(), d.summary.stats.total_active_power))
+        .collect::<Vec<_>>();
+    assert_eq!(januaries, [(2023, 1.0), (2024, 2.0)]);
+}
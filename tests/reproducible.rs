@@ -0,0 +1,81 @@
+// Exports in reproducible numeric mode must be bit-identical on every platform
+use std::fs;
+use voltcraft_energy_analyzer::export::{
+    save_blackouts_csv, save_daily_stats_csv, save_parameter_history_csv,
+};
+use voltcraft_energy_analyzer::prelude::*;
+use voltcraft_energy_analyzer::summary::QuickSummary;
+
+// FNV-1a, whose value unlike the std hashers is fixed across Rust versions and platforms
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Ninety days of pseudo-random power events with a few blackouts
+fn events() -> Vec<PowerEvent> {
+    let start = Local.with_ymd_and_hms(2014, 6, 1, 0, 0, 0).unwrap();
+    let mut seed = 42u64;
+    let mut random = move || {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        seed >> 33
+    };
+    (0..90 * 1440)
+        .filter(|m| m % 10007 > 90)
+        .map(|m| {
+            let voltage = 2200.0 + (random() % 300) as f64;
+            let current = (random() % 15000) as f64;
+            let power_factor = (random() % 100) as f64 / 100.0;
+            let power = voltage * current * power_factor / 1e8;
+            PowerEvent {
+                timestamp: start + Duration::minutes(m),
+                voltage: voltage / 10.0,
+                current: current / 1000.0,
+                power_factor,
+                power,
+                apparent_power: voltage * current / 1e8,
+                frequency: None,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn reproducible_export_hash() {
+    // Timestamps are exported in local time; the expected hash is the one of UTC
+    std::env::set_var("TZ", "UTC");
+    let events = events();
+    let dir = std::env::temp_dir();
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    save_parameter_history_csv(&path("voltcraft_reproducible_history.csv"), &events).unwrap();
+
+    let stats = VoltcraftStatistics::new(&events)
+        .with_numeric_mode(NumericMode::Reproducible)
+        .with_active_stats(0.1);
+    save_daily_stats_csv(
+        &path("voltcraft_reproducible_daily.csv"),
+        &stats.daily_stats(),
+    )
+    .unwrap();
+    save_blackouts_csv(
+        &path("voltcraft_reproducible_blackouts.csv"),
+        &stats.blackout_stats(),
+    )
+    .unwrap();
+    let json = QuickSummary::from_statistics(&stats, 1).to_json();
+
+    let mut exports = Vec::new();
+    for name in [
+        "voltcraft_reproducible_history.csv",
+        "voltcraft_reproducible_daily.csv",
+        "voltcraft_reproducible_blackouts.csv",
+    ] {
+        exports.extend(fs::read(path(name)).unwrap());
+    }
+    exports.extend(json.as_bytes());
+    assert_eq!(stats.event_count(), 128_417);
+    assert_eq!(format!("{:016x}", fnv1a(&exports)), "4c3a6ad6dfb3b07b");
+}